@@ -31,6 +31,10 @@ cross build --release --target aarch64-unknown-linux-musl
 cp "target/aarch64-unknown-linux-musl/release/$BIN_NAME" \
    "$DIST_DIR/${BIN_NAME}-linux-arm64"
 
+echo ""
+echo "==> Writing checksums (tengok self-update verifies against these)"
+(cd "$DIST_DIR" && shasum -a 256 "${BIN_NAME}"-* > SHA256SUMS)
+
 echo ""
 echo "==> Build complete!"
 ls -lh "$DIST_DIR"
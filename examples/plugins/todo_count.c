@@ -0,0 +1,44 @@
+/* Counts TODO markers. Build: cc -shared -fPIC -O2 -Iinclude \
+ *   examples/plugins/todo_count.c -o todo_count.so */
+#include <stdlib.h>
+#include <string.h>
+
+#include "tengok_plugin.h"
+
+static const char MARKER[] = "TODO";
+#define MARKER_LEN (sizeof(MARKER) - 1)
+
+struct state {
+    uint64_t count;
+    size_t matched; /* marker bytes matched at the end of the last chunk */
+};
+
+uint32_t tengok_plugin_abi(void) { return TENGOK_PLUGIN_ABI; }
+
+const char *tengok_plugin_name(void) { return "todos"; }
+
+void *tengok_plugin_begin(const char *path) {
+    (void)path;
+    return calloc(1, sizeof(struct state));
+}
+
+int tengok_plugin_chunk(void *state, const uint8_t *data, size_t len) {
+    struct state *s = state;
+    for (size_t i = 0; i < len; i++) {
+        if (data[i] == (uint8_t)MARKER[s->matched]) {
+            if (++s->matched == MARKER_LEN) {
+                s->count++;
+                s->matched = 0;
+            }
+        } else {
+            s->matched = data[i] == (uint8_t)MARKER[0];
+        }
+    }
+    return 0;
+}
+
+uint64_t tengok_plugin_end(void *state) {
+    uint64_t count = ((struct state *)state)->count;
+    free(state);
+    return count;
+}
@@ -0,0 +1,52 @@
+/* C API of libtengok; see src/ffi.rs for the implementation. */
+#ifndef TENGOK_H
+#define TENGOK_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Opaque result of tengok_scan. */
+typedef struct TengokSummary TengokSummary;
+
+/* One scanned file, valid only for the duration of the callback. */
+typedef struct TengokRecord {
+    const char *path; /* relative to the scanned root, UTF-8 */
+    uint64_t size_bytes;
+    uint64_t lines;
+    int64_t mtime; /* seconds since the Unix epoch, or -1 when unknown */
+} TengokRecord;
+
+/* Called for every file; return non-zero to stop the scan early. */
+typedef int (*tengok_record_cb)(const TengokRecord *record, void *user_data);
+
+/* Scan with CLI-style arguments, without the program name, e.g.
+ * {"src", "--by-ext"}. `callback` may be NULL and runs on the calling
+ * thread. Returns NULL on error; see tengok_last_error. */
+TengokSummary *tengok_scan(size_t argc, const char *const *argv,
+                           tengok_record_cb callback, void *user_data);
+
+uint64_t tengok_summary_files(const TengokSummary *summary);
+uint64_t tengok_summary_size_bytes(const TengokSummary *summary);
+uint64_t tengok_summary_lines(const TengokSummary *summary);
+
+/* The `tengok --json` document; free it with tengok_string_free. */
+char *tengok_summary_json(const TengokSummary *summary);
+
+void tengok_summary_free(TengokSummary *summary);
+void tengok_string_free(char *s);
+
+/* Message for the last failed call on this thread, or NULL. */
+const char *tengok_last_error(void);
+
+/* Library version, e.g. "0.1.1". */
+const char *tengok_version(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TENGOK_H */
@@ -0,0 +1,34 @@
+/* ABI for `tengok --plugin` metric plugins; see src/plugin.rs. */
+#ifndef TENGOK_PLUGIN_H
+#define TENGOK_PLUGIN_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define TENGOK_PLUGIN_ABI 1
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Return TENGOK_PLUGIN_ABI. */
+uint32_t tengok_plugin_abi(void);
+
+/* Metric name shown in the report and JSON; must stay valid. */
+const char *tengok_plugin_name(void);
+
+/* Start a file. Return per-file state, or NULL to skip the file (its value
+ * is then 0). Called concurrently from several threads. */
+void *tengok_plugin_begin(const char *path);
+
+/* Next `len` bytes of the file, in order. Return non-zero to stop reading. */
+int tengok_plugin_chunk(void *state, const uint8_t *data, size_t len);
+
+/* Free `state` and return the file's value; values are summed. */
+uint64_t tengok_plugin_end(void *state);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TENGOK_PLUGIN_H */
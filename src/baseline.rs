@@ -0,0 +1,166 @@
+//! `--baseline`: totals from an earlier run, shown as deltas next to the
+//! report's rows. Either a saved `tengok --json` document or one line of a
+//! `tengok history` store works; rows the snapshot doesn't carry get no
+//! delta. With `--max-growth`, the baseline is also a ratchet: the run
+//! fails when total bytes or lines grew by more than the allowed share.
+
+use std::{fs, path::Path};
+
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::scan::Summary;
+use crate::snapshot;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Baseline {
+    pub files: u64,
+    pub size_bytes: u64,
+    pub lines: u64,
+    #[serde(default)]
+    pub compressed_size_estimate_bytes: Option<u64>,
+    #[serde(default)]
+    pattern: Option<PatternBase>,
+    #[serde(default)]
+    plugins: Vec<Named<u64>>,
+    #[serde(default)]
+    metrics: Vec<Named<Option<f64>>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PatternBase {
+    matches: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Named<T> {
+    name: String,
+    value: T,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Document {
+    Report { summary: Baseline },
+    Snapshot(Baseline),
+}
+
+impl Baseline {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        Self::from_document(serde_json::from_str(text))
+    }
+
+    /// A JSON file, or a binary snapshot (`--snapshot`) of which only the
+    /// summary is read.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let unreadable = |e: std::io::Error| format!("Unable to read {}: {}", path.display(), e);
+        let parsed = if snapshot::is_binary(path).map_err(unreadable)? {
+            let doc = snapshot::read_summary(path).map_err(unreadable)?;
+            Self::from_document(serde_json::from_value(doc))
+        } else {
+            Self::parse(&fs::read_to_string(path).map_err(unreadable)?)
+        };
+        parsed.map_err(|e| format!("Invalid baseline {}: {}", path.display(), e))
+    }
+
+    fn from_document(doc: serde_json::Result<Document>) -> Result<Self, String> {
+        match doc {
+            Ok(Document::Report { summary } | Document::Snapshot(summary)) => Ok(summary),
+            Err(_) => Err("expected a `tengok --json` document or a history snapshot".to_string()),
+        }
+    }
+
+    pub fn matches(&self) -> Option<u64> {
+        self.pattern.as_ref().map(|p| p.matches)
+    }
+
+    /// Plugins and metrics are matched by name, since their order can change.
+    pub fn plugin(&self, name: &str) -> Option<u64> {
+        self.plugins
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| p.value)
+    }
+
+    pub fn metric(&self, name: &str) -> Option<f64> {
+        self.metrics.iter().find(|m| m.name == name)?.value
+    }
+}
+
+/// `+1.2 MB`, `-3,400` or `±0`, with `fmt` rendering the magnitude.
+pub fn delta(now: u64, then: u64, fmt: impl Fn(u64) -> String) -> String {
+    match now.cmp(&then) {
+        std::cmp::Ordering::Greater => format!("+{}", fmt(now - then)),
+        std::cmp::Ordering::Less => format!("-{}", fmt(then - now)),
+        std::cmp::Ordering::Equal => "±0".to_string(),
+    }
+}
+
+/// Growth in percent from `then` to `now`; `None` when `then` is zero and
+/// there is nothing to grow from.
+pub fn growth_percent(now: u64, then: u64) -> Option<f64> {
+    match (now, then) {
+        (0, 0) => Some(0.0),
+        (_, 0) => None,
+        _ => Some((now as f64 - then as f64) / then as f64 * 100.0),
+    }
+}
+
+/// The `--max-growth` check of a scan against its baseline.
+pub struct GrowthCheck {
+    pub max_percent: f64,
+    pub size: Option<f64>,
+    pub lines: Option<f64>,
+    pub exceeded: bool,
+}
+
+pub fn growth_check(config: &Config, summary: &Summary) -> Option<GrowthCheck> {
+    let (max_percent, base) = config.max_growth.zip(config.baseline.as_ref())?;
+    let size = growth_percent(summary.total_size, base.size_bytes);
+    let lines = growth_percent(summary.total_lines, base.lines);
+    // Growing from nothing is unbounded growth.
+    let over = |pct: Option<f64>| pct.is_none_or(|p| p > max_percent);
+    Some(GrowthCheck {
+        max_percent,
+        size,
+        lines,
+        exceeded: over(size) || over(lines),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_reports_and_history_lines() {
+        let report = Baseline::parse(
+            r#"{"schema_version":1,"root":".","summary":{"files":3,"size_bytes":10,"lines":7,
+                "pattern":{"regex":"x","matches":4,"files":1,"top_files":[]},
+                "plugins":[{"name":"todo","value":2,"files":1}],
+                "metrics":[{"name":"avg","expr":"avg(lines)","value":2.5},
+                           {"name":"none","expr":"max(lines)","value":null}]}}"#,
+        )
+        .unwrap();
+        assert_eq!((report.files, report.size_bytes, report.lines), (3, 10, 7));
+        assert_eq!(report.matches(), Some(4));
+        assert_eq!(report.plugin("todo"), Some(2));
+        assert_eq!(report.metric("avg"), Some(2.5));
+        assert_eq!(report.metric("none"), None);
+
+        let line =
+            Baseline::parse(r#"{"timestamp":1,"root":"/r","files":1,"size_bytes":2,"lines":3}"#)
+                .unwrap();
+        assert_eq!((line.files, line.compressed_size_estimate_bytes), (1, None));
+        assert!(Baseline::parse(r#"{"files":1}"#).is_err());
+
+        assert_eq!(delta(5, 3, |n| n.to_string()), "+2");
+        assert_eq!(delta(3, 5, |n| n.to_string()), "-2");
+        assert_eq!(delta(3, 3, |n| n.to_string()), "±0");
+
+        assert_eq!(growth_percent(105, 100), Some(5.0));
+        assert_eq!(growth_percent(90, 100), Some(-10.0));
+        assert_eq!(growth_percent(0, 0), Some(0.0));
+        assert_eq!(growth_percent(1, 0), None);
+    }
+}
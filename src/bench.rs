@@ -0,0 +1,132 @@
+use std::{
+    io,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::config::{Config, LineBackend, Output};
+use crate::format::{format_num, glyphs};
+use crate::report::{Style, print_table};
+use crate::scan;
+
+pub const DEFAULT_RUNS: usize = 3;
+
+/// Options that only make sense for `tengok bench`.
+pub struct BenchArgs {
+    pub runs: usize,
+}
+
+pub fn split_args(
+    args: impl IntoIterator<Item = String>,
+) -> Result<(BenchArgs, Vec<String>), String> {
+    let mut opts = BenchArgs { runs: DEFAULT_RUNS };
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--runs" || arg.starts_with("--runs=") {
+            let value = match arg.split_once('=') {
+                Some((_, v)) => v.to_string(),
+                None => args
+                    .next()
+                    .ok_or_else(|| "--runs requires a value".to_string())?,
+            };
+            let runs: usize = value
+                .parse()
+                .map_err(|_| "Unable to parse --runs".to_string())?;
+            opts.runs = runs.max(1);
+        } else {
+            rest.push(arg);
+        }
+    }
+    Ok((opts, rest))
+}
+
+/// 1, 2, 4, ... up to the CPU count, plus the CPU count itself.
+fn thread_counts() -> Vec<usize> {
+    let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut counts: Vec<usize> = std::iter::successors(Some(1), |n| Some(n * 2))
+        .take_while(|&n| n < cpus)
+        .collect();
+    counts.push(cpus);
+    counts
+}
+
+struct Timing {
+    backend: LineBackend,
+    threads: usize,
+    best: Duration,
+    mean: Duration,
+    files: u64,
+}
+
+pub fn run(config: &Config, opts: &BenchArgs) -> io::Result<()> {
+    let style = Style::new(config);
+    let mut base = config.clone();
+    // Nothing is printed per scan, so the spinner must stay off.
+    base.output = Output::Json;
+
+    eprintln!("{}", glyphs("Warming up the page cache…"));
+    scan::scan_dir(&Arc::new(base.clone()))?;
+
+    let mut timings = Vec::new();
+    for backend in LineBackend::ALL {
+        for threads in thread_counts() {
+            let config = Arc::new(Config {
+                line_backend: backend,
+                threads,
+                ..base.clone()
+            });
+            let status = format!("{} × {} threads…", backend.as_str(), threads);
+            eprintln!("{}", glyphs(&status));
+            let mut runs = Vec::with_capacity(opts.runs);
+            let mut files = 0;
+            for _ in 0..opts.runs {
+                let started = Instant::now();
+                files = scan::scan_dir(&config)?.total_files;
+                runs.push(started.elapsed());
+            }
+            timings.push(Timing {
+                backend,
+                threads,
+                best: runs.iter().copied().min().unwrap_or_default(),
+                mean: runs.iter().sum::<Duration>() / runs.len() as u32,
+                files,
+            });
+        }
+    }
+
+    let rows: Vec<Vec<String>> = timings
+        .iter()
+        .map(|t| {
+            vec![
+                t.threads.to_string(),
+                format!("{:.1} ms", t.best.as_secs_f64() * 1000.0),
+                format!("{:.1} ms", t.mean.as_secs_f64() * 1000.0),
+                format_num((t.files as f64 / t.best.as_secs_f64().max(1e-9)) as u64),
+                t.backend.as_str().to_string(),
+            ]
+        })
+        .collect();
+    print_table(
+        &style,
+        &format!(
+            "Benchmark: {} (best of {} runs)",
+            config.root.display(),
+            opts.runs
+        ),
+        &["Threads", "Best", "Mean", "Files/s", "Backend"],
+        &rows,
+    );
+
+    if let Some(fastest) = timings.iter().min_by_key(|t| t.best) {
+        println!();
+        println!(
+            "{} --line-backend {} --threads {}",
+            style.label("Fastest:"),
+            fastest.backend.as_str(),
+            fastest.threads
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,332 @@
+//! Size and line budgets per glob, read from `tengok-budgets.toml`:
+//!
+//! ```toml
+//! "assets/**" = "<= 50MB"
+//! "src/**/*.rs" = ["<= 2000 lines each", "<= 400 files"]
+//! ```
+//!
+//! A rule limits the bytes, lines or file count of all files matching the
+//! glob, or with `each`, of every matching file on its own. Globs match
+//! paths relative to the scanned root; `*` stays within one directory and
+//! `**` spans any number of them.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use globset::{GlobBuilder, GlobMatcher};
+use toml::Spanned;
+
+use crate::config::{Config, parse_bytes};
+use crate::format::display_relative_path;
+use crate::scan::{FileRecord, Summary};
+
+/// Looked up in the scanned root when `--budgets` isn't given.
+pub const FILE_NAME: &str = "tengok-budgets.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Bytes,
+    Lines,
+    Files,
+}
+
+#[derive(Debug, Clone)]
+pub struct Budget {
+    pub glob: String,
+    /// The rule as written, e.g. `<= 2000 lines each`.
+    pub rule: String,
+    pub limit: u64,
+    pub unit: Unit,
+    /// The limit applies to each matching file instead of their total.
+    pub each: bool,
+    matcher: GlobMatcher,
+}
+
+impl Budget {
+    fn parse(glob: &str, rule: &str) -> Result<Self, String> {
+        let err = |msg: &str| format!("Invalid budget {:?} = {:?}: {}", glob, rule, msg);
+        let matcher = GlobBuilder::new(glob)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| err(&e.to_string()))?
+            .compile_matcher();
+        let body = rule
+            .trim()
+            .strip_prefix("<=")
+            .ok_or_else(|| err("rules start with <="))?;
+        let mut words: Vec<&str> = body.split_whitespace().collect();
+        let each = words.last() == Some(&"each");
+        if each {
+            words.pop();
+        }
+        let (limit, unit) = match words.as_slice() {
+            [n, "lines" | "line"] => (n.parse().ok(), Unit::Lines),
+            [n, "files" | "file"] => (n.parse().ok(), Unit::Files),
+            [n, suffix] => (parse_bytes(&format!("{}{}", n, suffix)), Unit::Bytes),
+            [size] => (parse_bytes(size), Unit::Bytes),
+            _ => (None, Unit::Bytes),
+        };
+        let limit = limit.ok_or_else(|| err("expected a size, N lines or N files"))?;
+        if each && unit == Unit::Files {
+            return Err(err("a file count can't apply to each file"));
+        }
+        Ok(Self {
+            glob: glob.to_string(),
+            rule: rule.trim().to_string(),
+            limit,
+            unit,
+            each,
+            matcher,
+        })
+    }
+
+    /// What `record` counts towards this budget, if it's in scope.
+    fn value(&self, record: &FileRecord, root: &Path) -> Option<u64> {
+        if !self
+            .matcher
+            .is_match(display_relative_path(&record.path, root))
+        {
+            return None;
+        }
+        Some(match self.unit {
+            Unit::Bytes => record.size,
+            Unit::Lines => record.lines,
+            Unit::Files => 1,
+        })
+    }
+}
+
+/// Parse a budgets file; each key is a glob, each value a rule or a list
+/// of rules.
+pub fn parse(text: &str) -> Result<Vec<Budget>, String> {
+    let table: toml::Table = text
+        .parse()
+        .map_err(|e| format!("Invalid budgets: {}", e))?;
+    let mut budgets = Vec::new();
+    for (glob, value) in &table {
+        let rules = match value {
+            toml::Value::Array(rules) => rules.iter().collect(),
+            other => vec![other],
+        };
+        for rule in rules {
+            let rule = rule
+                .as_str()
+                .ok_or_else(|| format!("Invalid budget {:?}: expected a rule string", glob))?;
+            budgets.push(Budget::parse(glob, rule)?);
+        }
+    }
+    Ok(budgets)
+}
+
+/// `tengok config check`: every problem in a budgets file, by line,
+/// instead of stopping at the first. Besides what `parse` rejects, this
+/// catches a glob limited twice in the same unit.
+pub fn check(text: &str) -> Vec<(usize, String)> {
+    let line_of = |offset: usize| text[..offset.min(text.len())].matches('\n').count() + 1;
+    let table: BTreeMap<Spanned<String>, Spanned<toml::Value>> = match toml::from_str(text) {
+        Ok(table) => table,
+        Err(err) => {
+            let line = err.span().map_or(1, |span| line_of(span.start));
+            return vec![(line, err.message().to_string())];
+        }
+    };
+    let mut problems = Vec::new();
+    let mut seen: Vec<(usize, Budget)> = Vec::new();
+    for (glob, value) in &table {
+        let line = line_of(glob.span().start);
+        let rules: Vec<&toml::Value> = match value.get_ref() {
+            toml::Value::Array(rules) => rules.iter().collect(),
+            other => vec![other],
+        };
+        for rule in rules {
+            let Some(rule) = rule.as_str() else {
+                problems.push((
+                    line,
+                    format!(
+                        "Invalid budget {:?}: expected a rule string",
+                        glob.get_ref()
+                    ),
+                ));
+                continue;
+            };
+            let budget = match Budget::parse(glob.get_ref(), rule) {
+                Ok(budget) => budget,
+                Err(err) => {
+                    problems.push((line, err));
+                    continue;
+                }
+            };
+            if let Some((first, other)) = seen.iter().find(|(_, b)| {
+                b.glob == budget.glob && b.unit == budget.unit && b.each == budget.each
+            }) {
+                problems.push((
+                    line,
+                    format!(
+                        "Budget {:?} = {:?} conflicts with {:?} on line {}",
+                        budget.glob, budget.rule, other.rule, first
+                    ),
+                ));
+            }
+            seen.push((line, budget));
+        }
+    }
+    problems.sort_by_key(|(line, _)| *line);
+    problems
+}
+
+pub fn load(path: &Path) -> Result<Vec<Budget>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+    parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Running state of one budget over the scan.
+#[derive(Debug, Clone, Default)]
+pub struct BudgetTotal {
+    /// Total over all matching files (unused for `each` budgets).
+    pub value: u64,
+    /// Files over an `each` limit.
+    pub over: Vec<(PathBuf, u64)>,
+}
+
+impl BudgetTotal {
+    pub fn add(&mut self, budget: &Budget, record: &FileRecord, root: &Path) {
+        let Some(value) = budget.value(record, root) else {
+            return;
+        };
+        if !budget.each {
+            self.value += value;
+        } else if value > budget.limit {
+            self.over.push((record.path.clone(), value));
+        }
+    }
+
+    pub fn remove(&mut self, budget: &Budget, record: &FileRecord, root: &Path) {
+        let Some(value) = budget.value(record, root) else {
+            return;
+        };
+        if !budget.each {
+            self.value -= value;
+        } else {
+            self.over.retain(|(path, _)| path != &record.path);
+        }
+    }
+}
+
+/// One exceeded limit; `path` is set for `each` budgets.
+pub struct Violation<'a> {
+    pub budget: &'a Budget,
+    pub path: Option<&'a Path>,
+    pub value: u64,
+}
+
+/// Every exceeded limit, in budget order and then by path.
+pub fn violations<'a>(config: &'a Config, summary: &'a Summary) -> Vec<Violation<'a>> {
+    let mut out = Vec::new();
+    for (budget, total) in config.budgets.iter().zip(&summary.budgets) {
+        if budget.each {
+            let mut over: Vec<&(PathBuf, u64)> = total.over.iter().collect();
+            over.sort_by(|a, b| a.0.cmp(&b.0));
+            out.extend(over.into_iter().map(|(path, value)| Violation {
+                budget,
+                path: Some(path.as_path()),
+                value: *value,
+            }));
+        } else if total.value > budget.limit {
+            out.push(Violation {
+                budget,
+                path: None,
+                value: total.value,
+            });
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_parse_and_match_relative_paths() {
+        let budgets = parse(
+            r#"
+            "assets/**" = "<= 50MB"
+            "src/**/*.rs" = ["<= 2000 lines each", "<= 400 files"]
+            "*.md" = "<= 10 K"
+            "#,
+        )
+        .unwrap();
+        let rules: Vec<(&str, u64, Unit, bool)> = budgets
+            .iter()
+            .map(|b| (b.glob.as_str(), b.limit, b.unit, b.each))
+            .collect();
+        assert_eq!(
+            rules,
+            [
+                ("*.md", 10_000, Unit::Bytes, false),
+                ("assets/**", 50_000_000, Unit::Bytes, false),
+                ("src/**/*.rs", 2000, Unit::Lines, true),
+                ("src/**/*.rs", 400, Unit::Files, false),
+            ]
+        );
+
+        let root = Path::new("repo");
+        let record = |path: &str| FileRecord {
+            path: root.join(path),
+            parent: root.to_path_buf(),
+            size: 1,
+            lines: 1,
+            mtime: None,
+            compressed: 0,
+            lang: None,
+            category: None,
+            kind: None,
+            matches: 0,
+            metrics: Vec::new(),
+            minified: false,
+            placeholder: false,
+            uncounted: false,
+            comments: None,
+            interpreter: None,
+            executable: None,
+            secret: None,
+            license: None,
+            file_id: None,
+            owner: None,
+            atime: None,
+            decompressed: None,
+        };
+        assert!(budgets[0].value(&record("README.md"), root).is_some());
+        assert!(budgets[0].value(&record("docs/x.md"), root).is_none());
+        assert!(budgets[2].value(&record("src/a/b/c.rs"), root).is_some());
+        assert!(budgets[2].value(&record("tests/c.rs"), root).is_none());
+
+        for bad in [
+            r#""a" = "50MB""#,
+            r#""a" = "<= 3 files each""#,
+            r#""a" = "<= lots""#,
+        ] {
+            assert!(parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn check_reports_every_problem_by_line() {
+        let problems = check("\"src/**\" = \"<= 10MB\"\n\"src/**\" = \"<= 1MB\"\n");
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].0, 2);
+
+        let problems = check(
+            r#""a/[" = "<= 1MB"
+"b/**" = ["<= 5 lines", 3, "<= 9 lines"]
+"c" = "< 1MB"
+"#,
+        );
+        let lines: Vec<usize> = problems.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, [1, 2, 2, 3], "{:?}", problems);
+    }
+}
@@ -0,0 +1,323 @@
+//! `--resume`: a scan's progress kept in the root's store, so an interrupted
+//! walk of a huge tree can carry on where it stopped. The walk is split into
+//! parts (see `scan::scan_dir_with`), and the file records of each part are
+//! appended as they arrive; once a part is done, its directories' entry
+//! counts and its name follow, and only then does it count as checkpointed.
+//! A rerun feeds the checkpointed records back to the aggregator and walks
+//! the remaining parts.
+//!
+//! The file starts with `MAGIC` and the options it was written with, then holds tagged
+//! entries: `RECORD` (size, lines, compressed size, modification and access
+//! times as for `spill`, flags, executable kind, device and inode, owner
+//! and path), `ENTRIES` (a directory and its entry count) and `DONE` (a
+//! part's path relative to the root). Whatever follows the last `DONE` is a
+//! part that was cut short, and is dropped.
+
+use std::{
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Seek, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::config::Config;
+use crate::history;
+use crate::mime::ExecKind;
+use crate::scan::{EntryCounts, FileRecord};
+use crate::spill::{path_bytes, path_from_bytes, read_u64};
+use crate::store::{Lock, RootStore};
+
+const FILE_NAME: &str = "checkpoint";
+const MAGIC: &[u8] = b"tengok checkpoint 1\n";
+
+const RECORD: u8 = 1;
+const ENTRIES: u8 = 2;
+const DONE: u8 = 3;
+
+const MINIFIED: u8 = 1;
+const PLACEHOLDER: u8 = 2;
+const UNCOUNTED: u8 = 4;
+
+/// An open checkpoint, and the store's lock for as long as it's written.
+pub struct Checkpoint {
+    path: PathBuf,
+    out: BufWriter<File>,
+    done: HashSet<PathBuf>,
+    _lock: Lock,
+}
+
+impl Checkpoint {
+    /// Opens the root's checkpoint, handing the records of every part an
+    /// earlier run finished to `each` and returning their entry counts. A
+    /// checkpoint written with other options is started over.
+    pub fn open(
+        config: &Config,
+        each: &mut dyn FnMut(FileRecord),
+    ) -> io::Result<(Self, EntryCounts)> {
+        let data_dir = history::data_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+        let store = RootStore::open(&data_dir, &history::root_id(&config.root))?;
+        let lock = store.lock()?;
+        let path = store.path(FILE_NAME);
+        let header = header(MAGIC, config);
+
+        let mut done = HashSet::new();
+        let mut entries = EntryCounts::new();
+        let kept = match File::open(&path) {
+            Ok(file) => load(file, &header, &mut done, &mut entries, each)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+            Err(err) => return Err(err),
+        };
+
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(false);
+        // The paths of a whole tree are nobody else's business.
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options.open(&path)?;
+        file.set_len(kept)?;
+        let mut out = BufWriter::new(file);
+        if kept == 0 {
+            out.write_all(&header)?;
+        } else {
+            out.seek(io::SeekFrom::End(0))?;
+        }
+        Ok((
+            Checkpoint {
+                path,
+                out,
+                done,
+                _lock: lock,
+            },
+            entries,
+        ))
+    }
+
+    /// How many parts earlier runs finished.
+    pub fn parts_done(&self) -> usize {
+        self.done.len()
+    }
+
+    /// Whether an earlier run finished the part at `rel`.
+    pub fn is_done(&self, rel: &Path) -> bool {
+        self.done.contains(rel)
+    }
+
+    pub fn push(&mut self, record: &FileRecord) -> io::Result<()> {
+        self.out.write_all(&[RECORD])?;
+        write_record(&mut self.out, record)
+    }
+
+    /// Marks the part at `rel`, whose directories held `entries`, as done
+    /// once it's on disk.
+    pub fn complete(&mut self, rel: &Path, entries: &EntryCounts) -> io::Result<()> {
+        for (dir, n) in entries {
+            self.out.write_all(&[ENTRIES])?;
+            write_path(&mut self.out, dir)?;
+            self.out.write_all(&n.to_le_bytes())?;
+        }
+        self.out.write_all(&[DONE])?;
+        write_path(&mut self.out, rel)?;
+        self.out.flush()?;
+        self.out.get_ref().sync_data()?;
+        self.done.insert(rel.to_path_buf());
+        Ok(())
+    }
+
+    /// Removes the checkpoint of a scan that finished.
+    pub fn remove(self) -> io::Result<()> {
+        fs::remove_file(&self.path)
+    }
+}
+
+/// What a checkpoint (after `magic`) must start with to be picked up: the options that
+/// decide which files are walked and what their records hold. The rest,
+/// such as the output format, can change between runs.
+pub(crate) fn header(magic: &[u8], config: &Config) -> Vec<u8> {
+    let options = options(config);
+    let mut header = magic.to_vec();
+    header.extend_from_slice(&(options.len() as u32).to_le_bytes());
+    header.extend_from_slice(options.as_bytes());
+    header
+}
+
+fn options(config: &Config) -> String {
+    let mut ext_limits: Vec<_> = config.ext_max_line_bytes.iter().collect();
+    ext_limits.sort();
+    let only: Vec<&str> = config
+        .args
+        .iter()
+        .zip(config.args.iter().skip(1))
+        .filter(|(flag, _)| *flag == "--only")
+        .map(|(_, glob)| glob.as_str())
+        .chain(config.args.iter().filter_map(|a| a.strip_prefix("--only=")))
+        .collect();
+    format!(
+        "{:?}",
+        (
+            (&config.root, config.hidden, config.junk, config.include_git),
+            (
+                config.no_ignore,
+                &config.exclude_dirs,
+                only,
+                config.only_mine
+            ),
+            (config.skip_lines, config.force_lines, config.max_line_bytes),
+            (ext_limits, config.lines, config.include_minified),
+            (
+                config.compress_estimate,
+                config.exclude_generated,
+                config.xattrs
+            ),
+        )
+    )
+}
+
+/// Reads the finished parts, and returns how many bytes they take; 0 when
+/// the checkpoint is for other options.
+fn load(
+    file: File,
+    header: &[u8],
+    done: &mut HashSet<PathBuf>,
+    entries: &mut EntryCounts,
+    each: &mut dyn FnMut(FileRecord),
+) -> io::Result<u64> {
+    let mut input = BufReader::new(file);
+    let mut start = vec![0; header.len()];
+    if input.read_exact(&mut start).is_err() || start != header {
+        return Ok(0);
+    }
+    let mut kept = header.len() as u64;
+    let mut records = Vec::new();
+    let mut counts = Vec::new();
+    // A part cut short ends in an error here, whether at a tag or halfway
+    // through an entry.
+    while let Ok(tag) = read_u8(&mut input) {
+        let read = match tag {
+            RECORD => read_record(&mut input).map(|record| records.push(record)),
+            ENTRIES => read_path(&mut input).and_then(|dir| {
+                counts.push((dir, read_u64(&mut input)?));
+                Ok(())
+            }),
+            DONE => read_path(&mut input).map(|rel| {
+                records.drain(..).for_each(&mut *each);
+                entries.extend(counts.drain(..));
+                done.insert(rel);
+            }),
+            _ => Err(io::ErrorKind::InvalidData.into()),
+        };
+        if read.is_err() {
+            break;
+        }
+        if tag == DONE {
+            kept = input.stream_position()?;
+        }
+    }
+    Ok(kept)
+}
+
+/// A record's kept fields, as in the checkpoint and in `reuse`'s tree.
+pub(crate) fn write_record(out: &mut impl Write, record: &FileRecord) -> io::Result<()> {
+    let flags = if record.minified { MINIFIED } else { 0 }
+        | if record.placeholder { PLACEHOLDER } else { 0 }
+        | if record.uncounted { UNCOUNTED } else { 0 };
+    let executable = match record.executable {
+        None => 0,
+        Some(ExecKind::Script) => 1,
+        Some(ExecKind::Binary) => 2,
+        Some(ExecKind::Other) => 3,
+    };
+    let (dev, ino) = record.file_id.unwrap_or((u64::MAX, u64::MAX));
+    for n in [record.size, record.lines, record.compressed] {
+        out.write_all(&n.to_le_bytes())?;
+    }
+    write_time(out, record.mtime)?;
+    write_time(out, record.atime)?;
+    out.write_all(&[flags, executable])?;
+    out.write_all(&dev.to_le_bytes())?;
+    out.write_all(&ino.to_le_bytes())?;
+    out.write_all(&record.owner.map_or(u64::MAX, u64::from).to_le_bytes())?;
+    write_path(out, &record.path)
+}
+
+pub(crate) fn read_record(input: &mut impl Read) -> io::Result<FileRecord> {
+    let size = read_u64(input)?;
+    let lines = read_u64(input)?;
+    let compressed = read_u64(input)?;
+    let mtime = read_time(input)?;
+    let atime = read_time(input)?;
+    let flags = read_u8(input)?;
+    let executable = match read_u8(input)? {
+        1 => Some(ExecKind::Script),
+        2 => Some(ExecKind::Binary),
+        3 => Some(ExecKind::Other),
+        _ => None,
+    };
+    let dev = read_u64(input)?;
+    let ino = read_u64(input)?;
+    let owner = read_u64(input)?;
+    let path = read_path(input)?;
+    Ok(FileRecord {
+        parent: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        path,
+        size,
+        lines,
+        mtime,
+        compressed,
+        lang: None,
+        category: None,
+        kind: None,
+        matches: 0,
+        metrics: Vec::new(),
+        minified: flags & MINIFIED != 0,
+        placeholder: flags & PLACEHOLDER != 0,
+        uncounted: flags & UNCOUNTED != 0,
+        comments: None,
+        interpreter: None,
+        executable,
+        secret: None,
+        license: None,
+        file_id: (dev != u64::MAX || ino != u64::MAX).then_some((dev, ino)),
+        owner: u32::try_from(owner).ok(),
+        atime,
+        decompressed: None,
+    })
+}
+
+pub(crate) fn write_time(out: &mut impl Write, time: Option<SystemTime>) -> io::Result<()> {
+    let (secs, nanos) = match time.and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok()) {
+        Some(since) => (since.as_secs(), since.subsec_nanos()),
+        None => (u64::MAX, 0),
+    };
+    out.write_all(&secs.to_le_bytes())?;
+    out.write_all(&nanos.to_le_bytes())
+}
+
+pub(crate) fn read_time(input: &mut impl Read) -> io::Result<Option<SystemTime>> {
+    let secs = read_u64(input)?;
+    let mut nanos = [0; 4];
+    input.read_exact(&mut nanos)?;
+    Ok((secs != u64::MAX)
+        .then(|| SystemTime::UNIX_EPOCH + Duration::new(secs, u32::from_le_bytes(nanos))))
+}
+
+pub(crate) fn write_path(out: &mut impl Write, path: &Path) -> io::Result<()> {
+    let bytes = path_bytes(path);
+    out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    out.write_all(&bytes)
+}
+
+pub(crate) fn read_path(input: &mut impl Read) -> io::Result<PathBuf> {
+    let mut len = [0; 4];
+    input.read_exact(&mut len)?;
+    let mut bytes = vec![0; u32::from_le_bytes(len) as usize];
+    input.read_exact(&mut bytes)?;
+    Ok(path_from_bytes(bytes))
+}
+
+pub(crate) fn read_u8(input: &mut impl Read) -> io::Result<u8> {
+    let mut byte = [0];
+    input.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
@@ -0,0 +1,232 @@
+//! `--by-class`: files grouped into asset classes (code, docs, images,
+//! data, config, tests) for a composition view that doesn't need a table
+//! of raw extensions. The built-in mapping can be extended, or overridden
+//! entry by entry, from `tengok-classes.toml`:
+//!
+//! ```toml
+//! tests = ["e2e/**", "**/*.snap"]
+//! data = ["parquet", "sav"]
+//! ```
+//!
+//! Each entry is an extension, or a glob when it contains `/` or `*`
+//! (relative to the scanned root, as in budgets). The file's entries are
+//! tried before the built-in ones, and in each, globs before extensions, so
+//! `tests/fixtures/big.json` is a test and `data/big.json` data.
+
+use std::{collections::HashMap, fs, path::Path, sync::OnceLock};
+
+use globset::{GlobBuilder, GlobMatcher, GlobSet, GlobSetBuilder};
+
+use crate::scan::{extension_key, last_extension, normalize_ext};
+
+/// Looked up in the scanned root when `--classes` isn't given.
+pub const FILE_NAME: &str = "tengok-classes.toml";
+/// Files nothing matched.
+pub const OTHER: &str = "other";
+
+/// Where tests conventionally live.
+const TEST_GLOBS: &[&str] = &[
+    "**/tests/**",
+    "**/test/**",
+    "**/__tests__/**",
+    "**/spec/**",
+    "**/*_test.*",
+    "**/test_*.py",
+    "**/*.test.*",
+    "**/*.spec.*",
+];
+
+const DEFAULTS: &[(&str, &[&str])] = &[
+    ("tests", TEST_GLOBS),
+    (
+        "code",
+        &[
+            "rs", "c", "h", "cc", "cpp", "cxx", "hpp", "cs", "go", "java", "kt", "kts", "scala",
+            "swift", "m", "mm", "py", "rb", "php", "pl", "lua", "js", "mjs", "cjs", "jsx", "ts",
+            "tsx", "d.ts", "vue", "svelte", "dart", "ex", "exs", "erl", "hs", "ml", "clj", "r",
+            "jl", "zig", "nim", "sh", "bash", "zsh", "fish", "ps1", "sql", "html", "htm", "css",
+            "scss", "sass", "less",
+        ],
+    ),
+    (
+        "docs",
+        &[
+            "md", "markdown", "rst", "adoc", "asciidoc", "org", "txt", "tex", "pdf", "doc", "docx",
+            "odt", "rtf",
+        ],
+    ),
+    (
+        "images",
+        &[
+            "png", "jpg", "jpeg", "gif", "bmp", "webp", "avif", "heic", "ico", "svg", "tif",
+            "tiff", "psd",
+        ],
+    ),
+    (
+        "data",
+        &[
+            "csv", "tsv", "json", "jsonl", "ndjson", "xml", "parquet", "avro", "arrow", "npy",
+            "h5", "sqlite", "db", "xls", "xlsx",
+        ],
+    ),
+    (
+        "config",
+        &[
+            "toml",
+            "yaml",
+            "yml",
+            "ini",
+            "cfg",
+            "conf",
+            "env",
+            "properties",
+            "lock",
+            "**/Dockerfile",
+            "**/Makefile",
+            "**/.gitignore",
+            "**/.editorconfig",
+        ],
+    ),
+];
+
+/// Whether the file at `rel`, relative to the root, is a test by the
+/// built-in conventions.
+pub fn is_test(rel: &Path) -> bool {
+    static TESTS: OnceLock<GlobSet> = OnceLock::new();
+    TESTS
+        .get_or_init(|| {
+            let mut set = GlobSetBuilder::new();
+            for glob in TEST_GLOBS {
+                let glob = GlobBuilder::new(glob).literal_separator(true).build();
+                set.add(glob.expect("built-in test globs parse"));
+            }
+            set.build().expect("built-in test globs parse")
+        })
+        .is_match(rel)
+}
+
+/// One layer of the mapping: the file's, or the built-in one.
+#[derive(Debug, Clone, Default)]
+struct Layer {
+    globs: Vec<(GlobMatcher, String)>,
+    exts: HashMap<String, String>,
+}
+
+impl Layer {
+    fn add(&mut self, class: &str, entry: &str) -> Result<(), String> {
+        if entry.contains('/') || entry.contains('*') {
+            let glob = GlobBuilder::new(entry)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| format!("Invalid class glob {:?}: {}", entry, e))?;
+            self.globs.push((glob.compile_matcher(), class.to_string()));
+        } else {
+            self.exts
+                .entry(normalize_ext(entry))
+                .or_insert_with(|| class.to_string());
+        }
+        Ok(())
+    }
+
+    fn classify(&self, rel: &Path) -> Option<&str> {
+        if let Some((_, class)) = self.globs.iter().find(|(glob, _)| glob.is_match(rel)) {
+            return Some(class);
+        }
+        self.exts
+            .get(&extension_key(rel))
+            .or_else(|| last_extension(rel).and_then(|ext| self.exts.get(&ext)))
+            .map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Classes {
+    custom: Layer,
+    builtin: Layer,
+}
+
+impl Default for Classes {
+    fn default() -> Self {
+        let mut builtin = Layer::default();
+        for (class, entries) in DEFAULTS {
+            for entry in *entries {
+                builtin.add(class, entry).expect("built-in classes parse");
+            }
+        }
+        Classes {
+            custom: Layer::default(),
+            builtin,
+        }
+    }
+}
+
+impl Classes {
+    /// The built-in mapping under the one in `text`: each key a class, each
+    /// value an entry or a list of them.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let table: toml::Table = text
+            .parse()
+            .map_err(|e| format!("Invalid classes: {}", e))?;
+        let mut classes = Classes::default();
+        for (class, value) in &table {
+            let entries = match value {
+                toml::Value::Array(entries) => entries.iter().collect(),
+                other => vec![other],
+            };
+            for entry in entries {
+                let entry = entry.as_str().ok_or_else(|| {
+                    format!("Invalid class {:?}: expected extensions or globs", class)
+                })?;
+                classes.custom.add(class, entry)?;
+            }
+        }
+        Ok(classes)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// The class of the file at `rel`, relative to the root.
+    pub fn classify(&self, rel: &Path) -> &str {
+        self.custom
+            .classify(rel)
+            .or_else(|| self.builtin.classify(rel))
+            .unwrap_or(OTHER)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_entries_win_and_globs_beat_extensions() {
+        let classes = Classes::parse("tests = \"e2e/**\"\ndata = [\"sav\", \".RS\"]\n").unwrap();
+        let class = |path: &str| classes.classify(Path::new(path));
+        assert_eq!(class("src/main.rs"), "data");
+        assert_eq!(class("e2e/login.ts"), "tests");
+        assert_eq!(class("src/tests/fixture.json"), "tests");
+        assert_eq!(class("data/survey.SAV"), "data");
+        assert_eq!(class("types/index.d.ts"), "code");
+        assert_eq!(class("README.md"), "docs");
+        assert_eq!(class("Dockerfile"), "config");
+        assert_eq!(class("blob.bin"), OTHER);
+        assert!(Classes::parse("code = 3").is_err());
+    }
+
+    #[test]
+    fn finds_conventional_test_locations() {
+        for path in [
+            "tests/cli.rs",
+            "web/__tests__/app.js",
+            "pkg/server_test.go",
+            "src/app.spec.ts",
+        ] {
+            assert!(is_test(Path::new(path)), "{}", path);
+        }
+        assert!(!is_test(Path::new("src/testing.rs")));
+    }
+}
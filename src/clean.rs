@@ -0,0 +1,275 @@
+//! `--clean`: pick some of the largest entries after the report and delete
+//! them. The candidates are the biggest directories directly inside the
+//! root and the biggest files outside those, numbered; nothing is removed until
+//! the selection has been confirmed by typing `yes`. With `--dry-run` the
+//! selection is only listed; with `--trash` it goes to the platform trash
+//! (recycle bin) instead of being unlinked.
+
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::config::{Config, DEFAULT_TOP};
+use crate::format::{display_relative_path, format_num};
+use crate::lists;
+use crate::report::{Cell, Style, print_table};
+use crate::scan::Summary;
+
+struct Entry {
+    path: PathBuf,
+    dir: bool,
+    size: u64,
+    files: u64,
+}
+
+pub fn run(config: &Config, summary: &Summary) -> io::Result<()> {
+    let style = Style::new(config);
+    let mut entries = candidates(config, summary);
+    if entries.is_empty() {
+        println!("Nothing to clean");
+        return Ok(());
+    }
+    let rows: Vec<Vec<Cell>> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, e)| {
+            vec![
+                (i + 1).to_string().into(),
+                Cell::size(config, e.size),
+                format_num(e.files).into(),
+                name(config, e).into(),
+            ]
+        })
+        .collect();
+    println!();
+    print_table(
+        &style,
+        "Largest entries",
+        &["#", "Size", "Files", "Path"],
+        &rows,
+    );
+
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let answer = prompt(&mut input, "Remove which? (e.g. 1,3-5; empty to cancel) ")?;
+    let picked = match parse_selection(&answer, entries.len()) {
+        Ok(picked) => picked,
+        Err(err) => {
+            eprintln!("{}", err);
+            return Ok(());
+        }
+    };
+    if picked.is_empty() {
+        return Ok(());
+    }
+    // The listed sizes are what the scan kept; removing a directory takes
+    // its hidden, ignored and excluded contents too.
+    for &i in &picked {
+        let entry = &mut entries[i];
+        let (size, files) = match on_disk(&entry.path) {
+            Ok(measured) => measured,
+            Err(err) => {
+                eprintln!("could not measure {}: {}", name(config, entry), err);
+                println!("Nothing removed");
+                return Ok(());
+            }
+        };
+        if size > entry.size || files > entry.files {
+            println!(
+                "{} {} holds {} in {} files on disk, with what the scan left out",
+                style.label("note:"),
+                name(config, entry),
+                config.size(size),
+                format_num(files)
+            );
+        }
+        (entry.size, entry.files) = (size, files);
+    }
+    let size: u64 = picked.iter().map(|&i| entries[i].size).sum();
+    if config.dry_run {
+        for &i in &picked {
+            println!("would remove {}", name(config, &entries[i]));
+        }
+        println!(
+            "Would remove {} entries ({})",
+            picked.len(),
+            config.size(size)
+        );
+        return Ok(());
+    }
+    let answer = prompt(
+        &mut input,
+        &format!(
+            "{} {} entries ({}){}? Type yes to confirm: ",
+            if config.trash { "Move" } else { "Delete" },
+            picked.len(),
+            config.size(size),
+            if config.trash {
+                " to the trash"
+            } else {
+                " for good"
+            }
+        ),
+    )?;
+    if answer != "yes" {
+        println!("Nothing removed");
+        return Ok(());
+    }
+
+    let (mut removed, mut freed) = (0, 0);
+    for &i in &picked {
+        let entry = &entries[i];
+        let shown = name(config, entry);
+        match remove(config, &entry.path, entry.dir) {
+            Ok(()) => {
+                eprintln!(
+                    "{} {}",
+                    if config.trash { "trashed" } else { "removed" },
+                    shown
+                );
+                removed += 1;
+                freed += entry.size;
+            }
+            Err(err) => eprintln!("could not remove {}: {}", shown, err),
+        }
+    }
+    eprintln!(
+        "{} {} entries ({})",
+        if config.trash { "Trashed" } else { "Removed" },
+        format_num(removed),
+        config.size(freed)
+    );
+    Ok(())
+}
+
+/// The `--top` (default 10) largest directories directly inside the root,
+/// then as many of the largest files that aren't inside one of them.
+fn candidates(config: &Config, summary: &Summary) -> Vec<Entry> {
+    let limit = config.top.unwrap_or(DEFAULT_TOP);
+    let dirs: Vec<Entry> = lists::subdirs(summary, &config.root)
+        .into_iter()
+        .take(limit)
+        .map(|(path, stat)| Entry {
+            path,
+            dir: true,
+            size: stat.size,
+            files: stat.files,
+        })
+        .collect();
+    let mut files: Vec<Entry> = summary
+        .files
+        .iter()
+        .filter(|f| !dirs.iter().any(|d| f.path.starts_with(&d.path)))
+        .map(|f| Entry {
+            path: f.path.clone(),
+            dir: false,
+            size: f.size,
+            files: 1,
+        })
+        .collect();
+    files.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    files.truncate(limit);
+    dirs.into_iter().chain(files).collect()
+}
+
+/// Delete `path`, or move it to the trash with `--trash`. Shared with
+/// `--delete-junk`.
+pub fn remove(config: &Config, path: &Path, dir: bool) -> io::Result<()> {
+    // `--trash` is rejected on WASI.
+    if config.trash {
+        #[cfg(not(target_os = "wasi"))]
+        return trash::delete(path).map_err(io::Error::other);
+    }
+    if dir {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Bytes and files removing `path` deletes: everything below it, whatever
+/// the scan's filters hid. Symlinks count as themselves, since removal
+/// doesn't follow them.
+fn on_disk(path: &Path) -> io::Result<(u64, u64)> {
+    let meta = fs::symlink_metadata(path)?;
+    if !meta.is_dir() {
+        return Ok((meta.len(), 1));
+    }
+    let (mut size, mut files) = (0, 0);
+    for entry in fs::read_dir(path)? {
+        let (s, f) = on_disk(&entry?.path())?;
+        size += s;
+        files += f;
+    }
+    Ok((size, files))
+}
+
+fn name(config: &Config, entry: &Entry) -> String {
+    let shown = display_relative_path(&entry.path, &config.root);
+    if entry.dir {
+        format!("{}/", shown)
+    } else {
+        shown
+    }
+}
+
+fn prompt(input: &mut impl BufRead, question: &str) -> io::Result<String> {
+    print!("{}", question);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+/// `1,3-5` as zero-based indexes into `len` entries, in order and without
+/// repeats.
+fn parse_selection(input: &str, len: usize) -> Result<Vec<usize>, String> {
+    let mut picked = Vec::new();
+    for part in input.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (from, to) = part.split_once('-').unwrap_or((part, part));
+        let number = |s: &str| -> Result<usize, String> {
+            match s.trim().parse::<usize>() {
+                Ok(n) if (1..=len).contains(&n) => Ok(n - 1),
+                _ => Err(format!(
+                    "Not an entry: {} (expected 1 to {})",
+                    s.trim(),
+                    len
+                )),
+            }
+        };
+        for i in number(from)?..=number(to)? {
+            if !picked.contains(&i) {
+                picked.push(i);
+            }
+        }
+    }
+    Ok(picked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_selections() {
+        assert_eq!(parse_selection("1, 3-5,3", 6), Ok(vec![0, 2, 3, 4]));
+        assert_eq!(parse_selection("", 6), Ok(vec![]));
+        assert!(parse_selection("0", 6).is_err());
+        assert!(parse_selection("2-9", 6).is_err());
+        assert!(parse_selection("x", 6).is_err());
+    }
+
+    #[test]
+    fn on_disk_counts_what_filters_hide() {
+        let dir = std::env::temp_dir().join(format!("tengok-clean-{}", std::process::id()));
+        fs::create_dir_all(dir.join(".cache")).unwrap();
+        fs::write(dir.join("shown.txt"), "abc").unwrap();
+        fs::write(dir.join(".cache/hidden.bin"), vec![0; 1000]).unwrap();
+        fs::write(dir.join(".gitignore"), ".cache\n").unwrap();
+        let measured = on_disk(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(measured.unwrap(), (1010, 3));
+    }
+}
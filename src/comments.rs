@@ -0,0 +1,213 @@
+//! `--comments`: how many lines of each file are comments, for a density
+//! figure overall, per language and per file.
+//!
+//! Lines are classified from the language's comment markers alone, without
+//! tokenizing strings: a line counts as a comment when, once indented, it
+//! starts with a line comment or a block comment opener, or when it sits
+//! inside a block comment. Code with a trailing comment counts as code.
+//! Python docstrings count as comments. Prose (Markdown, plain text) and
+//! languages without comments (JSON) are left out of every density.
+
+use std::{
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+use crate::vfs::FileSystem;
+
+/// Files with at least this many lines are "large" for the
+/// least-commented list; shorter ones are often fine without comments.
+pub const LARGE_FILE_LINES: u64 = 200;
+
+struct Syntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+}
+
+const C_STYLE: Syntax = Syntax {
+    line: &["//"],
+    block: Some(("/*", "*/")),
+};
+const HASH: Syntax = Syntax {
+    line: &["#"],
+    block: None,
+};
+const MARKUP: Syntax = Syntax {
+    line: &[],
+    block: Some(("<!--", "-->")),
+};
+
+fn syntax(lang: &str) -> Option<Syntax> {
+    Some(match lang {
+        "Rust" | "Go" | "C" | "C++" | "C#" | "Java" | "Kotlin" | "Scala" | "Swift"
+        | "Objective-C" | "Objective-C++" | "JavaScript" | "TypeScript" | "Dart" | "Groovy"
+        | "Protocol Buffers" | "SCSS" | "Less" => C_STYLE,
+        "Zig" | "Sass" => Syntax {
+            line: &["//"],
+            block: None,
+        },
+        "CSS" => Syntax {
+            line: &[],
+            block: Some(("/*", "*/")),
+        },
+        "PHP" | "HCL" => Syntax {
+            line: &["//", "#"],
+            block: Some(("/*", "*/")),
+        },
+        "Shell" | "Fish" | "Perl" | "R" | "Elixir" | "Nim" | "YAML" | "TOML" | "Makefile"
+        | "CMake" | "Dockerfile" | "Starlark" | "Just" | "Awk" | "Tcl" => HASH,
+        "Python" => Syntax {
+            line: &["#"],
+            block: Some(("\"\"\"", "\"\"\"")),
+        },
+        "Ruby" => Syntax {
+            line: &["#"],
+            block: Some(("=begin", "=end")),
+        },
+        "Julia" => Syntax {
+            line: &["#"],
+            block: Some(("#=", "=#")),
+        },
+        "PowerShell" => Syntax {
+            line: &["#"],
+            block: Some(("<#", "#>")),
+        },
+        "Nix" => Syntax {
+            line: &["#"],
+            block: Some(("/*", "*/")),
+        },
+        "INI" => Syntax {
+            line: &[";", "#"],
+            block: None,
+        },
+        "SQL" => Syntax {
+            line: &["--"],
+            block: Some(("/*", "*/")),
+        },
+        "Lua" => Syntax {
+            line: &["--"],
+            block: Some(("--[[", "]]")),
+        },
+        "Haskell" => Syntax {
+            line: &["--"],
+            block: Some(("{-", "-}")),
+        },
+        "OCaml" => Syntax {
+            line: &[],
+            block: Some(("(*", "*)")),
+        },
+        "Erlang" | "TeX" => Syntax {
+            line: &["%"],
+            block: None,
+        },
+        "Clojure" => Syntax {
+            line: &[";"],
+            block: None,
+        },
+        "Batch" => Syntax {
+            line: &["::", "REM ", "rem ", "@REM ", "@rem "],
+            block: None,
+        },
+        "HTML" | "XML" | "Vue" | "Svelte" => MARKUP,
+        _ => return None,
+    })
+}
+
+/// Comment lines in the file at `path`, written in `lang`; `None` when the
+/// language has no known comment syntax.
+pub fn count(fs: &dyn FileSystem, path: &Path, lang: &str) -> Option<io::Result<u64>> {
+    let syntax = syntax(lang)?;
+    Some(
+        fs.open(path)
+            .and_then(|file| count_in(BufReader::new(file), &syntax)),
+    )
+}
+
+fn count_in(mut reader: impl BufRead, syntax: &Syntax) -> io::Result<u64> {
+    let mut comments = 0;
+    let mut in_block = false;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            return Ok(comments);
+        }
+        let line = String::from_utf8_lossy(&buf);
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let Some((open, close)) = syntax.block else {
+            comments += u64::from(syntax.line.iter().any(|m| text.starts_with(m)));
+            continue;
+        };
+        if in_block {
+            comments += 1;
+            in_block = !text.contains(close);
+        } else if let Some(rest) = text.strip_prefix(open) {
+            comments += 1;
+            in_block = !rest.contains(close);
+        } else if syntax.line.iter().any(|m| text.starts_with(m)) {
+            comments += 1;
+        } else if let Some(at) = text.find(open) {
+            // A block opened after code runs on into the following lines.
+            in_block = !text[at + open.len()..].contains(close);
+        }
+    }
+}
+
+/// Comment and total lines over files whose language has a comment syntax.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CommentStat {
+    pub comments: u64,
+    pub lines: u64,
+}
+
+impl CommentStat {
+    pub fn add(&mut self, comments: u64, lines: u64) {
+        self.comments += comments;
+        self.lines += lines;
+    }
+
+    pub fn remove(&mut self, comments: u64, lines: u64) {
+        self.comments -= comments;
+        self.lines -= lines;
+    }
+
+    pub fn density(&self) -> f64 {
+        density(self.comments, self.lines)
+    }
+}
+
+/// Comment lines as a share of all lines, in percent.
+pub fn density(comments: u64, lines: u64) -> f64 {
+    if lines == 0 {
+        0.0
+    } else {
+        comments as f64 / lines as f64 * 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comments(lang: &str, text: &str) -> u64 {
+        count_in(text.as_bytes(), &syntax(lang).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn classifies_line_and_block_comments() {
+        let rust = "//! Crate docs\n\nfn main() { // trailing\n    /* one */\n    /*\n     * two\n     */\n    let x = 1; /* opens\n    still */\n}\n";
+        assert_eq!(comments("Rust", rust), 6);
+
+        let python = "# header\n\"\"\"Docstring.\"\"\"\ndef f():\n    \"\"\"\n    More.\n    \"\"\"\n    return 1  # trailing\n";
+        assert_eq!(comments("Python", python), 5);
+
+        assert_eq!(comments("SQL", "-- a\nSELECT 1;\n"), 1);
+        assert_eq!(comments("HTML", "<!-- a\nb -->\n<p>x</p>\n"), 2);
+        assert!(syntax("JSON").is_none());
+        assert_eq!(density(1, 4), 25.0);
+        assert_eq!(density(0, 0), 0.0);
+    }
+}
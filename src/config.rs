@@ -0,0 +1,2158 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use humansize::{DECIMAL, format_size};
+use regex::bytes::Regex;
+use tracing::level_filters::LevelFilter;
+
+use crate::baseline::Baseline;
+use crate::budget::{self, Budget};
+use crate::classes::{self, Classes};
+use crate::expr::{Field, Metric};
+use crate::format::{self, format_num};
+use crate::groups::Groups;
+use crate::i18n::Lang;
+use crate::junk;
+use crate::only::Only;
+use crate::plugin::Plugin;
+use crate::scan::normalize_ext;
+use crate::submodules;
+use crate::theme::{self, Theme};
+use crate::throttle::Throttle;
+use crate::vfs;
+
+pub const DEFAULT_MAX_LINE_BYTES: u64 = 5 * 1024 * 1024; // ~5MB
+pub const DEFAULT_TOP: usize = 10;
+pub const DEFAULT_TINY_BYTES: u64 = 4 * 1024;
+/// Direct entries past which many tools and filesystems slow down.
+pub const DEFAULT_MAX_ENTRIES: u64 = 100_000;
+/// Records queued between the walker threads and the aggregator: enough
+/// to ride out a slow terminal redraw, a few MB at most.
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 8192;
+/// Directory names `--deps` counts as dependencies or build output.
+pub const DEFAULT_DEP_DIRS: &[&str] = &[
+    "node_modules",
+    "bower_components",
+    "vendor",
+    "target",
+    "build",
+    "dist",
+    "out",
+    ".venv",
+    "venv",
+    "__pycache__",
+    ".gradle",
+    "Pods",
+];
+
+/// How reports show points in time (`--time-format`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// `2024-02-29 19:34`.
+    Default,
+    /// `2024-02-29T19:34:56+07:00`.
+    Iso,
+    /// Seconds since the Unix epoch.
+    Epoch,
+}
+
+impl TimeFormat {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "default" => Ok(Self::Default),
+            "iso" => Ok(Self::Iso),
+            "epoch" => Ok(Self::Epoch),
+            _ => Err(format!(
+                "Unknown time format: {} (expected default, iso or epoch)",
+                value
+            )),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Iso => "iso",
+            Self::Epoch => "epoch",
+        }
+    }
+}
+
+/// Ordering applied to every list output (top files, top dirs, extensions).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Size,
+    Lines,
+    Name,
+    Mtime,
+    Count,
+}
+
+impl SortKey {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "size" => Ok(Self::Size),
+            "lines" => Ok(Self::Lines),
+            "name" => Ok(Self::Name),
+            "mtime" => Ok(Self::Mtime),
+            "count" => Ok(Self::Count),
+            _ => Err(format!(
+                "Unknown sort key: {} (expected size, lines, name, mtime or count)",
+                value
+            )),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Size => "size",
+            Self::Lines => "lines",
+            Self::Name => "name",
+            Self::Mtime => "mtime",
+            Self::Count => "count",
+        }
+    }
+}
+
+/// A core summary row, as picked by `--fields`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Row {
+    Files,
+    Size,
+    Lines,
+    LargestDir,
+    BusiestDir,
+    DeepestDir,
+    MaxLinesFile,
+    LargestFile,
+    AvgSize,
+    AvgLines,
+    NewestFile,
+}
+
+impl Row {
+    /// Shown without `--fields`; the rest are opt-in.
+    pub const DEFAULT: [Row; 8] = [
+        Row::Files,
+        Row::Size,
+        Row::Lines,
+        Row::LargestDir,
+        Row::BusiestDir,
+        Row::DeepestDir,
+        Row::MaxLinesFile,
+        Row::LargestFile,
+    ];
+
+    /// `--fields` value: the rows listed, or with a leading `+`, the
+    /// default rows and the ones listed.
+    fn parse_list(value: &str) -> Result<Vec<Self>, String> {
+        let (mut rows, list) = match value.strip_prefix('+') {
+            Some(list) => (Row::DEFAULT.to_vec(), list),
+            None => (Vec::new(), value),
+        };
+        for name in list.split(',') {
+            let row = Row::parse(name.trim())?;
+            if !rows.contains(&row) {
+                rows.push(row);
+            }
+        }
+        Ok(rows)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Files => "files",
+            Self::Size => "size",
+            Self::Lines => "lines",
+            Self::LargestDir => "largest_dir",
+            Self::BusiestDir => "busiest_dir",
+            Self::DeepestDir => "deepest_dir",
+            Self::MaxLinesFile => "max_lines_file",
+            Self::LargestFile => "largest_file",
+            Self::AvgSize => "avg_size",
+            Self::AvgLines => "avg_lines",
+            Self::NewestFile => "newest_file",
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "files" => Ok(Self::Files),
+            "size" => Ok(Self::Size),
+            "lines" => Ok(Self::Lines),
+            "largest_dir" => Ok(Self::LargestDir),
+            "busiest_dir" => Ok(Self::BusiestDir),
+            "deepest_dir" => Ok(Self::DeepestDir),
+            "max_lines_file" => Ok(Self::MaxLinesFile),
+            "largest_file" => Ok(Self::LargestFile),
+            "avg_size" => Ok(Self::AvgSize),
+            "avg_lines" => Ok(Self::AvgLines),
+            "newest_file" => Ok(Self::NewestFile),
+            _ => Err(format!(
+                "Unknown field: {} (expected files, size, lines, largest_dir, busiest_dir, deepest_dir, max_lines_file, largest_file, avg_size, avg_lines or newest_file)",
+                value
+            )),
+        }
+    }
+}
+
+/// How file contents are read for line counting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineBackend {
+    BufRead,
+    Memchr,
+    Mmap,
+}
+
+impl LineBackend {
+    pub const ALL: [Self; 3] = [Self::BufRead, Self::Memchr, Self::Mmap];
+
+    fn parse(value: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|b| b.as_str() == value)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown line backend: {} (expected bufread, memchr or mmap)",
+                    value
+                )
+            })
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::BufRead => "bufread",
+            Self::Memchr => "memchr",
+            Self::Mmap => "mmap",
+        }
+    }
+}
+
+/// `--follow-links`: where a file reached through a symlink is counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowLinks {
+    /// Under the link, as `du -L` does; a target inside the root then
+    /// counts once per path to it.
+    Link,
+    /// Once, where it really lives: targets inside the root are left to the
+    /// walk to find, targets outside it appear under their own path.
+    Target,
+}
+
+impl FollowLinks {
+    pub const ALL: [Self; 2] = [Self::Link, Self::Target];
+
+    fn parse(value: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|m| m.as_str() == value)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown --follow-links mode: {} (expected link or target)",
+                    value
+                )
+            })
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Link => "link",
+            Self::Target => "target",
+        }
+    }
+}
+
+/// `--cache-key`: what tells the `--dupes` hash cache a file is unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKey {
+    /// Size and modification time.
+    Mtime,
+    /// Size and a hash of the first and last 64 KB, for mounts whose mtimes
+    /// can't be trusted (some SMB servers).
+    Fingerprint,
+}
+
+impl CacheKey {
+    pub const ALL: [Self; 2] = [Self::Mtime, Self::Fingerprint];
+
+    fn parse(value: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|k| k.as_str() == value)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown --cache-key: {} (expected mtime or fingerprint)",
+                    value
+                )
+            })
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Mtime => "mtime",
+            Self::Fingerprint => "fingerprint",
+        }
+    }
+}
+
+/// Line-counting semantics. The defaults match `read_until(b'\n')`: every
+/// `\n` ends a line and a trailing unterminated line counts too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRules {
+    /// Count a final line that has no terminator (`--terminated-only` off).
+    pub unterminated: bool,
+    /// Skip lines that are empty or whitespace only (`--nonblank`).
+    pub nonblank: bool,
+    /// Treat a lone `\r` as a line break too (`--cr-lines`).
+    pub cr: bool,
+}
+
+impl Default for LineRules {
+    fn default() -> Self {
+        Self {
+            unterminated: true,
+            nonblank: false,
+            cr: false,
+        }
+    }
+}
+
+/// What gets written to stdout once the scan finishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    Report,
+    Json,
+    /// Every file, one per line (`--list`).
+    List,
+    /// Every file as a JSON object, one per line (`--ndjson`).
+    NdJson,
+    /// The directory hierarchy as nested JSON (`--json-tree`).
+    JsonTree,
+    /// One `dir;sub;file size` line per file, for flame graphs (`--folded`).
+    Folded,
+    /// The totals on a single line (`--oneline`).
+    Oneline,
+    /// Prometheus metrics in the OpenMetrics text format (`--openmetrics`).
+    OpenMetrics,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub root: PathBuf,
+    /// Every PATH given on the command line; `root` is the last one.
+    pub roots: Vec<PathBuf>,
+    pub plain: bool,
+    /// Report colors (`--theme`, else `TENGOK_THEME`).
+    pub theme: Theme,
+    /// Language of the report's words (`--lang`, else the locale).
+    pub lang: Lang,
+    /// `--heat`: the sizes from which values are shown warm and hot.
+    pub heat: Option<(u64, u64)>,
+    /// `--bytes`: sizes as exact byte counts rather than in kB, MB, ...
+    pub bytes: bool,
+    /// `--ascii`: plain ASCII for the box, the row labels' arrows and the
+    /// ellipsis, for terminals without Unicode.
+    pub ascii: bool,
+    /// `--utc`: times in UTC rather than the local timezone.
+    pub utc: bool,
+    pub time_format: TimeFormat,
+    pub skip_lines: bool,
+    /// `--metadata-only`: no file is opened at all; sizes and counts only.
+    pub metadata_only: bool,
+    pub force_lines: bool,
+    pub max_line_bytes: u64,
+    /// Per-extension (lowercase) replacements for `max_line_bytes`.
+    pub ext_max_line_bytes: HashMap<String, u64>,
+    /// What counts as a line; see `scan::line_count`.
+    pub lines: LineRules,
+    pub line_backend: LineBackend,
+    /// Walker threads; 0 lets the walker pick.
+    pub threads: usize,
+    /// `--dupes` hashing threads; 0 is one per CPU.
+    pub hash_threads: usize,
+    /// Walk serially, one directory at a time, with bigger reads: faster
+    /// than the parallel walker on latency-bound network shares.
+    pub sequential: bool,
+    /// Roughly how much RAM the scan may use; kept file records beyond
+    /// their share are spilled to a temporary file.
+    pub memory_limit: Option<u64>,
+    /// Checkpoint the walk in the root's store, and pick up from the last
+    /// checkpoint an interrupted `--resume` scan left.
+    pub resume: bool,
+    /// Stand the stored records of the last `--reuse-unchanged` scan in
+    /// for the files of directories that look unchanged.
+    pub reuse_unchanged: bool,
+    /// Records the walker threads may queue before they wait for the
+    /// aggregator.
+    pub channel_capacity: usize,
+    /// Follow symlinks, attributing what they point to as given; `None`
+    /// passes over them.
+    pub follow_links: Option<FollowLinks>,
+    /// How often the stderr progress line is redrawn; `None` for the
+    /// default, which depends on whether stderr is a terminal.
+    pub progress_interval: Option<Duration>,
+    /// Read bandwidth cap for line counting, shared by the walker threads.
+    pub throttle: Option<Arc<Throttle>>,
+    /// How often to retry a stat, read_dir or line count that failed with
+    /// a transient error (see `scan::is_transient`).
+    pub retries: u32,
+    pub top: Option<usize>,
+    /// List this many files with the most lines, whatever `--sort` says.
+    pub top_lines: Option<usize>,
+    /// `--min-lines`/`--max-lines`: the per-file lists keep only files with
+    /// a line count in this range.
+    pub min_lines: Option<u64>,
+    pub max_lines: Option<u64>,
+    pub by_ext: bool,
+    pub by_lang: bool,
+    /// Count comment lines for comment-density figures.
+    pub comments: bool,
+    /// Group extensionless scripts by their shebang interpreter.
+    pub scripts: bool,
+    /// List executables that aren't shebang scripts.
+    pub audit: bool,
+    /// Flag files that look like committed credentials.
+    pub secrets: bool,
+    /// Identify LICENSE, COPYING and NOTICE files.
+    pub licenses: bool,
+    /// Report READMEs and docs against code per top-level directory.
+    pub docs: bool,
+    /// Walk hidden files and directories too.
+    pub hidden: bool,
+    /// Count OS junk files (`.DS_Store`, `Thumbs.db`, ...), hidden or not.
+    pub junk: bool,
+    /// Remove the junk files after the scan.
+    pub delete_junk: bool,
+    /// Offer the largest entries for deletion after the report (`--clean`).
+    pub clean: bool,
+    /// With `--clean`, only list what would be removed; without it, list
+    /// what a scan would walk instead of scanning (see `plans`).
+    pub dry_run: bool,
+    /// Move what `--clean` and `--delete-junk` remove to the trash.
+    pub trash: bool,
+    /// Walk the root's `.git` and break its size down.
+    pub include_git: bool,
+    /// How files in git submodules count.
+    pub submodules: submodules::Mode,
+    /// Walk files that `.gitignore` and `.ignore` exclude too.
+    pub no_ignore: bool,
+    /// Walk virtual filesystems (`/proc`, `/sys`, cgroups) mounted in the
+    /// root instead of skipping them.
+    pub virtual_fs: bool,
+    /// Measure the layers behind an overlayfs root.
+    pub overlay_layers: bool,
+    /// Directory names whose contents count as dependencies rather than
+    /// source; `None` without `--deps`.
+    pub dep_dirs: Option<Vec<String>>,
+    /// Summarize hidden entries on their own; needs `hidden`.
+    pub dotfiles: bool,
+    /// `--stale`: list directories with nothing modified for this long.
+    pub stale: Option<Duration>,
+    /// `--cold`: report bytes not read for this long.
+    pub cold: Option<Duration>,
+    /// `--no-ignore` in a git repository: collect build output and caches
+    /// to suggest for `.gitignore`.
+    pub suggest_ignore: bool,
+    /// Append those suggestions to the root `.gitignore` (`--apply`).
+    pub apply: bool,
+    /// Chart files and bytes per directory depth.
+    pub by_depth: bool,
+    /// Bytes and files per file owner (Unix).
+    pub by_owner: bool,
+    /// Bytes and files per device, named by where it's mounted (Unix).
+    pub by_mount: bool,
+    /// The asset-class mapping, with `--by-class` or `--classes`.
+    pub classes: Option<Classes>,
+    /// The `--group-by` globs, when given.
+    pub groups: Option<Groups>,
+    /// Split code lines into tests and the rest, per language.
+    pub tests: bool,
+    /// Print hints under the summary box (`--no-hints` clears it).
+    pub hints: bool,
+    /// Add per-top-level-directory series to the metrics.
+    pub dir_metrics: bool,
+    /// `--statsd`: push the summary as DogStatsD gauges to this host:port.
+    pub statsd: Option<String>,
+    /// `--notify-webhook`: POST an alert here when a check fails.
+    pub notify_webhook: Option<String>,
+    /// With `--only-mine`, the invoking user's uid: files anyone else owns
+    /// are left out.
+    pub only_mine: Option<u32>,
+    pub sort: Option<SortKey>,
+    pub reverse: bool,
+    pub deterministic: bool,
+    /// Time each scan phase per walker thread.
+    pub profile: bool,
+    /// Core summary rows to show; `Row::DEFAULT` without `--fields`.
+    pub fields: Vec<Row>,
+    /// Replaces the report's "Folder Summary: PATH" header.
+    pub title: Option<String>,
+    /// A line shown under the header.
+    pub note: Option<String>,
+    pub output: Output,
+    /// `--human-stderr`: the box report on stderr too, beside a machine
+    /// output on stdout.
+    pub human_stderr: bool,
+    /// The page of `--list` and `--ndjson` rows: skip `offset`, then show
+    /// at most `limit`.
+    pub offset: usize,
+    pub limit: Option<usize>,
+    pub watch: bool,
+    /// `--growth-window`: how far back `--watch` looks for the fastest
+    /// growing directory.
+    pub growth_window: Duration,
+    /// Prompt for drill-down keys after the report (`--explore`).
+    pub explore: bool,
+    /// Open the max-lines file in `$VISUAL`/`$EDITOR` when done (`--open-max`).
+    pub open_max: bool,
+    pub compress_estimate: bool,
+    /// Count the lines of `.zst`, `.gz` and `.bz2` files through a
+    /// decompressor.
+    pub decompress: bool,
+    pub mime: bool,
+    pub linguist: bool,
+    pub exclude_generated: bool,
+    /// Files strictly smaller than this many bytes count as tiny.
+    pub tiny: Option<u64>,
+    /// Add distribution statistics (mean, median) to the report.
+    pub verbose: bool,
+    /// Tally matches of this regex while counting lines.
+    pub count_pattern: Option<Regex>,
+    /// Also write per-file records to this Parquet file.
+    pub parquet: Option<PathBuf>,
+    /// Also write the `--json` document here, as a binary snapshot.
+    pub snapshot: Option<PathBuf>,
+    /// Most verbose diagnostics to log; off unless `--log-level` or
+    /// `--log-file`, which defaults to info.
+    pub log_level: LevelFilter,
+    /// Append log lines here instead of stderr.
+    pub log_file: Option<PathBuf>,
+    /// `--plugin` metrics, in command-line order.
+    pub plugins: Vec<Arc<Plugin>>,
+    /// `--metric` expressions, in command-line order.
+    pub metrics: Vec<Metric>,
+    /// From `--budgets`, else the root's `tengok-budgets.toml`, if any.
+    pub budgets: Vec<Budget>,
+    /// `--baseline` snapshot the report shows deltas against.
+    pub baseline: Option<Baseline>,
+    /// `--max-growth` in percent; fails the run past it.
+    pub max_growth: Option<f64>,
+    /// `--exclude-dir` names, pruned wherever they appear in the tree.
+    pub exclude_dirs: Vec<String>,
+    /// `--only` globs; everything else is skipped.
+    pub only: Option<Only>,
+    /// Count the lines of minified and bundled assets too.
+    pub include_minified: bool,
+    /// Add extended attribute and resource fork bytes to file sizes (macOS).
+    pub xattrs: bool,
+    /// Flag file and directory names that aren't portable to other platforms.
+    pub lint_names: bool,
+    /// `--dup-names`: report basenames shared across directories, for
+    /// these extensions only when non-empty.
+    pub dup_names: Option<Vec<String>>,
+    /// Extensions to find the directory holding the most bytes of
+    /// (`--largest-dir-for`), normalized.
+    pub largest_dir_for: Vec<String>,
+    /// List files with the same contents (`--dupes`).
+    pub dupes: bool,
+    /// Direct entries above which a directory is reported as crowded; 0
+    /// turns the check off.
+    pub max_entries: u64,
+    /// Report which ignore rules pruned the most (`--profile-ignores`).
+    pub profile_ignores: bool,
+    pub cache_key: CacheKey,
+    /// List paths that differ only by case.
+    pub case_collisions: bool,
+    /// Exit with status 3 when `--lint-names` or `--case-collisions` finds
+    /// anything.
+    pub strict: bool,
+    /// Print nothing; the exit status says how the scan and its checks went.
+    pub check: bool,
+    /// The arguments the options were parsed from, kept for the JSON
+    /// output's `scan.options`.
+    pub args: Vec<String>,
+}
+
+impl Config {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let given: Vec<String> = args.into_iter().collect();
+        let mut args: VecDeque<String> = given.iter().cloned().collect();
+        let mut roots: Vec<PathBuf> = Vec::new();
+        let mut plain = false;
+        let mut bytes = false;
+        let mut ascii = false;
+        let mut human_stderr = false;
+        let mut utc = false;
+        let mut time_format = TimeFormat::Default;
+        let mut theme = None;
+        let mut heat = None;
+        let mut lang = None;
+        let mut skip_lines = false;
+        let mut metadata_only = false;
+        let mut force_lines = false;
+        let mut max_line_bytes = DEFAULT_MAX_LINE_BYTES;
+        let mut ext_max_line_bytes = HashMap::new();
+        let mut lines = LineRules::default();
+        let mut line_backend = LineBackend::BufRead;
+        let mut threads = 0;
+        let mut hash_threads = 0;
+        let mut retries = 0;
+        let mut throttle = None;
+        let mut sequential = false;
+        let mut memory_limit = None;
+        let mut resume = false;
+        let mut reuse_unchanged = false;
+        let mut channel_capacity = DEFAULT_CHANNEL_CAPACITY;
+        let mut follow_links = None;
+        let mut progress_interval = None;
+        let mut top = None;
+        let mut top_lines = None;
+        let mut min_lines = None;
+        let mut max_lines = None;
+        let mut by_ext = false;
+        let mut by_lang = false;
+        let mut comments = false;
+        let mut scripts = false;
+        let mut audit = false;
+        let mut secrets = false;
+        let mut licenses = false;
+        let mut docs = false;
+        let mut hidden = false;
+        let mut junk = false;
+        let mut delete_junk = false;
+        let mut clean = false;
+        let mut dry_run = false;
+        let mut trash = false;
+        let mut include_git = false;
+        let mut submodules = submodules::Mode::default();
+        let mut no_ignore = false;
+        let mut virtual_fs = false;
+        let mut overlay_layers = false;
+        let mut dep_dirs: Option<Vec<String>> = None;
+        let mut dotfiles = false;
+        let mut stale = None;
+        let mut cold = None;
+        let mut growth_window = None;
+        let mut apply = false;
+        let mut by_depth = false;
+        let mut by_owner = false;
+        let mut by_mount = false;
+        let mut by_class = false;
+        let mut tests = false;
+        let mut classes_file = None;
+        let mut group_globs: Vec<String> = Vec::new();
+        let mut hints = true;
+        let mut dir_metrics = false;
+        let mut statsd = None;
+        let mut notify_webhook = None;
+        let mut only_mine = None;
+        let mut sort = None;
+        let mut reverse = false;
+        let mut deterministic = false;
+        let mut profile = false;
+        let mut fields = Row::DEFAULT.to_vec();
+        let mut title = None;
+        let mut note = None;
+        let mut output = Output::Report;
+        let mut offset = None;
+        let mut limit = None;
+        let mut watch = false;
+        let mut explore = false;
+        let mut open_max = false;
+        let mut compress_estimate = false;
+        let mut decompress = false;
+        let mut mime = false;
+        let mut linguist = false;
+        let mut exclude_generated = false;
+        let mut tiny = None;
+        let mut verbose = false;
+        let mut count_pattern = None;
+        let mut parquet = None;
+        let mut snapshot = None;
+        let mut log_level = None;
+        let mut log_file = None;
+        let mut plugins = Vec::new();
+        let mut metrics = Vec::new();
+        let mut budgets_file = None;
+        let mut baseline = None;
+        let mut max_growth = None;
+        let mut exclude_dirs = Vec::new();
+        let mut only = Vec::new();
+        let mut include_minified = false;
+        let mut xattrs = false;
+        let mut lint_names = false;
+        let mut dup_names: Option<Vec<String>> = None;
+        let mut largest_dir_for: Vec<String> = Vec::new();
+        let mut dupes = false;
+        let mut max_entries = DEFAULT_MAX_ENTRIES;
+        let mut profile_ignores = false;
+        let mut cache_key = None;
+        let mut case_collisions = false;
+        let mut strict = false;
+        let mut check = false;
+
+        while let Some(arg) = args.pop_front() {
+            if arg == "--" {
+                roots.extend(args.drain(..).map(PathBuf::from));
+                break;
+            }
+            if let Some(shorts) = arg.strip_prefix('-').filter(|s| is_short_cluster(s)) {
+                for (i, long) in expand_shorts(shorts)?.into_iter().enumerate() {
+                    args.insert(i, long);
+                }
+                continue;
+            }
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if arg.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut inline_taken = false;
+            let mut value = |name: &str| -> Result<String, String> {
+                inline_taken = inline.is_some();
+                inline
+                    .clone()
+                    .or_else(|| args.pop_front())
+                    .ok_or_else(|| format!("{} requires a value", name))
+            };
+
+            match flag {
+                "--plain" | "--no-colors" => plain = true,
+                "--theme" => theme = Some(Theme::parse(&value(flag)?)?),
+                "--heat" => heat = Some(DEFAULT_HEAT),
+                "--bytes" => bytes = true,
+                "--ascii" => ascii = true,
+                "--utc" => utc = true,
+                "--time-format" => time_format = TimeFormat::parse(&value(flag)?)?,
+                "--heat-at" => {
+                    let spec = value(flag)?;
+                    let parsed = spec
+                        .split_once(',')
+                        .and_then(|(warm, hot)| Some((parse_bytes(warm)?, parse_bytes(hot)?)));
+                    heat = match parsed {
+                        Some((warm, hot)) if warm < hot => Some((warm, hot)),
+                        _ => {
+                            return Err(format!(
+                                "--heat-at takes two increasing sizes, e.g. 100M,1G: {:?}",
+                                spec
+                            ));
+                        }
+                    };
+                }
+                "--lang" => lang = Some(Lang::parse(&value(flag)?)?),
+                "--no-lines" => skip_lines = true,
+                "--metadata-only" => {
+                    metadata_only = true;
+                    skip_lines = true;
+                }
+                "--force-lines" => {
+                    force_lines = true;
+                    skip_lines = false;
+                }
+                "--max-line-bytes" => {
+                    max_line_bytes = parse_bytes(&value(flag)?)
+                        .ok_or_else(|| "Unable to parse --max-line-bytes".to_string())?;
+                }
+                "--ext-max-line-bytes" => {
+                    for pair in value(flag)?.split(',') {
+                        let (ext, limit) = pair
+                            .split_once('=')
+                            .and_then(|(ext, n)| Some((ext, parse_bytes(n)?)))
+                            .ok_or_else(|| {
+                                format!("Unable to parse --ext-max-line-bytes: {}", pair)
+                            })?;
+                        ext_max_line_bytes.insert(normalize_ext(ext), limit);
+                    }
+                }
+                "--line-backend" => line_backend = LineBackend::parse(&value(flag)?)?,
+                "--threads" => {
+                    threads = value(flag)?
+                        .parse()
+                        .map_err(|_| "Unable to parse --threads".to_string())?;
+                }
+                "--hash-threads" => {
+                    hash_threads = value(flag)?
+                        .parse()
+                        .map_err(|_| "Unable to parse --hash-threads".to_string())?;
+                }
+                "--sequential" => sequential = true,
+                "--channel-capacity" => {
+                    channel_capacity =
+                        value(flag)?
+                            .parse()
+                            .ok()
+                            .filter(|n| *n > 0)
+                            .ok_or_else(|| {
+                                "--channel-capacity needs a number of records".to_string()
+                            })?;
+                }
+                "--resume" => resume = true,
+                "--reuse-unchanged" => reuse_unchanged = true,
+                "--memory-limit" => {
+                    memory_limit = Some(
+                        parse_bytes(&value(flag)?)
+                            .filter(|bytes| *bytes > 0)
+                            .ok_or_else(|| "--memory-limit needs a size, e.g. 2G".to_string())?,
+                    );
+                }
+                "--progress-interval" => {
+                    progress_interval = value(flag)?
+                        .parse()
+                        .ok()
+                        .filter(|ms| *ms > 0)
+                        .map(Duration::from_millis)
+                        .map(Some)
+                        .ok_or_else(|| {
+                            "--progress-interval needs milliseconds, e.g. 500".to_string()
+                        })?;
+                }
+                "--follow-links" => {
+                    if cfg!(target_os = "wasi") {
+                        return Err("--follow-links is not supported on WASI".into());
+                    }
+                    follow_links = Some(FollowLinks::parse(&value(flag)?)?);
+                }
+                "--throttle" => throttle = Some(Arc::new(Throttle::parse(&value(flag)?)?)),
+                "--retries" => {
+                    retries = value(flag)?
+                        .parse()
+                        .map_err(|_| "Unable to parse --retries".to_string())?;
+                }
+                "--terminated-only" => lines.unterminated = false,
+                "--nonblank" => lines.nonblank = true,
+                "--cr-lines" => lines.cr = true,
+                "--top" => {
+                    top = Some(
+                        value(flag)?
+                            .parse()
+                            .map_err(|_| "Unable to parse --top".to_string())?,
+                    );
+                }
+                "--top-lines" => {
+                    top_lines = Some(
+                        value(flag)?
+                            .parse()
+                            .map_err(|_| "Unable to parse --top-lines".to_string())?,
+                    );
+                }
+                "--min-lines" | "--max-lines" => {
+                    let lines = Some(
+                        value(flag)?
+                            .replace(',', "")
+                            .parse()
+                            .map_err(|_| format!("Unable to parse {}", flag))?,
+                    );
+                    if flag == "--min-lines" {
+                        min_lines = lines;
+                    } else {
+                        max_lines = lines;
+                    }
+                }
+                "--by-ext" => by_ext = true,
+                "--by-lang" => by_lang = true,
+                "--comments" => comments = true,
+                "--scripts" => scripts = true,
+                "--audit" => audit = true,
+                "--secrets" => secrets = true,
+                "--licenses" => licenses = true,
+                "--docs" => docs = true,
+                "--hidden" => hidden = true,
+                "--junk" => junk = true,
+                "--include-git" => include_git = true,
+                "--submodules" => {
+                    submodules = submodules::Mode::parse(&value(flag)?).ok_or_else(|| {
+                        "--submodules must be include, exclude or separate".to_string()
+                    })?
+                }
+                "--delete-junk" => {
+                    junk = true;
+                    delete_junk = true;
+                }
+                "--no-ignore" => no_ignore = true,
+                "--virtual-fs" => virtual_fs = true,
+                "--overlay-layers" => overlay_layers = true,
+                "--deps" => {
+                    dep_dirs = dep_dirs
+                        .or_else(|| Some(DEFAULT_DEP_DIRS.iter().map(|d| d.to_string()).collect()))
+                }
+                "--dep-dirs" => {
+                    dep_dirs = Some(value(flag)?.split(',').map(str::to_string).collect())
+                }
+                "--dotfiles" => dotfiles = true,
+                "--stale" => {
+                    let value = value(flag)?;
+                    stale = Some(parse_duration(&value).ok_or_else(|| {
+                        format!(
+                            "Invalid --stale duration: {} (expected e.g. 90d, 12h, 2w, 1y)",
+                            value
+                        )
+                    })?);
+                }
+                "--cold" => {
+                    let value = value(flag)?;
+                    cold = Some(parse_duration(&value).ok_or_else(|| {
+                        format!(
+                            "Invalid --cold duration: {} (expected e.g. 90d, 12h, 2w, 1y)",
+                            value
+                        )
+                    })?);
+                }
+                "--growth-window" => {
+                    let value = value(flag)?;
+                    growth_window = Some(parse_duration(&value).ok_or_else(|| {
+                        format!(
+                            "Invalid --growth-window duration: {} (expected e.g. 5m, 30s, 1h)",
+                            value
+                        )
+                    })?);
+                }
+                "--apply" => apply = true,
+                "--clean" => clean = true,
+                "--dry-run" => dry_run = true,
+                "--trash" => {
+                    if cfg!(target_os = "wasi") {
+                        return Err("--trash is not supported on WASI".into());
+                    }
+                    trash = true;
+                }
+                "--by-depth" => by_depth = true,
+                "--no-hints" => hints = false,
+                "--by-class" => by_class = true,
+                "--tests" => tests = true,
+                "--classes" => classes_file = Some(PathBuf::from(value(flag)?)),
+                "--group-by" => group_globs.push(value(flag)?),
+                "--only-mine" => {
+                    only_mine = Some(
+                        vfs::current_uid()
+                            .ok_or_else(|| "--only-mine is only supported on Unix".to_string())?,
+                    );
+                }
+                "--by-owner" => {
+                    if cfg!(not(unix)) {
+                        return Err("--by-owner is only supported on Unix".into());
+                    }
+                    by_owner = true;
+                }
+                "--by-mount" => {
+                    if cfg!(not(unix)) {
+                        return Err("--by-mount is only supported on Unix".into());
+                    }
+                    by_mount = true;
+                }
+                "--sort" => sort = Some(SortKey::parse(&value(flag)?)?),
+                "--reverse" => reverse = true,
+                "--deterministic" => deterministic = true,
+                "--profile" => profile = true,
+                "--profile-ignores" => profile_ignores = true,
+                "--fields" => fields = Row::parse_list(&value(flag)?)?,
+                "--title" => title = Some(value(flag)?),
+                "--note" => note = Some(value(flag)?),
+                "--json" => output = Output::Json,
+                "--list" => output = Output::List,
+                "--ndjson" => output = Output::NdJson,
+                "--json-tree" => output = Output::JsonTree,
+                "--folded" => output = Output::Folded,
+                "--offset" => {
+                    offset = Some(
+                        value(flag)?
+                            .parse()
+                            .map_err(|_| "--offset needs a number of rows".to_string())?,
+                    );
+                }
+                "--limit" => {
+                    limit = Some(
+                        value(flag)?
+                            .parse()
+                            .map_err(|_| "--limit needs a number of rows".to_string())?,
+                    );
+                }
+                "--oneline" => output = Output::Oneline,
+                "--openmetrics" => output = Output::OpenMetrics,
+                "--human-stderr" => human_stderr = true,
+                "--dir-metrics" => dir_metrics = true,
+                "--statsd" => {
+                    if cfg!(target_os = "wasi") {
+                        return Err("--statsd is not supported on WASI".into());
+                    }
+                    statsd = Some(value(flag)?);
+                }
+                "--notify-webhook" => {
+                    if cfg!(target_os = "wasi") {
+                        return Err("--notify-webhook is not supported on WASI".into());
+                    }
+                    let url = value(flag)?;
+                    if !url.starts_with("https://") && !url.starts_with("http://") {
+                        return Err(format!("--notify-webhook needs an http(s) URL: {}", url));
+                    }
+                    notify_webhook = Some(url);
+                }
+                "--watch" => {
+                    if cfg!(target_os = "wasi") {
+                        return Err("--watch is not supported on WASI".into());
+                    }
+                    watch = true;
+                }
+                "--explore" => {
+                    if cfg!(target_os = "wasi") {
+                        return Err("--explore is not supported on WASI".into());
+                    }
+                    explore = true;
+                }
+                "--open-max" => {
+                    if cfg!(target_os = "wasi") {
+                        return Err("--open-max is not supported on WASI".into());
+                    }
+                    open_max = true;
+                }
+                "--compress-estimate" => {
+                    if cfg!(not(feature = "compress")) {
+                        return Err(
+                            "--compress-estimate needs tengok built with the `compress` feature"
+                                .into(),
+                        );
+                    }
+                    compress_estimate = true;
+                }
+                "--decompress" => {
+                    if cfg!(not(feature = "compress")) {
+                        return Err(
+                            "--decompress needs tengok built with the `compress` feature".into(),
+                        );
+                    }
+                    decompress = true;
+                }
+                "--parquet" => {
+                    if cfg!(not(feature = "parquet")) {
+                        return Err("--parquet needs tengok built with `--features parquet`".into());
+                    }
+                    parquet = Some(PathBuf::from(value(flag)?));
+                }
+                "--snapshot" => {
+                    if cfg!(not(feature = "compress")) {
+                        return Err(
+                            "--snapshot needs tengok built with the `compress` feature".into()
+                        );
+                    }
+                    snapshot = Some(PathBuf::from(value(flag)?));
+                }
+                "--log-level" => {
+                    let level = value(flag)?;
+                    log_level = Some(level.parse::<LevelFilter>().map_err(|_| {
+                        format!(
+                            "Unknown log level: {} (expected off, error, warn, info, debug or trace)",
+                            level
+                        )
+                    })?);
+                }
+                "--log-file" => log_file = Some(PathBuf::from(value(flag)?)),
+                "--plugin" => {
+                    plugins.push(Arc::new(Plugin::load(Path::new(&value(flag)?))?));
+                }
+                "--budgets" => budgets_file = Some(PathBuf::from(value(flag)?)),
+                "--baseline" => baseline = Some(Baseline::load(Path::new(&value(flag)?))?),
+                "--max-growth" => {
+                    let raw = value(flag)?;
+                    max_growth = Some(
+                        raw.trim_end_matches('%')
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|p| *p >= 0.0)
+                            .ok_or_else(|| "Unable to parse --max-growth".to_string())?,
+                    );
+                }
+                "--exclude-dir" => {
+                    let name = value(flag)?;
+                    if name.is_empty() || name.contains(['/', '\\']) {
+                        return Err(format!(
+                            "--exclude-dir takes a directory name, not a path: {:?}",
+                            name
+                        ));
+                    }
+                    exclude_dirs.push(name);
+                }
+                "--include-minified" => include_minified = true,
+                "--xattrs" if cfg!(target_os = "macos") => xattrs = true,
+                "--xattrs" => return Err("--xattrs is only supported on macOS".to_string()),
+                "--lint-names" => lint_names = true,
+                "--case-collisions" => case_collisions = true,
+                "--strict" => strict = true,
+                "--check" => check = true,
+                "--dup-names" => dup_names = dup_names.or(Some(Vec::new())),
+                "--dupes" => dupes = true,
+                "--max-entries" => {
+                    max_entries = value(flag)?
+                        .parse()
+                        .map_err(|_| "--max-entries needs a number".to_string())?;
+                }
+                "--cache-key" => cache_key = Some(CacheKey::parse(&value(flag)?)?),
+                "--dup-names-ext" => {
+                    let exts = dup_names.get_or_insert_with(Vec::new);
+                    for ext in value(flag)?.split(',') {
+                        exts.push(normalize_ext(ext));
+                    }
+                }
+                "--largest-dir-for" => {
+                    for ext in value(flag)?.split(',') {
+                        let ext = normalize_ext(ext);
+                        if !largest_dir_for.contains(&ext) {
+                            largest_dir_for.push(ext);
+                        }
+                    }
+                }
+                "--only" => only.push(value(flag)?),
+                "--metric" => metrics.push(Metric::parse(&value(flag)?)?),
+                "--verbose" => verbose = true,
+                "--count-pattern" => {
+                    let pattern = value(flag)?;
+                    count_pattern = Some(
+                        Regex::new(&pattern)
+                            .map_err(|err| format!("Invalid --count-pattern: {}", err))?,
+                    );
+                }
+                "--tiny" => tiny = tiny.or(Some(DEFAULT_TINY_BYTES)),
+                "--tiny-below" => {
+                    tiny = Some(
+                        parse_bytes(&value(flag)?)
+                            .ok_or_else(|| "Unable to parse --tiny-below".to_string())?,
+                    );
+                }
+                "--mime" => mime = true,
+                "--linguist" => linguist = true,
+                "--exclude-generated" => {
+                    exclude_generated = true;
+                    linguist = true;
+                }
+                _ if arg.starts_with('-') => return Err(unknown_flag(flag)),
+                _ => {
+                    roots.push(PathBuf::from(&arg));
+                }
+            }
+            if inline.is_some() && !inline_taken {
+                return Err(format!("{} doesn't take a value", flag));
+            }
+        }
+
+        // Sorting on its own implies the user wants to see the lists.
+        if sort.is_some() && top.is_none() && !by_ext {
+            top = Some(DEFAULT_TOP);
+        }
+
+        for field in metrics.iter().flat_map(Metric::fields) {
+            let (enabled, flag) = match field {
+                Field::Lang => (by_lang, "--by-lang"),
+                Field::Type => (mime, "--mime"),
+                Field::Kind => (linguist, "--linguist"),
+                Field::Matches => (count_pattern.is_some(), "--count-pattern"),
+                _ => continue,
+            };
+            if !enabled {
+                return Err(format!(
+                    "--metric field `{}` needs {}",
+                    field.as_str(),
+                    flag
+                ));
+            }
+        }
+
+        if max_growth.is_some() && baseline.is_none() {
+            return Err("--max-growth needs --baseline".to_string());
+        }
+        if strict && !lint_names && !case_collisions {
+            return Err("--strict needs --lint-names or --case-collisions".to_string());
+        }
+        if human_stderr && matches!(output, Output::Report | Output::Oneline) {
+            return Err("--human-stderr needs --json, --ndjson, --json-tree, --folded, --list or --openmetrics".to_string());
+        }
+        if growth_window.is_some() && !watch {
+            return Err("--growth-window needs --watch".to_string());
+        }
+        if output == Output::OpenMetrics && watch {
+            return Err("--openmetrics can't be combined with --watch".to_string());
+        }
+        if matches!(output, Output::NdJson | Output::JsonTree | Output::Folded) && watch {
+            return Err(
+                "--ndjson, --json-tree and --folded can't be combined with --watch".to_string(),
+            );
+        }
+        if (offset.is_some() || limit.is_some()) && !matches!(output, Output::List | Output::NdJson)
+        {
+            return Err("--offset and --limit need --list or --ndjson".to_string());
+        }
+        if let Some(flag) = [(min_lines, "--min-lines"), (max_lines, "--max-lines")]
+            .iter()
+            .find_map(|(lines, flag)| lines.and(Some(flag)))
+            && skip_lines
+        {
+            return Err(format!(
+                "{} needs line counting, which {} turns off",
+                flag,
+                if metadata_only {
+                    "--metadata-only"
+                } else {
+                    "--no-lines"
+                }
+            ));
+        }
+        if min_lines.zip(max_lines).is_some_and(|(min, max)| min > max) {
+            return Err("--min-lines is more than --max-lines".to_string());
+        }
+        if metadata_only {
+            // Everything that reads files' contents.
+            let reading = [
+                (force_lines, "--force-lines"),
+                (top_lines.is_some(), "--top-lines"),
+                (comments, "--comments"),
+                (scripts, "--scripts"),
+                (audit, "--audit"),
+                (secrets, "--secrets"),
+                (licenses, "--licenses"),
+                (mime, "--mime"),
+                (compress_estimate, "--compress-estimate"),
+                (decompress, "--decompress"),
+                (count_pattern.is_some(), "--count-pattern"),
+                (!plugins.is_empty(), "--plugin"),
+                (dupes, "--dupes"),
+                (xattrs, "--xattrs"),
+            ];
+            if let Some((_, flag)) = reading.iter().find(|(on, _)| *on) {
+                return Err(format!(
+                    "--metadata-only can't be combined with {}, which reads files",
+                    flag
+                ));
+            }
+        }
+        if memory_limit.is_some() {
+            // Everything that reads every file's record after the scan.
+            let whole = [
+                (comments, "--comments"),
+                (audit, "--audit"),
+                (secrets, "--secrets"),
+                (delete_junk, "--delete-junk"),
+                (clean, "--clean"),
+                (output == Output::List, "--list"),
+                (output == Output::NdJson, "--ndjson"),
+                (output == Output::Folded, "--folded"),
+                (verbose, "--verbose"),
+                (count_pattern.is_some(), "--count-pattern"),
+                (parquet.is_some(), "--parquet"),
+                (explore, "--explore"),
+                (dupes, "--dupes"),
+                (stale.is_some(), "--stale"),
+                (cold.is_some(), "--cold"),
+                (watch, "--watch"),
+                (deterministic, "--deterministic"),
+            ];
+            if let Some((_, flag)) = whole.iter().find(|(on, _)| *on) {
+                return Err(format!(
+                    "--memory-limit can't be combined with {}, which needs every file's record",
+                    flag
+                ));
+            }
+        }
+        if resume && reuse_unchanged {
+            return Err("--resume can't be combined with --reuse-unchanged".to_string());
+        }
+        let stored = if resume {
+            Some("--resume")
+        } else {
+            reuse_unchanged.then_some("--reuse-unchanged")
+        };
+        if let Some(stored) = stored {
+            // Walks other than the parallel one, and record fields stored
+            // records don't keep.
+            let unkept = [
+                (sequential, "--sequential"),
+                (watch, "--watch"),
+                (follow_links.is_some(), "--follow-links"),
+                (verbose, "--verbose"),
+                (profile, "--profile"),
+                (by_lang, "--by-lang"),
+                (comments, "--comments"),
+                (tests, "--tests"),
+                (scripts, "--scripts"),
+                (secrets, "--secrets"),
+                (licenses, "--licenses"),
+                (mime, "--mime"),
+                (linguist, "--linguist"),
+                (decompress, "--decompress"),
+                (count_pattern.is_some(), "--count-pattern"),
+                (!plugins.is_empty(), "--plugin"),
+            ];
+            if let Some((_, flag)) = unkept.iter().find(|(on, _)| *on) {
+                return Err(format!("{} can't be combined with {}", stored, flag));
+            }
+        }
+        if sequential && threads != 0 {
+            return Err("--sequential can't be combined with --threads".to_string());
+        }
+        if profile_ignores && no_ignore {
+            return Err("--profile-ignores can't be combined with --no-ignore".to_string());
+        }
+        if cache_key.is_some() && !dupes {
+            return Err("--cache-key needs --dupes".to_string());
+        }
+        if hash_threads != 0 && !dupes {
+            return Err("--hash-threads needs --dupes".to_string());
+        }
+        if sequential && hash_threads != 0 {
+            return Err("--sequential can't be combined with --hash-threads".to_string());
+        }
+        if follow_links.is_some() && (sequential || watch) {
+            return Err(
+                "--follow-links can't be combined with --sequential or --watch".to_string(),
+            );
+        }
+        if check && (watch || explore || clean || open_max) {
+            return Err(
+                "--check can't be combined with --watch, --explore, --clean or --open-max"
+                    .to_string(),
+            );
+        }
+        if dry_run && !clean && (watch || explore || output != Output::Report) {
+            return Err(
+                "--dry-run without --clean only prints what would be scanned; leave out \
+                 --watch, --explore and other output formats"
+                    .to_string(),
+            );
+        }
+        if trash && !clean && !delete_junk {
+            return Err("--trash needs --clean or --delete-junk".to_string());
+        }
+        if apply && !no_ignore {
+            return Err("--apply needs --no-ignore".to_string());
+        }
+        if dotfiles && !hidden {
+            return Err("--dotfiles needs --hidden".to_string());
+        }
+
+        let only = if only.is_empty() {
+            None
+        } else {
+            Some(Only::new(&only)?)
+        };
+
+        if roots.is_empty() {
+            roots.push(PathBuf::from("."));
+        }
+        let root = roots[roots.len() - 1].clone();
+        let suggest_ignore = no_ignore && root.join(".git").exists();
+        let theme = match (theme, std::env::var(theme::ENV)) {
+            (Some(theme), _) => theme,
+            (None, Ok(spec)) => {
+                Theme::parse(&spec).map_err(|err| format!("{}: {}", theme::ENV, err))?
+            }
+            (None, Err(_)) => Theme::default(),
+        };
+        let classes = match classes_file {
+            Some(path) => Some(Classes::load(&path)?),
+            None if by_class && root.join(classes::FILE_NAME).is_file() => {
+                Some(Classes::load(&root.join(classes::FILE_NAME))?)
+            }
+            None => by_class.then(Classes::default),
+        };
+        let groups = if group_globs.is_empty() {
+            None
+        } else {
+            Some(Groups::parse(&group_globs)?)
+        };
+        let budgets = match budgets_file {
+            Some(path) => budget::load(&path)?,
+            None if root.join(budget::FILE_NAME).is_file() => {
+                budget::load(&root.join(budget::FILE_NAME))?
+            }
+            None => Vec::new(),
+        };
+        Ok(Self {
+            root,
+            roots,
+            plain,
+            theme,
+            bytes,
+            ascii,
+            utc,
+            time_format,
+            lang: lang.unwrap_or_else(Lang::from_env),
+            skip_lines,
+            metadata_only,
+            heat,
+            force_lines,
+            max_line_bytes,
+            ext_max_line_bytes,
+            lines,
+            line_backend,
+            threads,
+            hash_threads,
+            retries,
+            throttle,
+            sequential,
+            memory_limit,
+            resume,
+            reuse_unchanged,
+            channel_capacity,
+            follow_links,
+            progress_interval,
+            top,
+            top_lines,
+            min_lines,
+            max_lines,
+            by_ext,
+            by_lang,
+            comments,
+            scripts,
+            audit,
+            secrets,
+            licenses,
+            docs,
+            hidden,
+            junk,
+            delete_junk,
+            clean,
+            dry_run,
+            trash,
+            include_git,
+            submodules,
+            no_ignore,
+            dep_dirs,
+            dotfiles,
+            stale,
+            cold,
+            growth_window: growth_window.unwrap_or(DEFAULT_GROWTH_WINDOW),
+            suggest_ignore,
+            apply,
+            by_depth,
+            by_owner,
+            by_mount,
+            classes,
+            groups,
+            tests,
+            hints,
+            dir_metrics,
+            statsd,
+            notify_webhook,
+            only_mine,
+            sort,
+            reverse,
+            deterministic,
+            profile,
+            fields,
+            title,
+            note,
+            output,
+            human_stderr,
+            offset: offset.unwrap_or(0),
+            limit,
+            watch,
+            explore,
+            open_max,
+            compress_estimate,
+            decompress,
+            mime,
+            linguist,
+            exclude_generated,
+            tiny,
+            verbose,
+            count_pattern,
+            parquet,
+            snapshot,
+            log_level: log_level.unwrap_or(if log_file.is_some() {
+                LevelFilter::INFO
+            } else {
+                LevelFilter::OFF
+            }),
+            log_file,
+            plugins,
+            metrics,
+            budgets,
+            baseline,
+            max_growth,
+            exclude_dirs,
+            only,
+            include_minified,
+            xattrs,
+            lint_names,
+            dup_names,
+            largest_dir_for,
+            dupes,
+            max_entries,
+            profile_ignores,
+            virtual_fs,
+            overlay_layers,
+            cache_key: cache_key.unwrap_or(CacheKey::Mtime),
+            case_collisions,
+            strict,
+            check,
+            args: given,
+        })
+    }
+
+    /// Same options, scanning a different root.
+    pub fn with_root(&self, root: PathBuf) -> Self {
+        Self {
+            roots: vec![root.clone()],
+            root,
+            ..self.clone()
+        }
+    }
+
+    /// Whether the walk visits `path`: directories named by `--exclude-dir`
+    /// are pruned, and with `--only` so are directories that can't hold a
+    /// match and files that don't match.
+    pub fn keeps(&self, path: &Path, is_dir: bool) -> bool {
+        // --junk and --include-git walk hidden entries to find `.DS_Store`
+        // and `.git`, and only keep those.
+        if !self.hidden
+            && let Some(name) = path.file_name().and_then(|n| n.to_str())
+            && name.starts_with('.')
+        {
+            let junk = self.junk && !is_dir && junk::kind(name).is_some();
+            let git = self.include_git && is_dir && *path == self.root.join(".git");
+            if !junk && !git {
+                return false;
+            }
+        }
+        if is_dir
+            && path
+                .file_name()
+                .is_some_and(|name| self.exclude_dirs.iter().any(|d| name == d.as_str()))
+        {
+            return false;
+        }
+        let Some(only) = &self.only else {
+            return true;
+        };
+        let rel = path.strip_prefix(&self.root).unwrap_or(path);
+        if is_dir {
+            only.may_contain(rel)
+        } else {
+            only.matches(rel)
+        }
+    }
+
+    /// Whether the file at `rel`, relative to the root, is a test: in the
+    /// `tests` class when there's a class mapping, else by the built-in
+    /// conventions.
+    pub fn is_test(&self, rel: &Path) -> bool {
+        match &self.classes {
+            Some(classes) => classes.classify(rel) == "tests",
+            None => classes::is_test(rel),
+        }
+    }
+
+    /// The stderr spinner is only drawn for the colored box report.
+    pub fn show_progress(&self) -> bool {
+        !self.plain && !self.check && self.output == Output::Report
+    }
+
+    /// Whether `--fields` keeps `row`.
+    pub fn shows(&self, row: Row) -> bool {
+        self.fields.contains(&row)
+    }
+
+    /// Whether a file with `lines` lines passes `--min-lines` and
+    /// `--max-lines`.
+    pub fn lines_in_range(&self, lines: u64) -> bool {
+        self.min_lines.is_none_or(|min| lines >= min)
+            && self.max_lines.is_none_or(|max| lines <= max)
+    }
+
+    /// A size as reports show it: `123.46 MB`, or `123,456,789 B` with
+    /// `--bytes`.
+    pub fn size(&self, bytes: u64) -> String {
+        if self.bytes {
+            format!("{} B", format_num(bytes))
+        } else {
+            format_size(bytes, DECIMAL)
+        }
+    }
+
+    /// A point in time, in seconds since the Unix epoch, as reports show
+    /// it. Without `--utc`, in the local timezone where the platform tells
+    /// it; a time shown in UTC by default says so.
+    pub fn time(&self, secs: u64) -> String {
+        let offset = if self.utc {
+            None
+        } else {
+            format::local_offset(secs)
+        };
+        match self.time_format {
+            TimeFormat::Epoch => secs.to_string(),
+            TimeFormat::Iso => format::format_iso8601(secs, offset.unwrap_or(0)),
+            TimeFormat::Default => match offset {
+                Some(offset) => format::format_timestamp_at(secs, offset),
+                None => format!("{} UTC", format::format_timestamp(secs)),
+            },
+        }
+    }
+
+    /// `--dry-run` without `--clean`: print what would be scanned, and scan
+    /// nothing.
+    pub fn plans(&self) -> bool {
+        self.dry_run && !self.clean
+    }
+
+    /// Whether the walker has to visit hidden entries; `keeps` then drops
+    /// the ones that aren't wanted.
+    pub fn walks_hidden(&self) -> bool {
+        self.hidden || self.junk || self.include_git
+    }
+
+    /// Whether some output needs the per-file records.
+    pub fn needs_files(&self) -> bool {
+        self.top.is_some()
+            || self.top_lines.is_some()
+            || self.comments
+            || self.audit
+            || self.secrets
+            || self.delete_junk
+            || self.clean
+            || matches!(self.output, Output::List | Output::NdJson | Output::Folded)
+            || self.tiny.is_some()
+            || self.verbose
+            || self.count_pattern.is_some()
+            || self.parquet.is_some()
+            || self.explore
+            || self.dupes
+            || self.stale.is_some()
+            || self.cold.is_some()
+    }
+
+    /// Whether per-file stats must be kept around after aggregation.
+    pub fn keep_files(&self) -> bool {
+        self.needs_files() || self.watch
+    }
+}
+
+/// Short options, the long ones they stand for, and whether they take a
+/// value.
+const SHORT_FLAGS: &[(char, &str, bool)] = &[
+    ('j', "--threads", true),
+    ('n', "--top", true),
+    ('p', "--plain", false),
+    ('v', "--verbose", false),
+];
+
+/// `-pv`, `-n5`: a dash and letters, not a negative number or a lone `-`.
+fn is_short_cluster(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic())
+}
+
+/// A cluster of short options spelled out: `pvj4` is `--plain --verbose
+/// --threads 4`. An option taking a value takes the rest of the cluster,
+/// or the next argument when nothing is left.
+fn expand_shorts(shorts: &str) -> Result<Vec<String>, String> {
+    let mut longs = Vec::new();
+    for (i, ch) in shorts.char_indices() {
+        let Some(&(_, long, takes_value)) = SHORT_FLAGS.iter().find(|(c, ..)| *c == ch) else {
+            return Err(unknown_flag(&format!("-{}", ch)));
+        };
+        longs.push(long.to_string());
+        if takes_value {
+            let rest = &shorts[i + 1..];
+            if !rest.is_empty() {
+                longs.push(rest.trim_start_matches('=').to_string());
+            }
+            break;
+        }
+    }
+    Ok(longs)
+}
+
+/// "Unknown flag", with the closest flag in `usage()` when one is near
+/// enough to be a typo.
+fn unknown_flag(flag: &str) -> String {
+    let known = usage()
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | '[' | ']' | '`' | '(' | ')'))
+        .filter(|word| word.starts_with("--") && word.len() > 2)
+        .map(|word| word.split('=').next().unwrap_or(word));
+    let closest = known
+        .map(|known| (edit_distance(flag, known), known))
+        .filter(|(distance, known)| *distance <= 2.max(known.len() / 5))
+        .min();
+    match closest {
+        Some((_, known)) => format!("Unknown flag: {} (did you mean {}?)", flag, known),
+        None => format!("Unknown flag: {}", flag),
+    }
+}
+
+/// Levenshtein distance, in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Byte count with an optional suffix: `4096`, `64K`, `100MB` in the
+/// decimal units reports print, or binary `1GiB`.
+pub fn parse_bytes(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (digits, suffix) = value.split_at(split);
+    let unit: u64 = match suffix.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1_000,
+        "m" | "mb" => 1_000_000,
+        "g" | "gb" => 1_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        _ => return None,
+    };
+    digits.parse::<u64>().ok()?.checked_mul(unit)
+}
+
+/// `--heat` without `--heat-at`: warm from 100 MB, hot from 1 GB.
+pub const DEFAULT_HEAT: (u64, u64) = (100_000_000, 1_000_000_000);
+
+/// `--watch` without `--growth-window`.
+pub const DEFAULT_GROWTH_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// Seconds per `parse_duration`/`format_duration` unit, largest first.
+pub const DURATION_UNITS: [(&str, u64); 6] = [
+    ("y", 365 * 86_400),
+    ("w", 7 * 86_400),
+    ("d", 86_400),
+    ("h", 3600),
+    ("m", 60),
+    ("s", 1),
+];
+
+/// A span with a unit suffix: `90d`, `12h`, `2w`, `1y` (365 days).
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (digits, suffix) = value.split_at(split);
+    let suffix = suffix.trim().to_ascii_lowercase();
+    let (_, unit) = DURATION_UNITS.iter().find(|(name, _)| *name == suffix)?;
+    digits
+        .parse::<u64>()
+        .ok()?
+        .checked_mul(*unit)
+        .map(Duration::from_secs)
+}
+
+/// `--version`: the crate version and the optional features built in.
+pub fn version() -> String {
+    let features: Vec<&str> = [
+        ("compress", cfg!(feature = "compress")),
+        ("parquet", cfg!(feature = "parquet")),
+        ("python", cfg!(feature = "python")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect();
+    format!(
+        "tengok {}\nfeatures: {}",
+        env!("CARGO_PKG_VERSION"),
+        if features.is_empty() {
+            "none".to_string()
+        } else {
+            features.join(", ")
+        }
+    )
+}
+
+pub fn usage() -> &'static str {
+    "Usage: tengok [OPTIONS] [PATH]
+       tengok history [--last <N>] [--no-record] [--keep-last <N>] [--keep-daily <N>]
+                      [--keep-weekly <N>] [OPTIONS] [PATH]
+       tengok history prune [--keep-last <N>] [--keep-daily <N>] [--keep-weekly <N>] [PATH]
+       tengok schedule install --every <DURATION> [--via <SCHEDULER>] [--dry-run]
+                              [HISTORY OPTIONS] [PATH]
+       tengok schedule remove [--via <SCHEDULER>] [--dry-run] [PATH]
+       tengok serve [--listen <ADDR>] [--interval <SECS>] [OPTIONS] [PATH...]
+       tengok bench [--runs <N>] [OPTIONS] [PATH]
+       tengok diff <SNAPSHOT> <SNAPSHOT>... [--plain]
+       tengok merge <SNAPSHOT>... [--json] [--plain] [--ascii] [--bytes]
+                              [--theme <THEME>]
+       tengok snapshot convert <IN> <OUT>
+       tengok self-update [--check] [--force]
+       tengok config [OPTIONS] [PATH]
+       tengok config check [--budgets <FILE>] [PATH]
+       tengok schema
+
+Commands:
+  history                     Record a snapshot of PATH and show its size/lines trend
+                              (--last N snapshots, default 20; --no-record to only show;
+                              --keep-* prunes the store after recording)
+  history prune               Drop PATH's snapshots no --keep-last N, --keep-daily N
+                              or --keep-weekly N rule keeps, without scanning
+  schedule install            Run `tengok history` of PATH every DURATION (30m, 6h,
+                              1d, 1w, ...) with the platform's scheduler: a systemd
+                              user timer or cron, launchd or Task Scheduler (--via
+                              cron, systemd, launchd or schtasks to pick one)
+  schedule remove             Unregister the scheduled scan of PATH
+  serve                       Re-scan every PATH periodically and serve the results
+                              over HTTP (/ or /json, /metrics); default listen
+                              address 127.0.0.1:9345, interval 60s
+  bench                       Time the scan with every --line-backend and a range of
+                              --threads values (best of --runs N, default 3)
+  diff                        Compare saved `tengok --json` snapshots (or history
+                              lines) side by side, one column per snapshot
+  merge                       Add up `tengok --json` snapshots from many hosts into
+                              fleet totals and a per-host table (the latest run of
+                              each host and root counts)
+  snapshot convert            Turn a JSON snapshot into a binary one (OUT ending in
+                              .tsnap) or back into JSON
+  self-update                 Replace this binary with the latest GitHub release once
+                              its SHA-256 checksum matches (--check to only look,
+                              --force to reinstall the same version)
+  config                      Show the settings a scan with OPTIONS would use and
+                              where each came from (default, file, env or flag);
+                              `config check` validates the budgets file and
+                              TENGOK_THEME, listing every problem by line, and
+                              exits with status 1 if there are any
+  schema                      Print the JSON Schema describing --json output
+
+Options:
+  -p, --plain, --no-colors    Disable ANSI colors in the report
+  --theme <THEME>             Report colors: default, deuteranopia or protanopia,
+                              then any of border=, label=, value=, warm=, hot=COLOR
+                              (also read from TENGOK_THEME)
+  --heat                      Color sizes by magnitude: warm from 100 MB, hot from 1 GB
+  --heat-at <WARM,HOT>        Like --heat with these thresholds, e.g. 10M,500M
+  --bytes                     Show sizes as exact byte counts (1,234,567 B) instead
+                              of kB, MB, GB
+  --ascii                     Draw the box, arrows and ellipses in plain ASCII, for
+                              serial consoles and log viewers without Unicode
+  --utc                       Show times in UTC instead of the local timezone
+  --time-format <FORMAT>      Show times as default (2024-02-29 19:34), iso
+                              (2024-02-29T19:34:56+07:00) or epoch (seconds)
+  --lang <LANG>               Language of the report's header and units: en, id
+                              (Indonesian) or ms (Malay); defaults to the locale
+  -v, --verbose               Add mean/median and p50/p90/p99 file size and lines rows
+  --progress-interval <MS>    Redraw the progress line (files/s, MB/s, lines, current
+                              directory) every MS milliseconds (default 80; 10000
+                              when stderr isn't a terminal or in CI, one line each)
+  --no-lines                  Skip line counting entirely
+  --metadata-only             Only stat files, never open them: sizes and counts
+                              alone, with the fewest syscalls (a faster du)
+  --no-hints                  Leave out the hints under the summary box
+  --force-lines               Always count lines (even for large/binary files)
+  --max-line-bytes <N>        Only count lines for files up to N bytes (default ~5MB;
+                              accepts K/M/G suffixes, or KiB/MiB/GiB)
+  --ext-max-line-bytes <EXT=N,...>
+                              Per-extension --max-line-bytes, e.g. sql=100M,json=1M
+                              (0 = no limit; also counts otherwise-binary types)
+  --line-backend <B>          Read files for line counting with bufread (default),
+                              memchr or mmap; see `tengok bench`
+  -j, --threads <N>           Walker threads (default: one per CPU)
+  --sequential                Walk one directory at a time with larger reads, which
+                              is faster on NFS/SMB shares than the parallel walker
+  --follow-links <MODE>       Follow symlinks, counting their targets under the
+                              link (link, like du -L) or once where they really
+                              live (target)
+  --memory-limit <SIZE>       Keep the scan's RAM near SIZE (e.g. 2G) by spilling
+                              file records for --top, --top-lines and --tiny to a
+                              temporary file
+  --resume                    Checkpoint the walk, and continue an interrupted
+                              --resume scan of the same root where it stopped
+  --reuse-unchanged           Reuse the last such scan's file records for
+                              directories whose time and entry count are unchanged
+  --channel-capacity <N>      Records walker threads queue before waiting for the
+                              aggregator (default 8192)
+  --throttle <MB/s>           Read at most this many megabytes per second while
+                              counting lines, across all threads
+  --retries <N>               Retry reads that fail with EIO, timeouts and similar
+                              transient errors up to N times, backing off from 10ms
+                              (default 0; for NFS/SMB mounts)
+  --terminated-only           Don't count a final line without a trailing newline
+  --nonblank                  Don't count empty or whitespace-only lines
+  --cr-lines                  Treat a lone \\r (classic Mac) as a line break too
+  --exclude-dir <NAME>        Skip every directory named NAME, at any depth
+                              (repeatable), e.g. --exclude-dir node_modules
+  --only <GLOB>               Count only files matching GLOB, relative to PATH
+                              (repeatable), e.g. --only 'src/**/*.rs'
+  -n, --top <N>               List the N largest files and directories
+  --top-lines <N>             List the N files with the most lines
+  --min-lines <N>             Only list files with at least N lines (--list, --ndjson,
+                              --top, --top-lines, --tiny)
+  --max-lines <N>             Only list files with at most N lines
+  --by-ext                    Show a per-extension breakdown
+  --by-lang                   Show a per-language breakdown (extensions, file names,
+                              shebangs and editor modelines)
+  --comments                  Report comment density overall, per language and for
+                              the least-commented large files
+  --scripts                   Count extensionless scripts by shebang interpreter
+                              (bash, python, node, ...)
+  --audit                     List executables that aren't shebang scripts: committed
+                              binaries and stray executable bits (Unix)
+  --secrets                   Flag files that look like credentials: .env, private
+                              keys, AWS keys, high-entropy tokens
+  --licenses                  Identify LICENSE, COPYING and NOTICE files, vendored
+                              ones included
+  --hidden                    Include hidden files and directories (.git too)
+  --junk                      Count OS junk files: .DS_Store, ._* AppleDouble files,
+                              Thumbs.db, desktop.ini
+  --delete-junk               Like --junk, then delete those files
+  --clean                     After the report, number the largest directories and
+                              files and delete the ones picked, after typing yes
+  --dry-run                   List the top-level directories a scan would walk or
+                              skip (and why) with estimated file counts, without
+                              stat'ing any file; with --clean, only list what would
+                              be removed
+  --trash                     With --clean or --delete-junk, move files to the trash
+                              (recycle bin) instead of deleting them
+  --include-git               Scan .git too and break it down into packs, loose
+                              objects, refs, hooks and the rest
+  --submodules <MODE>         Count git submodules normally (include, the default),
+                              leave them out (exclude) or subtotal each (separate)
+  --no-ignore                 Include files excluded by .gitignore and .ignore; in a
+                              git repository, also suggest .gitignore lines for large
+                              build output and caches
+  --virtual-fs                Walk /proc, /sys, cgroups and other virtual filesystems
+                              mounted in the root instead of skipping them
+  --overlay-layers            On an overlayfs root (a container's), measure the upper
+                              and lower layers behind it
+  --apply                     With --no-ignore, append the suggested lines to .gitignore
+  --deps                      Total sources and dependencies/build output (node_modules,
+                              target, vendor, ...) separately
+  --dep-dirs <NAME,...>       Like --deps with these directory names
+  --dotfiles                  With --hidden, list hidden files and directories on
+                              their own, largest first
+  --cold <DURATION>           Show bytes not read in DURATION, per directory, from
+                              access times
+  --stale <DURATION>          List the largest directories with nothing modified in
+                              DURATION (e.g. 90d, 2w, 1y) and their total sizes
+  --docs                      Show READMEs and doc bytes against code bytes per
+                              top-level directory
+  --by-depth                  Chart file counts and sizes per directory depth
+  --by-owner                  Show bytes and files per file owner (Unix)
+  --by-mount                  Show bytes and files per mounted device (Unix)
+  --tests                     Show test lines as a share of code lines per language
+                              (tests/, __tests__/, *_test.go, *.spec.ts, ...)
+  --by-class                  Show bytes and files per asset class (code, docs,
+                              images, data, config, tests, other)
+  --classes <FILE>            Like --by-class with extra class mappings from FILE
+                              (default: PATH/tengok-classes.toml)
+  --group-by <GLOB>           Show bytes and files per directory GLOB matches, e.g.
+                              'packages/*/' (repeatable; first match wins)
+  --only-mine                 Only count files you own (Unix)
+  --mime                      Show a breakdown by sniffed content type (image, video,
+                              text, archive, executable, ...)
+  --sort <KEY>                Order lists by size, lines, name, mtime or count
+  --reverse                   Reverse the list ordering
+  --deterministic             Sort records before reporting for byte-identical output
+  --profile                   Time traversal, metadata, line counting and other
+                              checks per walker thread, and aggregation
+  --profile-ignores           List the .gitignore/.ignore rules that pruned the most
+                              files and bytes (walks the ignored trees too)
+  --fields <FIELD,...>        Show only these summary rows (and JSON summary keys):
+                              files, size, lines, largest_dir, busiest_dir,
+                              deepest_dir, max_lines_file, largest_file, and the
+                              opt-in avg_size, avg_lines, newest_file; +FIELD,...
+                              adds to the defaults
+  --title <TEXT>              Use TEXT as the report header instead of
+                              'Folder Summary: PATH' (also in JSON)
+  --note <TEXT>               Add a line of TEXT under the header (also in JSON)
+  --json                      Print machine-readable JSON instead of the box report
+  --list                      Print every file with its size and lines instead of the
+                              report (honours --sort, default name, and --top)
+  --json-tree                 Print the directory tree as nested JSON with cumulative
+                              size, files and lines per directory (for treemaps)
+  --folded                    Print one `root;dir;file SIZE` line per file, the folded
+                              stack format flamegraph.pl and inferno-flamegraph read
+  --ndjson                    Like --list, but one JSON object per file (path,
+                              size_bytes, lines, mtime)
+  --offset <N>, --limit <N>   Page through --list and --ndjson: skip N rows, then
+                              print at most N
+  --oneline                   Print the totals and largest directory on one line,
+                              for shell prompts and status bars
+  --openmetrics               Print the totals as OpenMetrics text, e.g. for the
+                              node_exporter textfile collector
+  --human-stderr              With --json, --ndjson, --json-tree, --folded, --list or
+                              --openmetrics, also print the box report to stderr
+  --dir-metrics               Add per-top-level-directory series to --openmetrics,
+                              --statsd and `tengok serve`
+  --statsd <HOST:PORT>        Push the totals as DogStatsD gauges over UDP after
+                              the scan
+  --parquet <FILE>            Also write every file record to FILE as Apache Parquet
+                              (needs a build with the `parquet` feature)
+  --snapshot <FILE>           Also write the --json document to FILE as a compact
+                              zstd-compressed binary snapshot, which --baseline and
+                              `tengok diff` read like JSON
+  --log-level <LEVEL>         Log skipped entries, read errors and phase timings to
+                              stderr: off (default), error, warn, info, debug, trace
+  --log-file <FILE>           Append the log to FILE instead (level defaults to info)
+  --watch                     Keep running and update the report as files change
+  --growth-window <DURATION>  With --watch, show the directory whose files grew the
+                              most in the last DURATION (default 5m)
+  --explore                   After the report, drill into the largest directories
+                              and list top files with single keys (d, u, f, o, q)
+  --open-max                  Open the file with the most lines in $VISUAL or
+                              $EDITOR after the report
+  --linguist                  Report generated and vendored files separately
+                              (.gitattributes linguist-* or path heuristics)
+  --exclude-generated         Don't count lines of generated/vendored files
+                              (implies --linguist)
+  --include-minified          Count lines of minified/bundled JS and CSS, which are
+                              otherwise reported on their own [min] row
+  --tiny                      Report files under 4 KB and list the smallest files
+  --tiny-below <N>            Like --tiny with a threshold of N bytes
+  --count-pattern <REGEX>     Count regex matches per file during line counting
+  --xattrs                    Include extended attribute and resource fork bytes in
+                              file sizes, as Finder does (macOS)
+  --lint-names                Flag names that break on other platforms (Windows-invalid
+                              characters, reserved names like CON, non-NFC Unicode, ...)
+  --case-collisions           List paths that differ only by case (README.md vs
+                              readme.md)
+  --strict                    Exit with status 3 if --lint-names or --case-collisions
+                              finds anything
+  --dup-names                 List file names that occur in more than one directory
+  --dup-names-ext <EXT,...>   Like --dup-names for these extensions only,
+                              e.g. --dup-names-ext json,yml
+  --largest-dir-for <EXT,...> Show the directory holding the most bytes of these
+                              extensions, e.g. --largest-dir-for png,jpg
+  --max-entries <N>           Flag directories with more than N direct entries
+                              (default 100000; 0 = off)
+  --dupes                     List files with identical contents (SHA-256, hard links
+                              counted once); hashes are cached between runs
+  --cache-key <KEY>           How --dupes tells a cached hash is still good: mtime
+                              (default) or fingerprint, the size plus the first and
+                              last 64 KB, for mounts with unreliable mtimes
+  --hash-threads <N>          Threads hashing --dupes candidates alongside the walk
+                              (default: one per CPU)
+  --compress-estimate         Estimate the zstd-compressed size of the tree
+  --decompress                Count the lines of .zst, .gz and .bz2 files through a
+                              decompressor and report their decompressed size
+  --plugin <LIB>              Load a metric plugin (shared library; repeatable)
+  --budgets <FILE>            Check size/line budgets per glob and exit with status 3
+                              on violations (default: PATH/tengok-budgets.toml)
+  --notify-webhook <URL>      POST a JSON alert to URL when a budget, --max-growth
+                              or --strict check fails
+  --baseline <FILE>           Show deltas against a saved `tengok --json` snapshot
+  --max-growth <PCT>          With --baseline, exit with status 3 if total bytes or
+                              lines grew by more than PCT, e.g. 5%
+  --check                     Print nothing and only report through the exit status
+  --metric <NAME=EXPR>        Add a custom summary row, e.g.
+                              'rs_bytes=sum(size where ext==\"rs\")' (repeatable)
+  -h, --help                  Print this help
+  -V, --version               Print the version and the enabled features
+
+Exit status:
+  0    success
+  1    usage error (or an I/O error that stopped the scan)
+  2    some entries couldn't be read; the totals leave them out
+  3    a budget, --max-growth or --strict check failed (takes precedence over 2)
+  130  interrupted (Ctrl-C)
+"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_needs_a_unit() {
+        assert_eq!(
+            parse_duration("90d"),
+            Some(Duration::from_secs(90 * 86_400))
+        );
+        assert_eq!(parse_duration("12h"), Some(Duration::from_secs(12 * 3600)));
+        assert_eq!(
+            parse_duration("1Y"),
+            Some(Duration::from_secs(365 * 86_400))
+        );
+        assert_eq!(parse_duration("90"), None);
+        assert_eq!(parse_duration("d"), None);
+    }
+
+    #[test]
+    fn parse_bytes_accepts_decimal_and_binary_suffixes() {
+        assert_eq!(parse_bytes("4096"), Some(4096));
+        assert_eq!(parse_bytes("64K"), Some(64_000));
+        assert_eq!(parse_bytes("50kB"), Some(50_000));
+        assert_eq!(parse_bytes("100MB"), Some(100_000_000));
+        assert_eq!(parse_bytes("64KiB"), Some(64 * 1024));
+        assert_eq!(parse_bytes("1 GiB"), Some(1 << 30));
+        assert_eq!(parse_bytes("5x"), None);
+        assert_eq!(parse_bytes("M"), None);
+    }
+
+    #[test]
+    fn fields_replace_or_extend_the_default_rows() {
+        assert_eq!(
+            Row::parse_list("size,files").unwrap(),
+            vec![Row::Size, Row::Files]
+        );
+        let extended = Row::parse_list("+newest_file,files").unwrap();
+        assert_eq!(extended.len(), Row::DEFAULT.len() + 1);
+        assert_eq!(extended.last(), Some(&Row::NewestFile));
+        assert!(Row::parse_list("files,bogus").is_err());
+    }
+
+    #[test]
+    fn utc_times_in_each_format() {
+        let parse = |args: &[&str]| Config::from_args(args.iter().map(|a| a.to_string())).unwrap();
+        assert_eq!(
+            parse(&["--utc"]).time(1_709_210_096),
+            "2024-02-29 12:34 UTC"
+        );
+        let iso = parse(&["--utc", "--time-format", "iso"]);
+        assert_eq!(iso.time(1_709_210_096), "2024-02-29T12:34:56Z");
+        assert_eq!(
+            parse(&["--time-format=epoch"]).time(1_709_210_096),
+            "1709210096"
+        );
+    }
+
+    #[test]
+    fn human_stderr_needs_a_machine_output() {
+        let parse = |args: &[&str]| Config::from_args(args.iter().map(|a| a.to_string()));
+        assert!(parse(&["--human-stderr"]).is_err());
+        assert!(parse(&["--oneline", "--human-stderr"]).is_err());
+        assert!(parse(&["--json", "--human-stderr"]).unwrap().human_stderr);
+    }
+
+    #[test]
+    fn bytes_shows_exact_sizes() {
+        let config = Config::from_args(Vec::new()).unwrap();
+        assert_eq!(config.size(1_234_567), "1.23 MB");
+        let config = Config::from_args(["--bytes".to_string()]).unwrap();
+        assert_eq!(config.size(1_234_567), "1,234,567 B");
+    }
+
+    #[test]
+    fn short_flags_combine_and_dashes_end_options() {
+        let parse = |args: &[&str]| Config::from_args(args.iter().map(|a| a.to_string()));
+        let config = parse(&["-pvn5", "-j", "2", "--", "-weird", "src"]).unwrap();
+        assert!(config.plain && config.verbose);
+        assert_eq!((config.top, config.threads), (Some(5), 2));
+        assert_eq!(
+            config.roots,
+            [PathBuf::from("-weird"), PathBuf::from("src")]
+        );
+        assert_eq!(parse(&["--top=3"]).unwrap().top, Some(3));
+
+        assert_eq!(
+            parse(&["--by-exts"]).unwrap_err(),
+            "Unknown flag: --by-exts (did you mean --by-ext?)"
+        );
+        assert_eq!(parse(&["-px"]).unwrap_err(), "Unknown flag: -x");
+        assert_eq!(
+            parse(&["--frobnicate"]).unwrap_err(),
+            "Unknown flag: --frobnicate"
+        );
+        assert_eq!(
+            parse(&["--plain=yes"]).unwrap_err(),
+            "--plain doesn't take a value"
+        );
+    }
+}
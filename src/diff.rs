@@ -0,0 +1,73 @@
+//! `tengok diff a.json b.json ...`: saved snapshots side by side, one
+//! column per snapshot in the order given, each with its change from the
+//! one before. Anything `--baseline` reads works here too.
+
+use std::path::{Path, PathBuf};
+
+use crate::baseline::Baseline;
+use crate::config::Config;
+use crate::format::format_num;
+use crate::history::percent_change;
+use crate::report::{Cell, Style, print_table};
+
+/// Splits the snapshot files from the regular flags (`--plain`); every
+/// argument that isn't a flag is a snapshot.
+pub fn split_args(
+    args: impl IntoIterator<Item = String>,
+) -> Result<(Vec<PathBuf>, Vec<String>), String> {
+    let (flags, snapshots): (Vec<String>, Vec<String>) =
+        args.into_iter().partition(|arg| arg.starts_with('-'));
+    if snapshots.len() < 2 {
+        return Err("diff needs at least two snapshots".to_string());
+    }
+    Ok((snapshots.into_iter().map(PathBuf::from).collect(), flags))
+}
+
+pub fn run(config: &Config, paths: &[PathBuf]) -> Result<(), String> {
+    let snapshots = paths
+        .iter()
+        .map(|path| Baseline::load(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    print_diff(config, paths, &snapshots);
+    Ok(())
+}
+
+fn print_diff(config: &Config, paths: &[PathBuf], snapshots: &[Baseline]) {
+    let style = Style::new(config);
+    let names: Vec<String> = paths.iter().map(|p| snapshot_name(p)).collect();
+    let mut headers: Vec<&str> = names.iter().map(String::as_str).collect();
+    headers.push("");
+
+    // `sized` rows carry their bytes for `--heat`.
+    let row = |label: &str, pick: fn(&Baseline) -> u64, fmt: &dyn Fn(u64) -> String, sized| {
+        let values: Vec<u64> = snapshots.iter().map(pick).collect();
+        let mut cells: Vec<Cell> = values
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| {
+                let cell = Cell::from(match i {
+                    0 => fmt(value),
+                    _ => format!("{} ({})", fmt(value), percent_change(values[i - 1], value)),
+                });
+                if sized { cell.with_bytes(value) } else { cell }
+            })
+            .collect();
+        cells.push(label.to_string().into());
+        cells
+    };
+    let rows = vec![
+        row("Files", |s| s.files, &format_num, false),
+        row("Size", |s| s.size_bytes, &|n| config.size(n), true),
+        row("Lines", |s| s.lines, &format_num, false),
+    ];
+    print_table(&style, "Snapshots", &headers, &rows);
+}
+
+/// The file name, which is usually what tells snapshots apart
+/// (`v1.2.json`).
+fn snapshot_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    )
+}
@@ -0,0 +1,127 @@
+//! `--docs`: documentation health per top-level directory, for monorepos
+//! where each package should carry its own README. Files are sorted into
+//! docs (Markdown, reStructuredText, AsciiDoc, Org) and code (any language
+//! known by name that isn't docs or data) from their names alone; the rest
+//! count toward neither.
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::lang;
+
+const DOC_EXTS: &[&str] = &["md", "markdown", "mdx", "rst", "adoc", "asciidoc", "org"];
+/// Languages that are prose or data rather than code.
+const NOT_CODE: &[&str] = &[
+    "Markdown",
+    "reStructuredText",
+    "Text",
+    "TeX",
+    "JSON",
+    "YAML",
+    "TOML",
+    "XML",
+    "INI",
+];
+
+/// Whether `lang` is a programming language rather than prose or data.
+pub fn is_code(lang: &str) -> bool {
+    !NOT_CODE.contains(&lang)
+}
+
+/// Docs and code below one top-level directory.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DocStat {
+    pub files: u64,
+    /// READMEs directly in the directory.
+    pub readmes: u64,
+    pub doc_files: u64,
+    pub doc_bytes: u64,
+    pub code_bytes: u64,
+}
+
+impl DocStat {
+    /// Count the file at `rel`, relative to its top-level directory.
+    pub fn add(&mut self, rel: &Path, size: u64) {
+        self.apply(rel, size, true);
+    }
+
+    pub fn remove(&mut self, rel: &Path, size: u64) {
+        self.apply(rel, size, false);
+    }
+
+    fn apply(&mut self, rel: &Path, size: u64, add: bool) {
+        let step = |n: &mut u64, by: u64| {
+            if add {
+                *n += by;
+            } else {
+                *n -= by;
+            }
+        };
+        step(&mut self.files, 1);
+        let name = rel.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if is_readme(name) && rel.parent() == Some(Path::new("")) {
+            step(&mut self.readmes, 1);
+        }
+        if is_doc(name) {
+            step(&mut self.doc_files, 1);
+            step(&mut self.doc_bytes, size);
+        } else if lang::from_name(rel).is_some_and(is_code) {
+            step(&mut self.code_bytes, size);
+        }
+    }
+
+    /// Doc bytes as a percentage of code bytes; `None` without code.
+    pub fn ratio(&self) -> Option<f64> {
+        (self.code_bytes > 0).then(|| self.doc_bytes as f64 / self.code_bytes as f64 * 100.0)
+    }
+}
+
+/// The top-level directory `rel` sits in and its path below it; `None`
+/// for files directly in the root.
+pub fn split_top(rel: &Path) -> Option<(PathBuf, &Path)> {
+    let mut components = rel.components();
+    let Some(Component::Normal(top)) = components.next() else {
+        return None;
+    };
+    let rest = components.as_path();
+    (!rest.as_os_str().is_empty()).then(|| (PathBuf::from(top), rest))
+}
+
+fn is_readme(name: &str) -> bool {
+    let stem = name.split_once('.').map_or(name, |(stem, _)| stem);
+    stem.eq_ignore_ascii_case("README")
+}
+
+fn is_doc(name: &str) -> bool {
+    is_readme(name)
+        || name
+            .rsplit_once('.')
+            .is_some_and(|(_, ext)| DOC_EXTS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_docs_from_code() {
+        assert_eq!(split_top(Path::new("README.md")), None);
+        assert_eq!(
+            split_top(Path::new("pkg/src/lib.rs")),
+            Some((PathBuf::from("pkg"), Path::new("src/lib.rs")))
+        );
+
+        let mut stat = DocStat::default();
+        stat.add(Path::new("README.md"), 100);
+        stat.add(Path::new("docs/guide.rst"), 300);
+        stat.add(Path::new("docs/README"), 50);
+        stat.add(Path::new("src/lib.rs"), 900);
+        stat.add(Path::new("Cargo.toml"), 200);
+        assert_eq!(stat.readmes, 1);
+        assert_eq!(stat.doc_files, 3);
+        assert_eq!(stat.doc_bytes, 450);
+        assert_eq!(stat.code_bytes, 900);
+        assert_eq!(stat.ratio(), Some(50.0));
+        stat.remove(Path::new("src/lib.rs"), 900);
+        assert_eq!(stat.ratio(), None);
+    }
+}
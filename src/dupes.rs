@@ -0,0 +1,432 @@
+//! `--dupes`: files with the same contents. Files are grouped by size and
+//! only same-sized ones are hashed (SHA-256). Hard links (the same device
+//! and inode, on Unix) are one file on disk: they're hashed once and never
+//! count as wasted space.
+//!
+//! The hashes are kept in the root's store (`roots/<key>/hashes.tsv`),
+//! keyed by device and inode along with the size and modification time, so
+//! the next scan of a backup volume only hashes the files that changed.
+//! Where mtimes can't be trusted, `--cache-key fingerprint` replaces the
+//! modification time with a hash of the first and last 64 KB.
+//!
+//! On the parallel walker, same-sized files are hashed as they arrive, on a
+//! pool of `--hash-threads` threads of their own (see `Pipeline`), so the
+//! hashing overlaps line counting instead of waiting for it; `find` only
+//! hashes what the pool didn't.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Read, Seek, SeekFrom},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crossbeam_channel::{Sender, bounded};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+use crate::config::{CacheKey, Config, DEFAULT_TOP};
+use crate::history;
+use crate::scan::{FileRecord, Summary};
+use crate::store::RootStore;
+use crate::throttle::Throttled;
+use crate::vfs::{FileSystem, OsFs};
+
+const CACHE_FILE: &str = "hashes.tsv";
+/// How much of each end of a file `--cache-key fingerprint` reads.
+const FINGERPRINT_BLOCK: u64 = 64 * 1024;
+
+/// Files sharing one content hash.
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub size: u64,
+    pub hash: String,
+    /// Every path, hard links included, in path order.
+    pub paths: Vec<PathBuf>,
+    /// Distinct files on disk: `paths` minus the extra hard links.
+    pub copies: u64,
+}
+
+impl Group {
+    /// Bytes the copies beyond the first take up.
+    pub fn wasted(&self) -> u64 {
+        self.size * self.copies.saturating_sub(1)
+    }
+}
+
+/// (device, inode) → (size, stamp, hash). The stamp is the mtime in ns, or
+/// `fp:` and the fingerprint, so switching `--cache-key` re-hashes.
+type Cache = HashMap<(u64, u64), (u64, String, String)>;
+
+/// What hashing a file needs to know of it.
+#[derive(Debug, Clone)]
+struct Job {
+    path: PathBuf,
+    size: u64,
+    mtime: Option<SystemTime>,
+    file_id: Option<(u64, u64)>,
+}
+
+impl Job {
+    fn of(file: &FileRecord) -> Self {
+        Job {
+            path: file.path.clone(),
+            size: file.size,
+            mtime: file.mtime,
+            file_id: file.file_id,
+        }
+    }
+}
+
+/// A file's hash, and the cache key it's kept under.
+#[derive(Debug, Clone)]
+struct Found {
+    key: Option<((u64, u64), String)>,
+    hash: String,
+    reused: bool,
+}
+
+/// Hashes from the pool, by path; `None` for files that couldn't be read.
+type Early = HashMap<PathBuf, Option<Found>>;
+
+/// Hashes same-sized files on their own threads while the walk goes on.
+/// The aggregator `feed`s it every record; a file is sent to the pool once
+/// another of its size turns up, which is when it can have a duplicate.
+/// The pool's queue is bounded, so hashing that falls behind holds up the
+/// aggregator and, through it, the walker.
+pub struct Pipeline {
+    jobs: Option<Sender<Job>>,
+    workers: Vec<thread::JoinHandle<()>>,
+    /// The first file of each size, until a second turns up; `None` once
+    /// the size's files go straight to the pool.
+    first: HashMap<u64, Option<Job>>,
+    /// Files on disk sent already, so hard links are hashed once.
+    sent: HashSet<(u64, u64)>,
+    early: Arc<Mutex<Early>>,
+}
+
+impl Pipeline {
+    /// Starts `--hash-threads` threads (one per CPU by default) hashing host
+    /// files.
+    pub fn start(config: &Arc<Config>) -> Self {
+        let threads = match config.hash_threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        let (tx, rx) = bounded::<Job>(threads * 4);
+        let old = Arc::new(
+            history::data_dir()
+                .and_then(|dir| RootStore::open(&dir, &history::root_id(&config.root)).ok())
+                .map(|store| load(&store))
+                .unwrap_or_default(),
+        );
+        let early = Arc::new(Mutex::new(Early::new()));
+        let workers = (0..threads)
+            .map(|_| {
+                let (rx, old, early, config) =
+                    (rx.clone(), old.clone(), early.clone(), config.clone());
+                thread::spawn(move || {
+                    for job in rx {
+                        let found = hash_of(&OsFs, &config, &old, &job);
+                        lock(&early).insert(job.path, found);
+                    }
+                })
+            })
+            .collect();
+        debug!(threads, "hashing pipeline started");
+        Pipeline {
+            jobs: Some(tx),
+            workers,
+            first: HashMap::new(),
+            sent: HashSet::new(),
+            early,
+        }
+    }
+
+    pub fn feed(&mut self, file: &FileRecord) {
+        if file.size == 0 || file.placeholder {
+            return;
+        }
+        let job = Job::of(file);
+        let ready = match self.first.get_mut(&file.size) {
+            None => {
+                self.first.insert(file.size, Some(job));
+                return;
+            }
+            Some(first) => first.take().into_iter().chain([job]),
+        };
+        for job in ready {
+            if job.file_id.is_none_or(|id| self.sent.insert(id))
+                && let Some(jobs) = &self.jobs
+            {
+                // A send only fails once every worker is gone, and `find`
+                // hashes what they didn't.
+                let _ = jobs.send(job);
+            }
+        }
+    }
+
+    /// Waits for the pool to work through its queue.
+    fn finish(mut self) -> Early {
+        drop(self.jobs.take());
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+        std::mem::take(&mut *lock(&self.early))
+    }
+}
+
+/// The duplicate groups among `summary.files`, largest waste first,
+/// `--top` (default 10) of them, taking what `pipeline` hashed already.
+/// Files that can't be read are left out.
+pub fn find(
+    fs: &dyn FileSystem,
+    config: &Config,
+    summary: &Summary,
+    pipeline: Option<Pipeline>,
+) -> Vec<Group> {
+    let mut early = pipeline.map(Pipeline::finish).unwrap_or_default();
+    let mut by_size: HashMap<u64, Vec<&FileRecord>> = HashMap::new();
+    for file in summary
+        .files
+        .iter()
+        .filter(|f| f.size > 0 && !f.placeholder)
+    {
+        by_size.entry(file.size).or_default().push(file);
+    }
+    by_size.retain(|_, files| files.len() > 1);
+
+    // Only host files have inodes worth remembering.
+    let store = fs
+        .is_native()
+        .then(history::data_dir)
+        .flatten()
+        .and_then(|dir| RootStore::open(&dir, &history::root_id(&config.root)).ok());
+    let lock = store.as_ref().and_then(|s| s.lock().ok());
+    let old = store.as_ref().map(load).unwrap_or_default();
+    let mut cache = Cache::new();
+    let (mut hashed, mut reused) = (0, 0);
+
+    let mut groups: HashMap<String, Group> = HashMap::new();
+    for files in by_size.values() {
+        // One hash per file on disk, whichever of its links comes first.
+        let mut by_id: HashMap<(u64, u64), String> = HashMap::new();
+        for file in files {
+            let (key, hash) = match file.file_id.and_then(|id| by_id.get(&id).cloned()) {
+                Some(hash) => (None, hash),
+                None => {
+                    let found = match early.remove(&file.path) {
+                        Some(found) => found,
+                        None => hash_of(fs, config, &old, &Job::of(file)),
+                    };
+                    let Some(found) = found else {
+                        continue;
+                    };
+                    if found.reused {
+                        reused += 1;
+                    } else {
+                        hashed += 1;
+                    }
+                    (found.key, found.hash)
+                }
+            };
+            if let Some((id, stamp)) = key {
+                by_id.insert(id, hash.clone());
+                cache.insert(id, (file.size, stamp, hash.clone()));
+            }
+            let group = groups.entry(hash.clone()).or_insert_with(|| Group {
+                size: file.size,
+                hash,
+                paths: Vec::new(),
+                copies: 0,
+            });
+            group.paths.push(file.path.clone());
+        }
+    }
+    debug!(hashed, reused, "duplicate hashes");
+
+    if let Some(store) = &store {
+        // Entries for files this scan didn't see are dropped with the rest
+        // of the old cache.
+        let seen: HashSet<(u64, u64)> = summary.files.iter().filter_map(|f| f.file_id).collect();
+        for (id, entry) in old {
+            if seen.contains(&id) {
+                cache.entry(id).or_insert(entry);
+            }
+        }
+        if let Err(err) = save(store, &cache) {
+            warn!("cannot save duplicate hashes: {}", err);
+        }
+    }
+    drop(lock);
+
+    let ids: HashMap<&PathBuf, Option<(u64, u64)>> =
+        summary.files.iter().map(|f| (&f.path, f.file_id)).collect();
+    let mut groups: Vec<Group> = groups
+        .into_values()
+        .filter(|g| g.paths.len() > 1)
+        .map(|mut g| {
+            g.paths.sort();
+            let mut on_disk = HashSet::new();
+            g.copies = g
+                .paths
+                .iter()
+                .filter(|p| ids[p].is_none_or(|id| on_disk.insert(id)))
+                .count() as u64;
+            g
+        })
+        .filter(|g| g.copies > 1)
+        .collect();
+    groups.sort_by(|a, b| {
+        b.wasted()
+            .cmp(&a.wasted())
+            .then_with(|| a.paths.cmp(&b.paths))
+    });
+    groups.truncate(config.top.unwrap_or(DEFAULT_TOP));
+    groups
+}
+
+/// The hash of `file`, from `old` when its entry there still matches;
+/// `None` when it can't be read.
+fn hash_of(fs: &dyn FileSystem, config: &Config, old: &Cache, file: &Job) -> Option<Found> {
+    let key = file.file_id.zip(stamp(fs, config, file));
+    if let Some((_, _, hash)) = key
+        .as_ref()
+        .and_then(|(id, stamp)| old.get(id).filter(|e| e.0 == file.size && e.1 == *stamp))
+    {
+        return Some(Found {
+            hash: hash.clone(),
+            key,
+            reused: true,
+        });
+    }
+    match hash_file(fs, config, file) {
+        Ok(hash) => Some(Found {
+            key,
+            hash,
+            reused: false,
+        }),
+        Err(err) => {
+            warn!(path = %file.path.display(), "cannot hash: {}", err);
+            None
+        }
+    }
+}
+
+/// What the cache entry for `file` must match besides its size.
+fn stamp(fs: &dyn FileSystem, config: &Config, file: &Job) -> Option<String> {
+    match config.cache_key {
+        CacheKey::Mtime => file
+            .mtime
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|mtime| mtime.as_nanos().to_string()),
+        CacheKey::Fingerprint => match fingerprint(fs, config, file) {
+            Ok(fp) => Some(format!("fp:{}", fp)),
+            Err(err) => {
+                debug!(path = %file.path.display(), "cannot fingerprint: {}", err);
+                None
+            }
+        },
+    }
+}
+
+/// SHA-256 of the first and last `FINGERPRINT_BLOCK` bytes (the whole file
+/// when it's shorter than two blocks).
+fn fingerprint(fs: &dyn FileSystem, config: &Config, file: &Job) -> io::Result<String> {
+    let mut reader = fs.open(&file.path)?;
+    let mut buf = Vec::new();
+    let mut read_block = |reader: &mut dyn Read, len: u64| {
+        Throttled {
+            inner: reader.take(len),
+            throttle: config.throttle.as_deref(),
+        }
+        .read_to_end(&mut buf)
+    };
+    if file.size <= 2 * FINGERPRINT_BLOCK {
+        read_block(&mut reader, file.size)?;
+    } else {
+        read_block(&mut reader, FINGERPRINT_BLOCK)?;
+        reader.seek(SeekFrom::End(-(FINGERPRINT_BLOCK as i64)))?;
+        read_block(&mut reader, FINGERPRINT_BLOCK)?;
+    }
+    Ok(format!("{:x}", Sha256::digest(&buf)))
+}
+
+fn hash_file(fs: &dyn FileSystem, config: &Config, file: &Job) -> io::Result<String> {
+    let mut reader = Throttled {
+        inner: fs.open(&file.path)?,
+        throttle: config.throttle.as_deref(),
+    };
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// `dev ino size stamp hash` per line, tab-separated. A missing or
+/// damaged file only costs a re-hash.
+fn load(store: &RootStore) -> Cache {
+    let Ok(text) = std::fs::read_to_string(store.path(CACHE_FILE)) else {
+        return Cache::new();
+    };
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let dev = fields.next()?.parse().ok()?;
+            let ino = fields.next()?.parse().ok()?;
+            let size = fields.next()?.parse().ok()?;
+            let stamp = fields.next()?.to_string();
+            let hash = fields.next()?.to_string();
+            Some(((dev, ino), (size, stamp, hash)))
+        })
+        .collect()
+}
+
+fn save(store: &RootStore, cache: &Cache) -> io::Result<()> {
+    let mut text = String::new();
+    for ((dev, ino), (size, stamp, hash)) in cache {
+        text.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\n",
+            dev, ino, size, stamp, hash
+        ));
+    }
+    store.write_atomic(CACHE_FILE, text.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan;
+    use crate::vfs::MemFs;
+
+    #[test]
+    fn groups_identical_files() {
+        let mut fs = MemFs::default();
+        fs.insert("r/a.txt", "same\n");
+        fs.insert("r/sub/b.txt", "same\n");
+        fs.insert("r/c.txt", "diff\n");
+        fs.insert("r/d.txt", "other contents\n");
+
+        let config = Config::from_args(["r", "--dupes"].map(String::from)).unwrap();
+        let summary = scan::scan_fs(&fs, &config).unwrap();
+        assert_eq!(summary.duplicates.len(), 1);
+        let group = &summary.duplicates[0];
+        assert_eq!(
+            group.paths,
+            [PathBuf::from("r/a.txt"), PathBuf::from("r/sub/b.txt")]
+        );
+        assert_eq!((group.copies, group.wasted()), (2, 5));
+    }
+}
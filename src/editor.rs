@@ -0,0 +1,28 @@
+//! `--open-max` and the explore prompt's `o` key: open a file in the user's
+//! editor, `$VISUAL` or `$EDITOR` (which may carry arguments, like
+//! `code -w`), else `vi` (`notepad` on Windows).
+
+use std::{env, io, path::Path, process::Command};
+
+pub fn open(path: &Path) -> io::Result<()> {
+    let editor = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .map_err(|err| io::Error::other(format!("Cannot start {}: {}", program, err)))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{} exited with {}",
+            program, status
+        )))
+    }
+}
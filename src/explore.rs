@@ -0,0 +1,150 @@
+//! `--explore`: a one-key prompt after the report for poking around the
+//! results. `d` drills into the largest subdirectory of the current one,
+//! `u` goes back up, `f` lists the current directory's biggest files, `o`
+//! opens the file with the most lines below it in the editor and `q` quits. Everything comes from the records the scan already kept;
+//! nothing is read from disk again. Skipped when stdin or stdout isn't a
+//! terminal, so scripts never wait on it.
+
+use std::{
+    io::{self, IsTerminal, Write},
+    path::Path,
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    terminal,
+};
+
+use crate::config::{Config, DEFAULT_TOP};
+use crate::editor;
+use crate::format::{display_relative_path, format_num};
+use crate::lists;
+use crate::report::{Cell, Style, print_table};
+use crate::scan::{FileRecord, Summary};
+
+pub fn run(config: &Config, summary: &Summary) -> io::Result<()> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Ok(());
+    }
+    let style = Style::new(config);
+    let limit = config.top.unwrap_or(DEFAULT_TOP);
+    let mut current = config.root.clone();
+    loop {
+        let largest = lists::subdirs(summary, &current)
+            .first()
+            .map(|(path, _)| path.clone());
+        println!();
+        print!(
+            "{}",
+            style.label(&format!(
+                "{}: d drill into {} · u up · f top files · o open max-lines file · q quit ",
+                name(config, &current),
+                largest
+                    .as_deref()
+                    .map_or("-".to_string(), |p| name(config, p))
+            ))
+        );
+        io::stdout().flush()?;
+        let key = read_key()?;
+        println!();
+        match key {
+            'd' => match largest {
+                Some(dir) => {
+                    current = dir;
+                    let rows: Vec<Vec<Cell>> = lists::subdirs(summary, &current)
+                        .iter()
+                        .take(limit)
+                        .map(|(path, stat)| {
+                            vec![
+                                Cell::size(config, stat.size),
+                                format_num(stat.files).into(),
+                                format_num(stat.lines).into(),
+                                name(config, path).into(),
+                            ]
+                        })
+                        .collect();
+                    println!();
+                    print_table(
+                        &style,
+                        &format!("Subdirectories of {}", name(config, &current)),
+                        &["Size", "Files", "Lines", "Path"],
+                        &rows,
+                    );
+                }
+                None => println!("{} has no subdirectories", name(config, &current)),
+            },
+            'u' => match current.parent() {
+                Some(parent) if current != config.root => current = parent.to_path_buf(),
+                _ => println!("Already at the top"),
+            },
+            'f' => {
+                let mut files: Vec<&FileRecord> = summary
+                    .files
+                    .iter()
+                    .filter(|f| f.path.starts_with(&current))
+                    .collect();
+                files.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+                let rows: Vec<Vec<Cell>> = files
+                    .iter()
+                    .take(limit)
+                    .map(|f| {
+                        vec![
+                            Cell::size(config, f.size),
+                            format_num(f.lines).into(),
+                            name(config, &f.path).into(),
+                        ]
+                    })
+                    .collect();
+                println!();
+                print_table(
+                    &style,
+                    &format!("Top files in {}", name(config, &current)),
+                    &["Size", "Lines", "Path"],
+                    &rows,
+                );
+            }
+            'o' => {
+                let max = summary
+                    .files
+                    .iter()
+                    .filter(|f| f.path.starts_with(&current))
+                    .max_by(|a, b| a.lines.cmp(&b.lines).then_with(|| b.path.cmp(&a.path)));
+                match max {
+                    Some(file) => {
+                        if let Err(err) = editor::open(&file.path) {
+                            eprintln!("Cannot open {}: {}", name(config, &file.path), err);
+                        }
+                    }
+                    None => println!("{} has no files", name(config, &current)),
+                }
+            }
+            'q' => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+fn name(config: &Config, path: &Path) -> String {
+    display_relative_path(path, &config.root)
+}
+
+/// One key press, without waiting for Enter. Esc and Ctrl-C count as `q`.
+fn read_key() -> io::Result<char> {
+    terminal::enable_raw_mode()?;
+    let key = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Ok('q');
+                }
+                KeyCode::Char(c) => break Ok(c.to_ascii_lowercase()),
+                KeyCode::Esc => break Ok('q'),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(err) => break Err(err),
+        }
+    };
+    terminal::disable_raw_mode()?;
+    key
+}
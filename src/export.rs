@@ -0,0 +1,200 @@
+use std::{
+    fs::File,
+    io,
+    path::Path,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use parquet::{
+    basic::{Compression, ZstdLevel},
+    data_type::{ByteArray, ByteArrayType, Int64Type},
+    errors::ParquetError,
+    file::{metadata::KeyValue, properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+
+use crate::config::Config;
+use crate::format::display_relative_path;
+use crate::json;
+use crate::linguist::Kind;
+use crate::scan::{FileRecord, Summary, extension_key};
+
+/// Columns of `--parquet` output. Optional columns are null unless the
+/// matching scan flag (`--by-lang`, `--mime`, `--linguist`) was given.
+const SCHEMA: &str = "
+message tengok_file {
+    required binary path (STRING);
+    required binary dir (STRING);
+    required binary ext (STRING);
+    required int64 size_bytes (INTEGER(64, false));
+    required int64 lines (INTEGER(64, false));
+    optional int64 mtime (TIMESTAMP(MILLIS, true));
+    optional binary language (STRING);
+    optional binary content_type (STRING);
+    optional binary linguist (STRING);
+}
+";
+
+/// Records per row group; bounds the writer's buffers on huge scans.
+const ROW_GROUP: usize = 1 << 20;
+
+/// Write every kept file record to `path` as a zstd-compressed Parquet file.
+/// The JSON output's `scan` object goes in the file's `tengok.scan`
+/// key-value metadata.
+pub fn write_parquet(config: &Config, summary: &Summary, path: &Path) -> io::Result<()> {
+    let file = File::create(path)?;
+    let scan = json::scan_metadata(config, summary);
+    write_records(config, &summary.files, scan, file).map_err(io::Error::other)
+}
+
+fn write_records(
+    config: &Config,
+    records: &[FileRecord],
+    scan: Option<String>,
+    file: File,
+) -> Result<(), ParquetError> {
+    let schema = Arc::new(parse_message_type(SCHEMA)?);
+    let metadata = scan.map(|scan| vec![KeyValue::new("tengok.scan".to_string(), scan)]);
+    let props = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .set_key_value_metadata(metadata)
+            .build(),
+    );
+    let mut writer = SerializedFileWriter::new(file, schema, props)?;
+
+    for chunk in records.chunks(ROW_GROUP) {
+        let mut group = writer.next_row_group()?;
+        let mut index = 0;
+        while let Some(mut column) = group.next_column()? {
+            match index {
+                0 => write_strings(&mut column, chunk, |r| {
+                    Some(display_relative_path(&r.path, &config.root))
+                })?,
+                1 => write_strings(&mut column, chunk, |r| {
+                    Some(display_relative_path(&r.parent, &config.root))
+                })?,
+                2 => write_strings(&mut column, chunk, |r| Some(extension_key(&r.path)))?,
+                3 => write_ints(&mut column, chunk, |r| Some(r.size as i64))?,
+                4 => write_ints(&mut column, chunk, |r| Some(r.lines as i64))?,
+                5 => write_ints(&mut column, chunk, |r| r.mtime.and_then(epoch_millis))?,
+                6 => write_strings(&mut column, chunk, |r| r.lang.map(String::from))?,
+                7 => write_strings(&mut column, chunk, |r| r.category.map(String::from))?,
+                _ => write_strings(&mut column, chunk, |r| {
+                    r.kind.map(|k| {
+                        match k {
+                            Kind::Generated => "generated",
+                            Kind::Vendored => "vendored",
+                        }
+                        .to_string()
+                    })
+                })?,
+            }
+            column.close()?;
+            index += 1;
+        }
+        group.close()?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+type Column<'a> = parquet::file::writer::SerializedColumnWriter<'a>;
+
+/// Definition levels are only passed for optional columns; `required`
+/// columns reject them, and their values are never `None`.
+fn levels<T>(values: &[Option<T>], optional: bool) -> Option<Vec<i16>> {
+    optional.then(|| values.iter().map(|v| i16::from(v.is_some())).collect())
+}
+
+fn write_strings(
+    column: &mut Column<'_>,
+    records: &[FileRecord],
+    value: impl Fn(&FileRecord) -> Option<String>,
+) -> Result<(), ParquetError> {
+    let values: Vec<Option<String>> = records.iter().map(value).collect();
+    let writer = column.typed::<ByteArrayType>();
+    let def = levels(&values, writer.get_descriptor().max_def_level() > 0);
+    let present: Vec<ByteArray> = values
+        .into_iter()
+        .flatten()
+        .map(|s| ByteArray::from(s.as_str()))
+        .collect();
+    writer.write_batch(&present, def.as_deref(), None)?;
+    Ok(())
+}
+
+fn write_ints(
+    column: &mut Column<'_>,
+    records: &[FileRecord],
+    value: impl Fn(&FileRecord) -> Option<i64>,
+) -> Result<(), ParquetError> {
+    let values: Vec<Option<i64>> = records.iter().map(value).collect();
+    let writer = column.typed::<Int64Type>();
+    let def = levels(&values, writer.get_descriptor().max_def_level() > 0);
+    let present: Vec<i64> = values.into_iter().flatten().collect();
+    writer.write_batch(&present, def.as_deref(), None)?;
+    Ok(())
+}
+
+fn epoch_millis(time: SystemTime) -> Option<i64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_millis() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use std::path::PathBuf;
+
+    #[test]
+    fn records_round_trip() {
+        let dir = std::env::temp_dir().join(format!("tengok-parquet-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("files.parquet");
+        let config =
+            Config::from_args(["--parquet".to_string(), out.display().to_string()]).unwrap();
+        let record = |name: &str, lines| FileRecord {
+            path: PathBuf::from(".").join(name),
+            parent: PathBuf::from("."),
+            size: 10,
+            lines,
+            mtime: None,
+            compressed: 0,
+            lang: Some("Rust"),
+            category: None,
+            kind: None,
+            matches: 0,
+            metrics: Vec::new(),
+            minified: false,
+            placeholder: false,
+            uncounted: false,
+            comments: None,
+            interpreter: None,
+            executable: None,
+            secret: None,
+            license: None,
+            file_id: None,
+            owner: None,
+            atime: None,
+            decompressed: None,
+        };
+        let records = vec![record("a.rs", 1), record("b.rs", 2)];
+        write_records(&config, &records, None, File::create(&out).unwrap()).unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&out).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        assert_eq!(
+            reader
+                .metadata()
+                .file_metadata()
+                .schema_descr()
+                .num_columns(),
+            9
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
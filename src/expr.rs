@@ -0,0 +1,547 @@
+//! `--metric NAME=AGG(FIELD where COND)`: user-defined aggregations over the
+//! file records, evaluated as each record is added to the summary.
+//!
+//! ```text
+//! metric := NAME '=' AGG '(' [FIELD] ['where' cond] ')'
+//! AGG    := sum | count | avg | min | max
+//! cond   := and ('or' and)*
+//! and    := unary ('and' unary)*
+//! unary  := 'not' unary | '(' cond ')' | FIELD OP literal
+//! OP     := == != < <= > >= =~
+//! ```
+//!
+//! Numeric fields are `size`, `lines` and `matches`, compared with numbers
+//! that accept K/M/G suffixes. Text fields are `path` and `dir` (relative to
+//! the root), `name`, `ext`, `lang`, `type` and `kind`, compared with quoted
+//! strings; `=~` takes a regex. `&&`, `||` and `!` work too.
+
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::config::parse_bytes;
+use crate::format::display_relative_path;
+use crate::linguist::Kind;
+use crate::scan::{FileRecord, extension_key};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Agg {
+    Sum,
+    Count,
+    Avg,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Size,
+    Lines,
+    Matches,
+    Path,
+    Dir,
+    Name,
+    Ext,
+    Lang,
+    Type,
+    Kind,
+}
+
+impl Field {
+    const ALL: [Self; 10] = [
+        Self::Size,
+        Self::Lines,
+        Self::Matches,
+        Self::Path,
+        Self::Dir,
+        Self::Name,
+        Self::Ext,
+        Self::Lang,
+        Self::Type,
+        Self::Kind,
+    ];
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.as_str() == name)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Size => "size",
+            Self::Lines => "lines",
+            Self::Matches => "matches",
+            Self::Path => "path",
+            Self::Dir => "dir",
+            Self::Name => "name",
+            Self::Ext => "ext",
+            Self::Lang => "lang",
+            Self::Type => "type",
+            Self::Kind => "kind",
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(self, Self::Size | Self::Lines | Self::Matches)
+    }
+
+    fn number(self, record: &FileRecord) -> u64 {
+        match self {
+            Self::Size => record.size,
+            Self::Lines => record.lines,
+            _ => record.matches,
+        }
+    }
+
+    fn text(self, record: &FileRecord, root: &Path) -> String {
+        match self {
+            Self::Path => display_relative_path(&record.path, root),
+            Self::Dir => display_relative_path(&record.parent, root),
+            Self::Name => record
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            Self::Ext => extension_key(&record.path),
+            Self::Lang => record.lang.unwrap_or_default().to_string(),
+            Self::Type => record.category.unwrap_or_default().to_string(),
+            _ => match record.kind {
+                Some(Kind::Generated) => "generated",
+                Some(Kind::Vendored) => "vendored",
+                None => "",
+            }
+            .to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn test<T: Ord + ?Sized>(self, a: &T, b: &T) -> bool {
+        match self {
+            Self::Eq => a == b,
+            Self::Ne => a != b,
+            Self::Lt => a < b,
+            Self::Le => a <= b,
+            Self::Gt => a > b,
+            Self::Ge => a >= b,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Cond {
+    And(Box<Cond>, Box<Cond>),
+    Or(Box<Cond>, Box<Cond>),
+    Not(Box<Cond>),
+    Number(Field, Op, u64),
+    Text(Field, Op, String),
+    Regex(Field, Regex),
+}
+
+impl Cond {
+    fn eval(&self, record: &FileRecord, root: &Path) -> bool {
+        match self {
+            Self::And(a, b) => a.eval(record, root) && b.eval(record, root),
+            Self::Or(a, b) => a.eval(record, root) || b.eval(record, root),
+            Self::Not(c) => !c.eval(record, root),
+            Self::Number(field, op, n) => op.test(&field.number(record), n),
+            Self::Text(field, op, s) => op.test(field.text(record, root).as_str(), s.as_str()),
+            Self::Regex(field, re) => re.is_match(&field.text(record, root)),
+        }
+    }
+
+    fn fields(&self, out: &mut Vec<Field>) {
+        match self {
+            Self::And(a, b) | Self::Or(a, b) => {
+                a.fields(out);
+                b.fields(out);
+            }
+            Self::Not(c) => c.fields(out),
+            Self::Number(f, ..) | Self::Text(f, ..) | Self::Regex(f, _) => out.push(*f),
+        }
+    }
+}
+
+/// One parsed `--metric`.
+#[derive(Debug, Clone)]
+pub struct Metric {
+    pub name: String,
+    /// The expression as written, after `NAME=`.
+    pub expr: String,
+    pub agg: Agg,
+    /// `None` only for `count()`.
+    pub field: Option<Field>,
+    filter: Option<Cond>,
+}
+
+impl Metric {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let err = |msg: &str| format!("Invalid --metric {:?}: {}", spec, msg);
+        let (name, expr) = spec
+            .split_once('=')
+            .filter(|(name, _)| {
+                !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            })
+            .ok_or_else(|| err("expected NAME=AGG(...)"))?;
+        let mut parser = Parser {
+            tokens: tokenize(expr).map_err(|e| err(&e))?,
+            pos: 0,
+        };
+        let (agg, field, filter) = parser.metric().map_err(|e| err(&e))?;
+        Ok(Self {
+            name: name.to_string(),
+            expr: expr.trim().to_string(),
+            agg,
+            field,
+            filter,
+        })
+    }
+
+    /// Every field the metric reads, to check the scan collects them.
+    pub fn fields(&self) -> Vec<Field> {
+        let mut out: Vec<Field> = self.field.into_iter().collect();
+        if let Some(filter) = &self.filter {
+            filter.fields(&mut out);
+        }
+        out
+    }
+
+    /// The value this record contributes, or `None` when it's filtered out.
+    pub fn value(&self, record: &FileRecord, root: &Path) -> Option<u64> {
+        if self.filter.as_ref().is_some_and(|c| !c.eval(record, root)) {
+            return None;
+        }
+        Some(self.field.map_or(1, |f| f.number(record)))
+    }
+
+    /// Whether the result is a byte size (for formatting).
+    pub fn is_bytes(&self) -> bool {
+        self.agg != Agg::Count && self.field == Some(Field::Size)
+    }
+}
+
+/// Running aggregate of one metric. `min`/`max` are cleared when the
+/// record holding them is removed; the caller recomputes them, like
+/// `Summary::max_lines_file`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricTotal {
+    pub sum: u64,
+    pub count: u64,
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+}
+
+impl MetricTotal {
+    pub fn add(&mut self, value: u64) {
+        self.sum += value;
+        self.count += 1;
+        self.min = Some(self.min.map_or(value, |m| m.min(value)));
+        self.max = Some(self.max.map_or(value, |m| m.max(value)));
+    }
+
+    pub fn remove(&mut self, value: u64) {
+        self.sum -= value;
+        self.count -= 1;
+        if self.min == Some(value) {
+            self.min = None;
+        }
+        if self.max == Some(value) {
+            self.max = None;
+        }
+    }
+
+    /// Whether `min`/`max` need recomputing from the records.
+    pub fn is_stale(&self, agg: Agg) -> bool {
+        self.count > 0
+            && match agg {
+                Agg::Min => self.min.is_none(),
+                Agg::Max => self.max.is_none(),
+                _ => false,
+            }
+    }
+
+    /// The aggregate; `None` for min/max/avg over no files.
+    pub fn result(&self, agg: Agg) -> Option<f64> {
+        match agg {
+            Agg::Sum => Some(self.sum as f64),
+            Agg::Count => Some(self.count as f64),
+            Agg::Avg => (self.count > 0).then(|| self.sum as f64 / self.count as f64),
+            Agg::Min => self.min.map(|v| v as f64),
+            Agg::Max => self.max.map(|v| v as f64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(u64),
+    Str(String),
+    Op(&'static str),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    const OPS: &[&str] = &[
+        "==", "!=", "<=", ">=", "=~", "&&", "||", "<", ">", "!", "(", ")",
+    ];
+    let mut tokens = Vec::new();
+    let mut rest = input.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if let Some(op) = OPS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else if c == '"' {
+            let mut value = String::new();
+            let mut chars = rest[1..].char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((i, '"')) => break i + 2,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, c)) => value.push(c),
+                        None => return Err("unterminated string".to_string()),
+                    },
+                    Some((_, c)) => value.push(c),
+                    None => return Err("unterminated string".to_string()),
+                }
+            };
+            tokens.push(Token::Str(value));
+            rest = &rest[end..];
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            tokens.push(if c.is_ascii_digit() {
+                Token::Number(parse_bytes(word).ok_or_else(|| format!("bad number {}", word))?)
+            } else {
+                Token::Ident(word.to_string())
+            });
+            rest = &rest[end..];
+        } else {
+            return Err(format!("unexpected {:?}", c));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Op(o)) if *o == op);
+        self.pos += usize::from(found);
+        found
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Ident(w)) if w == word);
+        self.pos += usize::from(found);
+        found
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        if self.eat(op) {
+            Ok(())
+        } else {
+            Err(format!("expected `{}`", op))
+        }
+    }
+
+    fn metric(&mut self) -> Result<(Agg, Option<Field>, Option<Cond>), String> {
+        let agg = match self.next() {
+            Some(Token::Ident(w)) => match w.as_str() {
+                "sum" => Agg::Sum,
+                "count" => Agg::Count,
+                "avg" => Agg::Avg,
+                "min" => Agg::Min,
+                "max" => Agg::Max,
+                _ => return Err(format!("unknown aggregate {}", w)),
+            },
+            _ => return Err("expected sum, count, avg, min or max".to_string()),
+        };
+        self.expect("(")?;
+        let field = match self.peek() {
+            Some(Token::Ident(w)) if w != "where" => {
+                let w = w.clone();
+                self.pos += 1;
+                let field = Field::parse(&w).ok_or_else(|| format!("unknown field {}", w))?;
+                if !field.is_numeric() {
+                    return Err(format!("{} is not a numeric field", w));
+                }
+                Some(field)
+            }
+            _ => None,
+        };
+        if field.is_none() && agg != Agg::Count {
+            return Err("only count() works without a field".to_string());
+        }
+        let filter = if self.eat_word("where") {
+            Some(self.or()?)
+        } else {
+            None
+        };
+        self.expect(")")?;
+        if self.pos < self.tokens.len() {
+            return Err("unexpected input after `)`".to_string());
+        }
+        Ok((agg, field, filter))
+    }
+
+    fn or(&mut self) -> Result<Cond, String> {
+        let mut cond = self.and()?;
+        while self.eat("||") || self.eat_word("or") {
+            cond = Cond::Or(Box::new(cond), Box::new(self.and()?));
+        }
+        Ok(cond)
+    }
+
+    fn and(&mut self) -> Result<Cond, String> {
+        let mut cond = self.unary()?;
+        while self.eat("&&") || self.eat_word("and") {
+            cond = Cond::And(Box::new(cond), Box::new(self.unary()?));
+        }
+        Ok(cond)
+    }
+
+    fn unary(&mut self) -> Result<Cond, String> {
+        if self.eat("!") || self.eat_word("not") {
+            return Ok(Cond::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let cond = self.or()?;
+            self.expect(")")?;
+            return Ok(cond);
+        }
+        let field = match self.next() {
+            Some(Token::Ident(w)) => {
+                Field::parse(&w).ok_or_else(|| format!("unknown field {}", w))?
+            }
+            _ => return Err("expected a field".to_string()),
+        };
+        let op = match self.next() {
+            Some(Token::Op("==")) => Op::Eq,
+            Some(Token::Op("!=")) => Op::Ne,
+            Some(Token::Op("<")) => Op::Lt,
+            Some(Token::Op("<=")) => Op::Le,
+            Some(Token::Op(">")) => Op::Gt,
+            Some(Token::Op(">=")) => Op::Ge,
+            Some(Token::Op("=~")) => {
+                return match self.next() {
+                    Some(Token::Str(s)) if !field.is_numeric() => Regex::new(&s)
+                        .map(|re| Cond::Regex(field, re))
+                        .map_err(|e| e.to_string()),
+                    _ => Err("=~ needs a text field and a quoted regex".to_string()),
+                };
+            }
+            _ => return Err("expected a comparison".to_string()),
+        };
+        match (self.next(), field.is_numeric()) {
+            (Some(Token::Number(n)), true) => Ok(Cond::Number(field, op, n)),
+            (Some(Token::Str(s)), false) => Ok(Cond::Text(field, op, s)),
+            (_, true) => Err("numeric fields compare with numbers".to_string()),
+            (_, false) => Err("text fields compare with quoted strings".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn record(path: &str, size: u64, lines: u64) -> FileRecord {
+        let path = PathBuf::from("r").join(path);
+        FileRecord {
+            parent: path.parent().unwrap().to_path_buf(),
+            path,
+            size,
+            lines,
+            mtime: None,
+            compressed: 0,
+            lang: None,
+            category: None,
+            kind: None,
+            matches: 0,
+            metrics: Vec::new(),
+            minified: false,
+            placeholder: false,
+            uncounted: false,
+            comments: None,
+            interpreter: None,
+            executable: None,
+            secret: None,
+            license: None,
+            file_id: None,
+            owner: None,
+            atime: None,
+            decompressed: None,
+        }
+    }
+
+    #[test]
+    fn metrics_filter_and_aggregate() {
+        let records = [
+            record("src/a.rs", 1_500, 10),
+            record("src/b.RS", 500, 30),
+            record("docs/c.md", 2_000, 5),
+        ];
+        let run = |spec: &str| {
+            let metric = Metric::parse(spec).unwrap();
+            let mut total = MetricTotal::default();
+            for r in &records {
+                if let Some(v) = metric.value(r, Path::new("r")) {
+                    total.add(v);
+                }
+            }
+            total.result(metric.agg)
+        };
+        assert_eq!(run(r#"rs=sum(size where ext=="rs")"#), Some(2_000.0));
+        assert_eq!(run("n=count()"), Some(3.0));
+        assert_eq!(
+            run("big=count(where size >= 1K and not lines > 20)"),
+            Some(2.0)
+        );
+        assert_eq!(
+            run(r#"m=max(lines where path =~ "^src/" || name == "c.md")"#),
+            Some(30.0)
+        );
+        assert_eq!(run(r#"a=avg(lines where dir != "src")"#), Some(5.0));
+        assert_eq!(run(r#"e=min(size where ext == "go")"#), None);
+
+        for bad in [
+            "sum(size)",
+            "x=sum()",
+            "x=sum(path)",
+            "x=count(where size == \"1\")",
+            "x=count(where)",
+        ] {
+            assert!(Metric::parse(bad).is_err(), "{bad}");
+        }
+    }
+}
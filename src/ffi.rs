@@ -0,0 +1,256 @@
+//! C API for embedding the scanner; `include/tengok.h` is the matching
+//! header. Options are passed as CLI arguments, so new flags never change
+//! the ABI. Every function is safe to call from any thread, and errors are
+//! reported per thread through `tengok_last_error`.
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, c_char, c_int, c_void},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+    sync::Arc,
+    time::UNIX_EPOCH,
+};
+
+use crate::config::{Config, Output};
+use crate::format::display_relative_path;
+use crate::json;
+use crate::scan::{self, Summary};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(message: String) {
+    let message = CString::new(message).unwrap_or_else(|_| c"invalid error message".into());
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// One scanned file, valid only for the duration of the callback.
+#[repr(C)]
+pub struct TengokRecord {
+    /// Relative to the scanned root, NUL-terminated UTF-8 (lossy).
+    pub path: *const c_char,
+    pub size_bytes: u64,
+    pub lines: u64,
+    /// Seconds since the Unix epoch, or -1 when unknown.
+    pub mtime: i64,
+}
+
+/// Called for every file; return non-zero to stop the scan early.
+pub type TengokRecordCallback =
+    Option<unsafe extern "C" fn(record: *const TengokRecord, user_data: *mut c_void) -> c_int>;
+
+/// Opaque result of `tengok_scan`.
+pub struct TengokSummary {
+    config: Arc<Config>,
+    summary: Summary,
+}
+
+fn parse_args(argc: usize, argv: *const *const c_char) -> Result<Arc<Config>, String> {
+    let args: &[*const c_char] = if argc == 0 {
+        &[]
+    } else {
+        // SAFETY: the caller passes `argc` valid pointers.
+        unsafe { slice::from_raw_parts(argv, argc) }
+    };
+    let args = args
+        .iter()
+        .map(|&arg| {
+            // SAFETY: each pointer is a NUL-terminated string.
+            let arg = unsafe { CStr::from_ptr(arg) };
+            arg.to_str()
+                .map(String::from)
+                .map_err(|_| "arguments must be UTF-8".to_string())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut config = Config::from_args(args)?;
+    if !config.root.exists() {
+        return Err(format!("Path does not exist: {}", config.root.display()));
+    }
+    // Embedders own the terminal, if there is one.
+    config.output = Output::Json;
+    Ok(Arc::new(config))
+}
+
+/// Scan with CLI-style arguments (`argv` excludes the program name, e.g.
+/// `{"src", "--by-ext"}`), calling `callback` on the calling thread for each
+/// file. Returns NULL on error; see `tengok_last_error`.
+///
+/// # Safety
+///
+/// `argv` must point to `argc` NUL-terminated strings, and `callback`, if
+/// set, must be safe to call with `user_data`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tengok_scan(
+    argc: usize,
+    argv: *const *const c_char,
+    callback: TengokRecordCallback,
+    user_data: *mut c_void,
+) -> *mut TengokSummary {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let config = parse_args(argc, argv)?;
+        let summary = scan::scan_dir_with(&config, |record| {
+            let Some(callback) = callback else {
+                return true;
+            };
+            let path = display_relative_path(&record.path, &config.root);
+            let path = CString::new(path).unwrap_or_default();
+            let out = TengokRecord {
+                path: path.as_ptr(),
+                size_bytes: record.size,
+                lines: record.lines,
+                mtime: record
+                    .mtime
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map_or(-1, |d| d.as_secs() as i64),
+            };
+            // SAFETY: upheld by the caller.
+            unsafe { callback(&out, user_data) == 0 }
+        })
+        .map_err(|err| err.to_string())?;
+        Ok::<_, String>(TengokSummary { config, summary })
+    }));
+    match result {
+        Ok(Ok(summary)) => Box::into_raw(Box::new(summary)),
+        Ok(Err(err)) => {
+            set_error(err);
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_error("tengok panicked during the scan".to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Message for the last failed call on this thread, or NULL. Valid until
+/// the next failing call on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn tengok_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+}
+
+/// The library version, e.g. `"0.1.1"`.
+#[unsafe(no_mangle)]
+pub extern "C" fn tengok_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// # Safety
+///
+/// `summary` must come from `tengok_scan` and not have been freed.
+unsafe fn summary_ref<'a>(summary: *const TengokSummary) -> &'a TengokSummary {
+    // SAFETY: upheld by the caller.
+    unsafe { &*summary }
+}
+
+/// # Safety
+///
+/// `summary` must be a live result of `tengok_scan`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tengok_summary_files(summary: *const TengokSummary) -> u64 {
+    unsafe { summary_ref(summary) }.summary.total_files
+}
+
+/// # Safety
+///
+/// `summary` must be a live result of `tengok_scan`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tengok_summary_size_bytes(summary: *const TengokSummary) -> u64 {
+    unsafe { summary_ref(summary) }.summary.total_size
+}
+
+/// # Safety
+///
+/// `summary` must be a live result of `tengok_scan`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tengok_summary_lines(summary: *const TengokSummary) -> u64 {
+    unsafe { summary_ref(summary) }.summary.total_lines
+}
+
+/// The `tengok --json` document; free it with `tengok_string_free`.
+///
+/// # Safety
+///
+/// `summary` must be a live result of `tengok_scan`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tengok_summary_json(summary: *const TengokSummary) -> *mut c_char {
+    let summary = unsafe { summary_ref(summary) };
+    let text = json::to_value(&summary.config, &summary.summary).to_string();
+    // serde_json escapes control characters, so there is no interior NUL.
+    CString::new(text).unwrap_or_default().into_raw()
+}
+
+/// # Safety
+///
+/// `summary` must come from `tengok_scan` (or be NULL) and is invalid after.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tengok_summary_free(summary: *mut TengokSummary) {
+    if !summary.is_null() {
+        drop(unsafe { Box::from_raw(summary) });
+    }
+}
+
+/// # Safety
+///
+/// `s` must come from a tengok function documented to need this (or be
+/// NULL) and is invalid after.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tengok_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe extern "C" fn count(record: *const TengokRecord, user_data: *mut c_void) -> c_int {
+        let seen = unsafe { &mut *user_data.cast::<Vec<(String, u64)>>() };
+        let record = unsafe { &*record };
+        let path = unsafe { CStr::from_ptr(record.path) };
+        seen.push((path.to_string_lossy().into_owned(), record.lines));
+        0
+    }
+
+    #[test]
+    fn scan_through_the_c_api() {
+        let dir = std::env::temp_dir().join(format!("tengok-ffi-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), "1\n2\n").unwrap();
+        std::fs::write(dir.join("b.txt"), "3\n").unwrap();
+
+        let root = CString::new(dir.display().to_string()).unwrap();
+        let argv = [root.as_ptr(), c"--deterministic".as_ptr()];
+        let mut seen: Vec<(String, u64)> = Vec::new();
+        unsafe {
+            let summary = tengok_scan(
+                argv.len(),
+                argv.as_ptr(),
+                Some(count),
+                (&mut seen as *mut Vec<(String, u64)>).cast(),
+            );
+            assert!(!summary.is_null());
+            assert_eq!(tengok_summary_files(summary), 2);
+            assert_eq!(tengok_summary_lines(summary), 3);
+            let json = tengok_summary_json(summary);
+            assert!(
+                CStr::from_ptr(json)
+                    .to_str()
+                    .unwrap()
+                    .contains("\"files\":2")
+            );
+            tengok_string_free(json);
+            tengok_summary_free(summary);
+
+            let missing = [c"/no/such/tengok/path".as_ptr()];
+            assert!(tengok_scan(1, missing.as_ptr(), None, ptr::null_mut()).is_null());
+            assert!(!tengok_last_error().is_null());
+        }
+        seen.sort();
+        assert_eq!(seen, [("a.txt".to_string(), 2), ("b.txt".to_string(), 1)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,425 @@
+use std::{
+    borrow::Cow,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::DURATION_UNITS;
+
+pub fn display_relative_path(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .map(|p| {
+            if p.as_os_str().is_empty() {
+                ".".to_string()
+            } else {
+                p.display().to_string()
+            }
+        })
+        .unwrap_or_else(|_| path.display().to_string())
+}
+
+pub fn format_num(n: u64) -> String {
+    // basic thousand separator
+    let s = n.to_string();
+    let mut out = String::new();
+    let bytes = s.as_bytes();
+    let len = bytes.len();
+    for (i, ch) in bytes.iter().enumerate() {
+        out.push(*ch as char);
+        let left = len - i - 1;
+        if left > 0 && left.is_multiple_of(3) {
+            out.push(',');
+        }
+    }
+    out
+}
+
+/// Terminal columns `s` takes: two for CJK and most emoji, none for
+/// combining marks.
+pub fn width(s: &str) -> usize {
+    UnicodeWidthStr::width(s)
+}
+
+/// `s` left-aligned in `width` columns.
+pub fn pad_right(s: &str, width: usize) -> String {
+    format!("{}{}", s, " ".repeat(width.saturating_sub(self::width(s))))
+}
+
+/// `s` right-aligned in `width` columns.
+pub fn pad_left(s: &str, width: usize) -> String {
+    format!("{}{}", " ".repeat(width.saturating_sub(self::width(s))), s)
+}
+
+/// `--ascii`, for the whole process: set once from the config, read by
+/// everything that draws, down to `truncate`.
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+pub fn set_ascii(on: bool) {
+    ASCII.store(on, Ordering::Relaxed);
+}
+
+/// What stands for the cut-off part of a shortened string.
+fn ellipsis() -> &'static str {
+    if ASCII.load(Ordering::Relaxed) {
+        "..."
+    } else {
+        "…"
+    }
+}
+
+/// `s` as it is printed: with `--ascii`, box drawing, arrows, blocks and
+/// the ellipsis become the nearest ASCII, one column for one column except
+/// the arrow and ellipsis in running text.
+pub fn glyphs(s: &str) -> Cow<'_, str> {
+    if !ASCII.load(Ordering::Relaxed) || s.is_ascii() {
+        return Cow::Borrowed(s);
+    }
+    Cow::Owned(to_ascii(s))
+}
+
+fn to_ascii(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '─' => out.push('-'),
+            '│' => out.push('|'),
+            '┌' | '┐' | '└' | '┘' | '├' | '┤' | '┬' | '┴' | '┼' => out.push('+'),
+            '↑' => out.push('^'),
+            '↓' => out.push('v'),
+            '→' => out.push_str("->"),
+            '…' => out.push_str("..."),
+            '·' | '—' => out.push('-'),
+            '•' => out.push('*'),
+            '±' => out.push_str("+-"),
+            '≡' => out.push('='),
+            '×' => out.push('x'),
+            '▁' => out.push('_'),
+            '▂' => out.push('.'),
+            '▃' => out.push('-'),
+            '▄' => out.push('~'),
+            '▅' => out.push('='),
+            '▆' => out.push('+'),
+            '▇' => out.push('*'),
+            '█' => out.push('#'),
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+/// `s` in extended grapheme clusters, what a reader sees as one character.
+fn graphemes(s: &str) -> Vec<&str> {
+    s.graphemes(true).collect()
+}
+
+/// `s` cut to fit in `max` columns, ending in "…" (`...` with `--ascii`)
+/// when it doesn't already.
+pub fn truncate(s: &str, max: usize) -> String {
+    truncate_with(s, max, ellipsis())
+}
+
+fn truncate_with(s: &str, max: usize, ellipsis: &str) -> String {
+    if width(s) <= max {
+        return s.to_string();
+    }
+    let Some(keep) = max.checked_sub(width(ellipsis)) else {
+        return ellipsis[..max].to_string();
+    };
+    let mut out = String::new();
+    let mut used = 0;
+    for cluster in graphemes(s) {
+        used += width(cluster);
+        if used > keep {
+            break;
+        }
+        out.push_str(cluster);
+    }
+    out.push_str(ellipsis);
+    out
+}
+
+/// `s` fit in `max` columns by putting "…" (`...` with `--ascii`) in the
+/// middle, keeping both ends visible. A wide character that would straddle
+/// the cut is left out, so the result can be a column short.
+pub fn ellipsize_middle(s: &str, max: usize) -> String {
+    ellipsize_middle_with(s, max, ellipsis())
+}
+
+fn ellipsize_middle_with(s: &str, max: usize, ellipsis: &str) -> String {
+    if width(s) <= max {
+        return s.to_string();
+    }
+    let Some(keep) = max.checked_sub(width(ellipsis)) else {
+        return ellipsis[..max].to_string();
+    };
+
+    let clusters = graphemes(s);
+    let front_width = keep / 2;
+    let back_width = keep - front_width;
+
+    let mut front = 0;
+    let mut used = 0;
+    for cluster in &clusters {
+        if used + width(cluster) > front_width {
+            break;
+        }
+        used += width(cluster);
+        front += 1;
+    }
+    let mut back = clusters.len();
+    let mut used = 0;
+    while back > front && used + width(clusters[back - 1]) <= back_width {
+        used += width(clusters[back - 1]);
+        back -= 1;
+    }
+
+    let mut out = clusters[..front].concat();
+    out.push_str(ellipsis);
+    out.push_str(&clusters[back..].concat());
+    out
+}
+
+/// `duration` in the largest unit that divides it: `90d`, `2w`, `36h`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    DURATION_UNITS
+        .iter()
+        .find(|(_, unit)| secs.is_multiple_of(*unit))
+        .map_or(format!("{}s", secs), |(name, unit)| {
+            format!("{}{}", secs / unit, name)
+        })
+}
+
+/// Render seconds since the Unix epoch as `YYYY-MM-DD HH:MM` (UTC).
+pub fn format_timestamp(secs: u64) -> String {
+    format_timestamp_at(secs, 0)
+}
+
+/// Like `format_timestamp`, in the timezone `offset` seconds east of UTC.
+pub fn format_timestamp_at(secs: u64, offset: i64) -> String {
+    let (year, month, day, rem) = civil_at(secs, offset);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem % 3600) / 60
+    )
+}
+
+/// Render seconds since the Unix epoch as RFC 3339, `YYYY-MM-DDTHH:MM:SSZ`.
+pub fn format_rfc3339(secs: u64) -> String {
+    format_iso8601(secs, 0)
+}
+
+/// ISO 8601 in the timezone `offset` seconds east of UTC, with the offset:
+/// `2024-02-29T19:34:56+07:00`, or `Z` for UTC.
+pub fn format_iso8601(secs: u64, offset: i64) -> String {
+    let (year, month, day, rem) = civil_at(secs, offset);
+    let zone = if offset == 0 {
+        "Z".to_string()
+    } else {
+        let minutes = offset.unsigned_abs() / 60;
+        format!(
+            "{}{:02}:{:02}",
+            if offset < 0 { '-' } else { '+' },
+            minutes / 60,
+            minutes % 60
+        )
+    };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60,
+        zone
+    )
+}
+
+/// The date and the seconds into the day at `secs`, `offset` seconds east
+/// of UTC.
+fn civil_at(secs: u64, offset: i64) -> (i64, u32, u32, i64) {
+    let local = secs as i64 + offset;
+    let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+    (year, month, day, local.rem_euclid(86_400))
+}
+
+/// How far east of UTC the local timezone (`TZ`, else the system's) is at
+/// `secs`, in seconds; `None` where the platform doesn't say.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+pub fn local_offset(secs: u64) -> Option<i64> {
+    unsafe extern "C" {
+        fn tzset();
+    }
+    let time = libc::time_t::try_from(secs).ok()?;
+    // SAFETY: `tm` is plain data, which `localtime_r` fills in; `tzset`
+    // reads `TZ`, which `localtime_r` needn't.
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        tzset();
+        if libc::localtime_r(&time, &mut tm).is_null() {
+            return None;
+        }
+        Some(tm.tm_gmtoff as i64)
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+)))]
+pub fn local_offset(_secs: u64) -> Option<i64> {
+    None
+}
+
+// Howard Hinnant's days-to-civil conversion (proleptic Gregorian calendar).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_timestamp_renders_utc() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(1_709_210_096), "2024-02-29 12:34");
+        assert_eq!(format_rfc3339(1_709_210_096), "2024-02-29T12:34:56Z");
+        assert_eq!(
+            format_iso8601(1_709_210_096, 7 * 3600),
+            "2024-02-29T19:34:56+07:00"
+        );
+        assert_eq!(
+            format_timestamp_at(1_709_210_096, -(12 * 3600 + 45 * 60)),
+            "2024-02-28 23:49"
+        );
+    }
+
+    #[test]
+    fn ellipsize_leaves_short_strings_alone() {
+        assert_eq!(ellipsize_middle("short.txt", 20), "short.txt");
+    }
+
+    #[test]
+    fn ellipsize_compacts_middle_and_keeps_ends() {
+        let original = "somefilenameisverylong.txt";
+        assert_eq!(ellipsize_middle(original, 20), "somefilen…rylong.txt");
+    }
+
+    #[test]
+    fn ascii_uses_three_dots_and_plain_box_characters() {
+        let original = "somefilenameisverylong.txt";
+        assert_eq!(
+            ellipsize_middle_with(original, 20, "..."),
+            "somefile...ylong.txt"
+        );
+        assert_eq!(truncate_with(original, 10, "..."), "somefil...");
+        assert_eq!(truncate_with(original, 2, "..."), "..");
+        assert_eq!(to_ascii("┌──┐ [D↑] a → b…"), "+--+ [D^] a -> b...");
+        assert_eq!(to_ascii("▁▅█"), "_=#");
+    }
+
+    #[test]
+    fn fitting_counts_columns_not_chars() {
+        // Each CJK character is two columns wide.
+        let cjk = "資料/報告書/最終版.txt";
+        let short = ellipsize_middle(cjk, 12);
+        assert!(width(&short) <= 12, "{}", short);
+        assert_eq!(short, "資料/…版.txt");
+        assert_eq!(truncate("日本語のファイル", 7), "日本語…");
+        assert_eq!(width(&pad_left("日本", 6)), 6);
+        assert_eq!(pad_right("日本", 6), "日本  ");
+
+        // Combining accents stay on their letter and take no column.
+        let decomposed = "cafe\u{301}-re\u{301}sume\u{301}.md";
+        assert_eq!(width(decomposed), 14);
+        assert_eq!(truncate(decomposed, 5), "cafe\u{301}…");
+        assert_eq!(ellipsize_middle(decomposed, 9), "cafe\u{301}…e\u{301}.md");
+    }
+
+    #[test]
+    fn ellipsizing_never_splits_a_grapheme() {
+        let family = "👨\u{200d}👩\u{200d}👧";
+        let wave = "👋\u{1f3fd}";
+        let flag = "\u{1f1ef}\u{1f1f5}";
+        let hangul = "\u{1112}\u{1161}\u{11ab}"; // 한, decomposed as macOS stores it
+        let name = format!(
+            "{}{}{}{}-{}{}.png",
+            family, wave, flag, hangul, flag, family
+        );
+        assert_eq!(
+            graphemes(&name),
+            [
+                family, wave, flag, hangul, "-", flag, family, ".", "p", "n", "g"
+            ]
+        );
+        for max in 1..width(&name) {
+            let short = ellipsize_middle(&name, max);
+            assert!(width(&short) <= max, "{} in {}", short, max);
+            let kept: Vec<&str> = graphemes(&short)
+                .into_iter()
+                .filter(|g| *g != "…")
+                .collect();
+            let all = graphemes(&name);
+            assert!(kept.iter().all(|g| all.contains(g)), "{:?}", kept);
+            assert!(
+                graphemes(&truncate(&name, max))
+                    .iter()
+                    .all(|g| *g == "…" || all.contains(g))
+            );
+        }
+    }
+
+    #[test]
+    fn spacing_marks_stay_on_their_letter() {
+        // The vowel signs ि and ी take a column of their own but belong to
+        // the consonant before them.
+        let hindi = "हिन्दी.txt";
+        let clusters = graphemes(hindi);
+        assert_eq!(clusters[0], "हि");
+        assert!(
+            clusters
+                .iter()
+                .all(|g| !g.starts_with(['\u{93f}', '\u{940}']))
+        );
+        for max in 1..width(hindi) {
+            let kept = truncate(hindi, max);
+            let kept = kept.trim_end_matches('…');
+            assert!(
+                (0..=clusters.len()).any(|n| clusters[..n].concat() == kept),
+                "{} in {}",
+                kept,
+                max
+            );
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! `--include-git`: the root's `.git` directory, broken down so a heavy
+//! repository can be told apart from a heavy working tree. Packs and loose
+//! objects are the history; refs, hooks and the rest (index, logs, config)
+//! are usually small.
+
+use std::path::Path;
+
+use crate::scan::{FileRecord, GroupStat};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Part {
+    Packs,
+    Objects,
+    Refs,
+    Hooks,
+    Other,
+}
+
+impl Part {
+    pub const ALL: [Part; 5] = [
+        Part::Packs,
+        Part::Objects,
+        Part::Refs,
+        Part::Hooks,
+        Part::Other,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Part::Packs => "packs",
+            Part::Objects => "loose objects",
+            Part::Refs => "refs",
+            Part::Hooks => "hooks",
+            Part::Other => "other",
+        }
+    }
+
+    /// Key in the JSON output.
+    pub fn key(self) -> &'static str {
+        match self {
+            Part::Packs => "packs",
+            Part::Objects => "objects",
+            Part::Refs => "refs",
+            Part::Hooks => "hooks",
+            Part::Other => "other",
+        }
+    }
+
+    /// The part of `.git` that `rel`, relative to `.git`, belongs to.
+    fn of(rel: &Path) -> Part {
+        let mut names = rel.iter().filter_map(|n| n.to_str());
+        match (names.next(), names.next()) {
+            (Some("objects"), Some("pack")) => Part::Packs,
+            (Some("objects"), _) => Part::Objects,
+            (Some("refs" | "packed-refs"), _) => Part::Refs,
+            (Some("hooks"), _) => Part::Hooks,
+            _ => Part::Other,
+        }
+    }
+}
+
+/// Files in the root's `.git`, per part.
+#[derive(Debug, Clone, Default)]
+pub struct GitDir {
+    parts: [GroupStat; Part::ALL.len()],
+}
+
+impl GitDir {
+    /// Count `record` if it sits in `root`'s `.git`.
+    pub fn add(&mut self, record: &FileRecord, root: &Path) {
+        if let Some(part) = part(&record.path, root) {
+            self.parts[part as usize].add(record);
+        }
+    }
+
+    pub fn remove(&mut self, record: &FileRecord, root: &Path) {
+        if let Some(part) = part(&record.path, root) {
+            self.parts[part as usize].remove(record);
+        }
+    }
+
+    pub fn part(&self, part: Part) -> &GroupStat {
+        &self.parts[part as usize]
+    }
+
+    pub fn size(&self) -> u64 {
+        self.parts.iter().map(|p| p.size).sum()
+    }
+
+    pub fn files(&self) -> u64 {
+        self.parts.iter().map(|p| p.files).sum()
+    }
+}
+
+fn part(path: &Path, root: &Path) -> Option<Part> {
+    path.strip_prefix(root.join(".git")).ok().map(Part::of)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorts_git_files_into_parts() {
+        assert_eq!(Part::of(Path::new("objects/pack/pack-1.pack")), Part::Packs);
+        assert_eq!(Part::of(Path::new("objects/ab/cdef")), Part::Objects);
+        assert_eq!(Part::of(Path::new("refs/heads/main")), Part::Refs);
+        assert_eq!(Part::of(Path::new("packed-refs")), Part::Refs);
+        assert_eq!(Part::of(Path::new("hooks/pre-commit.sample")), Part::Hooks);
+        assert_eq!(Part::of(Path::new("index")), Part::Other);
+    }
+}
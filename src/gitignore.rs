@@ -0,0 +1,140 @@
+//! `--no-ignore` inside a git repository: build output and caches that
+//! nothing ignores yet. Directories with a well-known name that hold at
+//! least `MIN_SIZE`, aren't matched by the root `.gitignore` and have no
+//! tracked files are suggested as `.gitignore` lines; `--apply` appends
+//! them to the root `.gitignore`.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use ignore::gitignore::Gitignore;
+
+use crate::config::Config;
+use crate::format::display_relative_path;
+use crate::scan::Summary;
+
+pub const FILE_NAME: &str = ".gitignore";
+
+/// Directory names that are almost always generated. `vendor` and `Pods`
+/// are left out, since those are often committed on purpose.
+pub const CANDIDATE_DIRS: &[&str] = &[
+    "node_modules",
+    "bower_components",
+    "target",
+    "build",
+    "dist",
+    "out",
+    ".venv",
+    "venv",
+    "__pycache__",
+    ".gradle",
+    ".cache",
+    ".pytest_cache",
+    ".mypy_cache",
+    ".ruff_cache",
+    ".tox",
+    ".next",
+    ".nuxt",
+    ".parcel-cache",
+    ".turbo",
+    "coverage",
+    ".nyc_output",
+];
+
+/// Smaller directories aren't worth a line.
+pub const MIN_SIZE: u64 = 1_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// The `.gitignore` line, anchored to the root: `/target/`.
+    pub line: String,
+    pub files: u64,
+    pub size: u64,
+}
+
+/// The outermost candidate directory `path` sits in, with `--no-ignore` in a
+/// git repository.
+pub fn candidate(path: &Path, config: &Config) -> Option<PathBuf> {
+    if !config.suggest_ignore {
+        return None;
+    }
+    let rel = path.strip_prefix(&config.root).ok()?.parent()?;
+    let mut dir = config.root.clone();
+    for name in rel.iter() {
+        dir.push(name);
+        if name.to_str().is_some_and(|n| CANDIDATE_DIRS.contains(&n)) {
+            return Some(dir);
+        }
+    }
+    None
+}
+
+/// The candidates worth ignoring, largest first.
+pub fn suggestions(config: &Config, summary: &Summary) -> Vec<Suggestion> {
+    let (ignored, _) = Gitignore::new(config.root.join(FILE_NAME));
+    let mut dirs: Vec<(&PathBuf, u64, u64)> = summary
+        .untracked
+        .iter()
+        .filter(|(dir, stat)| {
+            stat.size >= MIN_SIZE && !ignored.matched_path_or_any_parents(dir, true).is_ignore()
+        })
+        .map(|(dir, stat)| (dir, stat.files, stat.size))
+        .collect();
+    let tracked = tracked(&config.root, dirs.iter().map(|(dir, _, _)| dir.as_path()));
+    dirs.retain(|(dir, _, _)| !tracked.iter().any(|path| path.starts_with(dir)));
+    dirs.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(b.0)));
+    dirs.into_iter()
+        .map(|(dir, files, size)| Suggestion {
+            line: format!(
+                "/{}/",
+                display_relative_path(dir, &config.root).replace('\\', "/")
+            ),
+            files,
+            size,
+        })
+        .collect()
+}
+
+/// Tracked files below `dirs`, from `git ls-files`. Without git every
+/// directory counts as untracked.
+fn tracked<'a>(root: &Path, dirs: impl Iterator<Item = &'a Path>) -> Vec<PathBuf> {
+    let dirs: Vec<&Path> = dirs.collect();
+    if dirs.is_empty() {
+        return Vec::new();
+    }
+    let Ok(output) = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["ls-files", "-z", "--"])
+        .args(dirs.iter().map(|dir| dir.strip_prefix(root).unwrap_or(dir)))
+        .output()
+    else {
+        return Vec::new();
+    };
+    output
+        .stdout
+        .split(|&b| b == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| root.join(String::from_utf8_lossy(path).as_ref()))
+        .collect()
+}
+
+/// `--apply`: append `suggestions` to the root `.gitignore`, starting on a
+/// fresh line.
+pub fn apply(root: &Path, suggestions: &[Suggestion]) -> io::Result<()> {
+    let path = root.join(FILE_NAME);
+    let existing = fs::read(&path).unwrap_or_default();
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    if !existing.is_empty() && !existing.ends_with(b"\n") {
+        writeln!(file)?;
+    }
+    writeln!(file, "# Build output and caches found by tengok")?;
+    for suggestion in suggestions {
+        writeln!(file, "{}", suggestion.line)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,139 @@
+//! `--group-by`: files grouped by the directory a glob picks out, such as
+//! `packages/*/` for the packages of a JS monorepo or `services/*/` for the
+//! services of an infra repo, without spelling out the structure.
+//!
+//! A glob is matched against the leading components of a file's path
+//! relative to the root, one component per segment, so `*` never crosses a
+//! `/`; a `**` segment stands for any number of components, of which the
+//! fewest that let the rest match are taken. The group is the part of the
+//! path the glob matched. With a trailing `/` the glob only matches
+//! directories, so `packages/*/` leaves `packages/README.md` out, where
+//! `packages/*` makes it a group of its own. With several globs, the first
+//! that matches wins; files none matches are in `OTHER`.
+
+use std::path::{Component, Path};
+
+use globset::{GlobBuilder, GlobMatcher};
+
+/// Files no glob matched.
+pub const OTHER: &str = "(other)";
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Any,
+    Glob(GlobMatcher),
+}
+
+#[derive(Debug, Clone)]
+struct GroupGlob {
+    segments: Vec<Segment>,
+    dirs_only: bool,
+}
+
+impl GroupGlob {
+    fn parse(glob: &str) -> Result<Self, String> {
+        let invalid = |why: &str| format!("Invalid --group-by glob {:?}: {}", glob, why);
+        let trimmed = glob.strip_prefix("./").unwrap_or(glob);
+        if trimmed.starts_with('/') {
+            return Err(invalid("it's relative to the root"));
+        }
+        let dirs_only = trimmed.ends_with('/');
+        let segments = trimmed
+            .trim_end_matches('/')
+            .split('/')
+            .map(|segment| match segment {
+                "" => Err(invalid("empty path segment")),
+                "**" => Ok(Segment::Any),
+                _ => GlobBuilder::new(segment)
+                    .literal_separator(true)
+                    .build()
+                    .map(|g| Segment::Glob(g.compile_matcher()))
+                    .map_err(|e| invalid(&e.kind().to_string())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(GroupGlob {
+            segments,
+            dirs_only,
+        })
+    }
+
+    /// How many of `parts` the glob matches, when it does.
+    fn matched(&self, parts: &[&str]) -> Option<usize> {
+        let limit = parts.len() - usize::from(self.dirs_only);
+        matched(&self.segments, &parts[..limit], 0)
+    }
+}
+
+fn matched(segments: &[Segment], parts: &[&str], taken: usize) -> Option<usize> {
+    match segments.split_first() {
+        None => Some(taken),
+        Some((Segment::Any, rest)) => {
+            (0..=parts.len()).find_map(|skip| matched(rest, &parts[skip..], taken + skip))
+        }
+        Some((Segment::Glob(glob), rest)) => match parts.split_first() {
+            Some((part, others)) if glob.is_match(part) => matched(rest, others, taken + 1),
+            _ => None,
+        },
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Groups {
+    globs: Vec<GroupGlob>,
+}
+
+impl Groups {
+    pub fn parse(globs: &[String]) -> Result<Self, String> {
+        Ok(Groups {
+            globs: globs
+                .iter()
+                .map(|glob| GroupGlob::parse(glob))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// The group of the file at `rel`, relative to the root.
+    pub fn group(&self, rel: &Path) -> String {
+        let parts: Vec<&str> = rel
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(part) => part.to_str(),
+                _ => None,
+            })
+            .collect();
+        if parts.is_empty() {
+            return OTHER.to_string();
+        }
+        self.globs
+            .iter()
+            .find_map(|glob| glob.matched(&parts))
+            .filter(|&n| n > 0)
+            .map_or_else(|| OTHER.to_string(), |n| parts[..n].join("/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_by_the_matched_prefix() {
+        let globs = ["packages/*/", "services/{api,web}/", "**/vendor/"];
+        let groups = Groups::parse(&globs.map(String::from)).unwrap();
+        let group = |path: &str| groups.group(Path::new(path));
+        assert_eq!(group("packages/app/src/index.ts"), "packages/app");
+        assert_eq!(group("packages/README.md"), OTHER);
+        assert_eq!(group("services/api/main.go"), "services/api");
+        assert_eq!(group("services/db/schema.sql"), OTHER);
+        assert_eq!(
+            group("third_party/go/vendor/x/y.go"),
+            "third_party/go/vendor"
+        );
+        assert_eq!(group("vendor/a.c"), "vendor");
+
+        let files = Groups::parse(&["docs/*".to_string()]).unwrap();
+        assert_eq!(files.group(Path::new("docs/intro.md")), "docs/intro.md");
+        assert!(Groups::parse(&["/abs/*".to_string()]).is_err());
+        assert!(Groups::parse(&["a//b".to_string()]).is_err());
+    }
+}
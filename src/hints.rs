@@ -0,0 +1,149 @@
+//! Hints printed under the summary box: findings that stand out enough to
+//! be worth a sentence, such as one directory holding nearly all the bytes,
+//! so a newcomer knows where to look first. `--no-hints` turns them off.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::config::Config;
+use crate::format::{display_relative_path, format_num};
+use crate::scan::Summary;
+
+/// Trees smaller than this have nothing surprising in them.
+const MIN_FILES: u64 = 20;
+/// A directory (with everything below it) holding this share of the bytes.
+const HEAVY_DIR_SHARE: f64 = 0.8;
+/// A single file holding this share of the lines.
+const HEAVY_FILE_SHARE: f64 = 0.5;
+/// The deepest directory is a spike when it is at least this deep and
+/// twice as deep as where 95% of the files sit.
+const MIN_SPIKE_DEPTH: usize = 6;
+
+pub fn hints(config: &Config, summary: &Summary) -> Vec<String> {
+    if summary.total_files < MIN_FILES {
+        return Vec::new();
+    }
+    let root = &config.root;
+    let mut hints = Vec::new();
+
+    if let Some((dir, size)) = heaviest_dir(summary, root) {
+        hints.push(format!(
+            "{} holds {:.0}% of all bytes ({}); start there to free space",
+            display_relative_path(&dir, root),
+            size as f64 / summary.total_size as f64 * 100.0,
+            config.size(size)
+        ));
+    }
+
+    if let Some(file) = &summary.max_lines_file
+        && summary.total_lines > 0
+        && file.lines as f64 > summary.total_lines as f64 * HEAVY_FILE_SHARE
+    {
+        hints.push(format!(
+            "{} alone has {:.0}% of all lines ({}); generated or data files skew line totals",
+            display_relative_path(&file.path, root),
+            file.lines as f64 / summary.total_lines as f64 * 100.0,
+            format_num(file.lines)
+        ));
+    }
+
+    if let Some((dir, depth, typical)) = depth_spike(summary, root) {
+        hints.push(format!(
+            "{} is {} levels deep while 95% of files are within {}; look for nested copies or runaway generated trees",
+            display_relative_path(&dir, root),
+            depth,
+            typical
+        ));
+    }
+    hints
+}
+
+/// The deepest directory below the root whose subtree holds
+/// `HEAVY_DIR_SHARE` of the bytes, and its subtree's size.
+fn heaviest_dir(summary: &Summary, root: &Path) -> Option<(PathBuf, u64)> {
+    if summary.total_size == 0 {
+        return None;
+    }
+    let mut subtree: HashMap<&Path, u64> = HashMap::new();
+    for (dir, stat) in &summary.dirs {
+        for ancestor in dir
+            .ancestors()
+            .take_while(|a| a.starts_with(root) && *a != root)
+        {
+            *subtree.entry(ancestor).or_default() += stat.size;
+        }
+    }
+    let threshold = summary.total_size as f64 * HEAVY_DIR_SHARE;
+    subtree
+        .into_iter()
+        .filter(|(_, size)| *size as f64 > threshold)
+        .max_by(|(a, _), (b, _)| {
+            a.components()
+                .count()
+                .cmp(&b.components().count())
+                .then_with(|| b.cmp(a))
+        })
+        .map(|(dir, size)| (dir.to_path_buf(), size))
+}
+
+/// The deepest directory, its depth, and the depth 95% of the files are
+/// at or above, when the deepest one sticks out.
+fn depth_spike(summary: &Summary, root: &Path) -> Option<(PathBuf, usize, usize)> {
+    let (dir, depth) = summary.deepest_dir.clone()?;
+    if depth < MIN_SPIKE_DEPTH {
+        return None;
+    }
+    let mut depths: Vec<(usize, u64)> = summary
+        .dirs
+        .iter()
+        .map(|(dir, stat)| {
+            let depth = dir
+                .strip_prefix(root)
+                .map_or(0, |rel| rel.components().count());
+            (depth, stat.files)
+        })
+        .collect();
+    depths.sort_unstable();
+    let wanted = (summary.total_files as f64 * 0.95).ceil() as u64;
+    let mut seen = 0;
+    let typical = depths
+        .iter()
+        .find(|(_, files)| {
+            seen += files;
+            seen >= wanted
+        })
+        .map_or(depth, |(depth, _)| *depth);
+    (depth >= typical * 2).then_some((dir, depth, typical))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan;
+    use crate::vfs::MemFs;
+
+    #[test]
+    fn flags_a_dominant_directory_file_and_depth() {
+        let mut fs = MemFs::default();
+        for i in 0..20 {
+            fs.insert(format!("r/src/f{}.rs", i), "x\n");
+        }
+        fs.insert("r/assets/video/big.bin", "0".repeat(1000));
+        fs.insert("r/data.csv", "a\n".repeat(100));
+        fs.insert("r/a/b/c/d/e/f/g/deep.txt", "y\n");
+
+        let config = Config::from_args(["r"].map(String::from)).unwrap();
+        let summary = scan::scan_fs(&fs, &config).unwrap();
+        let hints = hints(&config, &summary);
+        assert_eq!(hints.len(), 3, "{:?}", hints);
+        assert!(hints[0].starts_with("assets/video holds 8"), "{}", hints[0]);
+        assert!(hints[1].starts_with("data.csv alone has"), "{}", hints[1]);
+        assert!(
+            hints[2].starts_with("a/b/c/d/e/f/g is 7 levels deep"),
+            "{}",
+            hints[2]
+        );
+    }
+}
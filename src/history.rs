@@ -0,0 +1,423 @@
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::format::{format_num, glyphs};
+use crate::report::{Cell, Style, print_table};
+use crate::scan::Summary;
+use crate::store::{self, RootStore};
+
+pub const DEFAULT_LAST: usize = 20;
+const HISTORY_FILE: &str = "history.jsonl";
+const SPARK_LEVELS: &[char] = &['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// One line of a root's history file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub timestamp: u64,
+    pub root: String,
+    pub files: u64,
+    pub size_bytes: u64,
+    pub lines: u64,
+}
+
+/// Options that only make sense for `tengok history`; everything else is
+/// handed to the regular scan flags.
+pub struct HistoryArgs {
+    pub last: usize,
+    pub record: bool,
+    pub keep: Retention,
+}
+
+/// Which snapshots pruning keeps: the `last` newest, and the newest of each
+/// of the `daily` latest days with snapshots and of the `weekly` latest
+/// weeks (UTC, from Monday). Whatever any rule keeps stays.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Retention {
+    pub last: Option<usize>,
+    pub daily: Option<usize>,
+    pub weekly: Option<usize>,
+}
+
+impl Retention {
+    pub fn is_set(&self) -> bool {
+        *self != Retention::default()
+    }
+
+    /// Whether to keep each of `snapshots`, which are oldest first.
+    pub fn keep(&self, snapshots: &[Snapshot]) -> Vec<bool> {
+        let mut keep = vec![false; snapshots.len()];
+        let newest = snapshots.len().saturating_sub(self.last.unwrap_or(0));
+        keep[newest..].fill(true);
+        // Days are counted from 1970-01-01, a Thursday, so weeks are shifted
+        // by 3 days to start on Mondays.
+        for (limit, shift, days) in [(self.daily, 0, 1), (self.weekly, 3, 7)] {
+            let mut buckets = Vec::new();
+            for (i, snapshot) in snapshots.iter().enumerate().rev() {
+                let bucket = (snapshot.timestamp / 86_400 + shift) / days;
+                if buckets.last() != Some(&bucket) {
+                    if buckets.len() == limit.unwrap_or(0) {
+                        break;
+                    }
+                    buckets.push(bucket);
+                    keep[i] = true;
+                }
+            }
+        }
+        keep
+    }
+}
+
+pub fn split_args(
+    args: impl IntoIterator<Item = String>,
+) -> Result<(HistoryArgs, Vec<String>), String> {
+    let mut opts = HistoryArgs {
+        last: DEFAULT_LAST,
+        record: true,
+        keep: Retention::default(),
+    };
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let flag = arg.split_once('=').map_or(arg.as_str(), |(flag, _)| flag);
+        let mut count = |flag: &str| -> Result<usize, String> {
+            let value = match arg.split_once('=') {
+                Some((_, v)) => v.to_string(),
+                None => args
+                    .next()
+                    .ok_or_else(|| format!("{} requires a value", flag))?,
+            };
+            value
+                .parse()
+                .map_err(|_| format!("Unable to parse {}", flag))
+        };
+        match flag {
+            "--no-record" => opts.record = false,
+            "--last" => opts.last = count(flag)?,
+            "--keep-last" => opts.keep.last = Some(count(flag)?),
+            "--keep-daily" => opts.keep.daily = Some(count(flag)?),
+            "--keep-weekly" => opts.keep.weekly = Some(count(flag)?),
+            _ => rest.push(arg),
+        }
+    }
+    Ok((opts, rest))
+}
+
+/// `$TENGOK_HOME`, else `$XDG_DATA_HOME/tengok`, else `~/.local/share/tengok`.
+pub fn data_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("TENGOK_HOME") {
+        return Some(PathBuf::from(dir));
+    }
+    if let Some(dir) = env::var_os("XDG_DATA_HOME") {
+        return Some(PathBuf::from(dir).join("tengok"));
+    }
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".local/share/tengok"))
+}
+
+/// Stable identifier for a root: the canonical path, so `.` and the
+/// absolute spelling share a history.
+pub fn root_id(root: &Path) -> String {
+    fs::canonicalize(root)
+        .unwrap_or_else(|_| root.to_path_buf())
+        .display()
+        .to_string()
+}
+
+/// The root's history in its store. Histories from before the per-root
+/// stores (`history/<key>.jsonl`) are moved in on first use.
+fn history_file(dir: &Path, store: &RootStore, root_id: &str) -> PathBuf {
+    let path = store.path(HISTORY_FILE);
+    let old = dir
+        .join("history")
+        .join(format!("{}.jsonl", store::key(root_id)));
+    if !path.exists() && old.exists() {
+        let _ = fs::rename(&old, &path);
+    }
+    path
+}
+
+pub fn load(path: &Path) -> io::Result<Vec<Snapshot>> {
+    let file = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut snapshots = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        // A torn or hand-edited line shouldn't hide the rest of the history.
+        if let Ok(snapshot) = serde_json::from_str(&line) {
+            snapshots.push(snapshot);
+        }
+    }
+    Ok(snapshots)
+}
+
+fn append(path: &Path, snapshot: &Snapshot) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let line = serde_json::to_string(snapshot)?;
+    writeln!(file, "{}", line)
+}
+
+pub fn snapshot_of(config: &Config, summary: &Summary) -> Snapshot {
+    Snapshot {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        root: root_id(&config.root),
+        files: summary.total_files,
+        size_bytes: summary.total_size,
+        lines: summary.total_lines,
+    }
+}
+
+pub fn run(config: &Config, opts: &HistoryArgs, summary: Option<&Summary>) -> io::Result<()> {
+    let dir = data_dir().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "no history directory: set TENGOK_HOME or HOME",
+        )
+    })?;
+    let id = root_id(&config.root);
+    let store = RootStore::open(&dir, &id)?;
+    let lock = store.lock()?;
+    let path = history_file(&dir, &store, &id);
+
+    if let Some(summary) = summary {
+        append(&path, &snapshot_of(config, summary))?;
+    }
+    if opts.keep.is_set() {
+        prune_file(&store, &path, &id, opts.keep)?;
+    }
+
+    let mut snapshots = load(&path)?;
+    drop(lock);
+    snapshots.retain(|s| s.root == id);
+    let skip = snapshots.len().saturating_sub(opts.last);
+    print_history(config, &id, &snapshots[skip..]);
+    Ok(())
+}
+
+/// `tengok history prune`: applies `opts.keep` to the root's snapshots
+/// without scanning.
+pub fn prune(config: &Config, opts: &HistoryArgs) -> io::Result<()> {
+    let dir = data_dir().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "no history directory: set TENGOK_HOME or HOME",
+        )
+    })?;
+    let id = root_id(&config.root);
+    let store = RootStore::open(&dir, &id)?;
+    let _lock = store.lock()?;
+    let path = history_file(&dir, &store, &id);
+    let (removed, kept) = prune_file(&store, &path, &id, opts.keep)?;
+    let style = Style::new(config);
+    println!(
+        "{}",
+        style.label(&format!(
+            "History: {} (pruned {} snapshots, {} kept)",
+            id,
+            format_num(removed as u64),
+            format_num(kept as u64)
+        ))
+    );
+    Ok(())
+}
+
+/// Rewrites the history file with the snapshots of `id` that `keep` keeps
+/// (and those of any other root as they were); returns how many were
+/// removed and kept. The caller holds the store's lock.
+fn prune_file(
+    store: &RootStore,
+    path: &Path,
+    id: &str,
+    keep: Retention,
+) -> io::Result<(usize, usize)> {
+    let snapshots = load(path)?;
+    let ours: Vec<Snapshot> = snapshots.iter().filter(|s| s.root == id).cloned().collect();
+    let mut kept = keep.keep(&ours).into_iter();
+    let mut text = String::new();
+    let mut removed = 0;
+    for snapshot in &snapshots {
+        if snapshot.root == id && kept.next() == Some(false) {
+            removed += 1;
+            continue;
+        }
+        text.push_str(&serde_json::to_string(snapshot)?);
+        text.push('\n');
+    }
+    if removed > 0 {
+        store.write_atomic(HISTORY_FILE, text.as_bytes())?;
+    }
+    Ok((removed, ours.len() - removed))
+}
+
+fn print_history(config: &Config, id: &str, snapshots: &[Snapshot]) {
+    let style = Style::new(config);
+    let (Some(first), Some(last)) = (snapshots.first(), snapshots.last()) else {
+        println!(
+            "{}",
+            style.label(&format!("History: {} (no snapshots)", id))
+        );
+        return;
+    };
+
+    println!(
+        "{}",
+        style.label(&format!(
+            "History: {} ({} snapshots, {} → {})",
+            id,
+            snapshots.len(),
+            config.time(first.timestamp),
+            config.time(last.timestamp)
+        ))
+    );
+
+    let trend = |label: &str, pick: fn(&Snapshot) -> u64, fmt: &dyn Fn(u64) -> String| {
+        let values: Vec<u64> = snapshots.iter().map(pick).collect();
+        let (from, to) = (pick(first), pick(last));
+        println!(
+            "  {:<6} {}  {} {} {} ({})",
+            style.label(label),
+            style.value(&sparkline(&values)),
+            fmt(from),
+            glyphs("→"),
+            fmt(to),
+            percent_change(from, to)
+        );
+    };
+    trend("Files", |s| s.files, &format_num);
+    trend("Size", |s| s.size_bytes, &|n| config.size(n));
+    trend("Lines", |s| s.lines, &format_num);
+
+    println!();
+    let rows: Vec<Vec<Cell>> = snapshots
+        .iter()
+        .rev()
+        .map(|s| {
+            vec![
+                format_num(s.files).into(),
+                Cell::size(config, s.size_bytes),
+                format_num(s.lines).into(),
+                config.time(s.timestamp).into(),
+            ]
+        })
+        .collect();
+    print_table(
+        &style,
+        "Snapshots (newest first)",
+        &["Files", "Size", "Lines", "When"],
+        &rows,
+    );
+}
+
+/// Scale values onto eight block heights between their min and max.
+pub fn sparkline(values: &[u64]) -> String {
+    let min = values.iter().copied().min().unwrap_or(0);
+    let max = values.iter().copied().max().unwrap_or(0);
+    let top = SPARK_LEVELS.len() - 1;
+    values
+        .iter()
+        .map(|&v| {
+            if max == min {
+                SPARK_LEVELS[top / 2]
+            } else {
+                let level = ((v - min) as f64 / (max - min) as f64 * top as f64).round();
+                SPARK_LEVELS[level as usize]
+            }
+        })
+        .collect()
+}
+
+/// `+3.4%` from `from` to `to`, or `new` when growing from nothing.
+pub fn percent_change(from: u64, to: u64) -> String {
+    if from == 0 {
+        return if to == 0 {
+            "±0%".to_string()
+        } else {
+            "new".to_string()
+        };
+    }
+    let pct = (to as f64 - from as f64) / from as f64 * 100.0;
+    format!("{:+.1}%", pct)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sparkline_spans_min_to_max() {
+        assert_eq!(sparkline(&[0, 50, 100]), "▁▅█");
+        assert_eq!(sparkline(&[7, 7]), "▄▄");
+    }
+
+    #[test]
+    fn split_args_keeps_scan_flags() {
+        let args = ["--last", "5", "--plain", "--no-record", "src"].map(String::from);
+        let (opts, rest) = split_args(args).unwrap();
+        assert_eq!(opts.last, 5);
+        assert!(!opts.record);
+        assert_eq!(rest, ["--plain", "src"]);
+    }
+
+    #[test]
+    fn retention_keeps_the_newest_per_rule() {
+        const HOUR: u64 = 3600;
+        const DAY: u64 = 24 * HOUR;
+        // Monday 2024-01-01 00:00 UTC, then two scans a day for three weeks.
+        let monday = 1_704_067_200;
+        let snapshots: Vec<Snapshot> = (0..42)
+            .map(|i| Snapshot {
+                timestamp: monday + i / 2 * DAY + i % 2 * 12 * HOUR,
+                root: "r".into(),
+                files: i,
+                size_bytes: 0,
+                lines: 0,
+            })
+            .collect();
+        let kept = |keep: Retention| -> Vec<u64> {
+            let flags = keep.keep(&snapshots);
+            snapshots
+                .iter()
+                .zip(flags)
+                .filter(|(_, k)| *k)
+                .map(|(s, _)| s.files)
+                .collect()
+        };
+        let last = |n| Retention {
+            last: Some(n),
+            ..Retention::default()
+        };
+        assert_eq!(kept(last(3)), [39, 40, 41]);
+        let daily = Retention {
+            daily: Some(3),
+            ..Retention::default()
+        };
+        assert_eq!(kept(daily), [37, 39, 41]);
+        let weekly = Retention {
+            weekly: Some(5),
+            ..Retention::default()
+        };
+        assert_eq!(kept(weekly), [13, 27, 41]);
+        assert_eq!(
+            kept(Retention {
+                daily: Some(1),
+                ..last(2)
+            }),
+            [40, 41]
+        );
+        assert!(!Retention::default().is_set());
+    }
+}
@@ -0,0 +1,127 @@
+//! `--lang`: the words in the box report and `--oneline`. The row labels
+//! are symbols (`[F]`, `[D↑]`) and stay as they are; the header and the
+//! units next to each value are translated. JSON keys never are. Without
+//! the flag the language comes from the locale (`LC_ALL`, `LC_MESSAGES`,
+//! then `LANG`), falling back to English.
+
+use std::env;
+
+/// Where the locale is read from, first match wins.
+pub const LOCALE_VARS: [&str; 3] = ["LC_ALL", "LC_MESSAGES", "LANG"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    En,
+    /// Bahasa Indonesia.
+    Id,
+    /// Bahasa Melayu.
+    Ms,
+}
+
+/// A translatable piece of the report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    /// The default header, before the path.
+    FolderSummary,
+    /// After the file count in the `[F]` row.
+    FilesUnit,
+    /// After the line count in the `[L]` row.
+    LinesUnit,
+    /// Lowercase, inside parentheses and in `--oneline`.
+    Files,
+    Lines,
+    Depth,
+    PerFile,
+    LinesPerFile,
+    /// `--oneline`'s lead-in to the largest directory.
+    Largest,
+    /// Under the header with `--metadata-only`.
+    MetadataOnly,
+}
+
+impl Lang {
+    pub const ALL: [Self; 3] = [Self::En, Self::Id, Self::Ms];
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|l| l.as_str() == value)
+            .ok_or_else(|| format!("Unknown language: {} (expected en, id or ms)", value))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Id => "id",
+            Self::Ms => "ms",
+        }
+    }
+
+    /// The first locale variable that is set, e.g. `id_ID.UTF-8` → `id`.
+    /// Locales without a bundled language mean English.
+    pub fn from_env() -> Self {
+        LOCALE_VARS
+            .into_iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|locale| Self::from_locale(&locale))
+            .unwrap_or_default()
+    }
+
+    fn from_locale(locale: &str) -> Option<Self> {
+        let code = locale.split(['_', '.', '@', '-']).next()?;
+        Self::parse(&code.to_ascii_lowercase()).ok()
+    }
+
+    pub fn get(self, msg: Msg) -> &'static str {
+        use Msg::*;
+        match (self, msg) {
+            (Self::En, FolderSummary) => "Folder Summary",
+            (Self::En, FilesUnit) => "Files",
+            (Self::En, LinesUnit) => "Lines",
+            (Self::En, Files) => "files",
+            (Self::En, Lines) => "lines",
+            (Self::En, Depth) => "depth",
+            (Self::En, PerFile) => "per file",
+            (Self::En, LinesPerFile) => "lines per file",
+            (Self::En, Largest) => "largest",
+            (Self::En, MetadataOnly) => "Metadata only: no files opened",
+
+            (Self::Id, FolderSummary) => "Ringkasan Folder",
+            (Self::Id, FilesUnit) => "Berkas",
+            (Self::Id, LinesUnit) => "Baris",
+            (Self::Id, Files) => "berkas",
+            (Self::Id, Lines) => "baris",
+            (Self::Id, Depth) => "kedalaman",
+            (Self::Id, PerFile) => "per berkas",
+            (Self::Id, LinesPerFile) => "baris per berkas",
+            (Self::Id, Largest) => "terbesar",
+            (Self::Id, MetadataOnly) => "Hanya metadata: tanpa membuka berkas",
+
+            (Self::Ms, FolderSummary) => "Ringkasan Folder",
+            (Self::Ms, FilesUnit) => "Fail",
+            (Self::Ms, LinesUnit) => "Baris",
+            (Self::Ms, Files) => "fail",
+            (Self::Ms, Lines) => "baris",
+            (Self::Ms, Depth) => "kedalaman",
+            (Self::Ms, PerFile) => "setiap fail",
+            (Self::Ms, LinesPerFile) => "baris setiap fail",
+            (Self::Ms, Largest) => "terbesar",
+            (Self::Ms, MetadataOnly) => "Metadata sahaja: tiada fail dibuka",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_language_from_a_locale() {
+        assert_eq!(Lang::from_locale("id_ID.UTF-8"), Some(Lang::Id));
+        assert_eq!(Lang::from_locale("ms"), Some(Lang::Ms));
+        assert_eq!(Lang::from_locale("C.UTF-8"), None);
+        assert_eq!(Lang::from_locale("fr_FR"), None);
+    }
+}
@@ -0,0 +1,2246 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{self, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::baseline;
+use crate::budget::{self, Unit};
+use crate::comments;
+use crate::config::{Config, Row};
+use crate::format::{display_relative_path, format_rfc3339};
+use crate::git::Part;
+use crate::gitignore;
+use crate::lists::{self, ListEntry};
+use crate::names::{self, Issue};
+use crate::profile::{Phase, millis};
+use crate::report;
+use crate::scan::{FileStat, GroupStat, Summary};
+use crate::sketch::{PERCENTILES, Sketch};
+use crate::submodules;
+
+/// Bumped only for breaking changes (removed/renamed/retyped fields).
+/// Adding optional fields keeps the version; consumers must ignore unknown
+/// keys.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Report {
+    schema_version: u32,
+    tengok_version: &'static str,
+    root: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scan: Option<ScanOut>,
+    summary: SummaryOut,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_files: Option<Vec<FileOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_dirs: Option<Vec<DirOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    busiest_dirs: Option<Vec<DirOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_lines_files: Option<Vec<FileOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    smallest_files: Option<Vec<FileOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extensions: Option<Vec<ExtOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    languages: Option<Vec<LangOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_types: Option<Vec<TypeOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owners: Option<Vec<OwnerOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mounts: Option<Vec<MountStatOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    asset_classes: Option<Vec<ClassOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    groups: Option<Vec<GroupOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    largest_dir_for: Option<Vec<ExtDirOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tests_by_language: Option<Vec<TestsOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scripts: Option<Vec<ScriptOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unexpected_executables: Option<Vec<ExecutableOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secrets: Option<Vec<SecretOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    depths: Option<Vec<DepthOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplicate_names: Option<Vec<DuplicateOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplicate_files: Option<Vec<DuplicateFilesOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ignore_rules: Option<Vec<IgnoreRuleOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    overlay_layers: Option<Vec<LayerOut>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    skipped_mounts: Vec<MountOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    widest_dir: Option<EntriesOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    crowded_dirs: Option<Vec<EntriesOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    licenses: Option<Vec<LicenseOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    docs: Option<Vec<DocsOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dotfiles: Option<Vec<DirOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stale_dirs: Option<Vec<DirOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cold_dirs: Option<Vec<DirOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    junk: Option<Vec<JunkOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gitignore_suggestions: Option<Vec<SuggestionOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git: Option<GitOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    submodules: Option<SubmodulesOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment_density: Option<Vec<LangCommentsOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    least_commented_files: Option<Vec<FileCommentsOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    case_collisions: Option<Vec<Vec<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    budget_violations: Option<Vec<ViolationOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    growth: Option<GrowthOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profile: Option<ProfileOut>,
+}
+
+#[derive(Serialize)]
+struct GrowthOut {
+    max_percent: f64,
+    /// `None` when the baseline total was zero.
+    size_bytes_percent: Option<f64>,
+    lines_percent: Option<f64>,
+    exceeded: bool,
+}
+
+#[derive(Serialize)]
+struct ViolationOut {
+    glob: String,
+    rule: String,
+    unit: &'static str,
+    limit: u64,
+    value: u64,
+    /// Only for per-file (`each`) rules.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SummaryOut {
+    // The core keys are only left out by `--fields`, and the opt-in ones
+    // only added by it; an inner `None` is a `null`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lines: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    largest_dir: Option<Option<DirOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    busiest_dir: Option<DirOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deepest_dir: Option<DeepestOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_lines_file: Option<Option<FileOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    largest_file: Option<Option<FileOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    average_size_bytes: Option<Option<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    average_lines: Option<Option<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    newest_file: Option<Option<FileOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compressed_size_estimate_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decompressed: Option<DecompressedOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pattern: Option<PatternOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plugins: Option<Vec<PluginOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metrics: Option<Vec<MetricOut>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<StatsOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    symlinks: Option<SymlinksOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tiny: Option<TinyOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generated: Option<ShareOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vendored: Option<ShareOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cold: Option<ShareOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tests: Option<TestTotalOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<ShareOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dependencies: Option<ShareOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    minified: Option<ShareOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    placeholders: Option<ShareOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lines_not_counted: Option<ShareOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    long_paths: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    names: Option<NamesOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comments: Option<CommentsOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    executables: Option<ExecutablesOut>,
+}
+
+#[derive(Serialize)]
+struct ExecutablesOut {
+    scripts: u64,
+    binaries: u64,
+    other: u64,
+}
+
+#[derive(Serialize)]
+struct SecretOut {
+    path: String,
+    reason: &'static str,
+}
+
+#[derive(Serialize)]
+struct ExecutableOut {
+    path: String,
+    kind: &'static str,
+    size_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct CommentsOut {
+    comment_lines: u64,
+    lines: u64,
+    percent: f64,
+}
+
+#[derive(Serialize)]
+struct LangCommentsOut {
+    language: &'static str,
+    comment_lines: u64,
+    lines: u64,
+    percent: f64,
+}
+
+#[derive(Serialize)]
+struct FileCommentsOut {
+    path: String,
+    comment_lines: u64,
+    lines: u64,
+    percent: f64,
+}
+
+#[derive(Serialize)]
+struct DuplicateOut {
+    name: String,
+    files: u64,
+    paths: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct DuplicateFilesOut {
+    sha256: String,
+    size_bytes: u64,
+    copies: u64,
+    wasted_bytes: u64,
+    paths: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct DecompressedOut {
+    files: u64,
+    size_bytes: u64,
+    decompressed_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct EntriesOut {
+    path: String,
+    entries: u64,
+}
+
+#[derive(Serialize)]
+struct IgnoreRuleOut {
+    pattern: String,
+    source: String,
+    matched: u64,
+    files: u64,
+    size_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct MountOut {
+    path: String,
+    fstype: String,
+}
+
+#[derive(Serialize)]
+struct LayerOut {
+    layer: String,
+    path: String,
+    visible: bool,
+    files: u64,
+    size_bytes: u64,
+    whiteouts: u64,
+}
+
+#[derive(Serialize)]
+struct ProfileOut {
+    wall_ms: f64,
+    aggregation_ms: f64,
+    threads: Vec<ThreadProfileOut>,
+}
+
+#[derive(Serialize)]
+struct ThreadProfileOut {
+    files: u64,
+    traversal_ms: f64,
+    metadata_ms: f64,
+    lines_ms: f64,
+    checks_ms: f64,
+}
+
+/// Where, when and how the scan ran, so an archived report still explains
+/// itself.
+#[derive(Serialize)]
+struct ScanOut {
+    hostname: Option<String>,
+    root_path: String,
+    started_at: Option<String>,
+    started_at_epoch: Option<u64>,
+    finished_at: Option<String>,
+    finished_at_epoch: Option<u64>,
+    options: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct SubmodulesOut {
+    mode: &'static str,
+    submodules: Vec<SubmoduleOut>,
+}
+
+#[derive(Serialize)]
+struct SubmoduleOut {
+    path: String,
+    files: u64,
+    size_bytes: u64,
+    lines: u64,
+}
+
+#[derive(Serialize)]
+struct GitOut {
+    files: u64,
+    size_bytes: u64,
+    parts: BTreeMap<&'static str, ShareOut>,
+}
+
+#[derive(Serialize)]
+struct JunkOut {
+    kind: &'static str,
+    files: u64,
+    size_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct SuggestionOut {
+    line: String,
+    files: u64,
+    size_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct DocsOut {
+    path: String,
+    readme: bool,
+    doc_files: u64,
+    doc_bytes: u64,
+    code_bytes: u64,
+    docs_percent: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct LicenseOut {
+    license: String,
+    files: u64,
+    paths: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct NamesOut {
+    paths: u64,
+    issues: Vec<NameIssueOut>,
+}
+
+#[derive(Serialize)]
+struct NameIssueOut {
+    issue: &'static str,
+    paths: u64,
+    examples: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct PatternOut {
+    regex: String,
+    matches: u64,
+    files: u64,
+    top_files: Vec<MatchOut>,
+}
+
+#[derive(Serialize)]
+struct PluginOut {
+    name: String,
+    value: u64,
+    files: u64,
+}
+
+#[derive(Serialize)]
+struct MetricOut {
+    name: String,
+    expr: String,
+    /// Integral results serialize as integers.
+    value: Value,
+}
+
+#[derive(Serialize)]
+struct MatchOut {
+    path: String,
+    matches: u64,
+}
+
+#[derive(Serialize)]
+struct StatsOut {
+    mean_size_bytes: f64,
+    median_size_bytes: f64,
+    mean_lines: f64,
+    median_lines: f64,
+    /// Approximate (1% relative error) `p50`/`p90`/`p99`.
+    size_percentiles: BTreeMap<&'static str, f64>,
+    lines_percentiles: BTreeMap<&'static str, f64>,
+}
+
+fn percentiles(sketch: &Sketch) -> BTreeMap<&'static str, f64> {
+    PERCENTILES
+        .iter()
+        .filter_map(|(name, q)| sketch.quantile(*q).map(|v| (*name, v.round())))
+        .collect()
+}
+
+#[derive(Serialize)]
+struct SymlinksOut {
+    internal_files: u64,
+    internal_dirs: u64,
+    external_files: u64,
+    external_dirs: u64,
+    broken: u64,
+    junctions: u64,
+}
+
+#[derive(Serialize)]
+struct TinyOut {
+    threshold_bytes: u64,
+    files: u64,
+    size_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct ShareOut {
+    files: u64,
+    size_bytes: u64,
+    lines: u64,
+}
+
+impl From<&GroupStat> for ShareOut {
+    fn from(stat: &GroupStat) -> Self {
+        ShareOut {
+            files: stat.files,
+            size_bytes: stat.size,
+            lines: stat.lines,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct FileOut {
+    path: String,
+    size_bytes: u64,
+    lines: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mtime: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct DirOut {
+    path: String,
+    size_bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<u64>,
+    lines: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mtime: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct DeepestOut {
+    path: String,
+    depth: usize,
+}
+
+#[derive(Serialize)]
+struct ExtOut {
+    ext: String,
+    files: u64,
+    size_bytes: u64,
+    lines: u64,
+    mean_lines: f64,
+    median_lines: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_lines_file: Option<FileOut>,
+}
+
+#[derive(Serialize)]
+struct LangOut {
+    language: String,
+    files: u64,
+    size_bytes: u64,
+    lines: u64,
+    mean_lines: f64,
+    median_lines: f64,
+}
+
+#[derive(Serialize)]
+struct ScriptOut {
+    interpreter: String,
+    files: u64,
+    size_bytes: u64,
+    lines: u64,
+}
+
+#[derive(Serialize)]
+struct DepthOut {
+    depth: usize,
+    files: u64,
+    size_bytes: u64,
+    lines: u64,
+}
+
+#[derive(Serialize)]
+struct TypeOut {
+    category: String,
+    files: u64,
+    size_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct ClassOut {
+    class: String,
+    files: u64,
+    size_bytes: u64,
+    lines: u64,
+}
+
+#[derive(Serialize)]
+struct GroupOut {
+    group: String,
+    files: u64,
+    size_bytes: u64,
+    lines: u64,
+}
+
+#[derive(Serialize)]
+struct ExtDirOut {
+    ext: String,
+    /// `None` when no file has the extension.
+    path: Option<String>,
+    files: u64,
+    size_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct TestsOut {
+    language: &'static str,
+    code_files: u64,
+    code_lines: u64,
+    test_files: u64,
+    test_lines: u64,
+}
+
+#[derive(Serialize)]
+struct TestTotalOut {
+    files: u64,
+    lines: u64,
+    code_lines: u64,
+}
+
+#[derive(Serialize)]
+struct OwnerOut {
+    uid: u32,
+    /// Login name; omitted when the uid has no password entry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    files: u64,
+    size_bytes: u64,
+    lines: u64,
+}
+
+#[derive(Serialize)]
+struct MountStatOut {
+    device: u64,
+    /// Omitted when no mount point of the device is in view.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mount_point: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fstype: Option<String>,
+    files: u64,
+    size_bytes: u64,
+    lines: u64,
+}
+
+fn epoch_secs(time: Option<SystemTime>) -> Option<u64> {
+    time.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+/// `None` with `--deterministic`, whose output must not change from one
+/// run to the next.
+fn scan_out(config: &Config, summary: &Summary) -> Option<ScanOut> {
+    if config.deterministic {
+        return None;
+    }
+    let root_path = std::fs::canonicalize(&config.root)
+        .or_else(|_| std::env::current_dir().map(|dir| dir.join(&config.root)))
+        .unwrap_or_else(|_| config.root.clone());
+    let started = epoch_secs(summary.started_at);
+    let finished = epoch_secs(summary.finished_at);
+    Some(ScanOut {
+        hostname: hostname(),
+        root_path: root_path.display().to_string(),
+        started_at: started.map(format_rfc3339),
+        started_at_epoch: started,
+        finished_at: finished.map(format_rfc3339),
+        finished_at_epoch: finished,
+        options: config.args.clone(),
+    })
+}
+
+/// The `scan` object as a JSON string, for outputs other than `--json`.
+pub fn scan_metadata(config: &Config, summary: &Summary) -> Option<String> {
+    scan_out(config, summary).and_then(|scan| serde_json::to_string(&scan).ok())
+}
+
+fn hostname() -> Option<String> {
+    ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .chain(
+            ["/proc/sys/kernel/hostname", "/etc/hostname"]
+                .iter()
+                .filter_map(|path| std::fs::read_to_string(path).ok()),
+        )
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+}
+
+fn file_out(entry: &ListEntry) -> FileOut {
+    FileOut {
+        path: entry.name.clone(),
+        size_bytes: entry.size,
+        lines: entry.lines,
+        mtime: epoch_secs(entry.mtime),
+    }
+}
+
+fn file_stat_out(file: &FileStat, config: &Config) -> FileOut {
+    FileOut {
+        path: display_relative_path(&file.path, &config.root),
+        size_bytes: file.size,
+        lines: file.lines,
+        mtime: epoch_secs(file.mtime),
+    }
+}
+
+fn dir_out(entry: &ListEntry) -> DirOut {
+    DirOut {
+        path: entry.name.clone(),
+        size_bytes: entry.size,
+        files: Some(entry.count),
+        lines: entry.lines,
+        mtime: epoch_secs(entry.mtime),
+    }
+}
+
+fn build(config: &Config, summary: &Summary) -> Report {
+    Report {
+        schema_version: SCHEMA_VERSION,
+        tengok_version: env!("CARGO_PKG_VERSION"),
+        root: config.root.display().to_string(),
+        title: config.title.clone(),
+        note: config.note.clone(),
+        metadata_only: config.metadata_only.then_some(true),
+        scan: scan_out(config, summary),
+        summary: SummaryOut {
+            files: config.shows(Row::Files).then_some(summary.total_files),
+            size_bytes: config.shows(Row::Size).then_some(summary.total_size),
+            lines: config.shows(Row::Lines).then_some(summary.total_lines),
+            largest_dir: config.shows(Row::LargestDir).then(|| {
+                summary.largest_dir.as_ref().map(|(path, size)| DirOut {
+                    path: display_relative_path(path, &config.root),
+                    size_bytes: *size,
+                    files: None,
+                    lines: summary.dirs.get(path).map_or(0, |d| d.lines),
+                    mtime: None,
+                })
+            }),
+            busiest_dir: summary
+                .busiest_dir
+                .as_ref()
+                .filter(|_| config.shows(Row::BusiestDir))
+                .map(|(path, files)| DirOut {
+                    path: display_relative_path(path, &config.root),
+                    size_bytes: summary.dirs.get(path).map_or(0, |d| d.size),
+                    files: Some(*files),
+                    lines: summary.dirs.get(path).map_or(0, |d| d.lines),
+                    mtime: None,
+                }),
+            deepest_dir: summary
+                .deepest_dir
+                .as_ref()
+                .filter(|_| config.shows(Row::DeepestDir))
+                .map(|(path, depth)| DeepestOut {
+                    path: display_relative_path(path, &config.root),
+                    depth: *depth,
+                }),
+            max_lines_file: config.shows(Row::MaxLinesFile).then(|| {
+                summary
+                    .max_lines_file
+                    .as_ref()
+                    .map(|f| file_stat_out(f, config))
+            }),
+            largest_file: config.shows(Row::LargestFile).then(|| {
+                summary
+                    .largest_file
+                    .as_ref()
+                    .map(|f| file_stat_out(f, config))
+            }),
+            average_size_bytes: config.shows(Row::AvgSize).then(|| summary.average_size()),
+            average_lines: config.shows(Row::AvgLines).then(|| summary.average_lines()),
+            newest_file: config.shows(Row::NewestFile).then(|| {
+                summary
+                    .newest_file
+                    .as_ref()
+                    .map(|f| file_stat_out(f, config))
+            }),
+            compressed_size_estimate_bytes: config
+                .compress_estimate
+                .then_some(summary.total_compressed),
+            decompressed: config.decompress.then_some(DecompressedOut {
+                files: summary.decompressed.files,
+                size_bytes: summary.decompressed.size,
+                decompressed_bytes: summary.decompressed.bytes,
+            }),
+            pattern: config.count_pattern.as_ref().map(|re| PatternOut {
+                regex: re.as_str().to_string(),
+                matches: summary.total_matches,
+                files: summary.matching_files,
+                top_files: lists::top_matches(config, summary)
+                    .into_iter()
+                    .map(|(path, matches)| MatchOut { path, matches })
+                    .collect(),
+            }),
+            plugins: (!config.plugins.is_empty()).then(|| {
+                config
+                    .plugins
+                    .iter()
+                    .enumerate()
+                    .map(|(i, plugin)| {
+                        let total = summary.plugins.get(i).copied().unwrap_or_default();
+                        PluginOut {
+                            name: plugin.name.clone(),
+                            value: total.value,
+                            files: total.files,
+                        }
+                    })
+                    .collect()
+            }),
+            metrics: (!config.metrics.is_empty()).then(|| {
+                config
+                    .metrics
+                    .iter()
+                    .enumerate()
+                    .map(|(i, metric)| {
+                        let total = summary.metrics.get(i).copied().unwrap_or_default();
+                        MetricOut {
+                            name: metric.name.clone(),
+                            expr: metric.expr.clone(),
+                            value: match total.result(metric.agg) {
+                                None => Value::Null,
+                                Some(v) if v.fract() == 0.0 => json!(v as u64),
+                                Some(v) => json!(v),
+                            },
+                        }
+                    })
+                    .collect()
+            }),
+            stats: if config.verbose {
+                summary.stats().map(|s| StatsOut {
+                    mean_size_bytes: s.mean_size,
+                    median_size_bytes: s.median_size,
+                    mean_lines: s.mean_lines,
+                    median_lines: s.median_lines,
+                    size_percentiles: percentiles(&summary.size_sketch),
+                    lines_percentiles: percentiles(&summary.lines_sketch),
+                })
+            } else {
+                None
+            },
+            symlinks: config.verbose.then(|| {
+                let s = summary.symlinks;
+                SymlinksOut {
+                    internal_files: s.internal_files,
+                    internal_dirs: s.internal_dirs,
+                    external_files: s.external_files,
+                    external_dirs: s.external_dirs,
+                    broken: s.broken,
+                    junctions: s.junctions,
+                }
+            }),
+            tiny: config.tiny.map(|limit| TinyOut {
+                threshold_bytes: limit,
+                files: summary.tiny.files,
+                size_bytes: summary.tiny.size,
+            }),
+            generated: config.linguist.then(|| (&summary.generated).into()),
+            vendored: config.linguist.then(|| (&summary.vendored).into()),
+            cold: config
+                .cold
+                .map(|_| (&lists::cold(config, summary).0).into()),
+            tests: config.tests.then(|| {
+                let splits = lists::test_split(summary);
+                TestTotalOut {
+                    files: splits.iter().map(|s| s.tests.files).sum(),
+                    lines: splits.iter().map(|s| s.tests.lines).sum(),
+                    code_lines: splits.iter().map(|s| s.code.lines).sum(),
+                }
+            }),
+            source: config.dep_dirs.as_ref().map(|_| (&summary.source()).into()),
+            dependencies: config.dep_dirs.as_ref().map(|_| (&summary.deps).into()),
+            minified: (summary.minified.files > 0).then(|| (&summary.minified).into()),
+            placeholders: (summary.placeholders.files > 0).then(|| (&summary.placeholders).into()),
+            lines_not_counted: (summary.uncounted.files > 0).then(|| (&summary.uncounted).into()),
+            long_paths: (summary.long_paths > 0).then_some(summary.long_paths),
+            names: config.lint_names.then(|| NamesOut {
+                paths: summary.names.paths(),
+                issues: Issue::ALL
+                    .into_iter()
+                    .map(|issue| NameIssueOut {
+                        issue: issue.key(),
+                        paths: summary.names.count(issue),
+                        examples: summary
+                            .names
+                            .examples(issue, names::EXAMPLES)
+                            .map(|path| path.display().to_string())
+                            .collect(),
+                    })
+                    .collect(),
+            }),
+            executables: (summary.executables.files() > 0).then_some(ExecutablesOut {
+                scripts: summary.executables.scripts.files,
+                binaries: summary.executables.binaries.files,
+                other: summary.executables.other.files,
+            }),
+            comments: config.comments.then(|| CommentsOut {
+                comment_lines: summary.comments.comments,
+                lines: summary.comments.lines,
+                percent: summary.comments.density(),
+            }),
+        },
+        top_files: config.top.map(|_| {
+            lists::top_files(config, summary)
+                .iter()
+                .map(file_out)
+                .collect()
+        }),
+        top_dirs: config.top.map(|_| {
+            lists::top_dirs(config, summary)
+                .iter()
+                .map(dir_out)
+                .collect()
+        }),
+        busiest_dirs: config.top.map(|_| {
+            lists::busiest_dirs(config, summary)
+                .iter()
+                .map(dir_out)
+                .collect()
+        }),
+        top_lines_files: config.top_lines.map(|_| {
+            lists::top_lines(config, summary)
+                .iter()
+                .map(file_out)
+                .collect()
+        }),
+        smallest_files: config.tiny.map(|_| {
+            lists::smallest_files(config, summary)
+                .iter()
+                .map(file_out)
+                .collect()
+        }),
+        extensions: config.by_ext.then(|| {
+            lists::extensions(config, summary)
+                .iter()
+                .map(|e| {
+                    let (mean_lines, median_lines) =
+                        lists::lines_per_file(e, summary.ext_lines.get(&e.name));
+                    ExtOut {
+                        ext: e.name.clone(),
+                        files: e.count,
+                        size_bytes: e.size,
+                        lines: e.lines,
+                        mean_lines,
+                        median_lines,
+                        max_lines_file: summary
+                            .exts
+                            .get(&e.name)
+                            .and_then(|stat| stat.max_lines.as_ref())
+                            .map(|f| file_stat_out(f, config)),
+                    }
+                })
+                .collect()
+        }),
+        languages: config.by_lang.then(|| {
+            lists::languages(config, summary)
+                .iter()
+                .map(|e| {
+                    let (mean_lines, median_lines) =
+                        lists::lines_per_file(e, summary.lang_lines.get(e.name.as_str()));
+                    LangOut {
+                        language: e.name.clone(),
+                        files: e.count,
+                        size_bytes: e.size,
+                        lines: e.lines,
+                        mean_lines,
+                        median_lines,
+                    }
+                })
+                .collect()
+        }),
+        content_types: config.mime.then(|| {
+            lists::content_types(config, summary)
+                .iter()
+                .map(|e| TypeOut {
+                    category: e.name.clone(),
+                    files: e.count,
+                    size_bytes: e.size,
+                })
+                .collect()
+        }),
+        asset_classes: config.classes.as_ref().map(|_| {
+            lists::classes(config, summary)
+                .into_iter()
+                .map(|e| ClassOut {
+                    class: e.name,
+                    files: e.count,
+                    size_bytes: e.size,
+                    lines: e.lines,
+                })
+                .collect()
+        }),
+        groups: config.groups.as_ref().map(|_| {
+            lists::groups(config, summary)
+                .into_iter()
+                .map(|e| GroupOut {
+                    group: e.name,
+                    files: e.count,
+                    size_bytes: e.size,
+                    lines: e.lines,
+                })
+                .collect()
+        }),
+        largest_dir_for: (!config.largest_dir_for.is_empty()).then(|| {
+            lists::largest_dirs_for(config, summary)
+                .into_iter()
+                .map(|(ext, largest)| ExtDirOut {
+                    ext: ext.to_string(),
+                    path: largest.map(|(dir, _)| display_relative_path(dir, &config.root)),
+                    files: largest.map_or(0, |(_, stat)| stat.files),
+                    size_bytes: largest.map_or(0, |(_, stat)| stat.size),
+                })
+                .collect()
+        }),
+        tests_by_language: config.tests.then(|| {
+            lists::test_split(summary)
+                .into_iter()
+                .map(|s| TestsOut {
+                    language: s.lang,
+                    code_files: s.code.files,
+                    code_lines: s.code.lines,
+                    test_files: s.tests.files,
+                    test_lines: s.tests.lines,
+                })
+                .collect()
+        }),
+        owners: config.by_owner.then(|| {
+            lists::owners(config, summary)
+                .into_iter()
+                .map(|(e, uid)| OwnerOut {
+                    uid,
+                    name: (e.name != uid.to_string()).then_some(e.name),
+                    files: e.count,
+                    size_bytes: e.size,
+                    lines: e.lines,
+                })
+                .collect()
+        }),
+        mounts: config.by_mount.then(|| {
+            lists::mounts(config, summary)
+                .into_iter()
+                .map(|(e, device, fstype)| MountStatOut {
+                    device,
+                    mount_point: summary
+                        .mount_points
+                        .iter()
+                        .any(|(d, _, _)| *d == device)
+                        .then_some(e.name),
+                    fstype,
+                    files: e.count,
+                    size_bytes: e.size,
+                    lines: e.lines,
+                })
+                .collect()
+        }),
+        unexpected_executables: config.audit.then(|| {
+            lists::unexpected_executables(config, summary)
+                .into_iter()
+                .map(|(e, kind)| ExecutableOut {
+                    path: e.name,
+                    kind: kind.as_str(),
+                    size_bytes: e.size,
+                })
+                .collect()
+        }),
+        secrets: config.secrets.then(|| {
+            lists::secrets(config, summary)
+                .into_iter()
+                .map(|(e, reason)| SecretOut {
+                    path: e.name,
+                    reason: reason.key(),
+                })
+                .collect()
+        }),
+        scripts: config.scripts.then(|| {
+            lists::interpreters(summary)
+                .into_iter()
+                .map(|e| ScriptOut {
+                    interpreter: e.name,
+                    files: e.count,
+                    size_bytes: e.size,
+                    lines: e.lines,
+                })
+                .collect()
+        }),
+        depths: config.by_depth.then(|| {
+            summary
+                .depths
+                .iter()
+                .enumerate()
+                .map(|(depth, stat)| DepthOut {
+                    depth,
+                    files: stat.files,
+                    size_bytes: stat.size,
+                    lines: stat.lines,
+                })
+                .collect()
+        }),
+        duplicate_names: config.dup_names.as_ref().map(|_| {
+            lists::duplicate_names(config, summary)
+                .into_iter()
+                .map(|(name, paths)| DuplicateOut {
+                    name,
+                    files: paths.len() as u64,
+                    paths,
+                })
+                .collect()
+        }),
+        duplicate_files: config.dupes.then(|| {
+            summary
+                .duplicates
+                .iter()
+                .map(|group| DuplicateFilesOut {
+                    sha256: group.hash.clone(),
+                    size_bytes: group.size,
+                    copies: group.copies,
+                    wasted_bytes: group.wasted(),
+                    paths: group
+                        .paths
+                        .iter()
+                        .map(|p| display_relative_path(p, &config.root))
+                        .collect(),
+                })
+                .collect()
+        }),
+        widest_dir: summary
+            .widest_dir
+            .as_ref()
+            .map(|(dir, entries)| EntriesOut {
+                path: display_relative_path(dir, &config.root),
+                entries: *entries,
+            }),
+        crowded_dirs: (config.max_entries > 0).then(|| {
+            summary
+                .crowded_dirs
+                .iter()
+                .map(|(dir, entries)| EntriesOut {
+                    path: display_relative_path(dir, &config.root),
+                    entries: *entries,
+                })
+                .collect()
+        }),
+        ignore_rules: config.profile_ignores.then(|| {
+            summary
+                .ignore_rules
+                .iter()
+                .map(|rule| IgnoreRuleOut {
+                    pattern: rule.pattern.clone(),
+                    source: display_relative_path(&rule.source, &config.root),
+                    matched: rule.matched,
+                    files: rule.files,
+                    size_bytes: rule.bytes,
+                })
+                .collect()
+        }),
+        overlay_layers: config.overlay_layers.then(|| {
+            summary
+                .overlay_layers
+                .iter()
+                .map(|layer| LayerOut {
+                    layer: layer.name.clone(),
+                    path: layer.dir.display().to_string(),
+                    visible: layer.visible,
+                    files: layer.files,
+                    size_bytes: layer.bytes,
+                    whiteouts: layer.whiteouts,
+                })
+                .collect()
+        }),
+        skipped_mounts: summary
+            .skipped_mounts
+            .iter()
+            .map(|(path, fstype)| MountOut {
+                path: path.display().to_string(),
+                fstype: fstype.clone(),
+            })
+            .collect(),
+        submodules: (config.submodules != submodules::Mode::Include).then(|| SubmodulesOut {
+            mode: config.submodules.as_str(),
+            submodules: summary
+                .submodules
+                .iter()
+                .map(|(dir, stat)| SubmoduleOut {
+                    path: display_relative_path(dir, &config.root),
+                    files: stat.files,
+                    size_bytes: stat.size,
+                    lines: stat.lines,
+                })
+                .collect(),
+        }),
+        git: config.include_git.then(|| GitOut {
+            files: summary.git.files(),
+            size_bytes: summary.git.size(),
+            parts: Part::ALL
+                .into_iter()
+                .map(|part| (part.key(), summary.git.part(part).into()))
+                .collect(),
+        }),
+        junk: config.junk.then(|| {
+            lists::junk(summary)
+                .into_iter()
+                .map(|(kind, stat)| JunkOut {
+                    kind,
+                    files: stat.files,
+                    size_bytes: stat.size,
+                })
+                .collect()
+        }),
+        gitignore_suggestions: config.suggest_ignore.then(|| {
+            gitignore::suggestions(config, summary)
+                .into_iter()
+                .map(|s| SuggestionOut {
+                    line: s.line,
+                    files: s.files,
+                    size_bytes: s.size,
+                })
+                .collect()
+        }),
+        cold_dirs: config.cold.map(|_| {
+            lists::cold(config, summary)
+                .1
+                .into_iter()
+                .map(|(dir, stat)| DirOut {
+                    path: display_relative_path(&dir, &config.root),
+                    size_bytes: stat.size,
+                    files: Some(stat.files),
+                    lines: stat.lines,
+                    mtime: None,
+                })
+                .collect()
+        }),
+        stale_dirs: config.stale.map(|_| {
+            lists::stale(config, summary)
+                .into_iter()
+                .map(|(dir, stat)| DirOut {
+                    path: display_relative_path(&dir, &config.root),
+                    size_bytes: stat.size,
+                    files: Some(stat.files),
+                    lines: stat.lines,
+                    mtime: epoch_secs(stat.newest),
+                })
+                .collect()
+        }),
+        dotfiles: config.dotfiles.then(|| {
+            lists::dotfiles(config, summary)
+                .iter()
+                .map(dir_out)
+                .collect()
+        }),
+        docs: config.docs.then(|| {
+            lists::docs(summary)
+                .into_iter()
+                .map(|(path, stat)| DocsOut {
+                    path,
+                    readme: stat.readmes > 0,
+                    doc_files: stat.doc_files,
+                    doc_bytes: stat.doc_bytes,
+                    code_bytes: stat.code_bytes,
+                    docs_percent: stat.ratio(),
+                })
+                .collect()
+        }),
+        licenses: config.licenses.then(|| {
+            lists::licenses(config, summary)
+                .into_iter()
+                .map(|(license, paths)| LicenseOut {
+                    license,
+                    files: paths.len() as u64,
+                    paths,
+                })
+                .collect()
+        }),
+        comment_density: config.comments.then(|| {
+            lists::comment_density(summary)
+                .into_iter()
+                .map(|(language, stat)| LangCommentsOut {
+                    language,
+                    comment_lines: stat.comments,
+                    lines: stat.lines,
+                    percent: stat.density(),
+                })
+                .collect()
+        }),
+        least_commented_files: config.comments.then(|| {
+            lists::least_commented(config, summary)
+                .into_iter()
+                .map(|(e, comments)| FileCommentsOut {
+                    percent: comments::density(comments, e.lines),
+                    path: e.name,
+                    comment_lines: comments,
+                    lines: e.lines,
+                })
+                .collect()
+        }),
+        case_collisions: config.case_collisions.then(|| {
+            summary
+                .case_paths
+                .collisions()
+                .into_iter()
+                .map(|group| group.iter().map(|p| p.display().to_string()).collect())
+                .collect()
+        }),
+        budget_violations: (!config.budgets.is_empty()).then(|| {
+            budget::violations(config, summary)
+                .into_iter()
+                .map(|v| ViolationOut {
+                    glob: v.budget.glob.clone(),
+                    rule: v.budget.rule.clone(),
+                    unit: match v.budget.unit {
+                        Unit::Bytes => "bytes",
+                        Unit::Lines => "lines",
+                        Unit::Files => "files",
+                    },
+                    limit: v.budget.limit,
+                    value: v.value,
+                    path: v.path.map(|p| display_relative_path(p, &config.root)),
+                })
+                .collect()
+        }),
+        growth: baseline::growth_check(config, summary).map(|g| GrowthOut {
+            max_percent: g.max_percent,
+            size_bytes_percent: g.size,
+            lines_percent: g.lines,
+            exceeded: g.exceeded,
+        }),
+        profile: summary.profile.as_ref().map(|p| ProfileOut {
+            wall_ms: millis(p.wall),
+            aggregation_ms: millis(p.aggregation),
+            threads: p
+                .threads
+                .iter()
+                .map(|t| ThreadProfileOut {
+                    files: t.files,
+                    traversal_ms: millis(t.get(Phase::Traversal)),
+                    metadata_ms: millis(t.get(Phase::Metadata)),
+                    lines_ms: millis(t.get(Phase::Lines)),
+                    checks_ms: millis(t.get(Phase::Checks)),
+                })
+                .collect(),
+        }),
+    }
+}
+
+/// The `--json` document as a value, for embedding in other responses.
+pub fn to_value(config: &Config, summary: &Summary) -> Value {
+    serde_json::to_value(build(config, summary)).expect("report serializes")
+}
+
+pub fn print_json(config: &Config, summary: &Summary) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    serde_json::to_writer_pretty(&mut stdout, &build(config, summary))?;
+    writeln!(stdout)
+}
+
+/// One directory of `--json-tree`: totals for everything below it, and for
+/// the files directly inside (`own_*`) so a treemap can size each node by
+/// what it alone holds.
+#[derive(Serialize, Default)]
+struct TreeNode {
+    name: String,
+    path: String,
+    size_bytes: u64,
+    files: u64,
+    lines: u64,
+    own_size_bytes: u64,
+    own_files: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<TreeNode>,
+}
+
+/// The directories holding files, with every directory between them and
+/// the root, nested; children largest first.
+fn tree(config: &Config, summary: &Summary) -> TreeNode {
+    let root = &config.root;
+    let mut children: BTreeMap<&Path, Vec<&Path>> = BTreeMap::new();
+    let mut seen: HashSet<&Path> = HashSet::new();
+    for dir in summary.dirs.keys() {
+        for (child, parent) in dir.ancestors().zip(dir.ancestors().skip(1)) {
+            if !child.starts_with(root) || child == root.as_path() || !seen.insert(child) {
+                break;
+            }
+            children.entry(parent).or_default().push(child);
+        }
+    }
+
+    fn build(
+        dir: &Path,
+        name: String,
+        config: &Config,
+        summary: &Summary,
+        children: &BTreeMap<&Path, Vec<&Path>>,
+    ) -> TreeNode {
+        let own = summary.dirs.get(dir).cloned().unwrap_or_default();
+        let mut node = TreeNode {
+            name,
+            path: display_relative_path(dir, &config.root),
+            size_bytes: own.size,
+            files: own.files,
+            lines: own.lines,
+            own_size_bytes: own.size,
+            own_files: own.files,
+            children: Vec::new(),
+        };
+        for child in children.get(dir).into_iter().flatten() {
+            let name = child
+                .file_name()
+                .map_or(String::new(), |n| n.to_string_lossy().into_owned());
+            let child = build(child, name, config, summary, children);
+            node.size_bytes += child.size_bytes;
+            node.files += child.files;
+            node.lines += child.lines;
+            node.children.push(child);
+        }
+        node.children.sort_by(|a, b| {
+            b.size_bytes
+                .cmp(&a.size_bytes)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        node
+    }
+
+    build(root, root.display().to_string(), config, summary, &children)
+}
+
+/// `--json-tree`: the directory hierarchy as one nested object, which d3's
+/// `hierarchy()` (treemaps, sunbursts, icicles) takes as is.
+pub fn print_json_tree(config: &Config, summary: &Summary) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    serde_json::to_writer(&mut stdout, &tree(config, summary))?;
+    writeln!(stdout)
+}
+
+/// `--ndjson`: the `--list` page as one `top_files`-style object per line,
+/// for consumers that stream rather than parse one document.
+pub fn print_file_lines(config: &Config, summary: &Summary) -> io::Result<()> {
+    let (entries, next) = lists::file_page(config, summary);
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for entry in &entries {
+        let written = serde_json::to_writer(&mut out, &file_out(entry))
+            .map_err(io::Error::from)
+            .and_then(|()| writeln!(out));
+        if !report::until_closed(written)? {
+            return Ok(());
+        }
+    }
+    if report::until_closed(out.flush())? {
+        report::note_next_page(next);
+    }
+    Ok(())
+}
+
+/// JSON Schema (draft 2020-12) for the `--json` output, printed by
+/// `tengok schema`. Keep in sync with the structs above.
+pub fn schema() -> String {
+    let file = json!({
+        "type": "object",
+        "required": ["path", "size_bytes", "lines"],
+        "properties": {
+            "path": { "type": "string", "description": "Path relative to the scanned root." },
+            "size_bytes": { "type": "integer", "minimum": 0 },
+            "lines": { "type": "integer", "minimum": 0 },
+            "mtime": { "type": "integer", "description": "Modification time, seconds since the Unix epoch." }
+        }
+    });
+    let dir = json!({
+        "type": "object",
+        "required": ["path", "size_bytes", "lines"],
+        "properties": {
+            "path": { "type": "string", "description": "Directory relative to the scanned root." },
+            "size_bytes": { "type": "integer", "minimum": 0, "description": "Bytes of the files directly inside the directory." },
+            "files": { "type": "integer", "minimum": 0 },
+            "lines": { "type": "integer", "minimum": 0, "description": "Lines of the files directly inside the directory." },
+            "mtime": { "type": "integer", "description": "Newest file modification time, seconds since the Unix epoch." }
+        }
+    });
+    let percentiles = json!({
+        "type": "object",
+        "description": "Approximate percentiles (within 1% relative error).",
+        "properties": {
+            "p50": { "type": "number", "minimum": 0 },
+            "p90": { "type": "number", "minimum": 0 },
+            "p99": { "type": "number", "minimum": 0 }
+        }
+    });
+    let share = json!({
+        "type": "object",
+        "required": ["files", "size_bytes", "lines"],
+        "properties": {
+            "files": { "type": "integer", "minimum": 0 },
+            "size_bytes": { "type": "integer", "minimum": 0 },
+            "lines": { "type": "integer", "minimum": 0, "description": "0 with --exclude-generated." }
+        }
+    });
+    let ext = json!({
+        "type": "object",
+        "required": ["ext", "files", "size_bytes", "lines", "mean_lines", "median_lines"],
+        "properties": {
+            "ext": { "type": "string", "description": "Lowercased extension, or \"(none)\"." },
+            "files": { "type": "integer", "minimum": 0 },
+            "size_bytes": { "type": "integer", "minimum": 0 },
+            "lines": { "type": "integer", "minimum": 0 },
+            "mean_lines": { "type": "number", "minimum": 0 },
+            "median_lines": { "type": "number", "minimum": 0, "description": "Approximate, within 1%." },
+            "max_lines_file": file.clone()
+        }
+    });
+    let language = json!({
+        "type": "object",
+        "required": ["language", "files", "size_bytes", "lines", "mean_lines", "median_lines"],
+        "properties": {
+            "language": { "type": "string", "description": "Language name, or \"unknown\"." },
+            "files": { "type": "integer", "minimum": 0 },
+            "size_bytes": { "type": "integer", "minimum": 0 },
+            "lines": { "type": "integer", "minimum": 0 },
+            "mean_lines": { "type": "number", "minimum": 0 },
+            "median_lines": { "type": "number", "minimum": 0, "description": "Approximate, within 1%." }
+        }
+    });
+    let owners = json!({
+        "type": "array",
+        "description": "Files per owning user (--by-owner), in --sort order.",
+        "items": {
+            "type": "object",
+            "required": ["uid", "files", "size_bytes", "lines"],
+            "properties": {
+                "uid": { "type": "integer", "minimum": 0 },
+                "name": { "type": "string", "description": "Login name; omitted when the uid has no password entry." },
+                "files": { "type": "integer", "minimum": 0 },
+                "size_bytes": { "type": "integer", "minimum": 0 },
+                "lines": { "type": "integer", "minimum": 0 }
+            }
+        }
+    });
+    let mounts = json!({
+        "type": "array",
+        "description": "Files per device (--by-mount), in --sort order.",
+        "items": {
+            "type": "object",
+            "required": ["device", "files", "size_bytes", "lines"],
+            "properties": {
+                "device": { "type": "integer", "minimum": 0 },
+                "mount_point": { "type": "string", "description": "Where the device is mounted; omitted when no mount point of it is in view." },
+                "fstype": { "type": "string" },
+                "files": { "type": "integer", "minimum": 0 },
+                "size_bytes": { "type": "integer", "minimum": 0 },
+                "lines": { "type": "integer", "minimum": 0 }
+            }
+        }
+    });
+    let content_type = json!({
+        "type": "object",
+        "required": ["category", "files", "size_bytes"],
+        "properties": {
+            "category": {
+                "enum": ["image", "video", "audio", "archive", "executable", "document", "font", "text", "binary", "empty"],
+                "description": "Category sniffed from the file's leading bytes."
+            },
+            "files": { "type": "integer", "minimum": 0 },
+            "size_bytes": { "type": "integer", "minimum": 0 }
+        }
+    });
+
+    let comments = json!({
+        "type": "object",
+        "description": "Comment lines over files in a language with a known comment syntax (--comments).",
+        "required": ["comment_lines", "lines", "percent"],
+        "properties": {
+            "comment_lines": { "type": "integer", "minimum": 0 },
+            "lines": { "type": "integer", "minimum": 0 },
+            "percent": { "type": "number", "minimum": 0, "maximum": 100 }
+        }
+    });
+    let unexpected_executables = json!({
+        "type": "array",
+        "description": "Executables that aren't shebang scripts (--audit), binaries first.",
+        "items": {
+            "type": "object",
+            "required": ["path", "kind", "size_bytes"],
+            "properties": {
+                "path": { "type": "string" },
+                "kind": { "enum": ["binary", "other"] },
+                "size_bytes": { "type": "integer", "minimum": 0 }
+            }
+        }
+    });
+    let secrets = json!({
+        "type": "array",
+        "description": "--secrets files that look like credentials, by path. The matched text is never included.",
+        "items": {
+            "type": "object",
+            "required": ["path", "reason"],
+            "properties": {
+                "path": { "type": "string" },
+                "reason": { "enum": ["env_file", "key_file", "private_key", "aws_key", "token"] }
+            }
+        }
+    });
+    let executables = json!({
+        "type": "object",
+        "description": "Files with an executable bit (Unix) by their first bytes; only present when there are any.",
+        "required": ["scripts", "binaries", "other"],
+        "properties": {
+            "scripts": { "type": "integer", "minimum": 0, "description": "Starting with a shebang." },
+            "binaries": { "type": "integer", "minimum": 0, "description": "ELF, Mach-O, PE or WebAssembly." },
+            "other": { "type": "integer", "minimum": 0 }
+        }
+    });
+    let names = json!({
+        "type": "object",
+        "description": "Unportable file and directory names (--lint-names).",
+        "required": ["paths", "issues"],
+        "properties": {
+            "paths": { "type": "integer", "minimum": 0, "description": "Files and directories with at least one issue." },
+            "issues": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["issue", "paths", "examples"],
+                    "properties": {
+                        "issue": { "enum": ["invalid_chars", "reserved", "trailing_space_or_dot", "control_chars", "not_nfc"] },
+                        "paths": { "type": "integer", "minimum": 0 },
+                        "examples": { "type": "array", "items": { "type": "string" }, "description": "The first offenders in path order, relative to the root." }
+                    }
+                }
+            }
+        }
+    });
+
+    let minified = json!({
+        "type": "object",
+        "description": "Minified and bundled JS/CSS and source maps; only present when there are any.",
+        "required": ["files", "size_bytes", "lines"],
+        "properties": {
+            "files": { "type": "integer", "minimum": 0 },
+            "size_bytes": { "type": "integer", "minimum": 0 },
+            "lines": { "type": "integer", "minimum": 0, "description": "0 unless --include-minified." }
+        }
+    });
+
+    let lines_not_counted = json!({
+        "type": "object",
+        "description": "Files whose lines weren't counted, being over --max-line-bytes or of a binary extension (--force-lines counts them); only present when there are any.",
+        "required": ["files", "size_bytes", "lines"],
+        "properties": {
+            "files": { "type": "integer", "minimum": 0 },
+            "size_bytes": { "type": "integer", "minimum": 0 },
+            "lines": { "type": "integer", "minimum": 0, "description": "Always 0." }
+        }
+    });
+
+    let summary = json!({
+        "type": "object",
+        "description": "files through largest_file are always present unless --fields leaves them out.",
+        "properties": {
+            "files": { "type": "integer", "minimum": 0 },
+            "size_bytes": { "type": "integer", "minimum": 0 },
+            "lines": { "type": "integer", "minimum": 0 },
+            "largest_dir": { "oneOf": [dir, { "type": "null" }] },
+            "busiest_dir": {
+                "allOf": [dir],
+                "description": "The directory with the most files directly inside; absent for an empty tree."
+            },
+            "deepest_dir": {
+                "type": "object",
+                "required": ["path", "depth"],
+                "properties": {
+                    "path": { "type": "string" },
+                    "depth": { "type": "integer", "minimum": 0 }
+                },
+                "description": "The most deeply nested directory holding files, with its depth below the root; absent for an empty tree."
+            },
+            "max_lines_file": { "oneOf": [file, { "type": "null" }] },
+            "largest_file": { "oneOf": [file, { "type": "null" }], "description": "The file with the most bytes." },
+            "average_size_bytes": { "type": ["number", "null"], "minimum": 0, "description": "--fields avg_size; null without files." },
+            "average_lines": { "type": ["number", "null"], "minimum": 0, "description": "--fields avg_lines; null without files." },
+            "newest_file": { "oneOf": [file, { "type": "null" }], "description": "--fields newest_file: the most recently modified file." },
+            "compressed_size_estimate_bytes": {
+                "type": "integer",
+                "minimum": 0,
+                "description": "Estimated zstd level 1 size of all files (--compress-estimate)."
+            },
+            "decompressed": {
+                "type": "object",
+                "description": "--decompress: the .zst, .gz and .bz2 files whose lines were counted through a decompressor, their bytes on disk and decompressed. Their lines are part of the totals; their sizes stay the on-disk ones.",
+                "required": ["files", "size_bytes", "decompressed_bytes"],
+                "properties": {
+                    "files": { "type": "integer", "minimum": 0 },
+                    "size_bytes": { "type": "integer", "minimum": 0 },
+                    "decompressed_bytes": { "type": "integer", "minimum": 0 }
+                }
+            },
+            "pattern": {
+                "type": "object",
+                "description": "--count-pattern results; matches are counted per line in files whose lines were counted.",
+                "required": ["regex", "matches", "files", "top_files"],
+                "properties": {
+                    "regex": { "type": "string" },
+                    "matches": { "type": "integer", "minimum": 0 },
+                    "files": { "type": "integer", "minimum": 0, "description": "Files with at least one match." },
+                    "top_files": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["path", "matches"],
+                            "properties": {
+                                "path": { "type": "string" },
+                                "matches": { "type": "integer", "minimum": 1 }
+                            }
+                        }
+                    }
+                }
+            },
+            "plugins": {
+                "type": "array",
+                "description": "--plugin metrics in command-line order: the sum over all files, and how many files had a non-zero value.",
+                "items": {
+                    "type": "object",
+                    "required": ["name", "value", "files"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "value": { "type": "integer", "minimum": 0 },
+                        "files": { "type": "integer", "minimum": 0 }
+                    }
+                }
+            },
+            "metrics": {
+                "type": "array",
+                "description": "--metric results in command-line order; value is null for avg/min/max over no files.",
+                "items": {
+                    "type": "object",
+                    "required": ["name", "expr", "value"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "expr": { "type": "string" },
+                        "value": { "type": ["number", "null"] }
+                    }
+                }
+            },
+            "stats": {
+                "type": "object",
+                "description": "Per-file distribution (--verbose); absent for an empty tree.",
+                "required": ["mean_size_bytes", "median_size_bytes", "mean_lines", "median_lines"],
+                "properties": {
+                    "mean_size_bytes": { "type": "number", "minimum": 0 },
+                    "median_size_bytes": { "type": "number", "minimum": 0 },
+                    "mean_lines": { "type": "number", "minimum": 0 },
+                    "median_lines": { "type": "number", "minimum": 0 },
+                    "size_percentiles": percentiles.clone(),
+                    "lines_percentiles": percentiles
+                }
+            },
+            "symlinks": {
+                "type": "object",
+                "description": "Symlinks passed over by the walker (--verbose); internal/external is relative to the scanned root.",
+                "required": ["internal_files", "internal_dirs", "external_files", "external_dirs", "broken", "junctions"],
+                "properties": {
+                    "internal_files": { "type": "integer", "minimum": 0 },
+                    "internal_dirs": { "type": "integer", "minimum": 0 },
+                    "external_files": { "type": "integer", "minimum": 0 },
+                    "external_dirs": { "type": "integer", "minimum": 0 },
+                    "broken": { "type": "integer", "minimum": 0, "description": "Dangling targets and loops." },
+                    "junctions": { "type": "integer", "minimum": 0, "description": "NTFS junctions among the links above (Windows)." }
+                }
+            },
+            "tiny": {
+                "type": "object",
+                "required": ["threshold_bytes", "files", "size_bytes"],
+                "properties": {
+                    "threshold_bytes": { "type": "integer", "minimum": 0 },
+                    "files": { "type": "integer", "minimum": 0, "description": "Files strictly smaller than threshold_bytes." },
+                    "size_bytes": { "type": "integer", "minimum": 0 }
+                }
+            },
+            "generated": share.clone(),
+            "vendored": share.clone(),
+            "cold": share.clone(),
+            "tests": {
+                "type": "object",
+                "description": "Test files (--tests) among code files, and their lines against all code lines.",
+                "required": ["files", "lines", "code_lines"],
+                "properties": {
+                    "files": { "type": "integer", "minimum": 0 },
+                    "lines": { "type": "integer", "minimum": 0 },
+                    "code_lines": { "type": "integer", "minimum": 0 }
+                }
+            },
+            "source": share.clone(),
+            "dependencies": share.clone(),
+            "long_paths": { "type": "integer", "minimum": 1, "description": "Files whose absolute path is 260 or more UTF-16 units (Windows MAX_PATH); only present when there are any." },
+            "placeholders": {
+                "type": "object",
+                "description": "Cloud placeholders (Windows OneDrive and the like), counted by size without reading their contents; only present when there are any.",
+                "required": ["files", "size_bytes", "lines"],
+                "properties": {
+                    "files": { "type": "integer", "minimum": 0 },
+                    "size_bytes": { "type": "integer", "minimum": 0 },
+                    "lines": { "type": "integer", "minimum": 0, "description": "Always 0." }
+                }
+            },
+            "lines_not_counted": lines_not_counted,
+            "comments": comments,
+            "names": names,
+            "executables": executables,
+            "minified": minified
+        }
+    });
+
+    let submodules = json!({
+        "type": "object",
+        "description": "Submodules from the root's .gitmodules (--submodules exclude or separate). With exclude, their files are left out of every other figure.",
+        "required": ["mode", "submodules"],
+        "properties": {
+            "mode": { "enum": ["exclude", "separate"] },
+            "submodules": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["path", "files", "size_bytes", "lines"],
+                    "properties": {
+                        "path": { "type": "string" },
+                        "files": { "type": "integer", "minimum": 0 },
+                        "size_bytes": { "type": "integer", "minimum": 0 },
+                        "lines": { "type": "integer", "minimum": 0 }
+                    }
+                }
+            }
+        }
+    });
+    let git = json!({
+        "type": "object",
+        "description": "The root's .git directory (--include-git), whose files also count toward the totals.",
+        "required": ["files", "size_bytes", "parts"],
+        "properties": {
+            "files": { "type": "integer", "minimum": 0 },
+            "size_bytes": { "type": "integer", "minimum": 0 },
+            "parts": {
+                "type": "object",
+                "required": ["packs", "objects", "refs", "hooks", "other"],
+                "additionalProperties": share
+            }
+        }
+    });
+    let junk = json!({
+        "type": "array",
+        "description": "OS junk files per kind (--junk), most bytes first.",
+        "items": {
+            "type": "object",
+            "required": ["kind", "files", "size_bytes"],
+            "properties": {
+                "kind": { "enum": [".DS_Store", "AppleDouble (._*)", "Thumbs.db", "desktop.ini"] },
+                "files": { "type": "integer", "minimum": 1 },
+                "size_bytes": { "type": "integer", "minimum": 0 }
+            }
+        }
+    });
+    let docs = json!({
+        "type": "array",
+        "description": "Top-level directories (--docs), those without a README first, then by docs_percent.",
+        "items": {
+            "type": "object",
+            "required": ["path", "readme", "doc_files", "doc_bytes", "code_bytes", "docs_percent"],
+            "properties": {
+                "path": { "type": "string" },
+                "readme": { "type": "boolean", "description": "A README directly in the directory." },
+                "doc_files": { "type": "integer", "minimum": 0 },
+                "doc_bytes": { "type": "integer", "minimum": 0 },
+                "code_bytes": { "type": "integer", "minimum": 0 },
+                "docs_percent": { "type": ["number", "null"], "description": "doc_bytes over code_bytes; null without code." }
+            }
+        }
+    });
+    let licenses = json!({
+        "type": "array",
+        "description": "LICENSE, COPYING and NOTICE files per license (--licenses), most files first.",
+        "items": {
+            "type": "object",
+            "required": ["license", "files", "paths"],
+            "properties": {
+                "license": { "type": "string", "description": "SPDX identifier or expression where known, else \"notice\" or \"unknown\"." },
+                "files": { "type": "integer", "minimum": 1 },
+                "paths": { "type": "array", "items": { "type": "string" } }
+            }
+        }
+    });
+    let scan = json!({
+        "type": "object",
+        "description": "Where, when and how the scan ran; left out with --deterministic.",
+        "required": ["hostname", "root_path", "started_at", "started_at_epoch", "finished_at", "finished_at_epoch", "options"],
+        "properties": {
+            "hostname": { "type": ["string", "null"] },
+            "root_path": { "type": "string", "description": "Absolute path of the root." },
+            "started_at": { "type": ["string", "null"], "format": "date-time" },
+            "started_at_epoch": { "type": ["integer", "null"], "minimum": 0 },
+            "finished_at": { "type": ["string", "null"], "format": "date-time" },
+            "finished_at_epoch": { "type": ["integer", "null"], "minimum": 0 },
+            "options": { "type": "array", "items": { "type": "string" }, "description": "Command-line arguments the scan ran with." }
+        }
+    });
+    let mut properties = json!({
+        "schema_version": { "const": SCHEMA_VERSION },
+        "tengok_version": { "type": "string" },
+        "root": { "type": "string" },
+        "title": { "type": "string", "description": "--title, which replaced the report header." },
+        "note": { "type": "string", "description": "--note shown under the report header." },
+        "scan": scan,
+        "summary": summary,
+        "top_files": { "type": "array", "items": file },
+        "top_dirs": { "type": "array", "items": dir },
+        "busiest_dirs": { "type": "array", "items": dir, "description": "--top directories by file count, regardless of --sort." },
+        "top_lines_files": { "type": "array", "items": file, "description": "--top-lines files with the most lines, regardless of --sort." },
+        "smallest_files": { "type": "array", "items": file },
+        "extensions": { "type": "array", "items": ext },
+        "languages": { "type": "array", "items": language },
+        "content_types": { "type": "array", "items": content_type },
+        "owners": owners,
+        "asset_classes": {
+            "type": "array",
+            "description": "Files per asset class (--by-class), in --sort order.",
+            "items": {
+                "type": "object",
+                "required": ["class", "files", "size_bytes", "lines"],
+                "properties": {
+                    "class": { "type": "string", "description": "code, docs, images, data, config, tests, other, or a class from tengok-classes.toml." },
+                    "files": { "type": "integer", "minimum": 0 },
+                    "size_bytes": { "type": "integer", "minimum": 0 },
+                    "lines": { "type": "integer", "minimum": 0 }
+                }
+            }
+        },
+        "tests_by_language": {
+            "type": "array",
+            "description": "Per code language (--tests), most code lines first: all its files, and the test files among them.",
+            "items": {
+                "type": "object",
+                "required": ["language", "code_files", "code_lines", "test_files", "test_lines"],
+                "properties": {
+                    "language": { "type": "string" },
+                    "code_files": { "type": "integer", "minimum": 0 },
+                    "code_lines": { "type": "integer", "minimum": 0 },
+                    "test_files": { "type": "integer", "minimum": 0 },
+                    "test_lines": { "type": "integer", "minimum": 0 }
+                }
+            }
+        },
+        "unexpected_executables": unexpected_executables,
+        "secrets": secrets,
+        "scripts": {
+            "type": "array",
+            "description": "Extensionless scripts per shebang interpreter (--scripts), most scripts first.",
+            "items": {
+                "type": "object",
+                "required": ["interpreter", "files", "size_bytes", "lines"],
+                "properties": {
+                    "interpreter": { "type": "string", "description": "Program name without version, e.g. \"python\"." },
+                    "files": { "type": "integer", "minimum": 1 },
+                    "size_bytes": { "type": "integer", "minimum": 0 },
+                    "lines": { "type": "integer", "minimum": 0 }
+                }
+            }
+        },
+        "depths": {
+            "type": "array",
+            "description": "Files per directory depth (--by-depth); index 0 is the root itself.",
+            "items": {
+                "type": "object",
+                "required": ["depth", "files", "size_bytes", "lines"],
+                "properties": {
+                    "depth": { "type": "integer", "minimum": 0 },
+                    "files": { "type": "integer", "minimum": 0 },
+                    "size_bytes": { "type": "integer", "minimum": 0 },
+                    "lines": { "type": "integer", "minimum": 0 }
+                }
+            }
+        },
+        "duplicate_names": {
+            "type": "array",
+            "description": "--top file names found in more than one directory (--dup-names), most copies first.",
+            "items": {
+                "type": "object",
+                "required": ["name", "files", "paths"],
+                "properties": {
+                    "name": { "type": "string" },
+                    "files": { "type": "integer", "minimum": 2 },
+                    "paths": { "type": "array", "items": { "type": "string" } }
+                }
+            }
+        },
+        "duplicate_files": {
+            "type": "array",
+            "description": "--top groups of files with identical contents (--dupes), most wasted bytes first. copies counts hard links to one file once.",
+            "items": {
+                "type": "object",
+                "required": ["sha256", "size_bytes", "copies", "wasted_bytes", "paths"],
+                "properties": {
+                    "sha256": { "type": "string" },
+                    "size_bytes": { "type": "integer", "minimum": 1 },
+                    "copies": { "type": "integer", "minimum": 2 },
+                    "wasted_bytes": { "type": "integer", "minimum": 0 },
+                    "paths": { "type": "array", "items": { "type": "string" } }
+                }
+            }
+        },
+        "widest_dir": {
+            "type": "object",
+            "description": "The directory with the most direct entries (files, directories and anything else the walk visited); absent for a single file.",
+            "required": ["path", "entries"],
+            "properties": {
+                "path": { "type": "string" },
+                "entries": { "type": "integer", "minimum": 1 }
+            }
+        },
+        "crowded_dirs": {
+            "type": "array",
+            "description": "Directories with more than --max-entries (default 100000) direct entries, most first; absent with --max-entries 0.",
+            "items": {
+                "type": "object",
+                "required": ["path", "entries"],
+                "properties": {
+                    "path": { "type": "string" },
+                    "entries": { "type": "integer", "minimum": 1 }
+                }
+            }
+        },
+        "ignore_rules": {
+            "type": "array",
+            "description": "--top .gitignore/.ignore rules by the bytes they kept out of the scan (--profile-ignores). matched counts the entries the rule matched directly; files and size_bytes include everything below a matched directory.",
+            "items": {
+                "type": "object",
+                "required": ["pattern", "source", "matched", "files", "size_bytes"],
+                "properties": {
+                    "pattern": { "type": "string" },
+                    "source": { "type": "string" },
+                    "matched": { "type": "integer", "minimum": 1 },
+                    "files": { "type": "integer", "minimum": 0 },
+                    "size_bytes": { "type": "integer", "minimum": 0 }
+                }
+            }
+        }
+    });
+    // The rest goes in a second object to stay under json!'s recursion
+    // limit.
+    let profile = json!({
+        "type": "object",
+        "description": "Time per scan phase (--profile), in milliseconds.",
+        "required": ["wall_ms", "aggregation_ms", "threads"],
+        "properties": {
+            "wall_ms": { "type": "number", "minimum": 0 },
+            "aggregation_ms": { "type": "number", "minimum": 0 },
+            "threads": {
+                "type": "array",
+                "description": "Walker threads that read any files.",
+                "items": {
+                    "type": "object",
+                    "required": ["files", "traversal_ms", "metadata_ms", "lines_ms", "checks_ms"],
+                    "properties": {
+                        "files": { "type": "integer", "minimum": 1 },
+                        "traversal_ms": { "type": "number", "minimum": 0 },
+                        "metadata_ms": { "type": "number", "minimum": 0 },
+                        "lines_ms": { "type": "number", "minimum": 0 },
+                        "checks_ms": { "type": "number", "minimum": 0 }
+                    }
+                }
+            }
+        }
+    });
+    let overlay_layers = json!({
+        "type": "array",
+        "description": "The layers behind an overlayfs root, upper first, measured on disk (--overlay-layers). Layers tengok can't read have visible false and zero counts.",
+        "items": {
+            "type": "object",
+            "required": ["layer", "path", "visible", "files", "size_bytes", "whiteouts"],
+            "properties": {
+                "layer": { "type": "string" },
+                "path": { "type": "string" },
+                "visible": { "type": "boolean" },
+                "files": { "type": "integer", "minimum": 0 },
+                "size_bytes": { "type": "integer", "minimum": 0 },
+                "whiteouts": { "type": "integer", "minimum": 0 }
+            }
+        }
+    });
+    let more_properties = json!({
+        "metadata_only": { "const": true, "description": "--metadata-only: no file was opened, so lines are 0 and contents went unsniffed." },
+        "profile": profile,
+        "overlay_layers": overlay_layers,
+        "mounts": mounts,
+        "groups": {
+            "type": "array",
+            "description": "Files per --group-by group, in --sort order.",
+            "items": {
+                "type": "object",
+                "required": ["group", "files", "size_bytes", "lines"],
+                "properties": {
+                    "group": { "type": "string", "description": "The part of the path a --group-by glob matched, or (other)." },
+                    "files": { "type": "integer", "minimum": 0 },
+                    "size_bytes": { "type": "integer", "minimum": 0 },
+                    "lines": { "type": "integer", "minimum": 0 }
+                }
+            }
+        },
+        "skipped_mounts": {
+            "type": "array",
+            "description": "Virtual filesystems (proc, sysfs, cgroups, ...) mounted below the root, which the scan skipped; absent when there are none or with --virtual-fs.",
+            "items": {
+                "type": "object",
+                "required": ["path", "fstype"],
+                "properties": {
+                    "path": { "type": "string" },
+                    "fstype": { "type": "string" }
+                }
+            }
+        },
+        "submodules": submodules,
+        "git": git,
+        "junk": junk,
+        "gitignore_suggestions": {
+            "type": "array",
+            "description": "With --no-ignore in a git repository: large build-output and cache directories that the root .gitignore doesn't cover and git doesn't track, largest first.",
+            "items": {
+                "type": "object",
+                "required": ["line", "files", "size_bytes"],
+                "properties": {
+                    "line": { "type": "string", "description": "Suggested .gitignore line, e.g. /target/." },
+                    "files": { "type": "integer", "minimum": 0 },
+                    "size_bytes": { "type": "integer", "minimum": 0 }
+                }
+            }
+        },
+        "dotfiles": { "type": "array", "items": dir, "description": "--top outermost hidden files and directories (--dotfiles), largest first; size_bytes and files cover everything below a hidden directory." },
+        "cold_dirs": { "type": "array", "items": dir, "description": "--top directories with the most bytes in files not read within --cold, most first; size_bytes, files and lines cover only those files directly inside each." },
+        "stale_dirs": { "type": "array", "items": dir, "description": "--top outermost directories with no file modified within --stale, largest first; size_bytes, files and lines cover everything below each." },
+        "docs": docs,
+        "licenses": licenses,
+        "comment_density": {
+            "type": "array",
+            "description": "Comment density per language (--comments), densest first.",
+            "items": {
+                "type": "object",
+                "required": ["language", "comment_lines", "lines", "percent"],
+                "properties": {
+                    "language": { "type": "string" },
+                    "comment_lines": { "type": "integer", "minimum": 0 },
+                    "lines": { "type": "integer", "minimum": 0 },
+                    "percent": { "type": "number", "minimum": 0, "maximum": 100 }
+                }
+            }
+        },
+        "least_commented_files": {
+            "type": "array",
+            "description": "--top files of 200+ lines with the lowest comment density (--comments).",
+            "items": {
+                "type": "object",
+                "required": ["path", "comment_lines", "lines", "percent"],
+                "properties": {
+                    "path": { "type": "string" },
+                    "comment_lines": { "type": "integer", "minimum": 0 },
+                    "lines": { "type": "integer", "minimum": 0 },
+                    "percent": { "type": "number", "minimum": 0, "maximum": 100 }
+                }
+            }
+        },
+        "case_collisions": {
+            "type": "array",
+            "description": "Groups of paths relative to the root that differ only by case (--case-collisions); a colliding directory hides collisions below it.",
+            "items": { "type": "array", "minItems": 2, "items": { "type": "string" } }
+        },
+        "budget_violations": {
+            "type": "array",
+            "description": "Exceeded budgets (--budgets or tengok-budgets.toml); empty when all are met.",
+            "items": {
+                "type": "object",
+                "required": ["glob", "rule", "unit", "limit", "value"],
+                "properties": {
+                    "glob": { "type": "string" },
+                    "rule": { "type": "string" },
+                    "unit": { "enum": ["bytes", "lines", "files"] },
+                    "limit": { "type": "integer", "minimum": 0 },
+                    "value": { "type": "integer", "minimum": 0 },
+                    "path": { "type": "string", "description": "The offending file, for per-file (each) rules." }
+                }
+            }
+        },
+        "largest_dir_for": {
+            "type": "array",
+            "description": "Per --largest-dir-for extension, in the order given, the directory holding the most bytes of it, counting the files directly in each directory.",
+            "items": {
+                "type": "object",
+                "required": ["ext", "path", "files", "size_bytes"],
+                "properties": {
+                    "ext": { "type": "string" },
+                    "path": { "type": ["string", "null"], "description": "Null when no file has the extension." },
+                    "files": { "type": "integer", "minimum": 0 },
+                    "size_bytes": { "type": "integer", "minimum": 0 }
+                }
+            }
+        },
+        "growth": {
+            "type": "object",
+            "description": "--max-growth check against --baseline.",
+            "required": ["max_percent", "size_bytes_percent", "lines_percent", "exceeded"],
+            "properties": {
+                "max_percent": { "type": "number", "minimum": 0 },
+                "size_bytes_percent": { "type": ["number", "null"], "description": "Null when the baseline total was zero." },
+                "lines_percent": { "type": ["number", "null"] },
+                "exceeded": { "type": "boolean" }
+            }
+        }
+    });
+
+    if let (Value::Object(properties), Value::Object(more)) = (&mut properties, more_properties) {
+        properties.extend(more);
+    }
+
+    let schema: Value = json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": format!("https://github.com/skolhustick/tengok/schema/v{}.json", SCHEMA_VERSION),
+        "title": "tengok --json output",
+        "type": "object",
+        "required": ["schema_version", "tengok_version", "root", "summary"],
+        "properties": properties
+    });
+
+    serde_json::to_string_pretty(&schema).expect("schema is valid JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_pins_current_version() {
+        let schema: Value = serde_json::from_str(&schema()).unwrap();
+        assert_eq!(
+            schema["properties"]["schema_version"]["const"],
+            SCHEMA_VERSION
+        );
+    }
+
+    #[test]
+    fn tree_nests_directories_with_cumulative_totals() {
+        let mut fs = crate::vfs::MemFs::default();
+        fs.insert("r/README.md", "a\n");
+        fs.insert("r/src/lib.rs", "a\nb\n");
+        fs.insert("r/src/deep/x/y.rs", "0123456789\n");
+        let config = Config::from_args(["r", "--json-tree"].map(String::from)).unwrap();
+        let summary = crate::scan::scan_fs(&fs, &config).unwrap();
+        let tree = serde_json::to_value(tree(&config, &summary)).unwrap();
+        assert_eq!(
+            (&tree["path"], &tree["files"], &tree["own_files"]),
+            (&json!("."), &json!(3), &json!(1))
+        );
+        let src = &tree["children"][0];
+        assert_eq!(
+            (&src["name"], &src["files"], &src["lines"]),
+            (&json!("src"), &json!(2), &json!(3))
+        );
+        let deep = &src["children"][0];
+        assert_eq!(
+            (&deep["path"], &deep["own_files"], &deep["size_bytes"]),
+            (&json!("src/deep"), &json!(0), &json!(11))
+        );
+        assert_eq!(deep["children"][0]["path"], "src/deep/x");
+        assert!(deep["children"][0].get("children").is_none());
+    }
+}
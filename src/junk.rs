@@ -0,0 +1,66 @@
+//! `--junk`: files operating systems leave behind in every directory they
+//! touch: Finder's `.DS_Store` and AppleDouble `._*` files on macOS,
+//! thumbnail caches and `desktop.ini` on Windows. None of them belong in a
+//! repository, and `--delete-junk` removes them (or moves them to the trash
+//! with `--trash`).
+
+use crate::clean;
+use crate::config::Config;
+use crate::format::{display_relative_path, format_num};
+use crate::scan::Summary;
+
+/// What kind of junk the file called `name` is, if any.
+pub fn kind(name: &str) -> Option<&'static str> {
+    Some(match name {
+        ".DS_Store" => ".DS_Store",
+        "Thumbs.db" | "ehthumbs.db" | "ehthumbs_vista.db" => "Thumbs.db",
+        "desktop.ini" | "Desktop.ini" => "desktop.ini",
+        _ if name.starts_with("._") => "AppleDouble (._*)",
+        _ => return None,
+    })
+}
+
+/// `--delete-junk`: remove every junk file in `summary`, noting each on
+/// stderr. A file that can't be removed is reported and skipped.
+pub fn delete(config: &Config, summary: &Summary) {
+    let (mut files, mut size) = (0, 0);
+    for file in &summary.files {
+        if file
+            .path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(kind)
+            .is_none()
+        {
+            continue;
+        }
+        let shown = display_relative_path(&file.path, &config.root);
+        match clean::remove(config, &file.path, false) {
+            Ok(()) => {
+                eprintln!("removed {}", shown);
+                files += 1;
+                size += file.size;
+            }
+            Err(err) => eprintln!("could not remove {}: {}", shown, err),
+        }
+    }
+    eprintln!(
+        "Removed {} junk files ({})",
+        format_num(files),
+        config.size(size)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_junk_names() {
+        assert_eq!(kind(".DS_Store"), Some(".DS_Store"));
+        assert_eq!(kind("._main.rs"), Some("AppleDouble (._*)"));
+        assert_eq!(kind("Thumbs.db"), Some("Thumbs.db"));
+        assert_eq!(kind("thumbs.rs"), None);
+        assert_eq!(kind(".gitignore"), None);
+    }
+}
@@ -0,0 +1,363 @@
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::scan;
+use crate::vfs::FileSystem;
+
+/// Bucket for files no rule recognises.
+pub const UNKNOWN: &str = "unknown";
+
+/// Bytes read from each end of a file when looking for a shebang or
+/// modeline. Vim modelines usually sit in the last few lines.
+const PEEK_BYTES: u64 = 1024;
+const MODELINE_LINES: usize = 5;
+
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "Rust"),
+    ("go", "Go"),
+    ("c", "C"),
+    ("h", "C"),
+    ("cc", "C++"),
+    ("cpp", "C++"),
+    ("cxx", "C++"),
+    ("hh", "C++"),
+    ("hpp", "C++"),
+    ("cs", "C#"),
+    ("java", "Java"),
+    ("kt", "Kotlin"),
+    ("kts", "Kotlin"),
+    ("scala", "Scala"),
+    ("swift", "Swift"),
+    ("m", "Objective-C"),
+    ("mm", "Objective-C++"),
+    ("py", "Python"),
+    ("pyi", "Python"),
+    ("rb", "Ruby"),
+    ("pl", "Perl"),
+    ("pm", "Perl"),
+    ("php", "PHP"),
+    ("lua", "Lua"),
+    ("js", "JavaScript"),
+    ("mjs", "JavaScript"),
+    ("cjs", "JavaScript"),
+    ("jsx", "JavaScript"),
+    ("ts", "TypeScript"),
+    ("mts", "TypeScript"),
+    ("cts", "TypeScript"),
+    ("tsx", "TypeScript"),
+    ("vue", "Vue"),
+    ("svelte", "Svelte"),
+    ("html", "HTML"),
+    ("htm", "HTML"),
+    ("css", "CSS"),
+    ("scss", "SCSS"),
+    ("sass", "Sass"),
+    ("less", "Less"),
+    ("sh", "Shell"),
+    ("bash", "Shell"),
+    ("zsh", "Shell"),
+    ("fish", "Fish"),
+    ("ps1", "PowerShell"),
+    ("bat", "Batch"),
+    ("cmd", "Batch"),
+    ("sql", "SQL"),
+    ("r", "R"),
+    ("jl", "Julia"),
+    ("hs", "Haskell"),
+    ("ml", "OCaml"),
+    ("mli", "OCaml"),
+    ("ex", "Elixir"),
+    ("exs", "Elixir"),
+    ("erl", "Erlang"),
+    ("clj", "Clojure"),
+    ("dart", "Dart"),
+    ("zig", "Zig"),
+    ("nim", "Nim"),
+    ("tf", "HCL"),
+    ("hcl", "HCL"),
+    ("nix", "Nix"),
+    ("proto", "Protocol Buffers"),
+    ("md", "Markdown"),
+    ("markdown", "Markdown"),
+    ("rst", "reStructuredText"),
+    ("tex", "TeX"),
+    ("json", "JSON"),
+    ("yaml", "YAML"),
+    ("yml", "YAML"),
+    ("toml", "TOML"),
+    ("xml", "XML"),
+    ("ini", "INI"),
+    ("mk", "Makefile"),
+    ("cmake", "CMake"),
+    ("dockerfile", "Dockerfile"),
+    ("txt", "Text"),
+];
+
+/// Conventional file names that carry no (useful) extension.
+const FILENAMES: &[(&str, &str)] = &[
+    ("Makefile", "Makefile"),
+    ("makefile", "Makefile"),
+    ("GNUmakefile", "Makefile"),
+    ("Dockerfile", "Dockerfile"),
+    ("Containerfile", "Dockerfile"),
+    ("Jenkinsfile", "Groovy"),
+    ("Vagrantfile", "Ruby"),
+    ("Rakefile", "Ruby"),
+    ("Gemfile", "Ruby"),
+    ("Podfile", "Ruby"),
+    ("Brewfile", "Ruby"),
+    ("Justfile", "Just"),
+    ("justfile", "Just"),
+    ("CMakeLists.txt", "CMake"),
+    ("BUILD", "Starlark"),
+    ("BUILD.bazel", "Starlark"),
+    ("WORKSPACE", "Starlark"),
+    ("Tiltfile", "Starlark"),
+    ("PKGBUILD", "Shell"),
+    (".bashrc", "Shell"),
+    (".bash_profile", "Shell"),
+    (".profile", "Shell"),
+    (".zshrc", "Shell"),
+    (".vimrc", "Vim Script"),
+];
+
+/// Interpreter names (from shebangs) and editor mode/filetype names (from
+/// modelines), lowercased.
+const ALIASES: &[(&str, &str)] = &[
+    ("sh", "Shell"),
+    ("bash", "Shell"),
+    ("dash", "Shell"),
+    ("ksh", "Shell"),
+    ("zsh", "Shell"),
+    ("shell-script", "Shell"),
+    ("fish", "Fish"),
+    ("python", "Python"),
+    ("pypy", "Python"),
+    ("ruby", "Ruby"),
+    ("perl", "Perl"),
+    ("php", "PHP"),
+    ("lua", "Lua"),
+    ("node", "JavaScript"),
+    ("nodejs", "JavaScript"),
+    ("deno", "TypeScript"),
+    ("bun", "JavaScript"),
+    ("javascript", "JavaScript"),
+    ("js", "JavaScript"),
+    ("typescript", "TypeScript"),
+    ("ts-node", "TypeScript"),
+    ("tsx", "TypeScript"),
+    ("rust", "Rust"),
+    ("go", "Go"),
+    ("c", "C"),
+    ("cpp", "C++"),
+    ("c++", "C++"),
+    ("java", "Java"),
+    ("groovy", "Groovy"),
+    ("tcl", "Tcl"),
+    ("tclsh", "Tcl"),
+    ("wish", "Tcl"),
+    ("awk", "Awk"),
+    ("gawk", "Awk"),
+    ("rscript", "R"),
+    ("r", "R"),
+    ("julia", "Julia"),
+    ("elixir", "Elixir"),
+    ("escript", "Erlang"),
+    ("runhaskell", "Haskell"),
+    ("haskell", "Haskell"),
+    ("make", "Makefile"),
+    ("makefile", "Makefile"),
+    ("dockerfile", "Dockerfile"),
+    ("yaml", "YAML"),
+    ("json", "JSON"),
+    ("toml", "TOML"),
+    ("markdown", "Markdown"),
+    ("vim", "Vim Script"),
+    ("pwsh", "PowerShell"),
+    ("powershell", "PowerShell"),
+];
+
+/// Language of `path`: a conventional file name or known extension first,
+/// then, only when those don't match, a shebang or emacs/vim modeline read
+/// from the file itself.
+pub fn detect(fs: &dyn FileSystem, path: &Path) -> &'static str {
+    from_name(path)
+        .or_else(|| {
+            peek(fs, path)
+                .ok()
+                .and_then(|(head, tail)| from_contents(&head, &tail))
+        })
+        .unwrap_or(UNKNOWN)
+}
+
+/// Language of `path` from its file name or extension alone.
+pub fn from_name(path: &Path) -> Option<&'static str> {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .and_then(|name| lookup(FILENAMES, name))
+        .or_else(|| scan::last_extension(path).and_then(|ext| lookup(EXTENSIONS, &ext)))
+}
+
+fn lookup(table: &[(&str, &'static str)], key: &str) -> Option<&'static str> {
+    table.iter().find(|(k, _)| *k == key).map(|(_, lang)| *lang)
+}
+
+fn alias(name: &str) -> Option<&'static str> {
+    let name = name.to_ascii_lowercase();
+    // python3.12, ruby2.7, perl5 → python, ruby, perl
+    let base = name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    lookup(ALIASES, &name).or_else(|| lookup(ALIASES, base))
+}
+
+fn peek(fs: &dyn FileSystem, path: &Path) -> io::Result<(String, String)> {
+    let mut file = fs.open(path)?;
+    let mut head = Vec::new();
+    (&mut file).take(PEEK_BYTES).read_to_end(&mut head)?;
+    let mut tail = Vec::new();
+    let len = fs.metadata(path)?.len;
+    if len > PEEK_BYTES {
+        // Never re-read bytes already in `head`.
+        file.seek(SeekFrom::Start((len - PEEK_BYTES).max(PEEK_BYTES)))?;
+        file.read_to_end(&mut tail)?;
+    }
+    Ok((
+        String::from_utf8_lossy(&head).into_owned(),
+        String::from_utf8_lossy(&tail).into_owned(),
+    ))
+}
+
+/// Shebang first, then modelines in the first and last few lines.
+pub fn from_contents(head: &str, tail: &str) -> Option<&'static str> {
+    let mut lines = head.lines();
+    let first = lines.next()?;
+    if let Some(lang) = first.strip_prefix("#!").and_then(shebang) {
+        return Some(lang);
+    }
+    let head_lines = std::iter::once(first).chain(lines).take(MODELINE_LINES);
+    let tail_lines = tail.lines().rev().take(MODELINE_LINES);
+    head_lines.chain(tail_lines).find_map(modeline)
+}
+
+fn shebang(line: &str) -> Option<&'static str> {
+    alias(shebang_program(line)?)
+}
+
+/// The program a shebang runs, looking through `env`.
+fn shebang_program(line: &str) -> Option<&str> {
+    let mut words = line.split_whitespace();
+    let program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        // `#!/usr/bin/env -S deno run` and `#!/usr/bin/env python3`
+        return words.find(|w| !w.starts_with('-') && !w.contains('='));
+    }
+    Some(program)
+}
+
+/// `--scripts`: the interpreter named by the shebang of an extensionless
+/// file, without its version (`python3.12` → `python`).
+pub fn script_interpreter(fs: &dyn FileSystem, path: &Path) -> Option<String> {
+    if path.extension().is_some() {
+        return None;
+    }
+    let mut head = Vec::new();
+    fs.open(path)
+        .ok()?
+        .take(PEEK_BYTES)
+        .read_to_end(&mut head)
+        .ok()?;
+    let head = String::from_utf8_lossy(&head);
+    let program = head
+        .lines()
+        .next()?
+        .strip_prefix("#!")
+        .and_then(shebang_program)?;
+    let program = program
+        .to_ascii_lowercase()
+        .trim_end_matches(|c: char| c.is_ascii_digit() || c == '.')
+        .to_string();
+    (!program.is_empty()).then_some(program)
+}
+
+/// `-*- mode: python -*-`, `-*- python -*-`, `vim: set ft=python:` and
+/// `vim: filetype=python`.
+fn modeline(line: &str) -> Option<&'static str> {
+    if let Some(start) = line.find("-*-") {
+        let rest = &line[start + 3..];
+        let body = &rest[..rest.find("-*-")?];
+        if !body.contains(':') {
+            return alias(body.trim());
+        }
+        return body.split(';').find_map(|part| {
+            let (key, value) = part.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case("mode")
+                .then(|| alias(value.trim()))?
+        });
+    }
+    let start = ["vim:", "vi:", "ex:"]
+        .iter()
+        .find_map(|marker| line.find(marker).map(|i| i + marker.len()))?;
+    line[start..]
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .find_map(|opt| {
+            let value = opt
+                .strip_prefix("ft=")
+                .or_else(|| opt.strip_prefix("filetype="))
+                .or_else(|| opt.strip_prefix("syntax="))?;
+            alias(value)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_prefers_names_and_extensions() {
+        assert_eq!(detect(&crate::vfs::OsFs, Path::new("src/main.RS")), "Rust");
+        assert_eq!(
+            detect(&crate::vfs::OsFs, Path::new("ci/Jenkinsfile")),
+            "Groovy"
+        );
+        assert_eq!(
+            detect(&crate::vfs::OsFs, Path::new("Dockerfile")),
+            "Dockerfile"
+        );
+    }
+
+    #[test]
+    fn from_contents_reads_shebangs_and_modelines() {
+        assert_eq!(from_contents("#!/bin/bash\necho", ""), Some("Shell"));
+        assert_eq!(
+            from_contents("#!/usr/bin/env python3.12\n", ""),
+            Some("Python")
+        );
+        assert_eq!(
+            from_contents("#!/usr/bin/env -S deno run\n", ""),
+            Some("TypeScript")
+        );
+        assert_eq!(
+            from_contents("# -*- mode: ruby; coding: utf-8 -*-\n", ""),
+            Some("Ruby")
+        );
+        assert_eq!(from_contents(";; -*- lua -*-\n", ""), Some("Lua"));
+        assert_eq!(
+            from_contents("stuff\n", "more\n# vim: set ft=perl ts=4:\n"),
+            Some("Perl")
+        );
+        assert_eq!(from_contents("just text\n", ""), None);
+
+        let mut fs = crate::vfs::MemFs::default();
+        fs.insert("bin/deploy", "#!/usr/bin/env python3.12\n");
+        fs.insert("bin/build", "#!/bin/bash -e\n");
+        fs.insert("bin/tool.sh", "#!/bin/sh\n");
+        fs.insert("bin/data", "plain\n");
+        let interpreter = |p: &str| script_interpreter(&fs, Path::new(p));
+        assert_eq!(interpreter("bin/deploy").as_deref(), Some("python"));
+        assert_eq!(interpreter("bin/build").as_deref(), Some("bash"));
+        assert_eq!(interpreter("bin/tool.sh"), None);
+        assert_eq!(interpreter("bin/data"), None);
+    }
+}
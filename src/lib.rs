@@ -0,0 +1,74 @@
+//! The scanning core behind the `tengok` binary: configuration, the
+//! parallel walker and per-file measurements, and the report, JSON and
+//! list renderers. The CLI, the Python bindings and any other embedder
+//! all go through `config::Config` and `scan::scan_dir`.
+
+pub mod baseline;
+pub mod bench;
+pub mod budget;
+pub mod checkpoint;
+pub mod classes;
+pub mod clean;
+pub mod comments;
+pub mod config;
+pub mod diff;
+pub mod docs;
+pub mod dupes;
+#[cfg(not(target_os = "wasi"))]
+pub mod editor;
+#[cfg(not(target_os = "wasi"))]
+pub mod explore;
+#[cfg(feature = "parquet")]
+pub mod export;
+pub mod expr;
+pub mod ffi;
+pub mod format;
+pub mod git;
+pub mod gitignore;
+pub mod groups;
+pub mod hints;
+pub mod history;
+pub mod i18n;
+pub mod json;
+pub mod junk;
+pub mod lang;
+pub mod license;
+pub mod linguist;
+pub mod lists;
+pub mod logging;
+pub mod merge;
+pub mod metrics;
+pub mod mime;
+pub mod minified;
+pub mod mounts;
+pub mod names;
+pub mod only;
+pub mod plan;
+pub mod plugin;
+pub mod profile;
+pub mod progress;
+pub mod pruned;
+#[cfg(feature = "python")]
+mod python;
+pub mod report;
+pub mod reuse;
+pub mod scan;
+#[cfg(not(target_os = "wasi"))]
+pub mod schedule;
+pub mod secrets;
+pub mod serve;
+pub mod settings;
+pub mod sketch;
+pub mod snapshot;
+pub mod spill;
+pub mod store;
+pub mod submodules;
+pub mod theme;
+pub mod throttle;
+#[cfg(not(target_os = "wasi"))]
+pub mod update;
+pub mod vfs;
+#[cfg(not(target_os = "wasi"))]
+pub mod watch;
+#[cfg(not(target_os = "wasi"))]
+pub mod webhook;
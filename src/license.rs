@@ -0,0 +1,159 @@
+//! `--licenses`: LICENSE, COPYING and NOTICE files anywhere in the tree,
+//! vendored directories included, and which license each one is.
+//!
+//! A file is identified by an `SPDX-License-Identifier` line when it has
+//! one, and otherwise by phrases from the license's own text near the top.
+//! Only the common licenses are known; anything else is "unknown", and a
+//! NOTICE file without license text is "notice".
+
+use std::{io::Read, path::Path};
+
+use crate::vfs::FileSystem;
+
+/// How much of each file is matched against.
+const HEAD_BYTES: u64 = 8 * 1024;
+
+const STEMS: &[&str] = &[
+    "LICENSE",
+    "LICENCE",
+    "COPYING",
+    "COPYRIGHT",
+    "NOTICE",
+    "UNLICENSE",
+];
+/// Extensions a license file may have; `license.rs` is code.
+const TEXT_EXTS: &[&str] = &["TXT", "MD", "MARKDOWN", "RST", "HTML"];
+
+/// Phrases, all of which must appear in the lowercased, whitespace-folded
+/// text, in the order they're tried: more specific licenses first.
+const PHRASES: &[(&str, &[&str])] = &[
+    (
+        "AGPL-3.0",
+        &["gnu affero general public license", "version 3"],
+    ),
+    (
+        "LGPL-3.0",
+        &["gnu lesser general public license", "version 3"],
+    ),
+    (
+        "LGPL-2.1",
+        &["gnu lesser general public license", "version 2.1"],
+    ),
+    ("GPL-3.0", &["gnu general public license", "version 3"]),
+    ("GPL-2.0", &["gnu general public license", "version 2"]),
+    ("Apache-2.0", &["apache license", "version 2.0"]),
+    ("MPL-2.0", &["mozilla public license", "2.0"]),
+    (
+        "BSD-3-Clause",
+        &[
+            "redistribution and use in source and binary forms",
+            "neither the name",
+        ],
+    ),
+    (
+        "BSD-2-Clause",
+        &["redistribution and use in source and binary forms"],
+    ),
+    ("MIT", &["permission is hereby granted, free of charge"]),
+    (
+        "ISC",
+        &["permission to use, copy, modify, and/or distribute this software for any purpose"],
+    ),
+    ("BSL-1.0", &["boost software license"]),
+    (
+        "Unlicense",
+        &["free and unencumbered software released into the public domain"],
+    ),
+    ("CC0-1.0", &["cc0 1.0 universal"]),
+    (
+        "Zlib",
+        &[
+            "provided 'as-is'",
+            "altered source versions must be plainly marked",
+        ],
+    ),
+];
+
+/// Whether `name` is a license file: `LICENSE`, `COPYING.txt`,
+/// `LICENSE-MIT`, `notice.md` and the like.
+pub fn is_license_file(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    let stem = match upper.rsplit_once('.') {
+        Some((stem, ext)) if TEXT_EXTS.contains(&ext) => stem,
+        Some(_) => return false,
+        None => &upper,
+    };
+    STEMS.iter().any(|license| {
+        stem.strip_prefix(license)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(['-', '_']))
+    })
+}
+
+/// The license in the file at `path`, an SPDX identifier where known.
+pub fn identify(fs: &dyn FileSystem, path: &Path) -> String {
+    let mut head = Vec::new();
+    let read = fs
+        .open(path)
+        .and_then(|file| file.take(HEAD_BYTES).read_to_end(&mut head));
+    if read.is_err() {
+        return "unknown".to_string();
+    }
+    let notice = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.to_ascii_uppercase().starts_with("NOTICE"));
+    identify_text(&String::from_utf8_lossy(&head), notice)
+}
+
+fn identify_text(text: &str, notice: bool) -> String {
+    if let Some(id) = text.lines().find_map(|line| {
+        let (_, id) = line.split_once("SPDX-License-Identifier:")?;
+        Some(id.trim().trim_end_matches("*/").trim())
+    }) && !id.is_empty()
+    {
+        return id.to_string();
+    }
+    let folded = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    PHRASES
+        .iter()
+        .find(|(_, phrases)| phrases.iter().all(|p| folded.contains(p)))
+        .map(|(id, _)| id.to_string())
+        .unwrap_or_else(|| if notice { "notice" } else { "unknown" }.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_and_identifies_licenses() {
+        assert!(is_license_file("LICENSE"));
+        assert!(is_license_file("License-MIT.txt"));
+        assert!(is_license_file("COPYING"));
+        assert!(!is_license_file("licensed.rs"));
+        assert!(!is_license_file("license.rs"));
+        assert!(!is_license_file("README.md"));
+
+        let mit = "MIT License\n\nPermission is hereby granted, free of\n  charge, to any person";
+        assert_eq!(identify_text(mit, false), "MIT");
+        let apache = "                                 Apache License\n                           Version 2.0, January 2004";
+        assert_eq!(identify_text(apache, false), "Apache-2.0");
+        let gpl = "GNU GENERAL PUBLIC LICENSE\nVersion 3, 29 June 2007";
+        assert_eq!(identify_text(gpl, false), "GPL-3.0");
+        let bsd = "Redistribution and use in source and binary forms ... Neither the name";
+        assert_eq!(identify_text(bsd, false), "BSD-3-Clause");
+        assert_eq!(
+            identify_text("// SPDX-License-Identifier: MIT OR Apache-2.0\n", false),
+            "MIT OR Apache-2.0"
+        );
+        assert_eq!(
+            identify_text("This product includes software", true),
+            "notice"
+        );
+        assert_eq!(identify_text("All rights reserved.", false), "unknown");
+    }
+}
@@ -0,0 +1,186 @@
+use std::path::{Component, Path};
+
+use ignore::{
+    Match,
+    gitignore::{Gitignore, GitignoreBuilder},
+};
+
+use crate::vfs::FileSystem;
+
+/// Why a file is kept apart from hand-written code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Generated,
+    Vendored,
+}
+
+/// Directory names whose contents are third-party code.
+const VENDOR_DIRS: &[&str] = &[
+    "node_modules",
+    "vendor",
+    "vendors",
+    "third_party",
+    "third-party",
+    "thirdparty",
+    "bower_components",
+    "jspm_packages",
+    "Pods",
+    "Carthage",
+    ".yarn",
+];
+
+/// Lockfiles and other files tools write for you.
+const GENERATED_NAMES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "Gemfile.lock",
+    "Pipfile.lock",
+    "poetry.lock",
+    "uv.lock",
+    "composer.lock",
+    "go.sum",
+    "flake.lock",
+    "Package.resolved",
+];
+
+const GENERATED_SUFFIXES: &[&str] = &[
+    ".min.js",
+    ".min.css",
+    ".js.map",
+    ".css.map",
+    ".pb.go",
+    ".pb.cc",
+    ".pb.h",
+    "_pb2.py",
+    "_pb2_grpc.py",
+    ".g.dart",
+    ".freezed.dart",
+    ".Designer.cs",
+];
+
+/// `linguist-generated` / `linguist-vendored` overrides from the root
+/// `.gitattributes`, falling back to linguist-style path heuristics.
+#[derive(Debug)]
+pub struct Classifier {
+    generated: Gitignore,
+    vendored: Gitignore,
+}
+
+impl Classifier {
+    pub fn load(fs: &dyn FileSystem, root: &Path) -> Self {
+        let contents = fs
+            .read_to_string(&root.join(".gitattributes"))
+            .unwrap_or_default();
+        Self::from_gitattributes(root, &contents)
+    }
+
+    // Attribute patterns share gitignore's glob syntax, so each attribute
+    // becomes a gitignore matcher: set lines are plain patterns, unset ones
+    // are negated, and the last matching line wins just like in git.
+    fn from_gitattributes(root: &Path, contents: &str) -> Self {
+        let mut generated = GitignoreBuilder::new(root);
+        let mut vendored = GitignoreBuilder::new(root);
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let Some(pattern) = fields.next() else {
+                continue;
+            };
+            for attr in fields {
+                let (name, set) = match attr.split_once('=') {
+                    Some((name, value)) => (name, value != "false"),
+                    None => match attr.strip_prefix(['-', '!']) {
+                        Some(name) => (name, false),
+                        None => (attr, true),
+                    },
+                };
+                let builder = match name {
+                    "linguist-generated" => &mut generated,
+                    "linguist-vendored" => &mut vendored,
+                    _ => continue,
+                };
+                let glob = if set {
+                    pattern.to_string()
+                } else {
+                    format!("!{}", pattern)
+                };
+                // A malformed glob only loses that one override.
+                let _ = builder.add_line(None, &glob);
+            }
+        }
+        Self {
+            generated: generated.build().unwrap_or_else(|_| Gitignore::empty()),
+            vendored: vendored.build().unwrap_or_else(|_| Gitignore::empty()),
+        }
+    }
+
+    /// Classify `path`; explicit attributes beat the heuristics. Generated
+    /// wins over vendored, so a lockfile under `vendor/` counts as generated.
+    pub fn classify(&self, path: &Path) -> Option<Kind> {
+        let generated = match self.generated.matched(path, false) {
+            Match::Ignore(_) => true,
+            Match::Whitelist(_) => false,
+            Match::None => looks_generated(path),
+        };
+        if generated {
+            return Some(Kind::Generated);
+        }
+        let vendored = match self.vendored.matched(path, false) {
+            Match::Ignore(_) => true,
+            Match::Whitelist(_) => false,
+            Match::None => looks_vendored(path),
+        };
+        vendored.then_some(Kind::Vendored)
+    }
+}
+
+fn looks_generated(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    GENERATED_NAMES.contains(&name)
+        || GENERATED_SUFFIXES.iter().any(|s| name.ends_with(s))
+        || name.contains(".generated.")
+}
+
+fn looks_vendored(path: &Path) -> bool {
+    path.parent().is_some_and(|dir| {
+        dir.components().any(|c| match c {
+            Component::Normal(name) => name.to_str().is_some_and(|n| VENDOR_DIRS.contains(&n)),
+            _ => false,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gitattributes_override_heuristics() {
+        let root = Path::new("repo");
+        let classifier = Classifier::from_gitattributes(
+            root,
+            "# overrides\n\
+             api/*.rs linguist-generated\n\
+             Cargo.lock -linguist-generated\n\
+             vendor/** linguist-vendored=false\n\
+             extern/** linguist-vendored\n",
+        );
+        let kind = |p: &str| classifier.classify(&root.join(p));
+        assert_eq!(kind("api/types.rs"), Some(Kind::Generated));
+        assert_eq!(kind("Cargo.lock"), None);
+        assert_eq!(kind("web/package-lock.json"), Some(Kind::Generated));
+        assert_eq!(kind("vendor/lib.c"), None);
+        assert_eq!(kind("extern/lib.c"), Some(Kind::Vendored));
+        assert_eq!(kind("web/node_modules/x/index.js"), Some(Kind::Vendored));
+        assert_eq!(kind("src/main.rs"), None);
+    }
+}
@@ -0,0 +1,781 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::comments::{self, CommentStat};
+use crate::config::{Config, DEFAULT_TOP, Output, SortKey};
+use crate::docs::{self, DocStat};
+use crate::format::display_relative_path;
+use crate::mime::ExecKind;
+use crate::scan::{FileRecord, GroupStat, Summary};
+use crate::secrets::Reason;
+use crate::sketch::Sketch;
+use crate::vfs;
+
+/// One row of a list output, regardless of whether it is a file, a
+/// directory or an extension bucket.
+#[derive(Debug, Clone)]
+pub struct ListEntry {
+    pub name: String,
+    pub size: u64,
+    pub lines: u64,
+    pub count: u64,
+    pub mtime: Option<SystemTime>,
+}
+
+/// `--list` reads like `ls`, so it defaults to path order; everything else
+/// is about finding the big stuff.
+pub fn sort_key(config: &Config) -> SortKey {
+    config
+        .sort
+        .unwrap_or(if matches!(config.output, Output::List | Output::NdJson) {
+            SortKey::Name
+        } else {
+            SortKey::Size
+        })
+}
+
+/// Sort entries by `key`. Numeric keys and mtime list the biggest/newest
+/// first, names ascend; `reverse` flips either. Ties fall back to the name
+/// so the order never depends on walker scheduling.
+pub fn sort_entries(entries: &mut [ListEntry], key: SortKey, reverse: bool) {
+    entries.sort_by(|a, b| compare(a, b, key, reverse));
+}
+
+fn compare(a: &ListEntry, b: &ListEntry, key: SortKey, reverse: bool) -> Ordering {
+    let primary = match key {
+        SortKey::Size => b.size.cmp(&a.size),
+        SortKey::Lines => b.lines.cmp(&a.lines),
+        SortKey::Count => b.count.cmp(&a.count),
+        SortKey::Mtime => b.mtime.cmp(&a.mtime),
+        SortKey::Name => Ordering::Equal,
+    };
+    let ord = primary.then_with(|| a.name.cmp(&b.name));
+    if reverse { ord.reverse() } else { ord }
+}
+
+fn finish(mut entries: Vec<ListEntry>, config: &Config, limit: usize) -> Vec<ListEntry> {
+    sort_entries(&mut entries, sort_key(config), config.reverse);
+    entries.truncate(limit);
+    entries
+}
+
+pub fn top_files(config: &Config, summary: &Summary) -> Vec<ListEntry> {
+    finish(
+        file_entries(config, summary),
+        config,
+        config.top.unwrap_or(0),
+    )
+}
+
+/// The page of files for `--list` and `--ndjson` (`--top` caps them all,
+/// `--offset` and `--limit` pick the page), and the offset of the next page
+/// when there are more.
+pub fn file_page(config: &Config, summary: &Summary) -> (Vec<ListEntry>, Option<usize>) {
+    let mut entries = finish(
+        file_entries(config, summary),
+        config,
+        config.top.unwrap_or(usize::MAX),
+    );
+    let total = entries.len();
+    entries.drain(..config.offset.min(total));
+    entries.truncate(config.limit.unwrap_or(usize::MAX));
+    let end = config.offset + entries.len();
+    (entries, (end < total).then_some(end))
+}
+
+/// Smallest files first for `--tiny`, regardless of `--sort`.
+pub fn smallest_files(config: &Config, summary: &Summary) -> Vec<ListEntry> {
+    let mut entries = file_entries(config, summary);
+    entries.sort_by(smallest_first);
+    entries.truncate(config.top.unwrap_or(DEFAULT_TOP));
+    entries
+}
+
+/// Files with the most lines for `--top-lines`, regardless of `--sort`.
+pub fn top_lines(config: &Config, summary: &Summary) -> Vec<ListEntry> {
+    let mut entries = file_entries(config, summary);
+    entries.sort_by(most_lines_first);
+    entries.truncate(config.top_lines.unwrap_or(0));
+    entries
+}
+
+type EntryOrder<'a> = dyn Fn(&ListEntry, &ListEntry) -> Ordering + 'a;
+
+fn smallest_first(a: &ListEntry, b: &ListEntry) -> Ordering {
+    a.size.cmp(&b.size).then_with(|| a.name.cmp(&b.name))
+}
+
+fn most_lines_first(a: &ListEntry, b: &ListEntry) -> Ordering {
+    b.lines.cmp(&a.lines).then_with(|| a.name.cmp(&b.name))
+}
+
+/// `--memory-limit`: drops the records none of the per-file lists
+/// (`--top` files, `--top-lines`, `--tiny`) would show, so they come out
+/// the same as with every record kept.
+pub fn retain_listed(config: &Config, files: &mut Vec<FileRecord>) {
+    files.retain(|f| config.lines_in_range(f.lines));
+    let entries: Vec<ListEntry> = files.iter().map(|f| file_entry(config, f)).collect();
+    let mut keep = vec![false; files.len()];
+    let (key, reverse) = (sort_key(config), config.reverse);
+    let lists: [(Option<usize>, &EntryOrder<'_>); 3] = [
+        (config.top, &|a, b| compare(a, b, key, reverse)),
+        (config.top_lines, &most_lines_first),
+        (
+            config.tiny.map(|_| config.top.unwrap_or(DEFAULT_TOP)),
+            &smallest_first,
+        ),
+    ];
+    for (limit, order) in lists {
+        let Some(limit) = limit.filter(|limit| *limit > 0) else {
+            continue;
+        };
+        let mut indices: Vec<usize> = (0..entries.len()).collect();
+        if limit < indices.len() {
+            indices.select_nth_unstable_by(limit, |a, b| order(&entries[*a], &entries[*b]));
+            indices.truncate(limit);
+        }
+        for i in indices {
+            keep[i] = true;
+        }
+    }
+    let mut keep = keep.into_iter();
+    files.retain(|_| keep.next().unwrap_or(false));
+}
+
+/// Large files with the lowest comment density for `--comments`, with
+/// their comment lines: least commented first, longer files first on ties.
+pub fn least_commented(config: &Config, summary: &Summary) -> Vec<(ListEntry, u64)> {
+    let mut entries: Vec<(ListEntry, u64)> = summary
+        .files
+        .iter()
+        .filter(|f| f.lines >= comments::LARGE_FILE_LINES)
+        .filter_map(|f| {
+            let entry = ListEntry {
+                name: display_relative_path(&f.path, &config.root),
+                size: f.size,
+                lines: f.lines,
+                count: 1,
+                mtime: f.mtime,
+            };
+            Some((entry, f.comments?))
+        })
+        .collect();
+    entries.sort_by(|(a, a_comments), (b, b_comments)| {
+        // Cross-multiplied to compare densities without floats.
+        (a_comments * b.lines)
+            .cmp(&(b_comments * a.lines))
+            .then_with(|| b.lines.cmp(&a.lines))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    entries.truncate(config.top.unwrap_or(DEFAULT_TOP));
+    entries
+}
+
+/// Languages by comment density for `--comments`, densest first.
+pub fn comment_density(summary: &Summary) -> Vec<(&'static str, CommentStat)> {
+    let mut entries: Vec<(&'static str, CommentStat)> = summary
+        .lang_comments
+        .iter()
+        .map(|(lang, stat)| (*lang, *stat))
+        .collect();
+    entries.sort_by(|(a_lang, a), (b_lang, b)| {
+        (b.comments * a.lines)
+            .cmp(&(a.comments * b.lines))
+            .then_with(|| a_lang.cmp(b_lang))
+    });
+    entries
+}
+
+/// Files with the most `--count-pattern` matches, most first.
+pub fn top_matches(config: &Config, summary: &Summary) -> Vec<(String, u64)> {
+    let mut entries: Vec<(String, u64)> = summary
+        .files
+        .iter()
+        .filter(|f| f.matches > 0)
+        .map(|f| (display_relative_path(&f.path, &config.root), f.matches))
+        .collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    entries.truncate(config.top.unwrap_or(DEFAULT_TOP));
+    entries
+}
+
+/// File names found in more than one directory for `--dup-names`, most
+/// copies first, each with its paths in order.
+pub fn duplicate_names(config: &Config, summary: &Summary) -> Vec<(String, Vec<String>)> {
+    let mut entries: Vec<(String, Vec<String>)> = summary
+        .basenames
+        .iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .map(|(name, paths)| {
+            let paths = paths
+                .iter()
+                .map(|p| display_relative_path(p, &config.root))
+                .collect();
+            (name.clone(), paths)
+        })
+        .collect();
+    entries.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+    entries.truncate(config.top.unwrap_or(DEFAULT_TOP));
+    entries
+}
+
+/// `--licenses`: license files per license, most files first.
+pub fn licenses(config: &Config, summary: &Summary) -> Vec<(String, Vec<String>)> {
+    let mut entries: Vec<(String, Vec<String>)> = summary
+        .licenses
+        .iter()
+        .map(|(license, paths)| {
+            let paths = paths
+                .iter()
+                .map(|p| display_relative_path(p, &config.root))
+                .collect();
+            (license.clone(), paths)
+        })
+        .collect();
+    entries.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+    entries
+}
+
+/// `--docs`: top-level directories, undocumented ones first, then by how
+/// little documentation they have for their code.
+pub fn docs(summary: &Summary) -> Vec<(String, DocStat)> {
+    let mut entries: Vec<(String, DocStat)> = summary
+        .docs
+        .iter()
+        .map(|(dir, stat)| (dir.display().to_string(), *stat))
+        .collect();
+    entries.sort_by(|(a_dir, a), (b_dir, b)| {
+        (a.readmes > 0)
+            .cmp(&(b.readmes > 0))
+            .then_with(|| {
+                let ratio = |s: &DocStat| s.ratio().unwrap_or(f64::INFINITY);
+                ratio(a).total_cmp(&ratio(b))
+            })
+            .then_with(|| a_dir.cmp(b_dir))
+    });
+    entries
+}
+
+fn file_entries(config: &Config, summary: &Summary) -> Vec<ListEntry> {
+    summary
+        .files
+        .iter()
+        .filter(|f| config.lines_in_range(f.lines))
+        .map(|f| file_entry(config, f))
+        .collect()
+}
+
+fn file_entry(config: &Config, file: &FileRecord) -> ListEntry {
+    ListEntry {
+        name: display_relative_path(&file.path, &config.root),
+        size: file.size,
+        lines: file.lines,
+        count: 1,
+        mtime: file.mtime,
+    }
+}
+
+pub fn top_dirs(config: &Config, summary: &Summary) -> Vec<ListEntry> {
+    let entries = summary
+        .dirs
+        .iter()
+        .map(|(path, stat)| ListEntry {
+            name: display_relative_path(path, &config.root),
+            size: stat.size,
+            lines: stat.lines,
+            count: stat.files,
+            mtime: stat.newest,
+        })
+        .collect();
+    finish(entries, config, config.top.unwrap_or(0))
+}
+
+/// Per `--largest-dir-for` extension, in the order given, the directory
+/// holding the most bytes of it; ties go to the first path.
+pub fn largest_dirs_for<'a>(
+    config: &'a Config,
+    summary: &'a Summary,
+) -> Vec<(&'a str, Option<(&'a PathBuf, &'a GroupStat)>)> {
+    config
+        .largest_dir_for
+        .iter()
+        .map(|ext| {
+            let largest = summary.ext_dirs.get(ext).and_then(|dirs| {
+                dirs.iter().max_by(|(a_dir, a), (b_dir, b)| {
+                    a.size.cmp(&b.size).then_with(|| b_dir.cmp(a_dir))
+                })
+            });
+            (ext.as_str(), largest)
+        })
+        .collect()
+}
+
+/// `--junk` kinds, most bytes first.
+pub fn junk(summary: &Summary) -> Vec<(&'static str, &GroupStat)> {
+    let mut entries: Vec<(&'static str, &GroupStat)> = summary
+        .junk
+        .iter()
+        .map(|(kind, stat)| (*kind, stat))
+        .collect();
+    entries.sort_by(|a, b| b.1.size.cmp(&a.1.size).then_with(|| a.0.cmp(b.0)));
+    entries
+}
+
+/// `--dotfiles`: hidden files and directories, largest first.
+pub fn dotfiles(config: &Config, summary: &Summary) -> Vec<ListEntry> {
+    let mut entries: Vec<ListEntry> = summary
+        .dotfiles
+        .iter()
+        .map(|(path, stat)| ListEntry {
+            name: display_relative_path(path, &config.root),
+            size: stat.size,
+            lines: stat.lines,
+            count: stat.files,
+            mtime: stat.newest,
+        })
+        .collect();
+    entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    entries.truncate(config.top.unwrap_or(DEFAULT_TOP));
+    entries
+}
+
+/// The directories directly inside `dir` with everything below each,
+/// largest first, from the kept file records.
+pub fn subdirs(summary: &Summary, dir: &Path) -> Vec<(PathBuf, GroupStat)> {
+    let mut totals: HashMap<PathBuf, GroupStat> = HashMap::new();
+    for record in &summary.files {
+        let Ok(rest) = record.parent.strip_prefix(dir) else {
+            continue;
+        };
+        if let Some(child) = rest.components().next() {
+            totals.entry(dir.join(child)).or_default().add(record);
+        }
+    }
+    let mut dirs: Vec<(PathBuf, GroupStat)> = totals.into_iter().collect();
+    dirs.sort_by(|a, b| b.1.size.cmp(&a.1.size).then_with(|| a.0.cmp(&b.0)));
+    dirs
+}
+
+/// `--stale`: the outermost directories with no file modified within the
+/// window, with everything below each, largest first. The root counts as
+/// well when the whole tree is stale; files without an mtime never make a
+/// directory fresh.
+pub fn stale(config: &Config, summary: &Summary) -> Vec<(PathBuf, GroupStat)> {
+    let Some(window) = config.stale else {
+        return Vec::new();
+    };
+    let cutoff = SystemTime::now().checked_sub(window);
+    let mut totals: HashMap<&Path, GroupStat> = HashMap::new();
+    for record in &summary.files {
+        for dir in record.parent.ancestors() {
+            totals.entry(dir).or_default().add(record);
+            if dir == config.root {
+                break;
+            }
+        }
+    }
+    let is_stale = |stat: &GroupStat| stat.newest.is_none_or(|t| Some(t) < cutoff);
+    let mut entries: Vec<(PathBuf, GroupStat)> = totals
+        .iter()
+        .filter(|(dir, stat)| {
+            is_stale(stat)
+                && (**dir == config.root
+                    || dir
+                        .parent()
+                        .and_then(|p| totals.get(p))
+                        .is_none_or(|p| !is_stale(p)))
+        })
+        .map(|(dir, stat)| (dir.to_path_buf(), stat.clone()))
+        .collect();
+    entries.sort_by(|a, b| b.1.size.cmp(&a.1.size).then_with(|| a.0.cmp(&b.0)));
+    entries.truncate(config.top.unwrap_or(DEFAULT_TOP));
+    entries
+}
+
+/// `--cold`: files last read before the window, in total and per
+/// directory (the files directly inside), `--top` (default 10) directories
+/// with the most cold bytes. Files without an access time never count.
+pub fn cold(config: &Config, summary: &Summary) -> (GroupStat, Vec<(PathBuf, GroupStat)>) {
+    let mut total = GroupStat::default();
+    let Some(window) = config.cold else {
+        return (total, Vec::new());
+    };
+    let cutoff = SystemTime::now().checked_sub(window);
+    let mut dirs: HashMap<&Path, GroupStat> = HashMap::new();
+    for record in &summary.files {
+        if record.atime.is_some_and(|t| Some(t) < cutoff) {
+            total.add(record);
+            dirs.entry(&record.parent).or_default().add(record);
+        }
+    }
+    let mut dirs: Vec<(PathBuf, GroupStat)> = dirs
+        .into_iter()
+        .map(|(dir, stat)| (dir.to_path_buf(), stat))
+        .collect();
+    dirs.sort_by(|a, b| b.1.size.cmp(&a.1.size).then_with(|| a.0.cmp(&b.0)));
+    dirs.truncate(config.top.unwrap_or(DEFAULT_TOP));
+    (total, dirs)
+}
+
+/// Directories with the most files directly inside, for `--top`,
+/// regardless of `--sort`.
+pub fn busiest_dirs(config: &Config, summary: &Summary) -> Vec<ListEntry> {
+    let mut entries: Vec<ListEntry> = summary
+        .dirs
+        .iter()
+        .map(|(path, stat)| ListEntry {
+            name: display_relative_path(path, &config.root),
+            size: stat.size,
+            lines: stat.lines,
+            count: stat.files,
+            mtime: stat.newest,
+        })
+        .collect();
+    entries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    entries.truncate(config.top.unwrap_or(0));
+    entries
+}
+
+pub fn extensions(config: &Config, summary: &Summary) -> Vec<ListEntry> {
+    let entries = summary
+        .exts
+        .iter()
+        .map(|(ext, stat)| ListEntry {
+            name: ext.clone(),
+            size: stat.size,
+            lines: stat.lines,
+            count: stat.files,
+            mtime: stat.newest,
+        })
+        .collect();
+    finish(entries, config, config.top.unwrap_or(usize::MAX))
+}
+
+/// Mean and median lines per file of a `--by-ext` or `--by-lang` entry.
+/// The median comes from the group's sketch, so it is within 1%.
+pub fn lines_per_file(entry: &ListEntry, sketch: Option<&Sketch>) -> (f64, f64) {
+    let mean = if entry.count == 0 {
+        0.0
+    } else {
+        entry.lines as f64 / entry.count as f64
+    };
+    (mean, sketch.and_then(|s| s.quantile(0.5)).unwrap_or(0.0))
+}
+
+pub fn languages(config: &Config, summary: &Summary) -> Vec<ListEntry> {
+    let entries = summary
+        .langs
+        .iter()
+        .map(|(lang, stat)| ListEntry {
+            name: lang.to_string(),
+            size: stat.size,
+            lines: stat.lines,
+            count: stat.files,
+            mtime: stat.newest,
+        })
+        .collect();
+    finish(entries, config, config.top.unwrap_or(usize::MAX))
+}
+
+/// `--tests`: one code language's files, and the tests among them.
+#[derive(Debug, Clone)]
+pub struct TestSplit {
+    pub lang: &'static str,
+    pub code: GroupStat,
+    pub tests: GroupStat,
+}
+
+/// Every code language, with the most lines first. Languages without
+/// tests are listed too: no tests is the finding.
+pub fn test_split(summary: &Summary) -> Vec<TestSplit> {
+    let mut splits: Vec<TestSplit> = summary
+        .langs
+        .iter()
+        .filter(|(lang, _)| docs::is_code(lang))
+        .map(|(lang, stat)| TestSplit {
+            lang,
+            code: stat.clone(),
+            tests: summary.lang_tests.get(lang).cloned().unwrap_or_default(),
+        })
+        .collect();
+    splits.sort_by(|a, b| b.code.lines.cmp(&a.code.lines).then(a.lang.cmp(b.lang)));
+    splits
+}
+
+/// `--audit`: files with an executable bit that aren't shebang scripts,
+/// binaries first, then by path.
+pub fn unexpected_executables(config: &Config, summary: &Summary) -> Vec<(ListEntry, ExecKind)> {
+    let mut entries: Vec<(ListEntry, ExecKind)> = summary
+        .files
+        .iter()
+        .filter_map(|f| {
+            let kind = f.executable.filter(|k| *k != ExecKind::Script)?;
+            let entry = ListEntry {
+                name: display_relative_path(&f.path, &config.root),
+                size: f.size,
+                lines: f.lines,
+                count: 1,
+                mtime: f.mtime,
+            };
+            Some((entry, kind))
+        })
+        .collect();
+    entries.sort_by(|(a, a_kind), (b, b_kind)| {
+        (*a_kind != ExecKind::Binary)
+            .cmp(&(*b_kind != ExecKind::Binary))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    entries
+}
+
+/// `--secrets`: files that look like credentials, by path.
+pub fn secrets(config: &Config, summary: &Summary) -> Vec<(ListEntry, Reason)> {
+    let mut entries: Vec<(ListEntry, Reason)> = summary
+        .files
+        .iter()
+        .filter_map(|f| {
+            let entry = ListEntry {
+                name: display_relative_path(&f.path, &config.root),
+                size: f.size,
+                lines: f.lines,
+                count: 1,
+                mtime: f.mtime,
+            };
+            Some((entry, f.secret?))
+        })
+        .collect();
+    entries.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+    entries
+}
+
+/// `--scripts` interpreters, most scripts first.
+pub fn interpreters(summary: &Summary) -> Vec<ListEntry> {
+    let mut entries: Vec<ListEntry> = summary
+        .interpreters
+        .iter()
+        .map(|(name, stat)| ListEntry {
+            name: name.clone(),
+            size: stat.size,
+            lines: stat.lines,
+            count: stat.files,
+            mtime: stat.newest,
+        })
+        .collect();
+    entries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    entries
+}
+
+/// `--by-owner`: one entry per uid, named by login name or, without a
+/// password entry, by the uid itself.
+pub fn owners(config: &Config, summary: &Summary) -> Vec<(ListEntry, u32)> {
+    let mut entries: Vec<(ListEntry, u32)> = summary
+        .owners
+        .iter()
+        .map(|(&uid, stat)| {
+            let entry = ListEntry {
+                name: vfs::user_name(uid).unwrap_or_else(|| uid.to_string()),
+                size: stat.size,
+                lines: stat.lines,
+                count: stat.files,
+                mtime: stat.newest,
+            };
+            (entry, uid)
+        })
+        .collect();
+    let key = sort_key(config);
+    entries.sort_by(|(a, _), (b, _)| compare(a, b, key, config.reverse));
+    entries
+}
+
+/// `--by-mount`: one entry per device, named by its mount point or, with
+/// none in view, by its number; with the device and the filesystem type.
+pub fn mounts(config: &Config, summary: &Summary) -> Vec<(ListEntry, u64, Option<String>)> {
+    let mut entries: Vec<(ListEntry, u64, Option<String>)> = summary
+        .devices
+        .iter()
+        .map(|(&dev, stat)| {
+            let mount = summary.mount_points.iter().find(|(d, _, _)| *d == dev);
+            let entry = ListEntry {
+                name: mount.map_or_else(
+                    || format!("device {}", dev),
+                    |(_, point, _)| point.display().to_string(),
+                ),
+                size: stat.size,
+                lines: stat.lines,
+                count: stat.files,
+                mtime: stat.newest,
+            };
+            let fstype = mount
+                .map(|(_, _, fstype)| fstype.clone())
+                .filter(|t| !t.is_empty());
+            (entry, dev, fstype)
+        })
+        .collect();
+    let key = sort_key(config);
+    entries.sort_by(|(a, ..), (b, ..)| compare(a, b, key, config.reverse));
+    entries
+}
+
+pub fn classes(config: &Config, summary: &Summary) -> Vec<ListEntry> {
+    let entries = summary
+        .classes
+        .iter()
+        .map(|(class, stat)| ListEntry {
+            name: class.clone(),
+            size: stat.size,
+            lines: stat.lines,
+            count: stat.files,
+            mtime: stat.newest,
+        })
+        .collect();
+    finish(entries, config, usize::MAX)
+}
+
+pub fn groups(config: &Config, summary: &Summary) -> Vec<ListEntry> {
+    let entries = summary
+        .groups
+        .iter()
+        .map(|(group, stat)| ListEntry {
+            name: group.clone(),
+            size: stat.size,
+            lines: stat.lines,
+            count: stat.files,
+            mtime: stat.newest,
+        })
+        .collect();
+    finish(entries, config, usize::MAX)
+}
+
+pub fn content_types(config: &Config, summary: &Summary) -> Vec<ListEntry> {
+    let entries = summary
+        .mimes
+        .iter()
+        .map(|(category, stat)| ListEntry {
+            name: category.to_string(),
+            size: stat.size,
+            lines: stat.lines,
+            count: stat.files,
+            mtime: stat.newest,
+        })
+        .collect();
+    finish(entries, config, usize::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn entry(name: &str, size: u64, lines: u64, secs: u64) -> ListEntry {
+        ListEntry {
+            name: name.to_string(),
+            size,
+            lines,
+            count: 1,
+            mtime: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+        }
+    }
+
+    fn names(entries: &[ListEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn sort_entries_orders_numeric_keys_descending_with_name_tiebreak() {
+        let mut entries = vec![
+            entry("b.rs", 10, 1, 3),
+            entry("c.rs", 30, 2, 1),
+            entry("a.rs", 10, 3, 2),
+        ];
+        sort_entries(&mut entries, SortKey::Size, false);
+        assert_eq!(names(&entries), ["c.rs", "a.rs", "b.rs"]);
+
+        sort_entries(&mut entries, SortKey::Mtime, false);
+        assert_eq!(names(&entries), ["b.rs", "a.rs", "c.rs"]);
+    }
+
+    #[test]
+    fn sort_entries_reverse_flips_name_order() {
+        let mut entries = vec![
+            entry("b", 1, 1, 1),
+            entry("a", 1, 1, 1),
+            entry("c", 1, 1, 1),
+        ];
+        sort_entries(&mut entries, SortKey::Name, true);
+        assert_eq!(names(&entries), ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn file_page_skips_then_limits() {
+        let mut fs = crate::vfs::MemFs::default();
+        for name in ["a", "b", "c", "d", "e"] {
+            fs.insert(format!("r/{}", name), "x\n");
+        }
+        let page = |args: &[&str]| {
+            let args = ["r", "--list"].iter().chain(args).map(|a| a.to_string());
+            let config = Config::from_args(args).unwrap();
+            let summary = crate::scan::scan_fs(&fs, &config).unwrap();
+            let (entries, next) = file_page(&config, &summary);
+            (names(&entries).join(","), next)
+        };
+        assert_eq!(page(&["--limit", "2"]), ("a,b".to_string(), Some(2)));
+        assert_eq!(
+            page(&["--offset", "2", "--limit", "2"]),
+            ("c,d".to_string(), Some(4))
+        );
+        assert_eq!(
+            page(&["--offset", "4", "--limit", "2"]),
+            ("e".to_string(), None)
+        );
+        assert_eq!(page(&["--offset", "9"]), (String::new(), None));
+        assert_eq!(
+            page(&["--top", "3", "--offset", "1"]),
+            ("b,c".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn line_filters_keep_files_in_range() {
+        let mut fs = crate::vfs::MemFs::default();
+        for (name, lines) in [("a", 1), ("b", 5), ("c", 10), ("d", 20)] {
+            fs.insert(format!("r/{}", name), "x\n".repeat(lines));
+        }
+        let listed = |args: &[&str]| {
+            let args = ["r", "--list"].iter().chain(args).map(|a| a.to_string());
+            let config = Config::from_args(args).unwrap();
+            let summary = crate::scan::scan_fs(&fs, &config).unwrap();
+            assert_eq!(summary.total_files, 4);
+            names(&file_page(&config, &summary).0).join(",")
+        };
+        assert_eq!(listed(&["--min-lines", "5"]), "b,c,d");
+        assert_eq!(listed(&["--max-lines", "10"]), "a,b,c");
+        assert_eq!(listed(&["--min-lines", "5", "--max-lines", "10"]), "b,c");
+        assert!(
+            Config::from_args(["--metadata-only", "--min-lines", "5"].map(String::from)).is_err()
+        );
+    }
+
+    #[test]
+    fn largest_dir_for_counts_files_directly_in_each_dir() {
+        let mut fs = crate::vfs::MemFs::default();
+        fs.insert("r/img/big/a.png", "x".repeat(50));
+        fs.insert("r/img/b.png", "x".repeat(30));
+        fs.insert("r/img/c.PNG", "x".repeat(30));
+        fs.insert("r/img/d.txt", "x".repeat(100));
+        let args = ["r", "--largest-dir-for", ".png,jpg"].map(String::from);
+        let config = Config::from_args(args).unwrap();
+        let summary = crate::scan::scan_fs(&fs, &config).unwrap();
+        let largest: Vec<_> = largest_dirs_for(&config, &summary)
+            .into_iter()
+            .map(|(ext, dir)| (ext, dir.map(|(path, stat)| (path.clone(), stat.size))))
+            .collect();
+        assert_eq!(
+            largest,
+            [("png", Some((PathBuf::from("r/img"), 60))), ("jpg", None)]
+        );
+    }
+}
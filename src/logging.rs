@@ -0,0 +1,35 @@
+//! `--log-level` and `--log-file`: diagnostics for debugging large scans.
+//! Off by default; files that can't be read, entries the filters skip and
+//! how long each phase took are logged through `tracing`, to stderr or,
+//! with `--log-file`, appended to a file.
+
+use std::{fs::OpenOptions, io, sync::Mutex};
+
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+
+use crate::config::Config;
+
+/// Install the process-wide logger for `config`. Only the binary calls
+/// this; embedders bring their own subscriber.
+pub fn init(config: &Config) -> Result<(), String> {
+    if config.log_level == LevelFilter::OFF {
+        return Ok(());
+    }
+    let writer = match &config.log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|err| format!("Cannot open log file {}: {}", path.display(), err))?;
+            BoxMakeWriter::new(Mutex::new(file))
+        }
+        None => BoxMakeWriter::new(io::stderr),
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(config.log_level)
+        .with_writer(writer)
+        .try_init()
+        .map_err(|err| err.to_string())
+}
@@ -1,550 +1,276 @@
-use std::{
-    collections::HashMap,
-    env,
-    fs::File,
-    io::{self, BufRead, BufReader, Write},
-    path::{Path, PathBuf},
-    process,
-    sync::Arc,
-    time::{Duration, Instant},
+use std::{env, io, process, sync::Arc, time::Instant};
+
+use tengok::config::{Config, Output, usage, version};
+#[cfg(feature = "parquet")]
+use tengok::export;
+use tengok::{
+    baseline, bench, budget, clean, diff, format, gitignore, history, json, junk, logging, merge,
+    metrics, mounts, names, plan, report, scan, serve, settings, snapshot,
 };
+#[cfg(not(target_os = "wasi"))]
+use tengok::{editor, explore, schedule, update, watch, webhook};
+use tracing::info;
+
+/// Exit statuses, listed in `usage()` and the README. Usage errors, and I/O
+/// errors that stop the scan, are 1.
+const EXIT_USAGE: i32 = 1;
+/// The scan finished, but some entries couldn't be read.
+const EXIT_SCAN_ERRORS: i32 = 2;
+/// A check (budgets, --max-growth, --strict) failed. Wins over
+/// `EXIT_SCAN_ERRORS`, since it's what CI gates on.
+const EXIT_CHECK_FAILED: i32 = 3;
+/// Ctrl-C, as shells report a process killed by SIGINT.
+#[cfg(not(target_os = "wasi"))]
+const EXIT_INTERRUPTED: i32 = 130;
 
-use crossbeam_channel::unbounded;
-use crossterm::terminal;
-use humansize::{DECIMAL, format_size};
-use ignore::{WalkBuilder, WalkState};
-use owo_colors::OwoColorize;
-use unicode_width::UnicodeWidthStr;
-
-#[derive(Debug, Clone)]
-struct FileStat {
-    path: PathBuf,
-    size: u64,
-    lines: u64,
-}
-
-#[derive(Debug, Default)]
-struct Summary {
-    total_files: u64,
-    total_size: u64,
-    total_lines: u64,
-    max_lines_file: Option<FileStat>,
-    largest_dir: Option<(PathBuf, u64)>, // (path, size)
-}
-
-const DEFAULT_MAX_LINE_BYTES: u64 = 5 * 1024 * 1024; // ~5MB
-const BINARY_EXTS: &[&str] = &[
-    "png", "jpg", "jpeg", "gif", "bmp", "webp", "ico", "svg", "tif", "tiff", "pdf", "zip", "gz",
-    "bz2", "xz", "7z", "tar", "rar", "mp4", "mov", "avi", "mkv", "mp3", "wav", "flac", "ogg",
-    "ttf", "otf", "woff", "woff2", "exe", "dll", "so", "dylib", "class", "jar", "bin",
-];
-
-struct Config {
-    root: PathBuf,
-    plain: bool,
-    skip_lines: bool,
-    force_lines: bool,
-    max_line_bytes: u64,
-}
-
-impl Config {
-    fn from_args() -> Result<Self, String> {
-        let mut args = env::args().skip(1).peekable();
-        let mut root: Option<PathBuf> = None;
-        let mut plain = false;
-        let mut skip_lines = false;
-        let mut force_lines = false;
-        let mut max_line_bytes = DEFAULT_MAX_LINE_BYTES;
-
-        while let Some(arg) = args.next() {
-            match arg.as_str() {
-                "--plain" | "--no-colors" => plain = true,
-                "--no-lines" => skip_lines = true,
-                "--force-lines" => {
-                    force_lines = true;
-                    skip_lines = false;
-                }
-                "--max-line-bytes" => {
-                    let value = args
-                        .next()
-                        .ok_or_else(|| "--max-line-bytes requires a numeric value".to_string())?;
-                    max_line_bytes = value
-                        .parse()
-                        .map_err(|_| "Unable to parse --max-line-bytes".to_string())?;
-                }
-                _ if arg.starts_with("--max-line-bytes=") => {
-                    let value = arg.split_once('=').unwrap().1;
-                    max_line_bytes = value
-                        .parse()
-                        .map_err(|_| "Unable to parse --max-line-bytes".to_string())?;
-                }
-                _ if arg.starts_with('-') => {
-                    return Err(format!("Unknown flag: {}", arg));
-                }
-                _ => {
-                    root = Some(PathBuf::from(arg));
-                }
+fn main() -> io::Result<()> {
+    #[cfg(not(target_os = "wasi"))]
+    on_interrupt();
+    let mut args = env::args().skip(1).peekable();
+    // Anywhere before `--`, so `tengok diff --help` works too.
+    for arg in env::args().skip(1).take_while(|arg| arg != "--") {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", usage());
+                return Ok(());
+            }
+            "-V" | "--version" => {
+                println!("{}", version());
+                return Ok(());
             }
+            _ => {}
         }
-
-        let root = root.unwrap_or_else(|| PathBuf::from("."));
-        Ok(Self {
-            root,
-            plain,
-            skip_lines,
-            force_lines,
-            max_line_bytes,
-        })
     }
-}
-
-fn usage() -> &'static str {
-    "Usage: tengok [OPTIONS] [PATH]
-
-Options:
-  --plain, --no-colors        Disable ANSI colors in the report
-  --no-lines                  Skip line counting entirely
-  --force-lines               Always count lines (even for large/binary files)
-  --max-line-bytes <N>        Only count lines for files up to N bytes (default ~5MB)
-"
-}
-
-#[derive(Debug)]
-struct FileRecord {
-    path: PathBuf,
-    parent: PathBuf,
-    size: u64,
-    lines: u64,
-}
-
-fn main() -> io::Result<()> {
-    let config = match Config::from_args() {
-        Ok(cfg) => Arc::new(cfg),
-        Err(err) => {
-            eprintln!("{}", err);
-            eprintln!("{}", usage());
-            process::exit(1);
+    match args.peek().map(String::as_str) {
+        Some("config") => {
+            args.next();
+            if args.peek().map(String::as_str) == Some("check") {
+                args.next();
+                let ok = settings::check(args).unwrap_or_else(|err| exit_usage(&err));
+                process::exit(if ok { 0 } else { EXIT_USAGE });
+            }
+            let config = parse_config(args);
+            settings::run(&config);
+            return Ok(());
         }
-    };
-
-    if !config.root.exists() {
-        eprintln!("Path does not exist: {}", config.root.display());
-        process::exit(1);
-    }
-
-    let summary = scan_dir(&config)?;
-    print_report(&config, &summary);
-
-    Ok(())
-}
-
-fn scan_dir(config: &Arc<Config>) -> io::Result<Summary> {
-    let root = config.root.clone();
-    let (tx, rx) = unbounded::<FileRecord>();
-
-    let walker = WalkBuilder::new(&root).git_ignore(true).build_parallel();
-
-    let config_for_threads = Arc::clone(config);
-    let root_for_threads = root.clone();
-
-    walker.run(|| {
-        let tx = tx.clone();
-        let config = Arc::clone(&config_for_threads);
-        let root = root_for_threads.clone();
-        let mut line_buf = Vec::with_capacity(64 * 1024);
-        Box::new(move |result| {
-            let dent = match result {
-                Ok(d) => d,
-                Err(_) => return WalkState::Continue,
-            };
-
-            if !dent.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
-                return WalkState::Continue;
+        Some("schema") => {
+            println!("{}", json::schema());
+            return Ok(());
+        }
+        Some("history") => {
+            args.next();
+            if args.peek().map(String::as_str) == Some("prune") {
+                args.next();
+                let (opts, rest) = history::split_args(args).unwrap_or_else(|err| exit_usage(&err));
+                if !opts.keep.is_set() {
+                    exit_usage("history prune needs --keep-last, --keep-daily or --keep-weekly");
+                }
+                let config = parse_config(rest);
+                return history::prune(&config, &opts);
             }
-
-            let path = dent.into_path();
-            let meta = match path.metadata() {
-                Ok(m) => m,
-                Err(_) => return WalkState::Continue,
-            };
-
-            let size = meta.len();
-            let lines = if should_count_lines(&path, size, &config) {
-                count_lines_fast(&path, &mut line_buf).unwrap_or(0)
+            let (opts, rest) = history::split_args(args).unwrap_or_else(|err| exit_usage(&err));
+            let config = parse_config(rest);
+            let summary = if opts.record {
+                Some(scan::scan_dir(&config)?)
             } else {
-                0
+                None
             };
-
-            let parent = path
-                .parent()
-                .map(|p| p.to_path_buf())
-                .unwrap_or_else(|| root.clone());
-
-            if tx
-                .send(FileRecord {
-                    path,
-                    parent,
-                    size,
-                    lines,
-                })
-                .is_err()
-            {
-                return WalkState::Quit;
+            return history::run(&config, &opts, summary.as_ref());
+        }
+        Some("diff") => {
+            args.next();
+            let (snapshots, rest) = diff::split_args(args).unwrap_or_else(|err| exit_usage(&err));
+            let config = parse_config(rest);
+            if let Err(err) = diff::run(&config, &snapshots) {
+                eprintln!("{}", err);
+                process::exit(EXIT_USAGE);
             }
-
-            WalkState::Continue
-        })
-    });
-
-    drop(tx);
-
-    let mut summary = Summary::default();
-    let mut dir_sizes: HashMap<PathBuf, u64> = HashMap::new();
-
-    let spinner_frames: &[char] = &['-', '\\', '|', '/'];
-    let mut spinner_idx: usize = 0;
-    let mut last_draw = Instant::now();
-
-    for record in rx {
-        summary.total_files += 1;
-        summary.total_size += record.size;
-        summary.total_lines += record.lines;
-
-        let current_max = summary
-            .max_lines_file
-            .as_ref()
-            .map(|f| f.lines)
-            .unwrap_or(0);
-
-        if record.lines > current_max {
-            summary.max_lines_file = Some(FileStat {
-                path: record.path.clone(),
-                size: record.size,
-                lines: record.lines,
-            });
+            return Ok(());
         }
-
-        *dir_sizes.entry(record.parent).or_insert(0) += record.size;
-
-        if !config.plain && last_draw.elapsed() >= Duration::from_millis(80) {
-            last_draw = Instant::now();
-            spinner_idx = (spinner_idx + 1) % spinner_frames.len();
-            let frame = spinner_frames[spinner_idx];
-            let path_str = display_relative_path(&record.path, &config.root);
-            let path_short = ellipsize_middle(&path_str, 40);
-            let files = format_num(summary.total_files);
-            let size = format_size(summary.total_size, DECIMAL);
-            let msg = format!(
-                "{} Scanning… {} files, {} ({})",
-                frame, files, size, path_short
-            );
-            let mut stderr = io::stderr();
-            let _ = write!(stderr, "\r{}", msg);
-            let _ = stderr.flush();
+        Some("merge") => {
+            args.next();
+            let (snapshots, rest) = merge::split_args(args).unwrap_or_else(|err| exit_usage(&err));
+            let config = parse_config(rest);
+            if let Err(err) = merge::run(&config, &snapshots) {
+                eprintln!("{}", err);
+                process::exit(EXIT_USAGE);
+            }
+            return Ok(());
         }
+        Some("snapshot") => {
+            args.next();
+            if let Err(err) = snapshot::run(args) {
+                eprintln!("{}", err);
+                process::exit(EXIT_USAGE);
+            }
+            return Ok(());
+        }
+        Some("bench") => {
+            args.next();
+            let (opts, rest) = bench::split_args(args).unwrap_or_else(|err| exit_usage(&err));
+            let config = parse_config(rest);
+            return bench::run(&config, &opts);
+        }
+        #[cfg(not(target_os = "wasi"))]
+        Some("self-update") => {
+            args.next();
+            let opts = update::parse_args(args).unwrap_or_else(|err| exit_usage(&err));
+            if let Err(err) = update::run(&opts) {
+                eprintln!("{}", err);
+                process::exit(EXIT_USAGE);
+            }
+            return Ok(());
+        }
+        #[cfg(not(target_os = "wasi"))]
+        Some("schedule") => {
+            args.next();
+            let (opts, rest) = schedule::split_args(args).unwrap_or_else(|err| exit_usage(&err));
+            let config = parse_config(rest);
+            if let Err(err) = schedule::run(&config, &opts) {
+                eprintln!("{}", err);
+                process::exit(EXIT_USAGE);
+            }
+            return Ok(());
+        }
+        Some("serve") => {
+            args.next();
+            let (opts, rest) = serve::split_args(args).unwrap_or_else(|err| exit_usage(&err));
+            let config = parse_config(rest);
+            return serve::run(&config, &opts);
+        }
+        _ => {}
     }
 
-    if !config.plain {
-        let mut stderr = io::stderr();
-        let _ = writeln!(stderr);
-    }
-
-    if let Some((dir, size)) = dir_sizes.into_iter().max_by_key(|(_, s)| *s) {
-        summary.largest_dir = Some((dir, size));
+    let config = parse_config(args);
+    if config.plans() {
+        return plan::run(&config);
     }
-
-    Ok(summary)
-}
-
-fn count_lines_fast(path: &Path, buf: &mut Vec<u8>) -> io::Result<u64> {
-    let file = File::open(path)?;
-    let mut reader = BufReader::with_capacity(64 * 1024, file);
-    let mut lines: u64 = 0;
-
-    loop {
-        buf.clear();
-        let bytes = reader.read_until(b'\n', buf)?;
-        if bytes == 0 {
-            break;
-        }
-        lines += 1;
+    #[cfg(not(target_os = "wasi"))]
+    if config.watch {
+        return watch::run(config);
     }
 
-    Ok(lines)
-}
-
-fn should_count_lines(path: &Path, size: u64, config: &Config) -> bool {
-    if config.skip_lines {
-        return false;
-    }
-    if config.force_lines {
-        return true;
+    let summary = scan::scan_dir(&config)?;
+    #[cfg(feature = "parquet")]
+    if let Some(path) = &config.parquet {
+        let start = Instant::now();
+        export::write_parquet(&config, &summary, path)?;
+        info!("parquet written in {:?}", start.elapsed());
     }
-    if config.max_line_bytes > 0 && size > config.max_line_bytes {
-        return false;
+    if let Some(path) = &config.snapshot {
+        snapshot::write(path, &json::to_value(&config, &summary))?;
     }
-    if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
-        let ext_lower = ext.to_ascii_lowercase();
-        if BINARY_EXTS.contains(&ext_lower.as_str()) {
-            return false;
-        }
+    #[cfg(not(target_os = "wasi"))]
+    if let Some(addr) = &config.statsd {
+        metrics::send_statsd(addr, &metrics::Sample::of(&config, &summary))?;
     }
-    true
-}
-
-fn print_report(config: &Config, summary: &Summary) {
-    let title = format!("Folder Summary: {}", config.root.display());
-    let size_human = format_size(summary.total_size, DECIMAL);
-    let files_value = format_num(summary.total_files);
-    let lines_value = format_num(summary.total_lines);
-    let files_value_with_unit = format!("{} Files", files_value);
-    let lines_value_with_unit = format!("{} Lines", lines_value);
-
-    let (largest_dir_str, largest_dir_size) = match &summary.largest_dir {
-        Some((path, size)) => (
-            display_relative_path(path, &config.root),
-            format_size(*size, DECIMAL),
-        ),
-        None => ("-".to_string(), "-".to_string()),
-    };
-
-    let (max_file_path_raw, max_file_lines, max_file_size) = match &summary.max_lines_file {
-        Some(f) => (
-            display_relative_path(&f.path, &config.root),
-            f.lines,
-            format_size(f.size, DECIMAL),
-        ),
-        None => ("-".to_string(), 0, "-".to_string()),
-    };
-
-    let largest_dir_val = if largest_dir_str == "-" {
-        "-".to_string()
-    } else {
-        format!("{} ({})", largest_dir_str, largest_dir_size)
-    };
-    let max_file_val = if max_file_path_raw == "-" {
-        "-".to_string()
-    } else {
-        format!(
-            "{} ({} lines, {})",
-            max_file_path_raw,
-            format_num(max_file_lines),
-            max_file_size
-        )
-    };
-
-    // Layout: label + spacing + value widths add up to inner width.
-    // The box grows to fit the widest value (typically the max-line file),
-    // but never exceeds the current terminal width; long values are then
-    // ellipsized in the middle to stay on a single row.
-    const LABEL_WIDTH: usize = 6;
-    const MIN_VALUE_WIDTH: usize = 24;
-    const MAX_VALUE_WIDTH: usize = 96;
-
-    let mut value_width = [
-        files_value_with_unit.as_str(),
-        size_human.as_str(),
-        lines_value_with_unit.as_str(),
-        largest_dir_val.as_str(),
-        max_file_val.as_str(),
-    ]
-    .into_iter()
-    .map(|s| UnicodeWidthStr::width(s))
-    .max()
-    .unwrap_or(0)
-    .clamp(MIN_VALUE_WIDTH, MAX_VALUE_WIDTH);
-
-    if let Ok((cols, _)) = terminal::size() {
-        let cols = cols as usize;
-        let max_inner = cols.saturating_sub(3); // borders + spaces
-        if max_inner > LABEL_WIDTH + 3 {
-            let max_value = max_inner.saturating_sub(LABEL_WIDTH + 3);
-            if max_value > 0 {
-                value_width = value_width.min(max_value);
+    let start = Instant::now();
+    match config.output {
+        _ if config.check => {}
+        Output::Report => {
+            report::print_report(&config, &summary);
+            report::print_lists(&config, &summary);
+            #[cfg(not(target_os = "wasi"))]
+            if config.explore {
+                explore::run(&config, &summary)?;
             }
         }
-    }
-
-    let inner_width = LABEL_WIDTH + 3 + value_width;
-
-    // Color helpers
-    let color_border = |s: &str| -> String {
-        if config.plain {
-            s.to_string()
-        } else {
-            format!("{}", s.bright_green())
+        Output::Json => json::print_json(&config, &summary)?,
+        Output::List => report::print_file_list(&config, &summary)?,
+        Output::NdJson => json::print_file_lines(&config, &summary)?,
+        Output::JsonTree => json::print_json_tree(&config, &summary)?,
+        Output::Folded => report::print_folded(&config, &summary)?,
+        Output::Oneline => report::print_oneline(&config, &summary),
+        Output::OpenMetrics => {
+            let sample = metrics::Sample::of(&config, &summary);
+            print!("{}", metrics::render_openmetrics(&[sample]));
         }
-    };
-    let color_label = |s: &str| -> String {
-        if config.plain {
-            s.to_string()
-        } else {
-            format!("{}", s.bright_magenta())
+    }
+    if config.human_stderr {
+        report::write_report(&mut io::stderr().lock(), &config, &summary)?;
+    }
+    info!("output written in {:?}", start.elapsed());
+    #[cfg(not(target_os = "wasi"))]
+    if config.open_max {
+        match &summary.max_lines_file {
+            Some(file) => editor::open(&file.path)?,
+            None => eprintln!("No file to open"),
         }
-    };
-    let color_value = |s: &str| -> String {
-        if config.plain {
-            s.to_string()
-        } else {
-            format!("{}", s.bright_green())
+    }
+    if config.apply {
+        let suggestions = gitignore::suggestions(&config, &summary);
+        if !suggestions.is_empty() {
+            gitignore::apply(&config.root, &suggestions)?;
         }
-    };
-
-    // Box borders
-    let horizontal_raw = "─".repeat(inner_width + 2);
-    let border = color_border(&horizontal_raw);
-    let top_left = color_border("┌");
-    let top_right = color_border("┐");
-    let bottom_left = color_border("└");
-    let bottom_right = color_border("┘");
-    let vert_symbol = color_border("│");
-    let divider = color_border("├");
-    let divider_right = color_border("┤");
-
-    // Utility to print a line with padding
-    let vert_left = vert_symbol.clone();
-    let vert_right = vert_symbol.clone();
-    let plain_mode = config.plain;
-    let print_line = move |plain: &str, colored: String| {
-        let visible = UnicodeWidthStr::width(plain);
-        let padding = inner_width.saturating_sub(visible);
-        let body = if plain_mode {
-            plain.to_string()
-        } else {
-            colored
-        };
-        println!(
-            "{} {}{} {}",
-            vert_left,
-            body,
-            " ".repeat(padding),
-            vert_right
-        );
-    };
-
-    let format_row = |label: &str, value: &str| -> (String, String) {
-        let label_truncated = truncate(label, LABEL_WIDTH);
-        let label_fmt = format!("{:<label_w$}", label_truncated, label_w = LABEL_WIDTH);
-        let value_truncated = ellipsize_middle(value, value_width);
-        let value_fmt = format!("{:>value_w$}", value_truncated, value_w = value_width);
-        (label_fmt, value_fmt)
-    };
-
-    let (files_label, files_value_fmt) = format_row("[F]", &files_value_with_unit);
-    let (size_label, size_value_fmt) = format_row("[B]", &size_human);
-    let (lines_label, lines_value_fmt) = format_row("[L]", &lines_value_with_unit);
-    let (largest_label, largest_dir_value_fmt) = format_row("[D↑]", &largest_dir_val);
-    let (max_lines_label, max_file_value_fmt) = format_row("[L↑]", &max_file_val);
-
-    println!("{}{}{}", top_left, border, top_right);
-
-    let title_plain = truncate(&title, inner_width);
-    let title_colored = color_value(&title_plain);
-    print_line(&title_plain, title_colored);
-
-    println!("{}{}{}", divider, border, divider_right);
-    let row_plain_and_colored = |label: &str, value: &str| {
-        let plain = format!("{}   {}", label, value);
-        let colored = format!("{}   {}", color_label(label), color_value(value));
-        print_line(&plain, colored);
-    };
-
-    row_plain_and_colored(&files_label, &files_value_fmt);
-    row_plain_and_colored(&size_label, &size_value_fmt);
-    row_plain_and_colored(&lines_label, &lines_value_fmt);
-    row_plain_and_colored(&largest_label, &largest_dir_value_fmt);
-    row_plain_and_colored(&max_lines_label, &max_file_value_fmt);
-
-    println!("{}{}{}", bottom_left, border, bottom_right);
-}
-
-fn display_relative_path(path: &Path, root: &Path) -> String {
-    path.strip_prefix(root)
-        .map(|p| {
-            if p.as_os_str().is_empty() {
-                ".".to_string()
-            } else {
-                p.display().to_string()
-            }
-        })
-        .unwrap_or_else(|_| path.display().to_string())
-}
+    }
+    if config.clean {
+        clean::run(&config, &summary)?;
+    }
+    if config.delete_junk {
+        junk::delete(&config, &summary);
+    }
 
-fn format_num(n: u64) -> String {
-    // basic thousand separator
-    let s = n.to_string();
-    let mut out = String::new();
-    let bytes = s.as_bytes();
-    let len = bytes.len();
-    for (i, ch) in bytes.iter().enumerate() {
-        out.push(*ch as char);
-        let left = len - i - 1;
-        if left > 0 && left % 3 == 0 {
-            out.push(',');
+    if !budget::violations(&config, &summary).is_empty()
+        || baseline::growth_check(&config, &summary).is_some_and(|g| g.exceeded)
+        || names::strict_failure(&config, &summary)
+    {
+        // A webhook that can't be reached mustn't hide the failed check.
+        #[cfg(not(target_os = "wasi"))]
+        if let Some(url) = &config.notify_webhook
+            && let Err(err) = webhook::notify(url, &config, &summary)
+        {
+            eprintln!("{}", err);
         }
+        process::exit(EXIT_CHECK_FAILED);
     }
-    out
+    if scan::error_count() > 0 {
+        process::exit(EXIT_SCAN_ERRORS);
+    }
+    Ok(())
 }
 
-// Truncate & add "…" if too long to fit in n chars
-fn truncate(s: &str, max: usize) -> String {
-    if s.chars().count() <= max {
-        return s.to_string();
+/// Exit with `EXIT_INTERRUPTED` on Ctrl-C instead of the platform's default
+/// (killed by a signal on Unix, `STATUS_CONTROL_C_EXIT` on Windows).
+#[cfg(not(target_os = "wasi"))]
+fn on_interrupt() {
+    use signal_hook::{consts::SIGINT, low_level};
+    // Safety: the handler only calls `_exit`, which is async-signal-safe.
+    let registered = unsafe { low_level::register(SIGINT, || low_level::exit(EXIT_INTERRUPTED)) };
+    if let Err(err) = registered {
+        tracing::debug!("cannot handle Ctrl-C: {}", err);
     }
-    let mut out = String::new();
-    for (i, ch) in s.chars().enumerate() {
-        if i >= max - 1 {
-            out.push('…');
-            break;
-        }
-        out.push(ch);
-    }
-    out
 }
 
-// Insert "…" in the middle to keep both ends visible within max chars
-fn ellipsize_middle(s: &str, max: usize) -> String {
-    if max == 0 {
-        return String::new();
+fn parse_config(args: impl IntoIterator<Item = String>) -> Arc<Config> {
+    let config = Config::from_args(args).unwrap_or_else(|err| exit_usage(&err));
+    format::set_ascii(config.ascii);
+    if let Err(err) = logging::init(&config) {
+        eprintln!("{}", err);
+        process::exit(EXIT_USAGE);
     }
 
-    let chars: Vec<char> = s.chars().collect();
-    let len = chars.len();
-    if len <= max {
-        return s.to_string();
+    if let Some(missing) = config.roots.iter().find(|root| !root.exists()) {
+        eprintln!("Path does not exist: {}", missing.display());
+        process::exit(EXIT_USAGE);
     }
-    if max == 1 {
-        return "…".to_string();
+    if !config.virtual_fs {
+        for root in &config.roots {
+            if let Some(fstype) = mounts::virtual_fs(root) {
+                eprintln!(
+                    "{} is on a virtual filesystem ({}); pass --virtual-fs to scan it anyway",
+                    root.display(),
+                    fstype
+                );
+                process::exit(EXIT_USAGE);
+            }
+        }
     }
 
-    let keep = max - 1;
-    let front = keep / 2;
-    let back = keep - front;
-    let back_start = len - back;
-
-    let mut out = String::with_capacity(max);
-    for ch in chars.iter().take(front) {
-        out.push(*ch);
-    }
-    out.push('…');
-    for ch in chars.iter().skip(back_start) {
-        out.push(*ch);
-    }
-    out
+    Arc::new(config)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn ellipsize_leaves_short_strings_alone() {
-        assert_eq!(ellipsize_middle("short.txt", 20), "short.txt");
-    }
-
-    #[test]
-    fn ellipsize_compacts_middle_and_keeps_ends() {
-        let original = "somefilenameisverylong.txt";
-        assert_eq!(ellipsize_middle(original, 20), "somefilen…rylong.txt");
-    }
+fn exit_usage(err: &str) -> ! {
+    eprintln!("{}", err);
+    eprintln!("{}", usage());
+    process::exit(EXIT_USAGE);
 }
@@ -0,0 +1,315 @@
+//! `tengok merge a.json b.tsnap ...`: `--json` snapshots from many hosts
+//! combined into one report, for fleet-wide storage numbers from a central
+//! job. Each run is keyed by the host and root path its `scan` object
+//! records; when one key comes up more than once, the run that finished
+//! last counts, so collecting every night's files doesn't add them up.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::baseline::Baseline;
+use crate::config::{Config, Output};
+use crate::format::format_num;
+use crate::report::{Cell, Style, print_table};
+use crate::snapshot;
+
+/// What a snapshot without a hostname is listed under.
+const UNKNOWN_HOST: &str = "unknown";
+
+/// The flags `merge` reads, and whether they take a value.
+const FLAGS: &[(&str, bool)] = &[
+    ("--json", false),
+    ("--plain", false),
+    ("--no-colors", false),
+    ("-p", false),
+    ("--ascii", false),
+    ("--bytes", false),
+    ("--theme", true),
+];
+
+/// Splits the snapshot files from the flags in `FLAGS`, the value of one
+/// going with it: `--theme protanopia` is no snapshot. Any other flag is an
+/// error, since merge couldn't tell its value from a snapshot.
+pub fn split_args(
+    args: impl IntoIterator<Item = String>,
+) -> Result<(Vec<PathBuf>, Vec<String>), String> {
+    let (mut snapshots, mut flags) = (Vec::new(), Vec::new());
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            snapshots.push(PathBuf::from(arg));
+            continue;
+        }
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, _)) => (flag, true),
+            None => (arg.as_str(), false),
+        };
+        let Some(&(_, takes_value)) = FLAGS.iter().find(|(known, _)| *known == flag) else {
+            return Err(format!("merge doesn't take {}", flag));
+        };
+        let value = (takes_value && !inline).then(|| args.next()).flatten();
+        flags.push(arg);
+        flags.extend(value);
+    }
+    if snapshots.is_empty() {
+        return Err("merge needs at least one snapshot".to_string());
+    }
+    Ok((snapshots, flags))
+}
+
+#[derive(Deserialize)]
+struct Document {
+    #[serde(default)]
+    root: Option<String>,
+    #[serde(default)]
+    scan: Option<ScanIn>,
+    summary: Baseline,
+}
+
+#[derive(Deserialize)]
+struct ScanIn {
+    hostname: Option<String>,
+    root_path: String,
+    finished_at_epoch: Option<u64>,
+}
+
+/// One host's scan of one root.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Run {
+    pub host: String,
+    pub root: String,
+    pub finished_at_epoch: Option<u64>,
+    pub files: u64,
+    pub size_bytes: u64,
+    pub lines: u64,
+}
+
+impl Run {
+    fn parse(text: &str) -> Result<Self, String> {
+        Self::from_value(serde_json::from_str(text).map_err(|e| e.to_string())?)
+    }
+
+    /// A JSON file, or a binary snapshot (`--snapshot`), which is read
+    /// whole since the host is in its `scan` record.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let unreadable = |e: io::Error| format!("Unable to read {}: {}", path.display(), e);
+        let parsed = if snapshot::is_binary(path).map_err(unreadable)? {
+            Self::from_value(snapshot::read(path).map_err(unreadable)?)
+        } else {
+            Self::parse(&fs::read_to_string(path).map_err(unreadable)?)
+        };
+        parsed.map_err(|e| format!("Invalid snapshot {}: {}", path.display(), e))
+    }
+
+    fn from_value(doc: Value) -> Result<Self, String> {
+        let doc: Document = serde_json::from_value(doc)
+            .map_err(|_| "expected a `tengok --json` document".to_string())?;
+        let (host, root, finished_at_epoch) = match doc.scan {
+            Some(scan) => (scan.hostname, scan.root_path, scan.finished_at_epoch),
+            None => (None, doc.root.unwrap_or_default(), None),
+        };
+        Ok(Run {
+            host: host.unwrap_or_else(|| UNKNOWN_HOST.to_string()),
+            root,
+            finished_at_epoch,
+            files: doc.summary.files,
+            size_bytes: doc.summary.size_bytes,
+            lines: doc.summary.lines,
+        })
+    }
+}
+
+/// A host's runs added up.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct HostTotal {
+    pub host: String,
+    pub roots: u64,
+    pub files: u64,
+    pub size_bytes: u64,
+    pub lines: u64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Merged {
+    pub hosts: u64,
+    pub roots: u64,
+    pub files: u64,
+    pub size_bytes: u64,
+    pub lines: u64,
+    /// Largest first.
+    pub by_host: Vec<HostTotal>,
+    /// By host, then root.
+    pub runs: Vec<Run>,
+}
+
+/// One run per host and root, the latest one, then the totals.
+pub fn merge(runs: Vec<Run>) -> Merged {
+    let mut latest: BTreeMap<(String, String), Run> = BTreeMap::new();
+    for run in runs {
+        let key = (run.host.clone(), run.root.clone());
+        match latest.get(&key) {
+            Some(kept) if kept.finished_at_epoch > run.finished_at_epoch => {}
+            _ => {
+                latest.insert(key, run);
+            }
+        }
+    }
+    let runs: Vec<Run> = latest.into_values().collect();
+
+    let mut hosts: BTreeMap<&str, HostTotal> = BTreeMap::new();
+    for run in &runs {
+        let total = hosts.entry(&run.host).or_insert_with(|| HostTotal {
+            host: run.host.clone(),
+            ..HostTotal::default()
+        });
+        total.roots += 1;
+        total.files += run.files;
+        total.size_bytes += run.size_bytes;
+        total.lines += run.lines;
+    }
+    let mut by_host: Vec<HostTotal> = hosts.into_values().collect();
+    by_host.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes).then(a.host.cmp(&b.host)));
+
+    Merged {
+        hosts: by_host.len() as u64,
+        roots: runs.len() as u64,
+        files: runs.iter().map(|r| r.files).sum(),
+        size_bytes: runs.iter().map(|r| r.size_bytes).sum(),
+        lines: runs.iter().map(|r| r.lines).sum(),
+        by_host,
+        runs,
+    }
+}
+
+pub fn run(config: &Config, paths: &[PathBuf]) -> Result<(), String> {
+    let runs = paths
+        .iter()
+        .map(|path| Run::load(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let merged = merge(runs);
+    if config.output == Output::Json {
+        let mut stdout = io::stdout().lock();
+        serde_json::to_writer_pretty(&mut stdout, &merged).map_err(|e| e.to_string())?;
+        writeln!(stdout).map_err(|e| e.to_string())?;
+    } else {
+        print_merged(config, &merged);
+    }
+    Ok(())
+}
+
+fn print_merged(config: &Config, merged: &Merged) {
+    let style = Style::new(config);
+    println!(
+        "{} {} hosts, {} roots: {} files, {}, {} lines",
+        style.label("Merged:"),
+        format_num(merged.hosts),
+        format_num(merged.roots),
+        format_num(merged.files),
+        config.size(merged.size_bytes),
+        format_num(merged.lines)
+    );
+    println!();
+
+    let rows: Vec<Vec<Cell>> = merged
+        .by_host
+        .iter()
+        .map(|h| {
+            vec![
+                Cell::size(config, h.size_bytes),
+                format_num(h.files).into(),
+                format_num(h.lines).into(),
+                format_num(h.roots).into(),
+                h.host.clone().into(),
+            ]
+        })
+        .collect();
+    print_table(
+        &style,
+        "Hosts",
+        &["Size", "Files", "Lines", "Roots", "Host"],
+        &rows,
+    );
+    println!();
+
+    let rows: Vec<Vec<Cell>> = merged
+        .runs
+        .iter()
+        .map(|r| {
+            vec![
+                Cell::size(config, r.size_bytes),
+                format_num(r.files).into(),
+                format_num(r.lines).into(),
+                format!("{}:{}", r.host, r.root).into(),
+            ]
+        })
+        .collect();
+    print_table(&style, "Roots", &["Size", "Files", "Lines", "Root"], &rows);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(host: &str, root: &str, finished: u64, size: u64) -> Run {
+        Run::parse(&format!(
+            r#"{{"schema_version":1,"root":".","scan":{{"hostname":"{}","root_path":"{}",
+                "finished_at_epoch":{},"options":[]}},
+                "summary":{{"files":1,"size_bytes":{},"lines":2}}}}"#,
+            host, root, finished, size
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn split_args_keeps_flag_values_with_their_flag() {
+        let split = |args: &[&str]| split_args(args.iter().map(|a| a.to_string()));
+        let (snapshots, flags) =
+            split(&["a.json", "--theme", "protanopia", "--json", "b.tsnap"]).unwrap();
+        assert_eq!(
+            snapshots,
+            [PathBuf::from("a.json"), PathBuf::from("b.tsnap")]
+        );
+        assert_eq!(flags, ["--theme", "protanopia", "--json"]);
+        assert_eq!(split(&["--theme=protanopia", "a.json"]).unwrap().1.len(), 1);
+        assert!(
+            split(&["--lang", "id", "a.json"])
+                .unwrap_err()
+                .contains("--lang")
+        );
+        assert!(split(&["--theme", "protanopia"]).is_err());
+    }
+
+    #[test]
+    fn merge_keeps_the_latest_run_per_host_and_root() {
+        let merged = merge(vec![
+            doc("web1", "/srv", 10, 100),
+            doc("web1", "/srv", 20, 150),
+            doc("web1", "/var/log", 20, 40),
+            doc("db1", "/srv", 5, 500),
+        ]);
+        assert_eq!((merged.hosts, merged.roots), (2, 3));
+        assert_eq!(merged.size_bytes, 690);
+        assert_eq!(merged.files, 3);
+        let hosts: Vec<(&str, u64, u64)> = merged
+            .by_host
+            .iter()
+            .map(|h| (h.host.as_str(), h.roots, h.size_bytes))
+            .collect();
+        assert_eq!(hosts, [("db1", 1, 500), ("web1", 2, 190)]);
+
+        let bare =
+            Run::parse(r#"{"root":"src","summary":{"files":1,"size_bytes":2,"lines":3}}"#).unwrap();
+        assert_eq!(
+            (bare.host.as_str(), bare.root.as_str()),
+            (UNKNOWN_HOST, "src")
+        );
+        assert!(Run::parse(r#"{"timestamp":1,"files":1,"size_bytes":2,"lines":3}"#).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::config::Config;
+use crate::history::root_id;
+use crate::scan::{GroupStat, Summary};
+
+/// A finished scan as exported to Prometheus.
+pub struct Sample<'a> {
+    pub config: &'a Config,
+    pub summary: &'a Summary,
+    pub duration_secs: f64,
+    pub finished_at: u64,
+}
+
+impl<'a> Sample<'a> {
+    /// A one-off scan, timed by the summary's start and finish.
+    pub fn of(config: &'a Config, summary: &'a Summary) -> Self {
+        let finished = summary.finished_at.unwrap_or_else(SystemTime::now);
+        Sample {
+            config,
+            summary,
+            duration_secs: summary
+                .started_at
+                .and_then(|started| finished.duration_since(started).ok())
+                .map_or(0.0, |d| d.as_secs_f64()),
+            finished_at: finished
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        }
+    }
+}
+
+/// Name, help text and value of a `tengok_dir_*` family.
+type DirFamily = (&'static str, &'static str, fn(&GroupStat) -> u64);
+
+/// Render samples in the Prometheus text exposition format (one series per
+/// root, labelled with the canonical root path). Roots scanned with
+/// `--dir-metrics` add `tengok_dir_*` series per top-level directory.
+pub fn render(samples: &[Sample]) -> String {
+    let mut out = String::new();
+    let mut family = |name: &str, help: &str, value: &dyn Fn(&Sample) -> String| {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        for sample in samples {
+            let _ = writeln!(
+                out,
+                "{}{{root=\"{}\"}} {}",
+                name,
+                escape_label(&root_id(&sample.config.root)),
+                value(sample)
+            );
+        }
+    };
+
+    family("tengok_files", "Regular files scanned.", &|s| {
+        s.summary.total_files.to_string()
+    });
+    family("tengok_size_bytes", "Total bytes of scanned files.", &|s| {
+        s.summary.total_size.to_string()
+    });
+    family("tengok_lines", "Total counted lines.", &|s| {
+        s.summary.total_lines.to_string()
+    });
+    family(
+        "tengok_scan_duration_seconds",
+        "Wall time of the last scan.",
+        &|s| format!("{:.3}", s.duration_secs),
+    );
+    family(
+        "tengok_last_scan_timestamp_seconds",
+        "Unix time the last scan finished.",
+        &|s| s.finished_at.to_string(),
+    );
+
+    let dirs: Vec<(String, BTreeMap<String, GroupStat>)> = samples
+        .iter()
+        .filter(|s| s.config.dir_metrics)
+        .map(|s| (escape_label(&root_id(&s.config.root)), top_level_dirs(s)))
+        .collect();
+    if !dirs.is_empty() {
+        let families: [DirFamily; 3] = [
+            (
+                "tengok_dir_files",
+                "Regular files below a top-level directory.",
+                |d| d.files,
+            ),
+            (
+                "tengok_dir_bytes",
+                "Bytes of files below a top-level directory.",
+                |d| d.size,
+            ),
+            (
+                "tengok_dir_lines",
+                "Counted lines below a top-level directory.",
+                |d| d.lines,
+            ),
+        ];
+        for (name, help, value) in families {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            for (root, stats) in &dirs {
+                for (dir, stat) in stats {
+                    let _ = writeln!(
+                        out,
+                        "{}{{root=\"{}\",dir=\"{}\"}} {}",
+                        name,
+                        root,
+                        escape_label(dir),
+                        value(stat)
+                    );
+                }
+            }
+        }
+    }
+    out
+}
+
+/// `render`'s output as an OpenMetrics exposition, which must end in
+/// `# EOF`.
+pub fn render_openmetrics(samples: &[Sample]) -> String {
+    let mut out = render(samples);
+    out.push_str("# EOF\n");
+    out
+}
+
+/// The summary as DogStatsD gauges (`tengok.files:123|g|#root:/srv`), plus
+/// `tengok.dir.*` tagged with the directory under `--dir-metrics`.
+pub fn statsd_lines(sample: &Sample) -> Vec<String> {
+    let root = format!("root:{}", escape_tag(&root_id(&sample.config.root)));
+    let summary = sample.summary;
+    let mut lines: Vec<String> = [
+        ("files", summary.total_files.to_string()),
+        ("size_bytes", summary.total_size.to_string()),
+        ("lines", summary.total_lines.to_string()),
+        (
+            "scan_duration_seconds",
+            format!("{:.3}", sample.duration_secs),
+        ),
+    ]
+    .into_iter()
+    .map(|(name, value)| format!("tengok.{}:{}|g|#{}", name, value, root))
+    .collect();
+    if sample.config.dir_metrics {
+        for (dir, stat) in top_level_dirs(sample) {
+            let tags = format!("{},dir:{}", root, escape_tag(&dir));
+            for (name, value) in [
+                ("files", stat.files),
+                ("bytes", stat.size),
+                ("lines", stat.lines),
+            ] {
+                lines.push(format!("tengok.dir.{}:{}|g|#{}", name, value, tags));
+            }
+        }
+    }
+    lines
+}
+
+/// Sends `statsd_lines` to `addr` over UDP, as many lines per datagram as
+/// fit in `STATSD_PACKET` bytes.
+#[cfg(not(target_os = "wasi"))]
+pub fn send_statsd(addr: &str, sample: &Sample) -> std::io::Result<()> {
+    use std::net::{ToSocketAddrs, UdpSocket};
+
+    let target = addr.to_socket_addrs()?.next().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("no address for {}", addr),
+        )
+    })?;
+    let socket = UdpSocket::bind(if target.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    })?;
+    let mut packet = String::new();
+    for line in statsd_lines(sample) {
+        if !packet.is_empty() && packet.len() + 1 + line.len() > STATSD_PACKET {
+            socket.send_to(packet.as_bytes(), target)?;
+            packet.clear();
+        }
+        if !packet.is_empty() {
+            packet.push('\n');
+        }
+        packet.push_str(&line);
+    }
+    if !packet.is_empty() {
+        socket.send_to(packet.as_bytes(), target)?;
+    }
+    Ok(())
+}
+
+/// Keeps a datagram inside a typical 1500-byte MTU.
+#[cfg(not(target_os = "wasi"))]
+const STATSD_PACKET: usize = 1432;
+
+/// DogStatsD tag values can't hold the separators `,`, `|` and `#`.
+fn escape_tag(value: &str) -> String {
+    value.replace([',', '|', '#', '\n'], "_")
+}
+
+/// Totals per top-level directory of the root, by name; files directly in
+/// the root are under `.`.
+fn top_level_dirs(sample: &Sample) -> BTreeMap<String, GroupStat> {
+    let root = &sample.config.root;
+    let mut out: BTreeMap<String, GroupStat> = BTreeMap::new();
+    for (dir, stat) in &sample.summary.dirs {
+        let name = dir
+            .strip_prefix(root)
+            .ok()
+            .and_then(|rel| rel.components().next())
+            .map_or(".".to_string(), |c| {
+                c.as_os_str().to_string_lossy().into_owned()
+            });
+        let total = out.entry(name).or_default();
+        total.files += stat.files;
+        total.size += stat.size;
+        total.lines += stat.lines;
+    }
+    out
+}
+
+pub fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dir_metrics_label_top_level_dirs() {
+        let mut fs = crate::vfs::MemFs::default();
+        fs.insert("r/top.txt", "a\n");
+        fs.insert("r/src/main.rs", "fn main() {}\n");
+        fs.insert("r/src/lib/mod.rs", "x\ny\n");
+        let config = Config::from_args(["r", "--dir-metrics"].map(String::from)).unwrap();
+        let summary = crate::scan::scan_fs(&fs, &config).unwrap();
+        let sample = Sample {
+            config: &config,
+            summary: &summary,
+            duration_secs: 0.0,
+            finished_at: 0,
+        };
+        let text = render_openmetrics(std::slice::from_ref(&sample));
+        let root = escape_label(&root_id(&config.root));
+        assert!(text.contains(&format!(
+            "tengok_dir_bytes{{root=\"{}\",dir=\"src\"}} 17\n",
+            root
+        )));
+        assert!(text.contains(&format!(
+            "tengok_dir_files{{root=\"{}\",dir=\".\"}} 1\n",
+            root
+        )));
+        assert!(text.ends_with("# EOF\n"));
+
+        let lines = statsd_lines(&sample);
+        let tag = escape_tag(&root_id(&config.root));
+        assert_eq!(lines[0], format!("tengok.files:3|g|#root:{}", tag));
+        assert!(lines.contains(&format!("tengok.dir.bytes:17|g|#root:{},dir:src", tag)));
+    }
+
+    #[test]
+    fn escape_label_quotes_specials() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    io::{self, Read},
+    path::Path,
+};
+
+use crate::vfs::FileSystem;
+
+/// Bytes read from the start of a file to decide its category.
+const SNIFF_BYTES: u64 = 512;
+
+pub const IMAGE: &str = "image";
+pub const VIDEO: &str = "video";
+pub const AUDIO: &str = "audio";
+pub const ARCHIVE: &str = "archive";
+pub const EXECUTABLE: &str = "executable";
+pub const DOCUMENT: &str = "document";
+pub const FONT: &str = "font";
+pub const TEXT: &str = "text";
+pub const BINARY: &str = "binary";
+pub const EMPTY: &str = "empty";
+
+// (offset, magic, category), checked in order; the first match wins.
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", IMAGE),
+    (0, b"\xff\xd8\xff", IMAGE),
+    (0, b"GIF87a", IMAGE),
+    (0, b"GIF89a", IMAGE),
+    (0, b"II*\0", IMAGE),
+    (0, b"MM\0*", IMAGE),
+    (0, b"\0\0\x01\0", IMAGE), // ico
+    (8, b"WEBP", IMAGE),
+    (4, b"ftypheic", IMAGE),
+    (4, b"ftypavif", IMAGE),
+    (4, b"ftyp", VIDEO),             // mp4/mov/m4v after the image brands above
+    (0, b"\x1a\x45\xdf\xa3", VIDEO), // mkv/webm
+    (8, b"AVI ", VIDEO),
+    (0, b"ID3", AUDIO),
+    (0, b"\xff\xfb", AUDIO),
+    (0, b"fLaC", AUDIO),
+    (0, b"OggS", AUDIO),
+    (8, b"WAVE", AUDIO),
+    (0, b"PK\x03\x04", ARCHIVE),
+    (0, b"\x1f\x8b", ARCHIVE),
+    (0, b"BZh", ARCHIVE),
+    (0, b"\xfd7zXZ\0", ARCHIVE),
+    (0, b"7z\xbc\xaf\x27\x1c", ARCHIVE),
+    (0, b"Rar!\x1a\x07", ARCHIVE),
+    (0, b"\x28\xb5\x2f\xfd", ARCHIVE), // zstd
+    (257, b"ustar", ARCHIVE),
+    (0, b"\x7fELF", EXECUTABLE),
+    (0, b"\xcf\xfa\xed\xfe", EXECUTABLE), // Mach-O 64
+    (0, b"\xce\xfa\xed\xfe", EXECUTABLE), // Mach-O 32
+    (0, b"\xca\xfe\xba\xbe", EXECUTABLE), // fat Mach-O / Java class
+    (0, b"\0asm", EXECUTABLE),
+    (0, b"#!", EXECUTABLE),
+    (0, b"%PDF-", DOCUMENT),
+    (0, b"wOFF", FONT),
+    (0, b"wOF2", FONT),
+    (0, b"OTTO", FONT),
+    (0, b"\0\x01\0\0", FONT),
+];
+
+// Two-letter ASCII magics that plain text can start with too; only trusted
+// when the head doesn't look like text (real BMP/PE headers contain NULs).
+const WEAK_SIGNATURES: &[(&[u8], &str)] = &[(b"BM", IMAGE), (b"MZ", EXECUTABLE)];
+
+/// What a file with an executable bit turns out to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecKind {
+    /// Starts with a shebang.
+    Script,
+    /// ELF, Mach-O, PE or WebAssembly.
+    Binary,
+    /// Neither; usually a mode bit picked up from a zip or a FAT drive.
+    Other,
+}
+
+impl ExecKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExecKind::Script => "script",
+            ExecKind::Binary => "binary",
+            ExecKind::Other => "other",
+        }
+    }
+}
+
+pub fn exec_kind(fs: &dyn FileSystem, path: &Path) -> ExecKind {
+    let mut head = Vec::with_capacity(SNIFF_BYTES as usize);
+    let read = fs
+        .open(path)
+        .and_then(|file| file.take(SNIFF_BYTES).read_to_end(&mut head));
+    if read.is_err() {
+        ExecKind::Other
+    } else if head.starts_with(b"#!") {
+        ExecKind::Script
+    } else if classify(&head) == EXECUTABLE {
+        ExecKind::Binary
+    } else {
+        ExecKind::Other
+    }
+}
+
+/// Read the head of `path` and classify it.
+pub fn sniff(fs: &dyn FileSystem, path: &Path) -> io::Result<&'static str> {
+    let mut head = Vec::with_capacity(SNIFF_BYTES as usize);
+    fs.open(path)?.take(SNIFF_BYTES).read_to_end(&mut head)?;
+    Ok(classify(&head))
+}
+
+/// Category for a file whose first bytes are `head`: a known magic number,
+/// else `text` when the bytes look like UTF-8 without NULs, else `binary`.
+pub fn classify(head: &[u8]) -> &'static str {
+    if head.is_empty() {
+        return EMPTY;
+    }
+    for (offset, magic, category) in SIGNATURES {
+        if head.get(*offset..offset + magic.len()) == Some(*magic) {
+            return category;
+        }
+    }
+    if looks_like_text(head) {
+        return TEXT;
+    }
+    WEAK_SIGNATURES
+        .iter()
+        .find(|(magic, _)| head.starts_with(magic))
+        .map_or(BINARY, |(_, category)| category)
+}
+
+fn looks_like_text(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => true,
+        // The sample may end in the middle of a multi-byte sequence.
+        Err(err) => err.error_len().is_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_uses_magic_then_falls_back_to_text_or_binary() {
+        assert_eq!(classify(b"\x89PNG\r\n\x1a\n...."), IMAGE);
+        assert_eq!(classify(b"\0\0\0\x18ftypisom"), VIDEO);
+        assert_eq!(classify(b"\0\0\0\x18ftypheic"), IMAGE);
+        assert_eq!(classify(b"\x7fELF\x02\x01"), EXECUTABLE);
+        assert_eq!(classify("fn main() {} // résumé".as_bytes()), TEXT);
+        assert_eq!(classify(&"é".as_bytes()[..1]), TEXT);
+        assert_eq!(classify(b"MZ\x90\0\x03"), EXECUTABLE);
+        assert_eq!(classify(b"MZ is not a header"), TEXT);
+        assert_eq!(classify(b"\x01\x02\0\x03"), BINARY);
+        assert_eq!(classify(b""), EMPTY);
+    }
+}
@@ -0,0 +1,116 @@
+//! Minified and bundled frontend assets. Their lines say nothing about how
+//! much code a repo has, so by default the scanner keeps them out of the
+//! line totals and reports their size on its own row.
+//!
+//! A JS or CSS file counts as minified when its name says so (`.min.`,
+//! `.bundle.`, `.chunk.`), when it ends with a `sourceMappingURL` comment
+//! (build output), or when its average line is longer than any hand-written
+//! code has. Source maps (`.js.map`, `.css.map`) count as bundle output.
+
+use std::{
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::vfs::FileSystem;
+
+const EXTS: &[&str] = &["js", "mjs", "cjs", "css"];
+const NAME_INFIXES: &[&str] = &[".min.", "-min.", ".bundle.", ".chunk."];
+/// Average bytes per line above which a file can't be hand-written.
+const MAX_AVG_LINE: u64 = 500;
+/// Too small for the average to mean anything.
+const MIN_SIZE: u64 = 2048;
+/// How much of the tail is searched for a sourcemap comment.
+const TAIL_BYTES: u64 = 512;
+
+/// Whether `path` is a minified or bundled asset. `lines` is 0 when lines
+/// weren't counted, and then only the name and the tail are checked.
+pub fn detect(fs: &dyn FileSystem, path: &Path, size: u64, lines: u64) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    let name = name.to_ascii_lowercase();
+    if name.ends_with(".js.map") || name.ends_with(".css.map") {
+        return true;
+    }
+    let Some((_, ext)) = name.rsplit_once('.') else {
+        return false;
+    };
+    if !EXTS.contains(&ext) {
+        return false;
+    }
+    if NAME_INFIXES.iter().any(|infix| name.contains(infix)) {
+        return true;
+    }
+    if size >= MIN_SIZE && lines > 0 && size / lines > MAX_AVG_LINE {
+        return true;
+    }
+    has_sourcemap_comment(fs, path, size)
+}
+
+fn has_sourcemap_comment(fs: &dyn FileSystem, path: &Path, size: u64) -> bool {
+    let Ok(mut file) = fs.open(path) else {
+        return false;
+    };
+    let start = size.saturating_sub(TAIL_BYTES);
+    if file.seek(SeekFrom::Start(start)).is_err() {
+        return false;
+    }
+    let mut tail = Vec::with_capacity(TAIL_BYTES as usize);
+    if file.read_to_end(&mut tail).is_err() {
+        return false;
+    }
+    let tail = String::from_utf8_lossy(&tail);
+    tail.lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| {
+            let line = line.trim_start();
+            (line.starts_with("//#") || line.starts_with("/*#"))
+                && line.contains("sourceMappingURL=")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemFs;
+
+    #[test]
+    fn names_long_lines_and_sourcemaps() {
+        let mut fs = MemFs::default();
+        let long = "a".repeat(4000);
+        let code = "let x = 1;\n".repeat(400);
+        fs.insert("app.min.js", "x");
+        fs.insert("vendor.bundle.css", "x");
+        fs.insert("app.js.map", "{}");
+        fs.insert("packed.js", long.clone());
+        fs.insert(
+            "out.js",
+            format!("{}//# sourceMappingURL=out.js.map\n", code),
+        );
+        fs.insert("main.js", code.clone());
+        fs.insert("notes.txt", long);
+
+        let check = |name: &str| {
+            let path = Path::new(name);
+            let size = fs.metadata(path).unwrap().len;
+            let lines = match name {
+                "main.js" | "out.js" => 400,
+                _ => 1,
+            };
+            detect(&fs, path, size, lines)
+        };
+        for name in [
+            "app.min.js",
+            "vendor.bundle.css",
+            "app.js.map",
+            "packed.js",
+            "out.js",
+        ] {
+            assert!(check(name), "{name}");
+        }
+        assert!(!check("main.js"));
+        assert!(!check("notes.txt"));
+    }
+}
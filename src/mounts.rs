@@ -0,0 +1,347 @@
+//! What's mounted in a scanned root, from `/proc/self/mountinfo` (Linux).
+//! Virtual filesystems such as `/proc`, `/sys` and cgroups are skipped
+//! unless `--virtual-fs`: their files are kernel interfaces whose sizes
+//! mean nothing, and reading some of them blocks or has side effects. With
+//! `--overlay-layers`, a root on overlayfs (a running container's, say) is
+//! split into the layers behind it, measured where they live on disk: the
+//! upper one is what the container wrote, the lower ones its image.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Filesystem types with nothing on disk behind them.
+const VIRTUAL: &[&str] = &[
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "proc",
+    "pstore",
+    "rpc_pipefs",
+    "securityfs",
+    "selinuxfs",
+    "sysfs",
+    "tracefs",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub point: PathBuf,
+    pub fstype: String,
+    /// The filesystem's own options, such as overlayfs' `lowerdir=…`.
+    pub options: String,
+}
+
+/// One layer of an overlay root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layer {
+    /// `upper`, or `lower 1` (the topmost) onwards.
+    pub name: String,
+    /// The layer's directory for the scanned root.
+    pub dir: PathBuf,
+    /// Whether `dir` could be read; from inside a container the layers
+    /// usually can't.
+    pub visible: bool,
+    pub files: u64,
+    pub bytes: u64,
+    /// Entries recording a deletion from the layers below (character
+    /// devices numbered 0/0).
+    pub whiteouts: u64,
+}
+
+/// The mount table, in mount order; empty off Linux.
+pub fn table() -> Vec<Mount> {
+    if cfg!(target_os = "linux") {
+        fs::read_to_string("/proc/self/mountinfo")
+            .map(|text| parse(&text))
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Parses `mountinfo` lines: `ID PARENT MAJ:MIN ROOT POINT OPTIONS
+/// [OPTIONAL...] - TYPE SOURCE SUPER_OPTIONS`.
+pub fn parse(text: &str) -> Vec<Mount> {
+    text.lines()
+        .filter_map(|line| {
+            let (head, tail) = line.split_once(" - ")?;
+            let point = head.split(' ').nth(4)?;
+            let mut tail = tail.split(' ');
+            let fstype = tail.next()?;
+            let options = tail.nth(1).unwrap_or_default();
+            Some(Mount {
+                point: PathBuf::from(unescape(point)),
+                fstype: fstype.to_string(),
+                options: options.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Undoes the octal escapes (`\040` for a space) mountinfo writes.
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes.get(i + 1..i + 4).and_then(|digits| {
+            let digits = std::str::from_utf8(digits).ok()?;
+            u8::from_str_radix(digits, 8).ok()
+        });
+        match octal {
+            Some(byte) if bytes[i] == b'\\' => {
+                out.push(byte);
+                i += 4;
+            }
+            _ => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// The mount `path` (canonical) is on: the one with the deepest mount point
+/// above it, the last mounted winning.
+fn containing<'a>(mounts: &'a [Mount], path: &Path) -> Option<&'a Mount> {
+    mounts
+        .iter()
+        .filter(|mount| path.starts_with(&mount.point))
+        .max_by_key(|mount| mount.point.components().count())
+}
+
+/// The virtual filesystem type `root` is on, if it's on one.
+pub fn virtual_fs(root: &Path) -> Option<String> {
+    let root = fs::canonicalize(root).ok()?;
+    let table = table();
+    containing(&table, &root)
+        .filter(|mount| VIRTUAL.contains(&mount.fstype.as_str()))
+        .map(|mount| mount.fstype.clone())
+}
+
+/// Virtual filesystems mounted below `dir`, as paths under `dir` as given,
+/// with their types. Mounts inside another one listed are left out, since
+/// skipping the outer one skips them too.
+pub fn virtual_below(dir: &Path) -> Vec<(PathBuf, String)> {
+    match fs::canonicalize(dir) {
+        Ok(canonical) => virtual_points(&table(), &canonical, dir),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn virtual_points(mounts: &[Mount], canonical: &Path, dir: &Path) -> Vec<(PathBuf, String)> {
+    // Only the last mount at a point is visible.
+    let mut points: HashMap<&Path, &str> = HashMap::new();
+    for mount in mounts {
+        points.insert(&mount.point, &mount.fstype);
+    }
+    let mut below: Vec<(PathBuf, String)> = points
+        .into_iter()
+        .filter(|(_, fstype)| VIRTUAL.contains(fstype))
+        .filter_map(|(point, fstype)| Some((point.strip_prefix(canonical).ok()?, fstype)))
+        .filter(|(rel, _)| !rel.as_os_str().is_empty())
+        .map(|(rel, fstype)| (dir.join(rel), fstype.to_string()))
+        .collect();
+    below.sort();
+    // Sorted, an outer mount comes right before the ones inside it.
+    below.dedup_by(|inner, outer| inner.0.starts_with(&outer.0));
+    below
+}
+
+/// Where the devices files below `root` can be on are mounted: the mount
+/// `root` is on, and those below it, each with its device number and type.
+/// A device mounted in several places is named by the shallowest. Off
+/// Linux, only the root's own device is known.
+pub fn devices(root: &Path) -> Vec<(u64, PathBuf, String)> {
+    let Ok(canonical) = fs::canonicalize(root) else {
+        return Vec::new();
+    };
+    let table = table();
+    let mut candidates: Vec<(&Path, &str)> = table
+        .iter()
+        .filter(|mount| mount.point.starts_with(&canonical))
+        .chain(containing(&table, &canonical))
+        .map(|mount| (mount.point.as_path(), mount.fstype.as_str()))
+        .collect();
+    if candidates.is_empty() {
+        candidates.push((&canonical, ""));
+    }
+    candidates.sort_by_key(|(point, _)| point.components().count());
+    let mut devices: Vec<(u64, PathBuf, String)> = Vec::new();
+    for (point, fstype) in candidates {
+        if let Some(dev) = device(point)
+            && !devices.iter().any(|(known, _, _)| *known == dev)
+        {
+            devices.push((dev, point.to_path_buf(), fstype.to_string()));
+        }
+    }
+    devices
+}
+
+#[cfg(unix)]
+fn device(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|meta| meta.dev())
+}
+
+#[cfg(not(unix))]
+fn device(_: &Path) -> Option<u64> {
+    None
+}
+
+/// The layers behind `root` when it's on overlayfs, upper first.
+pub fn overlay_layers(root: &Path) -> Option<Vec<Layer>> {
+    let root = fs::canonicalize(root).ok()?;
+    let table = table();
+    let mount = containing(&table, &root).filter(|mount| mount.fstype == "overlay")?;
+    let rel = root.strip_prefix(&mount.point).unwrap_or(Path::new(""));
+    let (upper, lower) = layer_dirs(&mount.options);
+    let named = upper
+        .map(|dir| ("upper".to_string(), dir))
+        .into_iter()
+        .chain(
+            lower
+                .into_iter()
+                .enumerate()
+                .map(|(i, dir)| (format!("lower {}", i + 1), dir)),
+        );
+    Some(
+        named
+            .map(|(name, dir)| {
+                let dir = dir.join(rel);
+                let visible = fs::symlink_metadata(&dir).is_ok_and(|meta| meta.is_dir());
+                let (files, bytes, whiteouts) = if visible { measure(&dir) } else { (0, 0, 0) };
+                Layer {
+                    name,
+                    dir,
+                    visible,
+                    files,
+                    bytes,
+                    whiteouts,
+                }
+            })
+            .collect(),
+    )
+}
+
+/// The upper directory and the lower ones, topmost first, from overlayfs
+/// options (`lowerdir=a:b`, or one `lowerdir+=` per layer). `\:` and `\,`
+/// escape the separators.
+fn layer_dirs(options: &str) -> (Option<PathBuf>, Vec<PathBuf>) {
+    let mut upper = None;
+    let mut lower = Vec::new();
+    for option in split_escaped(options, ',') {
+        if let Some(dirs) = option.strip_prefix("lowerdir=") {
+            lower.extend(split_escaped(dirs, ':').into_iter().map(PathBuf::from));
+        } else if let Some(dir) = option.strip_prefix("lowerdir+=") {
+            lower.push(PathBuf::from(dir));
+        } else if let Some(dir) = option.strip_prefix("upperdir=") {
+            upper = Some(PathBuf::from(dir));
+        }
+    }
+    (upper, lower)
+}
+
+fn split_escaped(text: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // Other escapes are left for the next split.
+            '\\' => match chars.next() {
+                Some(next) if next == separator => parts.last_mut().unwrap().push(next),
+                Some(next) => parts.last_mut().unwrap().extend(['\\', next]),
+                None => {}
+            },
+            c if c == separator => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+    parts
+}
+
+/// Regular files, their bytes, and whiteouts anywhere below `dir`, without
+/// following links.
+fn measure(dir: &Path) -> (u64, u64, u64) {
+    let (mut files, mut bytes, mut whiteouts) = (0, 0, 0);
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                pending.push(entry.path());
+            } else if meta.is_file() {
+                files += 1;
+                bytes += meta.len();
+            } else if is_whiteout(&meta) {
+                whiteouts += 1;
+            }
+        }
+    }
+    (files, bytes, whiteouts)
+}
+
+#[cfg(unix)]
+fn is_whiteout(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt};
+    meta.file_type().is_char_device() && meta.rdev() == 0
+}
+
+#[cfg(not(unix))]
+fn is_whiteout(_: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_virtual_mounts_and_overlay_layers() {
+        let mounts = parse(
+            "28 1 254:0 / / rw - ext4 /dev/vda rw\n\
+             23 28 0:22 / /proc rw - proc proc rw\n\
+             24 28 0:23 / /srv/my\\040data rw - sysfs sysfs rw\n\
+             32 24 0:28 / /srv/my\\040data/fs/cgroup rw - cgroup2 cgroup2 rw\n\
+             25 28 0:24 / /srv/tmp rw - proc proc rw\n\
+             26 28 0:25 / /srv/tmp rw - tmpfs tmpfs rw\n\
+             90 28 0:50 / /var/ctr rw - overlay overlay rw,lowerdir=/l/b\\:c:/l/a,upperdir=/u,workdir=/w\n",
+        );
+        assert_eq!(mounts.len(), 7);
+        assert_eq!(mounts[2].point, Path::new("/srv/my data"));
+        assert_eq!(
+            virtual_points(&mounts, Path::new("/"), Path::new("r")),
+            [
+                (PathBuf::from("r/proc"), "proc".to_string()),
+                (PathBuf::from("r/srv/my data"), "sysfs".to_string())
+            ]
+        );
+        assert_eq!(
+            containing(&mounts, Path::new("/var/ctr/etc")).map(|m| m.fstype.as_str()),
+            Some("overlay")
+        );
+        let (upper, lower) = layer_dirs(&mounts[6].options);
+        assert_eq!(upper, Some(PathBuf::from("/u")));
+        assert_eq!(lower, [PathBuf::from("/l/b:c"), PathBuf::from("/l/a")]);
+    }
+}
@@ -0,0 +1,302 @@
+//! `--lint-names`: file and directory names that won't survive a trip to
+//! another platform, typically through a zip made on Linux or macOS and
+//! unpacked on Windows. Each name is checked for characters Windows
+//! rejects, device names Windows reserves (`CON`, `NUL`, `COM1`, ...), a
+//! trailing space or dot (which Windows silently drops), control
+//! characters, and Unicode that isn't in NFC form, which macOS and Linux
+//! tools disagree about and which shows up as "duplicate" files.
+//!
+//! `--case-collisions` finds paths that differ only by case, which a
+//! case-insensitive filesystem (Windows, macOS by default) can't check out
+//! side by side. With `--strict`, any finding fails the run.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use unicode_normalization::is_nfc;
+
+use crate::config::Config;
+use crate::scan::Summary;
+
+/// Offending paths shown per issue in the report and the JSON.
+pub const EXAMPLES: usize = 5;
+
+const WINDOWS_INVALID: &[char] = &['<', '>', ':', '"', '|', '?', '*', '\\'];
+/// Device names, reserved with any extension: `nul.txt` is `NUL` too.
+const WINDOWS_RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Issue {
+    InvalidChars,
+    Reserved,
+    TrailingSpaceOrDot,
+    ControlChars,
+    NotNfc,
+}
+
+impl Issue {
+    pub const ALL: [Issue; 5] = [
+        Issue::InvalidChars,
+        Issue::Reserved,
+        Issue::TrailingSpaceOrDot,
+        Issue::ControlChars,
+        Issue::NotNfc,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Issue::InvalidChars => "invalid on Windows",
+            Issue::Reserved => "reserved on Windows",
+            Issue::TrailingSpaceOrDot => "trailing space or dot",
+            Issue::ControlChars => "control characters",
+            Issue::NotNfc => "not NFC",
+        }
+    }
+
+    /// Key in the JSON output.
+    pub fn key(self) -> &'static str {
+        match self {
+            Issue::InvalidChars => "invalid_chars",
+            Issue::Reserved => "reserved",
+            Issue::TrailingSpaceOrDot => "trailing_space_or_dot",
+            Issue::ControlChars => "control_chars",
+            Issue::NotNfc => "not_nfc",
+        }
+    }
+
+    fn applies(self, name: &str) -> bool {
+        match self {
+            Issue::InvalidChars => name.contains(WINDOWS_INVALID),
+            Issue::Reserved => {
+                let stem = name.split('.').next().unwrap_or(name).trim_end_matches(' ');
+                WINDOWS_RESERVED
+                    .iter()
+                    .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+            }
+            Issue::TrailingSpaceOrDot => name.ends_with([' ', '.']),
+            Issue::ControlChars => name.chars().any(char::is_control),
+            Issue::NotNfc => !is_nfc(name),
+        }
+    }
+}
+
+/// The issues with a file or directory name. Names that aren't valid UTF-8
+/// aren't checked.
+pub fn issues(name: &str) -> impl Iterator<Item = Issue> {
+    Issue::ALL
+        .into_iter()
+        .filter(move |issue| issue.applies(name))
+}
+
+/// Offending paths relative to the root, per issue. A directory is named
+/// once however many files sit below it, and counted once per file, so the
+/// last file leaving under `--watch` takes it out.
+#[derive(Debug, Default)]
+pub struct NameLint {
+    offenders: [BTreeMap<PathBuf, u64>; Issue::ALL.len()],
+}
+
+impl NameLint {
+    /// Check the file at `rel`, relative to the root, and its directories.
+    pub fn add(&mut self, rel: &Path) {
+        for (path, issue) in offenders(rel) {
+            *self.offenders[issue as usize].entry(path).or_default() += 1;
+        }
+    }
+
+    pub fn remove(&mut self, rel: &Path) {
+        for (path, issue) in offenders(rel) {
+            let paths = &mut self.offenders[issue as usize];
+            if let Some(count) = paths.get_mut(&path) {
+                *count -= 1;
+                if *count == 0 {
+                    paths.remove(&path);
+                }
+            }
+        }
+    }
+
+    pub fn count(&self, issue: Issue) -> u64 {
+        self.offenders[issue as usize].len() as u64
+    }
+
+    /// Files and directories with at least one issue.
+    pub fn paths(&self) -> u64 {
+        self.offenders
+            .iter()
+            .flat_map(BTreeMap::keys)
+            .collect::<BTreeSet<_>>()
+            .len() as u64
+    }
+
+    /// The first `n` offenders for `issue`, in path order.
+    pub fn examples(&self, issue: Issue, n: usize) -> impl Iterator<Item = &Path> {
+        self.offenders[issue as usize]
+            .keys()
+            .take(n)
+            .map(PathBuf::as_path)
+    }
+}
+
+/// Each path from the root down to `rel` whose last name has an issue.
+fn offenders(rel: &Path) -> impl Iterator<Item = (PathBuf, Issue)> {
+    rel.ancestors()
+        .filter_map(|path| Some((path, path.file_name()?.to_str()?)))
+        .flat_map(|(path, name)| issues(name).map(move |issue| (path.to_path_buf(), issue)))
+}
+
+/// Every file and directory below the root, grouped by its lowercased
+/// relative path. Directories are counted once per file inside, so the last
+/// file leaving under `--watch` takes them out too.
+#[derive(Debug, Default)]
+pub struct CaseIndex {
+    paths: HashMap<String, BTreeMap<PathBuf, u64>>,
+}
+
+impl CaseIndex {
+    /// Add the file at `rel`, relative to the root, with its directories.
+    pub fn add(&mut self, rel: &Path) {
+        for path in rel.ancestors().filter(|p| !p.as_os_str().is_empty()) {
+            *self
+                .paths
+                .entry(lowercase(path))
+                .or_default()
+                .entry(path.to_path_buf())
+                .or_default() += 1;
+        }
+    }
+
+    pub fn remove(&mut self, rel: &Path) {
+        for path in rel.ancestors().filter(|p| !p.as_os_str().is_empty()) {
+            let key = lowercase(path);
+            let Some(group) = self.paths.get_mut(&key) else {
+                continue;
+            };
+            if let Some(count) = group.get_mut(path) {
+                *count -= 1;
+                if *count == 0 {
+                    group.remove(path);
+                }
+            }
+            if group.is_empty() {
+                self.paths.remove(&key);
+            }
+        }
+    }
+
+    /// Groups of paths that differ only by case, in path order. Only the
+    /// outermost collision is listed: `Docs/` and `docs/` colliding already
+    /// covers whatever sits below them.
+    pub fn collisions(&self) -> Vec<Vec<&Path>> {
+        let colliding: HashSet<&str> = self
+            .paths
+            .iter()
+            .filter(|(_, group)| group.len() > 1)
+            .map(|(key, _)| key.as_str())
+            .collect();
+        let mut groups: Vec<Vec<&Path>> = self
+            .paths
+            .iter()
+            .filter(|(key, group)| {
+                group.len() > 1
+                    && !Path::new(key.as_str())
+                        .ancestors()
+                        .skip(1)
+                        .any(|dir| dir.to_str().is_some_and(|d| colliding.contains(d)))
+            })
+            .map(|(_, group)| group.keys().map(PathBuf::as_path).collect())
+            .collect();
+        groups.sort();
+        groups
+    }
+}
+
+fn lowercase(path: &Path) -> String {
+    path.to_string_lossy().to_lowercase()
+}
+
+/// Whether `--strict` fails the run: a portability check found something.
+pub fn strict_failure(config: &Config, summary: &Summary) -> bool {
+    config.strict && (summary.names.paths() > 0 || !summary.case_paths.collisions().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_unportable_names() {
+        let check = |name: &str| issues(name).collect::<Vec<_>>();
+        assert_eq!(check("main.rs"), []);
+        assert_eq!(check("what?.txt"), [Issue::InvalidChars]);
+        assert_eq!(check("a:b"), [Issue::InvalidChars]);
+        assert_eq!(check("nul.txt"), [Issue::Reserved]);
+        assert_eq!(check("Com1 .tar.gz"), [Issue::Reserved]);
+        assert_eq!(check("console.log"), []);
+        assert_eq!(check("notes. "), [Issue::TrailingSpaceOrDot]);
+        assert_eq!(check("bell\u{7}"), [Issue::ControlChars]);
+        // "é" as "e" plus a combining acute accent, as macOS writes it.
+        assert_eq!(check("cafe\u{301}.txt"), [Issue::NotNfc]);
+        assert_eq!(check("caf\u{e9}.txt"), []);
+        assert_eq!(
+            check("*tab\t."),
+            [
+                Issue::InvalidChars,
+                Issue::TrailingSpaceOrDot,
+                Issue::ControlChars
+            ]
+        );
+
+        let mut lint = NameLint::default();
+        lint.add(Path::new("x/a?."));
+        lint.add(Path::new("x/b?"));
+        lint.add(Path::new("x/ok"));
+        lint.add(Path::new("aux/one"));
+        lint.add(Path::new("aux/two"));
+        assert_eq!(lint.count(Issue::InvalidChars), 2);
+        assert_eq!(lint.count(Issue::Reserved), 1);
+        assert_eq!(lint.paths(), 3);
+        lint.remove(Path::new("x/a?."));
+        lint.remove(Path::new("aux/one"));
+        assert_eq!(lint.count(Issue::TrailingSpaceOrDot), 0);
+        assert_eq!(
+            lint.examples(Issue::InvalidChars, 5).collect::<Vec<_>>(),
+            [Path::new("x/b?")]
+        );
+        assert_eq!(
+            lint.examples(Issue::Reserved, 5).collect::<Vec<_>>(),
+            [Path::new("aux")]
+        );
+    }
+
+    #[test]
+    fn finds_the_outermost_case_collision() {
+        let mut index = CaseIndex::default();
+        for rel in [
+            "Docs/a.md",
+            "docs/b.md",
+            "README.md",
+            "readme.md",
+            "src/x.rs",
+            "src/X.rs",
+        ] {
+            index.add(Path::new(rel));
+        }
+        assert_eq!(
+            index.collisions(),
+            [
+                vec![Path::new("Docs"), Path::new("docs")],
+                vec![Path::new("README.md"), Path::new("readme.md")],
+                vec![Path::new("src/X.rs"), Path::new("src/x.rs")],
+            ]
+        );
+        index.remove(Path::new("docs/b.md"));
+        index.remove(Path::new("src/x.rs"));
+        assert_eq!(index.collisions().len(), 1);
+    }
+}
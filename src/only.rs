@@ -0,0 +1,92 @@
+//! `--only`: count just the files matching any of a set of globs, and skip
+//! directories that can't contain a match without reading them.
+//!
+//! Globs are matched like budget globs: relative to the scanned root, with
+//! `*` inside one directory and `**` across any number of them. Pruning
+//! uses each glob's literal leading directories, so `src/**/*.rs` never
+//! enters `docs/`, while a glob starting with a wildcard walks everything.
+
+use std::path::{Component, Path};
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+#[derive(Debug, Clone)]
+pub struct Only {
+    set: GlobSet,
+    /// Literal leading components per glob; `None` when some glob starts with
+    /// a wildcard and no directory can be ruled out.
+    prefixes: Option<Vec<Vec<String>>>,
+}
+
+impl Only {
+    pub fn new(globs: &[String]) -> Result<Self, String> {
+        let mut set = GlobSetBuilder::new();
+        let mut prefixes = Some(Vec::new());
+        for glob in globs {
+            set.add(
+                GlobBuilder::new(glob)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| format!("Invalid --only {:?}: {}", glob, e))?,
+            );
+            let prefix: Vec<String> = glob
+                .split('/')
+                .take_while(|part| !part.contains(['*', '?', '[', '{', '\\']))
+                .map(String::from)
+                .collect();
+            if prefix.is_empty() {
+                prefixes = None;
+            } else if let Some(prefixes) = &mut prefixes {
+                prefixes.push(prefix);
+            }
+        }
+        let set = set.build().map_err(|e| format!("Invalid --only: {}", e))?;
+        Ok(Self { set, prefixes })
+    }
+
+    /// Whether the file at `rel` (relative to the root) is counted.
+    pub fn matches(&self, rel: &Path) -> bool {
+        self.set.is_match(rel)
+    }
+
+    /// Whether the directory at `rel` may hold a matching file.
+    pub fn may_contain(&self, rel: &Path) -> bool {
+        let Some(prefixes) = &self.prefixes else {
+            return true;
+        };
+        let parts: Vec<&str> = rel
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(part) => part.to_str(),
+                _ => None,
+            })
+            .collect();
+        prefixes
+            .iter()
+            .any(|prefix| parts.iter().zip(prefix).all(|(a, b)| a == b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prunes_directories_outside_the_literal_prefix() {
+        let only = Only::new(&["src/**/*.rs".to_string(), "docs/api/*.md".to_string()]).unwrap();
+        assert!(only.matches(Path::new("src/a/b.rs")));
+        assert!(!only.matches(Path::new("src/a/b.md")));
+        assert!(!only.matches(Path::new("docs/api/x/y.md")));
+
+        for dir in ["", "src", "src/deep/er", "docs", "docs/api", "docs/api/x"] {
+            assert!(only.may_contain(Path::new(dir)), "{dir}");
+        }
+        for dir in ["tests", "docs/guide", "srcx"] {
+            assert!(!only.may_contain(Path::new(dir)), "{dir}");
+        }
+
+        let anywhere = Only::new(&["src/*.rs".to_string(), "**/*.toml".to_string()]).unwrap();
+        assert!(anywhere.may_contain(Path::new("vendor/x")));
+        assert!(Only::new(&["a/[".to_string()]).is_err());
+    }
+}
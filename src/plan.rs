@@ -0,0 +1,269 @@
+//! `--dry-run` without `--clean`: what a scan with the same options would
+//! walk, to check the filters before a long scan. The scan's own walker and
+//! filters list the tree, but no file is stat'ed or opened: the entries'
+//! types come from the directory listings. Each entry directly in the root
+//! is either scanned, with the files and directories below it, or skipped,
+//! with the rule that skips it.
+//!
+//! The file counts are an estimate: they leave nothing out for per-file
+//! checks the walk can't make without a stat, such as `--only-mine`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use ignore::WalkState;
+use tracing::warn;
+
+use crate::config::Config;
+use crate::format::format_num;
+use crate::mounts;
+use crate::report::{Style, print_table};
+use crate::scan::walk_builder;
+use crate::submodules::{self, Submodules};
+use crate::vfs::OsFs;
+
+/// Files and directories found below one entry of the root.
+#[derive(Debug, Default, Clone, Copy)]
+struct Count {
+    files: u64,
+    dirs: u64,
+}
+
+/// One walker thread's counts, merged into `out` when its visitor is
+/// dropped, as `scan::EntryCounter` does.
+struct Counter<'a> {
+    counts: HashMap<PathBuf, Count>,
+    out: &'a Mutex<HashMap<PathBuf, Count>>,
+}
+
+impl Drop for Counter<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut out) = self.out.lock() {
+            for (top, n) in self.counts.drain() {
+                let total = out.entry(top).or_default();
+                total.files += n.files;
+                total.dirs += n.dirs;
+            }
+        }
+    }
+}
+
+pub fn run(config: &Config) -> io::Result<()> {
+    let root = &config.root;
+    let style = Style::new(config);
+    let virtual_mounts = if config.virtual_fs {
+        Vec::new()
+    } else {
+        mounts::virtual_below(root)
+    };
+    let submodules = if config.submodules == submodules::Mode::Exclude {
+        Submodules::load(&OsFs, root)
+    } else {
+        Submodules::default()
+    };
+
+    // What the walk keeps directly in the root, through every rule.
+    let mut top = walk_builder(root, config);
+    top.max_depth(Some(1));
+    let kept: HashSet<PathBuf> = top
+        .build()
+        .flatten()
+        .filter(|dent| dent.depth() == 1)
+        .map(|dent| dent.into_path())
+        .collect();
+
+    let counts = Mutex::new(HashMap::new());
+    let unreadable = Mutex::new(0u64);
+    walk_builder(root, config)
+        .threads(config.threads)
+        .build_parallel()
+        .run(|| {
+            let mut counter = Counter {
+                counts: HashMap::new(),
+                out: &counts,
+            };
+            let unreadable = &unreadable;
+            Box::new(move |result| {
+                let dent = match result {
+                    Ok(dent) => dent,
+                    Err(err) => {
+                        warn!("{}", err);
+                        if let Ok(mut n) = unreadable.lock() {
+                            *n += 1;
+                        }
+                        return WalkState::Continue;
+                    }
+                };
+                let Ok(rel) = dent.path().strip_prefix(root) else {
+                    return WalkState::Continue;
+                };
+                let Some(first) = rel.components().next() else {
+                    return WalkState::Continue;
+                };
+                let is_dir = dent.file_type().is_some_and(|t| t.is_dir());
+                let is_file = dent.file_type().is_some_and(|t| t.is_file());
+                // Files directly in the root count under the root itself.
+                let key = if dent.depth() == 1 && !is_dir {
+                    root.clone()
+                } else {
+                    root.join(first)
+                };
+                let count = counter.counts.entry(key).or_default();
+                if is_dir {
+                    count.dirs += 1;
+                } else if is_file {
+                    count.files += 1;
+                }
+                WalkState::Continue
+            })
+        });
+    let counts = counts.into_inner().unwrap_or_else(|e| e.into_inner());
+    let unreadable = unreadable.into_inner().unwrap_or_else(|e| e.into_inner());
+
+    let mut scanned: Vec<(PathBuf, Count)> = Vec::new();
+    let mut skipped: Vec<(PathBuf, String)> = Vec::new();
+    let mut total = counts.get(root).copied().unwrap_or_default();
+    let mut listing: Vec<_> = fs::read_dir(root)?.flatten().collect();
+    listing.sort_by_key(|entry| entry.file_name());
+    for entry in listing {
+        let path = entry.path();
+        let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+        if !is_dir {
+            continue;
+        }
+        if kept.contains(&path) && submodules.of(&path).is_none() {
+            let count = counts.get(&path).copied().unwrap_or_default();
+            total.files += count.files;
+            total.dirs += count.dirs;
+            scanned.push((path, count));
+        } else {
+            let why = reason(config, &path, &virtual_mounts, &submodules);
+            skipped.push((path, why));
+        }
+    }
+    scanned.sort_by(|a, b| b.1.files.cmp(&a.1.files).then_with(|| a.0.cmp(&b.0)));
+
+    println!(
+        "{}",
+        style.label(&format!(
+            "Dry run: {} (no file stat'ed or read)",
+            root.display()
+        ))
+    );
+    println!("  {}", style.value(&filters(config)));
+    println!();
+    let name = |path: &Path| {
+        path.strip_prefix(root).map_or_else(
+            |_| path.display().to_string(),
+            |rel| rel.display().to_string(),
+        ) + "/"
+    };
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    if let Some(count) = counts.get(root) {
+        rows.push(vec![
+            format_num(count.files),
+            "-".to_string(),
+            "(files in the root)".to_string(),
+        ]);
+    }
+    for (path, count) in &scanned {
+        rows.push(vec![
+            format_num(count.files),
+            format_num(count.dirs),
+            name(path),
+        ]);
+    }
+    for (path, why) in &skipped {
+        rows.push(vec![
+            "-".to_string(),
+            "-".to_string(),
+            format!("{} (skipped: {})", name(path), why),
+        ]);
+    }
+    print_table(
+        &style,
+        "Top-level subtrees",
+        &["Files", "Dirs", "Subtree"],
+        &rows,
+    );
+    println!();
+    println!(
+        "{}",
+        style.label(&format!(
+            "About {} files in {} directories would be scanned",
+            format_num(total.files),
+            format_num(total.dirs + 1)
+        ))
+    );
+    if unreadable > 0 {
+        println!(
+            "  {}",
+            style.value(&format!(
+                "{} entries couldn't be listed (see --log-level warn)",
+                format_num(unreadable)
+            ))
+        );
+    }
+    Ok(())
+}
+
+/// The rules in effect, on one line.
+fn filters(config: &Config) -> String {
+    let mut rules = Vec::new();
+    if !config.no_ignore {
+        rules.push(".gitignore/.ignore rules".to_string());
+    }
+    if !config.hidden {
+        rules.push("hidden entries skipped".to_string());
+    }
+    if !config.exclude_dirs.is_empty() {
+        rules.push(format!("--exclude-dir {}", config.exclude_dirs.join(", ")));
+    }
+    if config.only.is_some() {
+        rules.push("--only globs".to_string());
+    }
+    if !config.virtual_fs {
+        rules.push("virtual filesystems skipped".to_string());
+    }
+    if config.submodules == submodules::Mode::Exclude {
+        rules.push("submodules skipped".to_string());
+    }
+    if config.follow_links.is_some() {
+        rules.push("symlinks followed".to_string());
+    }
+    if rules.is_empty() {
+        "Filters: none".to_string()
+    } else {
+        format!("Filters: {}", rules.join(", "))
+    }
+}
+
+/// Why the walk leaves out the directory at `path`, directly in the root.
+fn reason(
+    config: &Config,
+    path: &Path,
+    virtual_mounts: &[(PathBuf, String)],
+    submodules: &Submodules,
+) -> String {
+    if let Some((_, fstype)) = virtual_mounts.iter().find(|(m, _)| m == path) {
+        return format!("virtual filesystem ({})", fstype);
+    }
+    if submodules.of(path).is_some() {
+        return "submodule".to_string();
+    }
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    if name.starts_with('.') && !config.hidden && !config.keeps(path, true) {
+        return "hidden".to_string();
+    }
+    if config.exclude_dirs.iter().any(|d| d == name) {
+        return "--exclude-dir".to_string();
+    }
+    if !config.keeps(path, true) {
+        return "no --only glob can match".to_string();
+    }
+    ".gitignore or .ignore".to_string()
+}
@@ -0,0 +1,155 @@
+//! `--plugin`: shared libraries that compute a custom per-file metric.
+//!
+//! A plugin exports the functions declared in `include/tengok_plugin.h`.
+//! For each file, `begin` gets the path and returns per-file state (or NULL
+//! to skip the file), `chunk` is fed the contents in order, and `end` frees
+//! the state and returns the file's value. Values are summed per plugin.
+//! Walker threads call in concurrently, each with its own state.
+
+use std::{
+    ffi::{CString, c_char, c_int, c_void},
+    fmt,
+    io::{self, Read},
+    path::Path,
+};
+
+use crate::vfs::FileSystem;
+
+/// Bumped whenever the exported signatures change.
+pub const ABI_VERSION: u32 = 1;
+
+const CHUNK_BYTES: usize = 64 * 1024;
+
+type BeginFn = unsafe extern "C" fn(path: *const c_char) -> *mut c_void;
+type ChunkFn = unsafe extern "C" fn(state: *mut c_void, data: *const u8, len: usize) -> c_int;
+type EndFn = unsafe extern "C" fn(state: *mut c_void) -> u64;
+
+pub struct Plugin {
+    pub name: String,
+    begin: BeginFn,
+    chunk: ChunkFn,
+    end: EndFn,
+    // Keeps the function pointers above valid.
+    #[cfg(not(target_os = "wasi"))]
+    _lib: Option<libloading::Library>,
+}
+
+impl fmt::Debug for Plugin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Plugin").field("name", &self.name).finish()
+    }
+}
+
+impl Plugin {
+    /// Load the library at `path` and check its ABI version.
+    #[cfg(not(target_os = "wasi"))]
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let err = |e: libloading::Error| format!("Unable to load plugin {}: {}", path.display(), e);
+        // SAFETY: loading a library runs its initialisers; `--plugin` is an
+        // explicit request to trust it.
+        let lib = unsafe { libloading::Library::new(path) }.map_err(err)?;
+        // SAFETY: the symbol types match `include/tengok_plugin.h`, and the
+        // copied pointers live as long as `lib`, which `Plugin` owns.
+        unsafe {
+            let abi: unsafe extern "C" fn() -> u32 = *lib.get(b"tengok_plugin_abi").map_err(err)?;
+            if abi() != ABI_VERSION {
+                return Err(format!(
+                    "Plugin {} targets ABI {}, this tengok needs {}",
+                    path.display(),
+                    abi(),
+                    ABI_VERSION
+                ));
+            }
+            let name: unsafe extern "C" fn() -> *const c_char =
+                *lib.get(b"tengok_plugin_name").map_err(err)?;
+            let name = std::ffi::CStr::from_ptr(name())
+                .to_string_lossy()
+                .into_owned();
+            Ok(Self {
+                name,
+                begin: *lib.get(b"tengok_plugin_begin").map_err(err)?,
+                chunk: *lib.get(b"tengok_plugin_chunk").map_err(err)?,
+                end: *lib.get(b"tengok_plugin_end").map_err(err)?,
+                _lib: Some(lib),
+            })
+        }
+    }
+
+    #[cfg(target_os = "wasi")]
+    pub fn load(_: &Path) -> Result<Self, String> {
+        Err("--plugin needs a host that can load shared libraries".to_string())
+    }
+
+    /// Stream `path` through the plugin; 0 when it skips the file.
+    pub fn measure(&self, fs: &dyn FileSystem, path: &Path, buf: &mut Vec<u8>) -> io::Result<u64> {
+        let c_path = CString::new(path.to_string_lossy().into_owned())
+            .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        // SAFETY: the plugin contract; `c_path` outlives the call.
+        let state = unsafe { (self.begin)(c_path.as_ptr()) };
+        if state.is_null() {
+            return Ok(0);
+        }
+        // `end` must run even when reading fails, or the state leaks.
+        let streamed = fs.open(path).and_then(|mut file| {
+            buf.resize(CHUNK_BYTES, 0);
+            loop {
+                let n = file.read(buf)?;
+                // SAFETY: `buf[..n]` is initialised and outlives the call.
+                if n == 0 || unsafe { (self.chunk)(state, buf.as_ptr(), n) } != 0 {
+                    return Ok(());
+                }
+            }
+        });
+        // SAFETY: `state` came from `begin` and is not used afterwards.
+        let value = unsafe { (self.end)(state) };
+        streamed.map(|()| value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemFs;
+
+    // A plugin counting `x` bytes in files under `src/`, read to the first `!`.
+    unsafe extern "C" fn begin(path: *const c_char) -> *mut c_void {
+        let path = unsafe { std::ffi::CStr::from_ptr(path) }.to_string_lossy();
+        if !path.starts_with("src/") {
+            return std::ptr::null_mut();
+        }
+        Box::into_raw(Box::new(0u64)).cast()
+    }
+
+    unsafe extern "C" fn chunk(state: *mut c_void, data: *const u8, len: usize) -> c_int {
+        let count = unsafe { &mut *state.cast::<u64>() };
+        let data = unsafe { std::slice::from_raw_parts(data, len) };
+        let end = data.iter().position(|&b| b == b'!').unwrap_or(len);
+        *count += data[..end].iter().filter(|&&b| b == b'x').count() as u64;
+        c_int::from(end < len)
+    }
+
+    unsafe extern "C" fn end(state: *mut c_void) -> u64 {
+        *unsafe { Box::from_raw(state.cast::<u64>()) }
+    }
+
+    #[test]
+    fn measure_streams_contents_until_the_plugin_stops() {
+        let plugin = Plugin {
+            name: "x".to_string(),
+            begin,
+            chunk,
+            end,
+            #[cfg(not(target_os = "wasi"))]
+            _lib: None,
+        };
+        let mut fs = MemFs::default();
+        fs.insert("src/a", "x".repeat(CHUNK_BYTES + 3));
+        fs.insert("src/b", "xx!xx");
+        fs.insert("doc/c", "xxx");
+        let mut buf = Vec::new();
+        let measure = |p: &str, buf: &mut Vec<u8>| plugin.measure(&fs, Path::new(p), buf).unwrap();
+        assert_eq!(measure("src/a", &mut buf), CHUNK_BYTES as u64 + 3);
+        assert_eq!(measure("src/b", &mut buf), 2);
+        assert_eq!(measure("doc/c", &mut buf), 0);
+    }
+}
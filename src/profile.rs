@@ -0,0 +1,147 @@
+//! `--profile`: where a scan's time went, per walker thread. Traversal is
+//! the walker's own work between files (reading directories, applying
+//! ignore rules, waiting for work); metadata is the `stat` of each file;
+//! lines is line counting; checks are the other per-file reads (language,
+//! MIME, comments, plugins and the like). Aggregation happens on the
+//! collecting thread, folding records into the summary.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Traversal,
+    Metadata,
+    Lines,
+    Checks,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 4] = [
+        Phase::Traversal,
+        Phase::Metadata,
+        Phase::Lines,
+        Phase::Checks,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::Traversal => "Traversal",
+            Phase::Metadata => "Metadata",
+            Phase::Lines => "Lines",
+            Phase::Checks => "Checks",
+        }
+    }
+}
+
+/// Time one thread spent per phase, and the files it read.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timings {
+    phases: [Duration; Phase::ALL.len()],
+    pub files: u64,
+}
+
+impl Timings {
+    pub fn add(&mut self, phase: Phase, time: Duration) {
+        self.phases[phase as usize] += time;
+    }
+
+    pub fn get(&self, phase: Phase) -> Duration {
+        self.phases[phase as usize]
+    }
+
+    pub fn total(&self) -> Duration {
+        self.phases.iter().sum()
+    }
+
+    /// Every thread's timings added up.
+    pub fn sum<'a>(all: impl IntoIterator<Item = &'a Timings>) -> Timings {
+        all.into_iter().fold(Timings::default(), |mut acc, t| {
+            for phase in Phase::ALL {
+                acc.add(phase, t.get(phase));
+            }
+            acc.files += t.files;
+            acc
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    /// One entry per walker thread that read any files, in no particular
+    /// order.
+    pub threads: Vec<Timings>,
+    pub aggregation: Duration,
+    /// Start of the walk to the finished summary.
+    pub wall: Duration,
+}
+
+/// A walker thread's clock. Time between `lap`s goes to a phase; the
+/// timings are handed to `out` when the thread's visitor is dropped at the
+/// end of the walk. The walker also makes a visitor to start the walk
+/// with, which never reads a file and is left out.
+pub struct ThreadTimer<'a> {
+    pub timings: Timings,
+    mark: Instant,
+    out: &'a Mutex<Vec<Timings>>,
+}
+
+impl<'a> ThreadTimer<'a> {
+    pub fn new(out: &'a Mutex<Vec<Timings>>) -> Self {
+        ThreadTimer {
+            timings: Timings::default(),
+            mark: Instant::now(),
+            out,
+        }
+    }
+
+    /// Charge the time since the last lap to `phase`.
+    pub fn lap(&mut self, phase: Phase) {
+        let now = Instant::now();
+        self.timings.add(phase, now - self.mark);
+        self.mark = now;
+    }
+
+    /// Restart the clock without charging anything, after time that was
+    /// accounted for elsewhere.
+    pub fn reset(&mut self) {
+        self.mark = Instant::now();
+    }
+}
+
+impl Drop for ThreadTimer<'_> {
+    fn drop(&mut self) {
+        if self.timings.files > 0
+            && let Ok(mut out) = self.out.lock()
+        {
+            out.push(self.timings);
+        }
+    }
+}
+
+/// `time` in milliseconds, the unit of every profile figure.
+pub fn millis(time: Duration) -> f64 {
+    time.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sums_threads_per_phase() {
+        let mut a = Timings::default();
+        a.add(Phase::Lines, Duration::from_millis(3));
+        a.files = 2;
+        let mut b = Timings::default();
+        b.add(Phase::Lines, Duration::from_millis(1));
+        b.add(Phase::Metadata, Duration::from_millis(2));
+        b.files = 1;
+        let all = Timings::sum([&a, &b]);
+        assert_eq!(all.get(Phase::Lines), Duration::from_millis(4));
+        assert_eq!(all.total(), Duration::from_millis(6));
+        assert_eq!(all.files, 3);
+    }
+}
@@ -0,0 +1,134 @@
+//! The stderr status line drawn during a scan of the box report: files and
+//! bytes so far, lines counted, throughput, and the directory being read, so
+//! a long scan shows whether it's stuck or just slow. It's redrawn at most
+//! every `--progress-interval` (default 80ms).
+//!
+//! When stderr isn't a terminal, or a CI service set its marker variable,
+//! carriage-return redraws would pile up in the log, so a plain status line
+//! is printed every 10 seconds instead.
+
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use humansize::{DECIMAL, format_size};
+
+use crate::config::Config;
+use crate::format::{display_relative_path, ellipsize_middle, format_num, glyphs};
+use crate::scan::FileRecord;
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(80);
+/// Between plain status lines.
+pub const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Set (to anything but `false` or `0`) by CI services.
+const CI_VARS: [&str; 5] = [
+    "CI",
+    "TF_BUILD",
+    "JENKINS_URL",
+    "BUILDKITE",
+    "TEAMCITY_VERSION",
+];
+
+const SPINNER_FRAMES: &[char] = &['-', '\\', '|', '/'];
+
+pub struct Progress {
+    /// Redraw one line in place rather than print a line per update.
+    animated: bool,
+    interval: Duration,
+    lines: bool,
+    started: Instant,
+    last_draw: Instant,
+    frame: usize,
+    files: u64,
+    bytes: u64,
+    counted_lines: u64,
+}
+
+impl Progress {
+    /// `None` when the config doesn't draw a status line.
+    pub fn new(config: &Config) -> Option<Self> {
+        if !config.show_progress() {
+            return None;
+        }
+        let now = Instant::now();
+        let animated = io::stderr().is_terminal() && !in_ci();
+        let default = if animated {
+            DEFAULT_INTERVAL
+        } else {
+            LOG_INTERVAL
+        };
+        Some(Progress {
+            animated,
+            interval: config.progress_interval.unwrap_or(default),
+            lines: !config.skip_lines,
+            started: now,
+            last_draw: now,
+            frame: 0,
+            files: 0,
+            bytes: 0,
+            counted_lines: 0,
+        })
+    }
+
+    pub fn update(&mut self, record: &FileRecord, root: &Path) {
+        self.files += 1;
+        self.bytes += record.size;
+        self.counted_lines += record.lines;
+        if self.last_draw.elapsed() < self.interval {
+            return;
+        }
+        self.last_draw = Instant::now();
+        let line = self.status(&display_relative_path(&record.parent, root));
+        let mut stderr = io::stderr();
+        if self.animated {
+            self.frame = (self.frame + 1) % SPINNER_FRAMES.len();
+            // Clear to the end of the line: the previous status may be longer.
+            let _ = write!(stderr, "\r{} {}\x1b[K", SPINNER_FRAMES[self.frame], line);
+        } else {
+            let _ = writeln!(stderr, "{}", line);
+        }
+        let _ = stderr.flush();
+    }
+
+    /// Ends the animated line, leaving the last status on screen.
+    pub fn finish(self) {
+        if self.animated {
+            let _ = writeln!(io::stderr());
+        }
+    }
+
+    fn status(&self, dir: &str) -> String {
+        let secs = self.started.elapsed().as_secs_f64().max(0.001);
+        let mut parts = vec![
+            format!("{} files", format_num(self.files)),
+            format_size(self.bytes, DECIMAL),
+        ];
+        if self.lines {
+            parts.push(format!("{} lines", format_num(self.counted_lines)));
+        }
+        parts.push(format!(
+            "{} files/s",
+            format_num((self.files as f64 / secs) as u64)
+        ));
+        parts.push(format!(
+            "{}/s",
+            format_size((self.bytes as f64 / secs) as u64, DECIMAL)
+        ));
+        let line = format!(
+            "Scanning… {} ({})",
+            parts.join(" · "),
+            ellipsize_middle(dir, 40)
+        );
+        glyphs(&line).into_owned()
+    }
+}
+
+fn in_ci() -> bool {
+    CI_VARS
+        .iter()
+        .any(|var| env::var(var).is_ok_and(|v| !v.is_empty() && v != "false" && v != "0"))
+}
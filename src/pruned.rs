@@ -0,0 +1,145 @@
+//! `--profile-ignores`: which ignore rules pruned the most. A second,
+//! serial pass walks what the scan's ignore rules skipped and charges each
+//! skipped entry, with everything below it, to the rule that matched it:
+//! the deepest `.gitignore` or `.ignore` file wins, as in git. Counting an
+//! ignored `target/` means walking it, so this costs a walk of the skipped
+//! trees on top of the scan.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ignore::{Match, gitignore::Gitignore};
+use tracing::debug;
+
+use crate::config::{Config, DEFAULT_TOP};
+use crate::scan::load_ignores;
+use crate::vfs::FileSystem;
+
+/// What one rule kept out of the scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleStat {
+    /// The ignore file the rule is in.
+    pub source: PathBuf,
+    /// As written in that file.
+    pub pattern: String,
+    /// Entries the rule matched directly; a pruned directory is one.
+    pub matched: u64,
+    /// Files below those entries, and their bytes.
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// The `--top` (default 10) rules that pruned the most bytes.
+pub fn profile(fs: &dyn FileSystem, config: &Config) -> Vec<RuleStat> {
+    let mut rules: HashMap<(PathBuf, String), RuleStat> = HashMap::new();
+    let mut pending: Vec<(PathBuf, Vec<Arc<Gitignore>>)> = vec![(config.root.clone(), Vec::new())];
+    while let Some((dir, mut ignores)) = pending.pop() {
+        ignores.extend(load_ignores(fs, &dir));
+        let Ok(entries) = fs.read_dir(&dir) else {
+            continue;
+        };
+        for path in entries {
+            let Ok(meta) = fs.metadata(&path) else {
+                continue;
+            };
+            let hidden = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with('.'));
+            if (hidden && !config.walks_hidden()) || !config.keeps(&path, meta.is_dir) {
+                continue;
+            }
+            let rule = ignores
+                .iter()
+                .rev()
+                .find_map(|ignore| match ignore.matched(&path, meta.is_dir) {
+                    Match::Ignore(glob) => Some(Some(glob)),
+                    Match::Whitelist(_) => Some(None),
+                    Match::None => None,
+                })
+                .flatten();
+            match rule {
+                Some(glob) => {
+                    let source = glob.from().map(Path::to_path_buf).unwrap_or_default();
+                    let key = (source.clone(), glob.original().to_string());
+                    let stat = rules.entry(key).or_insert_with(|| RuleStat {
+                        source,
+                        pattern: glob.original().to_string(),
+                        matched: 0,
+                        files: 0,
+                        bytes: 0,
+                    });
+                    stat.matched += 1;
+                    let (files, bytes) = if meta.is_dir {
+                        measure(fs, &path)
+                    } else {
+                        (u64::from(meta.is_file), meta.len)
+                    };
+                    stat.files += files;
+                    stat.bytes += bytes;
+                }
+                None if meta.is_dir => pending.push((path, ignores.clone())),
+                None => {}
+            }
+        }
+    }
+    debug!(rules = rules.len(), "ignore rules profiled");
+
+    let mut rules: Vec<RuleStat> = rules.into_values().collect();
+    rules.sort_by(|a, b| {
+        b.bytes
+            .cmp(&a.bytes)
+            .then(b.files.cmp(&a.files))
+            .then_with(|| (&a.source, &a.pattern).cmp(&(&b.source, &b.pattern)))
+    });
+    rules.truncate(config.top.unwrap_or(DEFAULT_TOP));
+    rules
+}
+
+/// Files and bytes anywhere below `dir`.
+fn measure(fs: &dyn FileSystem, dir: &Path) -> (u64, u64) {
+    let (mut files, mut bytes) = (0, 0);
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for path in fs.read_dir(&dir).unwrap_or_default() {
+            match fs.metadata(&path) {
+                Ok(meta) if meta.is_dir => pending.push(path),
+                Ok(meta) if meta.is_file => {
+                    files += 1;
+                    bytes += meta.len;
+                }
+                _ => {}
+            }
+        }
+    }
+    (files, bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemFs;
+
+    #[test]
+    fn charges_pruned_trees_to_their_rule() {
+        let mut fs = MemFs::default();
+        fs.insert("r/.gitignore", "target/\n*.log\n");
+        fs.insert("r/src/main.rs", "fn main() {}\n");
+        fs.insert("r/target/debug/app", "0123456789");
+        fs.insert("r/target/debug/deps/lib.rlib", "01234");
+        fs.insert("r/a.log", "x\n");
+        fs.insert("r/src/b.log", "yy\n");
+
+        let config = Config::from_args(["r", "--profile-ignores"].map(String::from)).unwrap();
+        let rules = profile(&fs, &config);
+        let summary: Vec<_> = rules
+            .iter()
+            .map(|r| (r.pattern.as_str(), r.matched, r.files, r.bytes))
+            .collect();
+        assert_eq!(summary, [("target/", 1, 2, 15), ("*.log", 2, 2, 5)]);
+        assert_eq!(rules[0].source, Path::new("r/.gitignore"));
+    }
+}
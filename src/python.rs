@@ -0,0 +1,212 @@
+//! `import tengok`: PyO3 bindings over the same scanner the CLI runs.
+//!
+//! Keyword options are the CLI's long flags with `_` for `-`, so
+//! `scan(".", by_ext=True, max_line_bytes="5M")` is `tengok . --by-ext
+//! --max-line-bytes 5M`; `True` passes a switch, `False`/`None` leave it off.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::UNIX_EPOCH,
+};
+
+use crossbeam_channel::{Receiver, bounded};
+use pyo3::{
+    exceptions::{PyFileNotFoundError, PyValueError},
+    prelude::*,
+    types::{PyBool, PyDict},
+};
+
+use crate::config::{Config, Output};
+use crate::format::display_relative_path;
+use crate::json;
+use crate::linguist::Kind;
+use crate::scan::{self, FileRecord, extension_key};
+
+/// Records buffered between the walker threads and a slow Python consumer.
+const ITER_BUFFER: usize = 1024;
+
+fn config_from(path: PathBuf, options: Option<&Bound<'_, PyDict>>) -> PyResult<Arc<Config>> {
+    let mut args = vec![path.to_string_lossy().into_owned()];
+    for (key, value) in options.into_iter().flat_map(|o| o.iter()) {
+        let flag = format!("--{}", key.extract::<String>()?.replace('_', "-"));
+        if value.is_none() {
+            continue;
+        }
+        if value.is_instance_of::<PyBool>() {
+            if value.is_truthy()? {
+                args.push(flag);
+            }
+            continue;
+        }
+        args.push(flag);
+        args.push(value.str()?.to_string());
+    }
+    let mut config = Config::from_args(args).map_err(PyValueError::new_err)?;
+    if !config.root.exists() {
+        return Err(PyFileNotFoundError::new_err(format!(
+            "Path does not exist: {}",
+            config.root.display()
+        )));
+    }
+    // Never draw the spinner into a notebook or a script's stderr.
+    config.output = Output::Json;
+    Ok(Arc::new(config))
+}
+
+/// Totals of one scan; `to_dict()` has the exact shape of `tengok --json`.
+#[pyclass(name = "Summary", module = "tengok", frozen)]
+struct PySummary {
+    config: Arc<Config>,
+    summary: scan::Summary,
+}
+
+#[pymethods]
+impl PySummary {
+    #[getter]
+    fn root(&self) -> String {
+        self.config.root.display().to_string()
+    }
+
+    #[getter]
+    fn files(&self) -> u64 {
+        self.summary.total_files
+    }
+
+    #[getter]
+    fn size_bytes(&self) -> u64 {
+        self.summary.total_size
+    }
+
+    #[getter]
+    fn lines(&self) -> u64 {
+        self.summary.total_lines
+    }
+
+    fn to_json(&self) -> String {
+        json::to_value(&self.config, &self.summary).to_string()
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        py.import("json")?.call_method1("loads", (self.to_json(),))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Summary(root={:?}, files={}, size_bytes={}, lines={})",
+            self.root(),
+            self.files(),
+            self.size_bytes(),
+            self.lines()
+        )
+    }
+}
+
+/// One scanned file; the same columns as `--parquet`, plus `matches`.
+#[pyclass(name = "FileRecord", module = "tengok", frozen, get_all)]
+struct PyFileRecord {
+    path: String,
+    dir: String,
+    ext: String,
+    size_bytes: u64,
+    lines: u64,
+    /// Seconds since the Unix epoch.
+    mtime: Option<f64>,
+    language: Option<String>,
+    content_type: Option<String>,
+    linguist: Option<String>,
+    matches: u64,
+}
+
+impl PyFileRecord {
+    fn new(record: &FileRecord, root: &Path) -> Self {
+        Self {
+            path: display_relative_path(&record.path, root),
+            dir: display_relative_path(&record.parent, root),
+            ext: extension_key(&record.path),
+            size_bytes: record.size,
+            lines: record.lines,
+            mtime: record
+                .mtime
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs_f64()),
+            language: record.lang.map(String::from),
+            content_type: record.category.map(String::from),
+            linguist: record.kind.map(|k| {
+                match k {
+                    Kind::Generated => "generated",
+                    Kind::Vendored => "vendored",
+                }
+                .to_string()
+            }),
+            matches: record.matches,
+        }
+    }
+}
+
+#[pymethods]
+impl PyFileRecord {
+    fn __repr__(&self) -> String {
+        format!(
+            "FileRecord(path={:?}, size_bytes={}, lines={})",
+            self.path, self.size_bytes, self.lines
+        )
+    }
+}
+
+/// Records as the walker threads produce them, in no particular order.
+/// Dropping the iterator stops the walk.
+#[pyclass(name = "FileIter", module = "tengok")]
+struct FileIter {
+    root: PathBuf,
+    rx: Receiver<FileRecord>,
+}
+
+#[pymethods]
+impl FileIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&self, py: Python<'_>) -> Option<PyFileRecord> {
+        let rx = &self.rx;
+        py.detach(|| rx.recv().ok())
+            .map(|record| PyFileRecord::new(&record, &self.root))
+    }
+}
+
+/// Scan `path` and return its summary.
+#[pyfunction(name = "scan")]
+#[pyo3(signature = (path = PathBuf::from("."), **options))]
+fn scan_py(
+    py: Python<'_>,
+    path: PathBuf,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PySummary> {
+    let config = config_from(path, options)?;
+    let summary = py.detach(|| scan::scan_dir(&config))?;
+    Ok(PySummary { config, summary })
+}
+
+/// Iterate over the file records of `path` while the scan runs.
+#[pyfunction(name = "files")]
+#[pyo3(signature = (path = PathBuf::from("."), **options))]
+fn files_py(path: PathBuf, options: Option<&Bound<'_, PyDict>>) -> PyResult<FileIter> {
+    let config = config_from(path, options)?;
+    let (tx, rx) = bounded(ITER_BUFFER);
+    let root = config.root.clone();
+    thread::spawn(move || scan::walk_records(&config, tx));
+    Ok(FileIter { root, rx })
+}
+
+#[pymodule]
+fn tengok(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add_function(wrap_pyfunction!(scan_py, m)?)?;
+    m.add_function(wrap_pyfunction!(files_py, m)?)?;
+    m.add_class::<PySummary>()?;
+    m.add_class::<PyFileRecord>()?;
+    m.add_class::<FileIter>()?;
+    Ok(())
+}
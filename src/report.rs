@@ -0,0 +1,2187 @@
+use std::{
+    io::{self, Write},
+    time::UNIX_EPOCH,
+};
+
+use owo_colors::OwoColorize;
+
+use crate::baseline;
+use crate::budget::{self, Unit};
+use crate::comments;
+use crate::config::{Config, Row};
+use crate::format::{
+    display_relative_path, ellipsize_middle, format_duration, format_num, glyphs, pad_left,
+    pad_right, truncate, width,
+};
+use crate::git::Part;
+use crate::gitignore;
+use crate::hints;
+use crate::i18n::Msg;
+use crate::lists;
+use crate::names::{self, Issue};
+use crate::profile::{self, Phase, Profile, Timings};
+use crate::scan::{GroupStat, Summary};
+use crate::sketch::{PERCENTILES, Sketch};
+use crate::submodules;
+use crate::theme::Theme;
+
+/// Columns of the attached terminal, if there is one we can ask.
+#[cfg(not(target_os = "wasi"))]
+pub fn terminal_width() -> Option<usize> {
+    crossterm::terminal::size()
+        .ok()
+        .map(|(cols, _)| cols as usize)
+}
+
+#[cfg(target_os = "wasi")]
+pub fn terminal_width() -> Option<usize> {
+    None
+}
+
+/// Color helpers shared by the box report and the list tables. Borders,
+/// labels and values also come out in ASCII with `--ascii`.
+pub struct Style {
+    plain: bool,
+    theme: Theme,
+    heat: Option<(u64, u64)>,
+}
+
+impl Style {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            plain: config.plain,
+            theme: config.theme,
+            heat: config.heat,
+        }
+    }
+
+    pub fn border(&self, s: &str) -> String {
+        let s = glyphs(s);
+        if self.plain {
+            s.into_owned()
+        } else {
+            format!("{}", s.color(self.theme.border))
+        }
+    }
+
+    pub fn label(&self, s: &str) -> String {
+        let s = glyphs(s);
+        if self.plain {
+            s.into_owned()
+        } else {
+            format!("{}", s.color(self.theme.label))
+        }
+    }
+
+    pub fn value(&self, s: &str) -> String {
+        let s = glyphs(s);
+        if self.plain {
+            s.into_owned()
+        } else {
+            format!("{}", s.color(self.theme.value))
+        }
+    }
+
+    /// A value showing `bytes`, if it shows a size: with `--heat`, colored
+    /// warm or hot by them.
+    pub fn sized(&self, s: &str, bytes: Option<u64>) -> String {
+        let color = match self.heat.zip(bytes) {
+            Some(((_, hot), bytes)) if bytes >= hot => self.theme.hot,
+            Some(((warm, _), bytes)) if bytes >= warm => self.theme.warm,
+            _ => self.theme.value,
+        };
+        if self.plain {
+            s.to_string()
+        } else {
+            format!("{}", s.color(color))
+        }
+    }
+}
+
+/// A `print_table` cell: its text, and the bytes `--heat` colors it by when
+/// it shows a size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    text: String,
+    bytes: Option<u64>,
+}
+
+impl Cell {
+    /// `bytes` as `config` writes sizes.
+    pub fn size(config: &Config, bytes: u64) -> Self {
+        Self {
+            text: config.size(bytes),
+            bytes: Some(bytes),
+        }
+    }
+
+    /// This cell, colored by `bytes` with `--heat`.
+    pub fn with_bytes(self, bytes: u64) -> Self {
+        Self {
+            bytes: Some(bytes),
+            ..self
+        }
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Self { text, bytes: None }
+    }
+}
+
+/// What `print_table` needs of a cell; plain `String`s never get heat.
+pub trait TableCell {
+    fn text(&self) -> &str;
+
+    fn bytes(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl TableCell for String {
+    fn text(&self) -> &str {
+        self
+    }
+}
+
+impl TableCell for Cell {
+    fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn bytes(&self) -> Option<u64> {
+        self.bytes
+    }
+}
+
+// Layout: label + spacing + value widths add up to inner width.
+// The box grows to fit the widest value (typically the max-line file),
+// but never exceeds the current terminal width; long values are then
+// ellipsized in the middle to stay on a single row.
+const LABEL_WIDTH: usize = 6;
+const MIN_VALUE_WIDTH: usize = 24;
+const MAX_VALUE_WIDTH: usize = 96;
+/// Terminals at least this wide get the two-column report.
+const WIDE_COLS: usize = 120;
+
+/// A box row: its label, its value, and the bytes `--heat` colors the value
+/// by when it shows a size.
+type BoxRow = (&'static str, String, Option<u64>);
+
+pub fn print_report(config: &Config, summary: &Summary) {
+    let _ = write_report(&mut io::stdout().lock(), config, summary);
+}
+
+/// The box report and its hints, written to `out`: stdout, or stderr for
+/// `--human-stderr`.
+pub fn write_report(out: &mut impl Write, config: &Config, summary: &Summary) -> io::Result<()> {
+    let style = Style::new(config);
+    let t = |msg| config.lang.get(msg);
+    let title = config
+        .title
+        .clone()
+        .unwrap_or_else(|| format!("{}: {}", t(Msg::FolderSummary), config.root.display()));
+    let base = config.baseline.as_ref();
+    let size_human = with_delta(
+        config.size(summary.total_size),
+        base.map(|b| baseline::delta(summary.total_size, b.size_bytes, |n| config.size(n))),
+    );
+    let files_value = format_num(summary.total_files);
+    let lines_value = format_num(summary.total_lines);
+    let files_value_with_unit = with_delta(
+        format!("{} {}", files_value, t(Msg::FilesUnit)),
+        base.map(|b| baseline::delta(summary.total_files, b.files, format_num)),
+    );
+    let lines_value_with_unit = with_delta(
+        format!("{} {}", lines_value, t(Msg::LinesUnit)),
+        base.map(|b| baseline::delta(summary.total_lines, b.lines, format_num)),
+    );
+
+    let (largest_dir_str, largest_dir_size) = match &summary.largest_dir {
+        Some((path, size)) => {
+            let size = config.size(*size);
+            let lines = summary.dirs.get(path).map_or(0, |d| d.lines);
+            (
+                display_relative_path(path, &config.root),
+                if config.skip_lines {
+                    size
+                } else {
+                    format!("{}, {} {}", size, format_num(lines), t(Msg::Lines))
+                },
+            )
+        }
+        None => ("-".to_string(), "-".to_string()),
+    };
+
+    let (max_file_path_raw, max_file_lines, max_file_size) = match &summary.max_lines_file {
+        Some(f) => (
+            display_relative_path(&f.path, &config.root),
+            f.lines,
+            config.size(f.size),
+        ),
+        None => ("-".to_string(), 0, "-".to_string()),
+    };
+
+    let busiest_dir_val = match &summary.busiest_dir {
+        Some((path, files)) => format!(
+            "{} ({} {})",
+            display_relative_path(path, &config.root),
+            format_num(*files),
+            t(Msg::Files)
+        ),
+        None => "-".to_string(),
+    };
+    let deepest_dir_val = match &summary.deepest_dir {
+        Some((path, depth)) => format!(
+            "{} ({} {})",
+            display_relative_path(path, &config.root),
+            t(Msg::Depth),
+            depth
+        ),
+        None => "-".to_string(),
+    };
+    let largest_dir_val = if largest_dir_str == "-" {
+        "-".to_string()
+    } else {
+        format!("{} ({})", largest_dir_str, largest_dir_size)
+    };
+    let max_file_val = if max_file_path_raw == "-" {
+        "-".to_string()
+    } else {
+        format!(
+            "{} ({} {}, {})",
+            max_file_path_raw,
+            format_num(max_file_lines),
+            t(Msg::Lines),
+            max_file_size
+        )
+    };
+
+    let largest_file_val = summary.largest_file.as_ref().map_or("-".to_string(), |f| {
+        format!(
+            "{} ({})",
+            display_relative_path(&f.path, &config.root),
+            config.size(f.size)
+        )
+    });
+
+    let avg_size_val = summary.average_size().map_or("-".to_string(), |avg| {
+        format!("{} {}", config.size(avg.round() as u64), t(Msg::PerFile))
+    });
+    let avg_lines_val = summary.average_lines().map_or("-".to_string(), |avg| {
+        format!(
+            "{} {}",
+            format_num(avg.round() as u64),
+            t(Msg::LinesPerFile)
+        )
+    });
+    let newest_val = summary
+        .newest_file
+        .as_ref()
+        .and_then(|f| Some((f, f.mtime?.duration_since(UNIX_EPOCH).ok()?)))
+        .map_or("-".to_string(), |(f, mtime)| {
+            format!(
+                "{} ({})",
+                display_relative_path(&f.path, &config.root),
+                config.time(mtime.as_secs())
+            )
+        });
+
+    // The bytes `--heat` colors a row by, for the rows showing a size.
+    let heat = |row: Row| match row {
+        Row::Size => Some(summary.total_size),
+        Row::LargestDir => summary.largest_dir.as_ref().map(|(_, size)| *size),
+        Row::MaxLinesFile => summary.max_lines_file.as_ref().map(|f| f.size),
+        Row::LargestFile => summary.largest_file.as_ref().map(|f| f.size),
+        Row::AvgSize => summary.average_size().map(|avg| avg.round() as u64),
+        _ => None,
+    };
+    let core: Vec<(Row, BoxRow)> = [
+        (Row::Files, "[F]", files_value_with_unit),
+        (Row::Size, "[B]", size_human),
+        (Row::Lines, "[L]", lines_value_with_unit),
+        (Row::LargestDir, "[D↑]", largest_dir_val),
+        (Row::BusiestDir, "[D#]", busiest_dir_val),
+        (Row::DeepestDir, "[D↓]", deepest_dir_val),
+        (Row::MaxLinesFile, "[L↑]", max_file_val),
+        (Row::LargestFile, "[B↑]", largest_file_val),
+        (Row::AvgSize, "[B/F]", avg_size_val),
+        (Row::AvgLines, "[L/F]", avg_lines_val),
+        (Row::NewestFile, "[new]", newest_val),
+    ]
+    .into_iter()
+    .filter(|(field, _, _)| config.shows(*field))
+    .map(|(field, label, value)| (field, (label, glyphs(&value).into_owned(), heat(field))))
+    .collect();
+
+    if let Some(cols) = terminal_width().filter(|&cols| cols >= WIDE_COLS) {
+        let (right, left): (Vec<_>, Vec<_>) = core.iter().cloned().partition(|(row, _)| {
+            matches!(
+                row,
+                Row::LargestDir
+                    | Row::BusiestDir
+                    | Row::DeepestDir
+                    | Row::MaxLinesFile
+                    | Row::LargestFile
+                    | Row::NewestFile
+            )
+        });
+        let mut left: Vec<BoxRow> = left.into_iter().map(|(_, row)| row).collect();
+        left.extend(extra_rows(config, summary));
+        let right: Vec<BoxRow> = right.into_iter().map(|(_, row)| row).collect();
+        if !left.is_empty() && !right.is_empty() {
+            write_wide_box(out, &style, config, &title, &left, &right, cols)?;
+            return write_footer(out, &style, config, summary);
+        }
+    }
+
+    let mut rows: Vec<BoxRow> = core.into_iter().map(|(_, row)| row).collect();
+    rows.extend(extra_rows(config, summary));
+
+    let mut value_width = rows
+        .iter()
+        .map(|(_, value, _)| width(value.as_str()))
+        .max()
+        .unwrap_or(0)
+        .clamp(MIN_VALUE_WIDTH, MAX_VALUE_WIDTH);
+
+    if let Some(cols) = terminal_width() {
+        let max_inner = cols.saturating_sub(3); // borders + spaces
+        if max_inner > LABEL_WIDTH + 3 {
+            let max_value = max_inner.saturating_sub(LABEL_WIDTH + 3);
+            if max_value > 0 {
+                value_width = value_width.min(max_value);
+            }
+        }
+    }
+
+    let inner_width = LABEL_WIDTH + 3 + value_width;
+
+    // Box borders
+    let horizontal_raw = "─".repeat(inner_width + 2);
+    let border = style.border(&horizontal_raw);
+    let top_left = style.border("┌");
+    let top_right = style.border("┐");
+    let bottom_left = style.border("└");
+    let bottom_right = style.border("┘");
+    let vert_symbol = style.border("│");
+    let divider = style.border("├");
+    let divider_right = style.border("┤");
+
+    // Utility to print a line with padding
+    let vert_left = vert_symbol.clone();
+    let vert_right = vert_symbol.clone();
+    let plain_mode = config.plain;
+    let print_line = move |out: &mut dyn Write, plain: &str, colored: String| {
+        let visible = width(plain);
+        let padding = inner_width.saturating_sub(visible);
+        let body = if plain_mode {
+            plain.to_string()
+        } else {
+            colored
+        };
+        writeln!(
+            out,
+            "{} {}{} {}",
+            vert_left,
+            body,
+            " ".repeat(padding),
+            vert_right
+        )
+    };
+
+    let format_row = |label: &str, value: &str| -> (String, String) {
+        let label_truncated = truncate(&glyphs(label), LABEL_WIDTH);
+        let label_fmt = pad_right(&label_truncated, LABEL_WIDTH);
+        let value_truncated = ellipsize_middle(value, value_width);
+        let value_fmt = pad_left(&value_truncated, value_width);
+        (label_fmt, value_fmt)
+    };
+
+    writeln!(out, "{}{}{}", top_left, border, top_right)?;
+
+    let title_plain = truncate(&title, inner_width);
+    let title_colored = style.value(&title_plain);
+    print_line(out, &title_plain, title_colored)?;
+    if let Some(note) = &config.note {
+        let note = truncate(note, inner_width);
+        print_line(out, &note, note.clone())?;
+    }
+    if config.metadata_only {
+        let label = truncate(t(Msg::MetadataOnly), inner_width);
+        print_line(out, &label, style.label(&label))?;
+    }
+
+    writeln!(out, "{}{}{}", divider, border, divider_right)?;
+    let row_plain_and_colored =
+        |out: &mut dyn Write, label: &str, value: &str, bytes: Option<u64>| {
+            let plain = format!("{}   {}", label, value);
+            let colored = format!("{}   {}", style.label(label), style.sized(value, bytes));
+            print_line(out, &plain, colored)
+        };
+
+    for (label, value, bytes) in &rows {
+        let (label_fmt, value_fmt) = format_row(label, value);
+        row_plain_and_colored(out, &label_fmt, &value_fmt, *bytes)?;
+    }
+
+    writeln!(out, "{}{}{}", bottom_left, border, bottom_right)?;
+    write_footer(out, &style, config, summary)
+}
+
+/// What goes under the box: the note on uncounted lines, then the hints.
+fn write_footer(
+    out: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    summary: &Summary,
+) -> io::Result<()> {
+    let uncounted = &summary.uncounted;
+    if uncounted.files > 0 {
+        let note = format!(
+            "line counts exclude {} {} totaling {} — use --force-lines to include",
+            format_num(uncounted.files),
+            if uncounted.files == 1 {
+                "file"
+            } else {
+                "files"
+            },
+            config.size(uncounted.size)
+        );
+        writeln!(out, "{} {}", style.label("note:"), glyphs(&note))?;
+    }
+    if !config.hints {
+        return Ok(());
+    }
+    for hint in hints::hints(config, summary) {
+        writeln!(out, "{} {}", style.label("hint:"), hint)?;
+    }
+    Ok(())
+}
+
+/// The report in two columns for wide terminals: counts on the left, the
+/// paths of the largest entries on the right, where they get whatever width
+/// the counts leave over.
+fn write_wide_box(
+    out: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    title: &str,
+    left: &[BoxRow],
+    right: &[BoxRow],
+    cols: usize,
+) -> io::Result<()> {
+    let widest = |rows: &[BoxRow]| {
+        rows.iter()
+            .map(|(_, value, _)| width(value.as_str()))
+            .max()
+            .unwrap_or(0)
+    };
+    // Borders and spacing: "│ " + left + " │ " + right + " │".
+    let spare = cols.saturating_sub(7 + 2 * (LABEL_WIDTH + 3) + MIN_VALUE_WIDTH);
+    let left_value = widest(left).clamp(MIN_VALUE_WIDTH, MAX_VALUE_WIDTH.min(spare));
+    let left_inner = LABEL_WIDTH + 3 + left_value;
+    let right_value = widest(right)
+        .max(MIN_VALUE_WIDTH)
+        .min(cols.saturating_sub(7 + left_inner + LABEL_WIDTH + 3));
+    let right_inner = LABEL_WIDTH + 3 + right_value;
+    let full_inner = left_inner + 3 + right_inner;
+
+    let cell = |row: Option<&BoxRow>, value_width: usize| -> String {
+        let Some((label, value, bytes)) = row else {
+            return " ".repeat(LABEL_WIDTH + 3 + value_width);
+        };
+        let label = pad_right(&truncate(&glyphs(label), LABEL_WIDTH), LABEL_WIDTH);
+        let value = ellipsize_middle(value, value_width);
+        let padding = value_width.saturating_sub(width(value.as_str()));
+        format!(
+            "{}   {}{}",
+            style.label(&label),
+            " ".repeat(padding),
+            style.sized(&value, *bytes)
+        )
+    };
+    let vert = style.border("│");
+    let print_full = |out: &mut dyn Write, plain: &str, colored: String| {
+        let padding = full_inner.saturating_sub(width(plain));
+        writeln!(out, "{} {}{} {}", vert, colored, " ".repeat(padding), vert)
+    };
+    let rule = |width: usize| "─".repeat(width + 2);
+
+    writeln!(out, "{}", style.border(&format!("┌{}┐", rule(full_inner))))?;
+    let title = truncate(title, full_inner);
+    print_full(out, &title, style.value(&title))?;
+    if let Some(note) = &config.note {
+        let note = truncate(note, full_inner);
+        print_full(out, &note, note.clone())?;
+    }
+    if config.metadata_only {
+        let label = truncate(config.lang.get(Msg::MetadataOnly), full_inner);
+        print_full(out, &label, style.label(&label))?;
+    }
+    writeln!(
+        out,
+        "{}",
+        style.border(&format!("├{}┬{}┤", rule(left_inner), rule(right_inner)))
+    )?;
+    for i in 0..left.len().max(right.len()) {
+        writeln!(
+            out,
+            "{} {} {} {} {}",
+            vert,
+            cell(left.get(i), left_value),
+            vert,
+            cell(right.get(i), right_value),
+            vert
+        )?;
+    }
+    writeln!(
+        out,
+        "{}",
+        style.border(&format!("└{}┴{}┘", rule(left_inner), rule(right_inner)))
+    )
+}
+
+/// `value (+delta)` when there is a `--baseline` to compare with.
+fn with_delta(value: String, delta: Option<String>) -> String {
+    match delta {
+        Some(delta) => format!("{} ({})", value, delta),
+        None => value,
+    }
+}
+
+fn extra_rows(config: &Config, summary: &Summary) -> Vec<BoxRow> {
+    let base = config.baseline.as_ref();
+    let mut rows = Vec::new();
+    if config.compress_estimate {
+        let value = if summary.total_size == 0 {
+            "-".to_string()
+        } else {
+            format!(
+                "~{} ({:.0}%)",
+                config.size(summary.total_compressed),
+                summary.total_compressed as f64 / summary.total_size as f64 * 100.0
+            )
+        };
+        let delta = base
+            .and_then(|b| b.compressed_size_estimate_bytes)
+            .map(|then| baseline::delta(summary.total_compressed, then, |n| config.size(n)));
+        rows.push((
+            "[Z]",
+            with_delta(value, delta),
+            Some(summary.total_compressed),
+        ));
+    }
+    if config.tests {
+        let (tests, code) = lists::test_split(summary)
+            .iter()
+            .fold((0, 0), |(t, c), s| (t + s.tests.lines, c + s.code.lines));
+        rows.push((
+            "[test]",
+            format!(
+                "{} of {} code lines ({:.1}%)",
+                format_num(tests),
+                format_num(code),
+                percent(tests, code)
+            ),
+            None,
+        ));
+    }
+    for (ext, largest) in lists::largest_dirs_for(config, summary) {
+        let value = match largest {
+            Some((dir, stat)) => format!(
+                "{}: {} ({}, {} {})",
+                ext,
+                display_relative_path(dir, &config.root),
+                config.size(stat.size),
+                format_num(stat.files),
+                config.lang.get(Msg::Files)
+            ),
+            None => format!("{}: -", ext),
+        };
+        rows.push(("[D↑.]", value, None));
+    }
+    if config.cold.is_some() {
+        let (cold, _) = lists::cold(config, summary);
+        rows.push(("[cold]", share(config, &cold, summary.total_size), None));
+    }
+    if config.decompress {
+        let dz = &summary.decompressed;
+        rows.push((
+            "[dz]",
+            format!(
+                "{} compressed files, {} → {}",
+                format_num(dz.files),
+                config.size(dz.size),
+                config.size(dz.bytes)
+            ),
+            None,
+        ));
+    }
+    if config.count_pattern.is_some() {
+        let delta = base
+            .and_then(|b| b.matches())
+            .map(|then| baseline::delta(summary.total_matches, then, format_num));
+        rows.push((
+            "[M]",
+            with_delta(
+                format!(
+                    "{} matches in {} files",
+                    format_num(summary.total_matches),
+                    format_num(summary.matching_files)
+                ),
+                delta,
+            ),
+            None,
+        ));
+    }
+    for (i, plugin) in config.plugins.iter().enumerate() {
+        let total = summary.plugins.get(i).copied().unwrap_or_default();
+        let delta = base
+            .and_then(|b| b.plugin(&plugin.name))
+            .map(|then| baseline::delta(total.value, then, format_num));
+        rows.push((
+            "[P]",
+            with_delta(
+                format!(
+                    "{}: {} in {} files",
+                    plugin.name,
+                    format_num(total.value),
+                    format_num(total.files)
+                ),
+                delta,
+            ),
+            None,
+        ));
+    }
+    for (i, metric) in config.metrics.iter().enumerate() {
+        let total = summary.metrics.get(i).copied().unwrap_or_default();
+        let render = |v: f64| match v {
+            v if metric.is_bytes() => config.size(v.round() as u64),
+            v if v.fract() != 0.0 => format!("{:.1}", v),
+            v => format_num(v as u64),
+        };
+        let result = total.result(metric.agg);
+        let delta = result
+            .zip(base.and_then(|b| b.metric(&metric.name)))
+            .map(|(now, then)| match now - then {
+                d if d > 0.0 => format!("+{}", render(d)),
+                d if d < 0.0 => format!("-{}", render(-d)),
+                _ => "±0".to_string(),
+            });
+        let value = result.map_or_else(|| "-".to_string(), render);
+        rows.push((
+            "[=]",
+            with_delta(format!("{}: {}", metric.name, value), delta),
+            None,
+        ));
+    }
+    if !config.budgets.is_empty() {
+        let over = budget::violations(config, summary).len();
+        rows.push((
+            "[$]",
+            format!(
+                "{} budgets, {}",
+                format_num(config.budgets.len() as u64),
+                match over {
+                    0 => "all met".to_string(),
+                    1 => "1 violation".to_string(),
+                    n => format!("{} violations", format_num(n as u64)),
+                }
+            ),
+            None,
+        ));
+    }
+    if let Some(growth) = baseline::growth_check(config, summary) {
+        let pct = |p: Option<f64>| p.map_or_else(|| "new".to_string(), |p| format!("{:+.1}%", p));
+        rows.push((
+            "[%]",
+            format!(
+                "{} bytes, {} lines (max {}%{})",
+                pct(growth.size),
+                pct(growth.lines),
+                growth.max_percent,
+                if growth.exceeded { ", exceeded" } else { "" }
+            ),
+            None,
+        ));
+    }
+    if config.verbose {
+        let (avg, med) = match summary.stats() {
+            Some(s) => (
+                format!(
+                    "{}, {} lines",
+                    config.size(s.mean_size.round() as u64),
+                    format_num(s.mean_lines.round() as u64)
+                ),
+                format!(
+                    "{}, {} lines",
+                    config.size(s.median_size.round() as u64),
+                    format_num(s.median_lines.round() as u64)
+                ),
+            ),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        rows.push(("[avg]", avg, None));
+        rows.push(("[med]", med, None));
+        rows.push((
+            "[pB]",
+            percentiles(&summary.size_sketch, &|v| config.size(v)),
+            None,
+        ));
+        rows.push((
+            "[pL]",
+            percentiles(&summary.lines_sketch, &format_num),
+            None,
+        ));
+        let links = &summary.symlinks;
+        rows.push((
+            "[ln]",
+            if links.total() == 0 {
+                "no symlinks".to_string()
+            } else {
+                let mut text = format!(
+                    "{} symlinks: in {} files, {} dirs; out {} files, {} dirs; {} broken",
+                    format_num(links.total()),
+                    format_num(links.internal_files),
+                    format_num(links.internal_dirs),
+                    format_num(links.external_files),
+                    format_num(links.external_dirs),
+                    format_num(links.broken)
+                );
+                if links.junctions > 0 {
+                    text += &format!(" ({} junctions)", format_num(links.junctions));
+                }
+                text
+            },
+            None,
+        ));
+    }
+    if let Some(limit) = config.tiny {
+        let pct = if summary.total_files == 0 {
+            0.0
+        } else {
+            summary.tiny.files as f64 / summary.total_files as f64 * 100.0
+        };
+        rows.push((
+            "[T]",
+            format!(
+                "{} files < {} ({:.1}%), {}",
+                format_num(summary.tiny.files),
+                config.size(limit),
+                pct,
+                config.size(summary.tiny.size)
+            ),
+            None,
+        ));
+    }
+    if let Some((dir, entries)) = &summary.widest_dir
+        && (config.verbose || !summary.crowded_dirs.is_empty())
+    {
+        rows.push((
+            "[D≡]",
+            format!(
+                "{} ({} entries)",
+                display_relative_path(dir, &config.root),
+                format_num(*entries)
+            ),
+            None,
+        ));
+    }
+    if summary.long_paths > 0 {
+        rows.push((
+            "[lp]",
+            format!(
+                "{} paths of {}+ characters",
+                format_num(summary.long_paths),
+                crate::vfs::MAX_PATH
+            ),
+            None,
+        ));
+    }
+    if (config.verbose || config.audit) && summary.executables.files() > 0 {
+        let exec = &summary.executables;
+        rows.push((
+            "[x]",
+            format!(
+                "{} executables: {} scripts, {} binaries, {} other",
+                format_num(exec.files()),
+                format_num(exec.scripts.files),
+                format_num(exec.binaries.files),
+                format_num(exec.other.files)
+            ),
+            None,
+        ));
+    }
+    if config.secrets {
+        let found = lists::secrets(config, summary).len() as u64;
+        rows.push((
+            "[key]",
+            if found == 0 {
+                "no likely secrets".to_string()
+            } else {
+                format!("{} files look like secrets", format_num(found))
+            },
+            None,
+        ));
+    }
+    if config.scripts {
+        let scripts: u64 = summary.interpreters.values().map(|s| s.files).sum();
+        rows.push((
+            "[#!]",
+            format!(
+                "{} extensionless scripts, {} interpreters",
+                format_num(scripts),
+                format_num(summary.interpreters.len() as u64)
+            ),
+            None,
+        ));
+    }
+    if config.comments {
+        rows.push((
+            "[C%]",
+            format!(
+                "{:.1}% comments ({} of {} lines)",
+                summary.comments.density(),
+                format_num(summary.comments.comments),
+                format_num(summary.comments.lines)
+            ),
+            None,
+        ));
+    }
+    if config.lint_names {
+        rows.push((
+            "[nm]",
+            match summary.names.paths() {
+                0 => "all names portable".to_string(),
+                n => format!("{} unportable names", format_num(n)),
+            },
+            None,
+        ));
+    }
+    if config.case_collisions {
+        rows.push((
+            "[Aa]",
+            match summary.case_paths.collisions().len() {
+                0 => "no case collisions".to_string(),
+                n => format!("{} case collisions", format_num(n as u64)),
+            },
+            None,
+        ));
+    }
+    if config.dup_names.is_some() {
+        let (names, files) = summary.duplicate_names();
+        rows.push((
+            "[dup]",
+            format!(
+                "{} names shared by {} files",
+                format_num(names),
+                format_num(files)
+            ),
+            None,
+        ));
+    }
+    match config.submodules {
+        submodules::Mode::Include => {}
+        submodules::Mode::Exclude => {
+            let (_, size) = summary.submodules.total();
+            rows.push((
+                "[sub]",
+                format!(
+                    "{} submodules skipped ({})",
+                    format_num(summary.submodules.len() as u64),
+                    config.size(size)
+                ),
+                None,
+            ));
+        }
+        submodules::Mode::Separate => {
+            let (files, size) = summary.submodules.total();
+            let stat = GroupStat {
+                files,
+                size,
+                ..GroupStat::default()
+            };
+            rows.push((
+                "[sub]",
+                format!(
+                    "{} submodules: {}",
+                    format_num(summary.submodules.len() as u64),
+                    share(config, &stat, summary.total_size)
+                ),
+                None,
+            ));
+        }
+    }
+    if config.include_git {
+        let git = &summary.git;
+        let pct = if summary.total_size == 0 {
+            0.0
+        } else {
+            git.size() as f64 / summary.total_size as f64 * 100.0
+        };
+        rows.push((
+            "[git]",
+            format!(
+                ".git {} ({:.1}%), {} files",
+                config.size(git.size()),
+                pct,
+                format_num(git.files())
+            ),
+            None,
+        ));
+    }
+    if config.junk {
+        let (files, size) = summary
+            .junk
+            .values()
+            .fold((0, 0), |(files, size), s| (files + s.files, size + s.size));
+        rows.push((
+            "[jnk]",
+            format!("{} junk files, {}", format_num(files), config.size(size)),
+            None,
+        ));
+    }
+    if config.dotfiles {
+        let (files, size) = summary
+            .dotfiles
+            .values()
+            .fold((0, 0), |(files, size), s| (files + s.files, size + s.size));
+        rows.push((
+            "[.*]",
+            format!(
+                "{} hidden entries, {} files, {}",
+                format_num(summary.dotfiles.len() as u64),
+                format_num(files),
+                config.size(size)
+            ),
+            None,
+        ));
+    }
+    if config.docs {
+        let missing = summary.docs.values().filter(|s| s.readmes == 0).count();
+        rows.push((
+            "[doc]",
+            format!(
+                "{} of {} top-level dirs without a README",
+                format_num(missing as u64),
+                format_num(summary.docs.len() as u64)
+            ),
+            None,
+        ));
+    }
+    if config.licenses {
+        let files: usize = summary.licenses.values().map(|paths| paths.len()).sum();
+        rows.push((
+            "[lic]",
+            format!(
+                "{} license files, {} licenses",
+                format_num(files as u64),
+                format_num(summary.licenses.len() as u64)
+            ),
+            None,
+        ));
+    }
+    if summary.placeholders.files > 0 {
+        rows.push((
+            "[ph]",
+            format!(
+                "{}, cloud placeholders, contents not read",
+                share(config, &summary.placeholders, summary.total_size)
+            ),
+            None,
+        ));
+    }
+    if summary.minified.files > 0 {
+        let note = if config.include_minified {
+            format!("{} lines", format_num(summary.minified.lines))
+        } else {
+            "lines not counted".to_string()
+        };
+        rows.push((
+            "[min]",
+            format!(
+                "{}, {}",
+                share(config, &summary.minified, summary.total_size),
+                note
+            ),
+            None,
+        ));
+    }
+    if config.dep_dirs.is_some() {
+        rows.push((
+            "[src]",
+            share(config, &summary.source(), summary.total_size),
+            None,
+        ));
+        rows.push((
+            "[dep]",
+            share(config, &summary.deps, summary.total_size),
+            None,
+        ));
+    }
+    if config.linguist {
+        rows.push((
+            "[G]",
+            share(config, &summary.generated, summary.total_size),
+            None,
+        ));
+        rows.push((
+            "[V]",
+            share(config, &summary.vendored, summary.total_size),
+            None,
+        ));
+    }
+    rows.into_iter()
+        .map(|(label, value, bytes)| (label, glyphs(&value).into_owned(), bytes))
+        .collect()
+}
+
+/// "p50 X, p90 Y, p99 Z" from a sketch.
+fn percentiles(sketch: &Sketch, fmt: &dyn Fn(u64) -> String) -> String {
+    let parts: Vec<String> = PERCENTILES
+        .iter()
+        .filter_map(|(name, q)| {
+            sketch
+                .quantile(*q)
+                .map(|v| format!("{} {}", name, fmt(v.round() as u64)))
+        })
+        .collect();
+    if parts.is_empty() {
+        "-".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+/// "N files, SIZE (P%)" for a subset of the tree.
+fn share(config: &Config, stat: &GroupStat, total_size: u64) -> String {
+    let pct = if total_size == 0 {
+        0.0
+    } else {
+        stat.size as f64 / total_size as f64 * 100.0
+    };
+    format!(
+        "{} files, {} ({:.1}%)",
+        format_num(stat.files),
+        config.size(stat.size),
+        pct
+    )
+}
+
+/// `part` as a percentage of `whole`, 0 when there's no whole.
+fn percent(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64 * 100.0
+    }
+}
+
+/// Each size's `%` of `total` and the running `Cum %` down the list.
+fn shares(sizes: impl IntoIterator<Item = u64>, total: u64) -> Vec<[String; 2]> {
+    let mut running = 0;
+    sizes
+        .into_iter()
+        .map(|size| {
+            running += size;
+            [
+                format!("{:.1}%", percent(size, total)),
+                format!("{:.1}%", percent(running, total)),
+            ]
+        })
+        .collect()
+}
+
+pub fn print_lists(config: &Config, summary: &Summary) {
+    let style = Style::new(config);
+    let title_suffix = format!(
+        "by {}{}",
+        lists::sort_key(config).as_str(),
+        if config.reverse { ", reversed" } else { "" }
+    );
+
+    if config.top.is_some() {
+        let files = lists::top_files(config, summary);
+        let rows: Vec<Vec<Cell>> = files
+            .iter()
+            .zip(shares(files.iter().map(|e| e.size), summary.total_size))
+            .map(|(e, [share, cumulative])| {
+                vec![
+                    Cell::size(config, e.size),
+                    share.into(),
+                    cumulative.into(),
+                    format_num(e.lines).into(),
+                    e.name.clone().into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            &format!("Top files {}", title_suffix),
+            &["Size", "%", "Cum %", "Lines", "Path"],
+            &rows,
+        );
+
+        // A directory's own files only, so the shares add up.
+        let dirs = lists::top_dirs(config, summary);
+        let rows: Vec<Vec<Cell>> = dirs
+            .iter()
+            .zip(shares(dirs.iter().map(|e| e.size), summary.total_size))
+            .map(|(e, [share, cumulative])| {
+                vec![
+                    Cell::size(config, e.size),
+                    share.into(),
+                    cumulative.into(),
+                    format_num(e.count).into(),
+                    format_num(e.lines).into(),
+                    e.name.clone().into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            &format!("Top dirs {}", title_suffix),
+            &["Size", "%", "Cum %", "Files", "Lines", "Path"],
+            &rows,
+        );
+
+        let rows: Vec<Vec<Cell>> = lists::busiest_dirs(config, summary)
+            .iter()
+            .map(|e| {
+                vec![
+                    format_num(e.count).into(),
+                    Cell::size(config, e.size),
+                    format_num(e.lines).into(),
+                    e.name.clone().into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            "Busiest dirs by files",
+            &["Files", "Size", "Lines", "Path"],
+            &rows,
+        );
+    }
+
+    if config.top_lines.is_some() {
+        let rows: Vec<Vec<Cell>> = lists::top_lines(config, summary)
+            .iter()
+            .map(|e| {
+                vec![
+                    format_num(e.lines).into(),
+                    Cell::size(config, e.size),
+                    e.name.clone().into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            "Top files by lines",
+            &["Lines", "Size", "Path"],
+            &rows,
+        );
+    }
+
+    if config.comments {
+        let rows: Vec<Vec<String>> = lists::comment_density(summary)
+            .into_iter()
+            .map(|(lang, stat)| {
+                vec![
+                    format!("{:.1}%", stat.density()),
+                    format_num(stat.comments),
+                    format_num(stat.lines),
+                    lang.to_string(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            "Comment density by language",
+            &["Density", "Comments", "Lines", "Language"],
+            &rows,
+        );
+
+        let rows: Vec<Vec<String>> = lists::least_commented(config, summary)
+            .into_iter()
+            .map(|(e, comments)| {
+                vec![
+                    format!("{:.1}%", comments::density(comments, e.lines)),
+                    format_num(comments),
+                    format_num(e.lines),
+                    e.name,
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            &format!(
+                "Least-commented files of {}+ lines",
+                format_num(comments::LARGE_FILE_LINES)
+            ),
+            &["Density", "Comments", "Lines", "Path"],
+            &rows,
+        );
+    }
+
+    if let Some(pattern) = &config.count_pattern {
+        let rows: Vec<Vec<String>> = lists::top_matches(config, summary)
+            .into_iter()
+            .map(|(path, matches)| vec![format_num(matches), path])
+            .collect();
+        println!();
+        print_table(
+            &style,
+            &format!("Top files by matches of /{}/", pattern.as_str()),
+            &["Matches", "Path"],
+            &rows,
+        );
+    }
+
+    if config.tiny.is_some() {
+        let rows: Vec<Vec<Cell>> = lists::smallest_files(config, summary)
+            .iter()
+            .map(|e| {
+                vec![
+                    Cell::size(config, e.size),
+                    format_num(e.lines).into(),
+                    e.name.clone().into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(&style, "Smallest files", &["Size", "Lines", "Path"], &rows);
+    }
+
+    if config.by_ext {
+        let exts = lists::extensions(config, summary);
+        let rows: Vec<Vec<Cell>> = exts
+            .iter()
+            .zip(shares(exts.iter().map(|e| e.size), summary.total_size))
+            .map(|(e, [share, cumulative])| {
+                let longest = summary
+                    .exts
+                    .get(&e.name)
+                    .and_then(|stat| stat.max_lines.as_ref())
+                    .map(|f| {
+                        format!(
+                            "{} ({} lines)",
+                            display_relative_path(&f.path, &config.root),
+                            format_num(f.lines)
+                        )
+                    })
+                    .unwrap_or_else(|| "-".to_string());
+                let (mean, median) = lists::lines_per_file(e, summary.ext_lines.get(&e.name));
+                vec![
+                    format_num(e.count).into(),
+                    Cell::size(config, e.size),
+                    share.into(),
+                    cumulative.into(),
+                    format_num(e.lines).into(),
+                    format_num(mean.round() as u64).into(),
+                    format_num(median.round() as u64).into(),
+                    e.name.clone().into(),
+                    longest.into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            &format!("Extensions {}", title_suffix),
+            &[
+                "Files",
+                "Size",
+                "%",
+                "Cum %",
+                "Lines",
+                "Avg",
+                "Median",
+                "Ext",
+                "Longest file",
+            ],
+            &rows,
+        );
+    }
+
+    if config.by_lang {
+        let rows: Vec<Vec<Cell>> = lists::languages(config, summary)
+            .iter()
+            .map(|e| {
+                let (mean, median) =
+                    lists::lines_per_file(e, summary.lang_lines.get(e.name.as_str()));
+                vec![
+                    format_num(e.count).into(),
+                    Cell::size(config, e.size),
+                    format_num(e.lines).into(),
+                    format_num(mean.round() as u64).into(),
+                    format_num(median.round() as u64).into(),
+                    e.name.clone().into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            &format!("Languages {}", title_suffix),
+            &["Files", "Size", "Lines", "Avg", "Median", "Language"],
+            &rows,
+        );
+    }
+
+    if config.tests {
+        let rows: Vec<Vec<String>> = lists::test_split(summary)
+            .iter()
+            .map(|s| {
+                vec![
+                    format_num(s.code.lines),
+                    format_num(s.tests.lines),
+                    format!("{:.1}%", percent(s.tests.lines, s.code.lines)),
+                    format_num(s.tests.files),
+                    s.lang.to_string(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            "Test code by language",
+            &[
+                "Code lines",
+                "Test lines",
+                "Test %",
+                "Test files",
+                "Language",
+            ],
+            &rows,
+        );
+    }
+
+    if config.by_depth {
+        const BAR_WIDTH: u64 = 30;
+        // Bars scale with bytes, the usual question being where bloat lives.
+        let most = summary.depths.iter().map(|d| d.size).max().unwrap_or(0);
+        let rows: Vec<Vec<Cell>> = summary
+            .depths
+            .iter()
+            .enumerate()
+            .map(|(depth, stat)| {
+                let bar = match stat.size {
+                    0 => String::new(),
+                    n => glyphs("█")
+                        .repeat((n as u128 * BAR_WIDTH as u128).div_ceil(most as u128) as usize),
+                };
+                vec![
+                    depth.to_string().into(),
+                    format_num(stat.files).into(),
+                    Cell::size(config, stat.size),
+                    bar.into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            "Files per depth",
+            &["Depth", "Files", "Size", ""],
+            &rows,
+        );
+    }
+
+    if config.audit {
+        let rows: Vec<Vec<Cell>> = lists::unexpected_executables(config, summary)
+            .into_iter()
+            .map(|(e, kind)| {
+                vec![
+                    kind.as_str().to_string().into(),
+                    Cell::size(config, e.size),
+                    e.name.into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            "Unexpected executables",
+            &["Kind", "Size", "Path"],
+            &rows,
+        );
+    }
+
+    if config.secrets {
+        let rows: Vec<Vec<String>> = lists::secrets(config, summary)
+            .into_iter()
+            .map(|(e, reason)| vec![reason.label().to_string(), e.name])
+            .collect();
+        println!();
+        print_table(&style, "Possible secrets", &["Reason", "Path"], &rows);
+    }
+
+    if config.scripts {
+        let rows: Vec<Vec<Cell>> = lists::interpreters(summary)
+            .iter()
+            .map(|e| {
+                vec![
+                    format_num(e.count).into(),
+                    Cell::size(config, e.size),
+                    format_num(e.lines).into(),
+                    e.name.clone().into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            "Scripts by interpreter",
+            &["Files", "Size", "Lines", "Interpreter"],
+            &rows,
+        );
+    }
+
+    if config.mime {
+        let rows: Vec<Vec<Cell>> = lists::content_types(config, summary)
+            .iter()
+            .map(|e| {
+                vec![
+                    format_num(e.count).into(),
+                    Cell::size(config, e.size),
+                    e.name.clone().into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            &format!("Content types {}", title_suffix),
+            &["Files", "Size", "Type"],
+            &rows,
+        );
+    }
+
+    if config.classes.is_some() {
+        let rows: Vec<Vec<Cell>> = lists::classes(config, summary)
+            .iter()
+            .map(|e| {
+                let pct = if summary.total_size == 0 {
+                    0.0
+                } else {
+                    e.size as f64 / summary.total_size as f64 * 100.0
+                };
+                vec![
+                    Cell::size(config, e.size),
+                    format!("{:.1}%", pct).into(),
+                    format_num(e.count).into(),
+                    format_num(e.lines).into(),
+                    e.name.clone().into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            &format!("By asset class {}", title_suffix),
+            &["Size", "%", "Files", "Lines", "Class"],
+            &rows,
+        );
+    }
+
+    if config.groups.is_some() {
+        let rows: Vec<Vec<Cell>> = lists::groups(config, summary)
+            .iter()
+            .map(|e| {
+                vec![
+                    Cell::size(config, e.size),
+                    format!("{:.1}%", percent(e.size, summary.total_size)).into(),
+                    format_num(e.count).into(),
+                    format_num(e.lines).into(),
+                    e.name.clone().into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            &format!("By group {}", title_suffix),
+            &["Size", "%", "Files", "Lines", "Group"],
+            &rows,
+        );
+    }
+
+    if config.by_owner {
+        let rows: Vec<Vec<Cell>> = lists::owners(config, summary)
+            .iter()
+            .map(|(e, _)| {
+                let pct = if summary.total_size == 0 {
+                    0.0
+                } else {
+                    e.size as f64 / summary.total_size as f64 * 100.0
+                };
+                vec![
+                    Cell::size(config, e.size),
+                    format!("{:.1}%", pct).into(),
+                    format_num(e.count).into(),
+                    e.name.clone().into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            &format!("By owner {}", title_suffix),
+            &["Size", "%", "Files", "Owner"],
+            &rows,
+        );
+    }
+
+    if config.by_mount {
+        let rows: Vec<Vec<Cell>> = lists::mounts(config, summary)
+            .iter()
+            .map(|(e, _, fstype)| {
+                vec![
+                    Cell::size(config, e.size),
+                    format!("{:.1}%", percent(e.size, summary.total_size)).into(),
+                    format_num(e.count).into(),
+                    match fstype {
+                        Some(fstype) => format!("{} ({})", e.name, fstype),
+                        None => e.name.clone(),
+                    }
+                    .into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            &format!("By mount {}", title_suffix),
+            &["Size", "%", "Files", "Mount"],
+            &rows,
+        );
+    }
+
+    if config.dup_names.is_some() {
+        let rows: Vec<Vec<String>> = lists::duplicate_names(config, summary)
+            .into_iter()
+            .map(|(name, paths)| vec![format_num(paths.len() as u64), name, paths.join(", ")])
+            .collect();
+        println!();
+        print_table(
+            &style,
+            "Duplicate names",
+            &["Files", "Name", "Paths"],
+            &rows,
+        );
+    }
+
+    if config.dupes {
+        let rows: Vec<Vec<Cell>> = summary
+            .duplicates
+            .iter()
+            .map(|group| {
+                vec![
+                    format_num(group.paths.len() as u64).into(),
+                    format_num(group.copies).into(),
+                    Cell::size(config, group.size),
+                    Cell::size(config, group.wasted()),
+                    group
+                        .paths
+                        .iter()
+                        .map(|p| display_relative_path(p, &config.root))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                        .into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            "Duplicate files",
+            &["Files", "Copies", "Size", "Wasted", "Paths"],
+            &rows,
+        );
+    }
+
+    if !summary.crowded_dirs.is_empty() {
+        let rows: Vec<Vec<String>> = summary
+            .crowded_dirs
+            .iter()
+            .map(|(dir, entries)| {
+                vec![
+                    format_num(*entries),
+                    display_relative_path(dir, &config.root),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            &format!(
+                "Crowded directories (over {} entries; slow for many tools and filesystems)",
+                format_num(config.max_entries)
+            ),
+            &["Entries", "Path"],
+            &rows,
+        );
+    }
+
+    if config.profile_ignores {
+        let rows: Vec<Vec<Cell>> = summary
+            .ignore_rules
+            .iter()
+            .map(|rule| {
+                vec![
+                    Cell::size(config, rule.bytes),
+                    format_num(rule.files).into(),
+                    format_num(rule.matched).into(),
+                    format!(
+                        "{} ({})",
+                        rule.pattern,
+                        display_relative_path(&rule.source, &config.root)
+                    )
+                    .into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            "Ignore rules by what they pruned",
+            &["Size", "Files", "Matches", "Rule"],
+            &rows,
+        );
+    }
+
+    if config.overlay_layers && !summary.overlay_layers.is_empty() {
+        let rows: Vec<Vec<Cell>> = summary
+            .overlay_layers
+            .iter()
+            .map(|layer| {
+                let (files, size, whiteouts) = if layer.visible {
+                    (
+                        format_num(layer.files),
+                        Cell::size(config, layer.bytes),
+                        format_num(layer.whiteouts),
+                    )
+                } else {
+                    (
+                        "-".to_string(),
+                        "not visible".to_string().into(),
+                        "-".to_string(),
+                    )
+                };
+                vec![
+                    files.into(),
+                    size,
+                    whiteouts.into(),
+                    format!("{} ({})", layer.name, layer.dir.display()).into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            "Overlay layers",
+            &["Files", "Size", "Whiteouts", "Layer"],
+            &rows,
+        );
+    }
+
+    if config.submodules != submodules::Mode::Include {
+        let rows: Vec<Vec<Cell>> = summary
+            .submodules
+            .iter()
+            .map(|(dir, stat)| {
+                vec![
+                    format_num(stat.files).into(),
+                    Cell::size(config, stat.size),
+                    format_num(stat.lines).into(),
+                    display_relative_path(dir, &config.root).into(),
+                ]
+            })
+            .collect();
+        let title = if config.submodules == submodules::Mode::Exclude {
+            "Skipped submodules"
+        } else {
+            "Submodules"
+        };
+        println!();
+        print_table(&style, title, &["Files", "Size", "Lines", "Path"], &rows);
+    }
+
+    if config.include_git {
+        let rows: Vec<Vec<Cell>> = Part::ALL
+            .into_iter()
+            .map(|part| {
+                let stat = summary.git.part(part);
+                vec![
+                    format_num(stat.files).into(),
+                    Cell::size(config, stat.size),
+                    part.label().to_string().into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(&style, "Git internals", &["Files", "Size", "Part"], &rows);
+    }
+
+    if config.junk {
+        let rows: Vec<Vec<Cell>> = lists::junk(summary)
+            .into_iter()
+            .map(|(kind, stat)| {
+                vec![
+                    format_num(stat.files).into(),
+                    Cell::size(config, stat.size),
+                    kind.to_string().into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(&style, "Junk files", &["Files", "Size", "Kind"], &rows);
+    }
+
+    if config.dotfiles {
+        let rows: Vec<Vec<Cell>> = lists::dotfiles(config, summary)
+            .into_iter()
+            .map(|e| {
+                vec![
+                    format_num(e.count).into(),
+                    Cell::size(config, e.size),
+                    e.name.into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            "Hidden files and directories",
+            &["Files", "Size", "Path"],
+            &rows,
+        );
+    }
+
+    if let Some(window) = config.stale {
+        let rows: Vec<Vec<Cell>> = lists::stale(config, summary)
+            .into_iter()
+            .map(|(dir, stat)| {
+                vec![
+                    Cell::size(config, stat.size),
+                    format_num(stat.files).into(),
+                    stat.newest
+                        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                        .map_or("-".to_string(), |t| config.time(t.as_secs()))
+                        .into(),
+                    display_relative_path(&dir, &config.root).into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            &format!(
+                "Stale directories (nothing modified in {})",
+                format_duration(window)
+            ),
+            &["Size", "Files", "Newest", "Path"],
+            &rows,
+        );
+    }
+
+    if let Some(window) = config.cold {
+        let rows: Vec<Vec<Cell>> = lists::cold(config, summary)
+            .1
+            .into_iter()
+            .map(|(dir, stat)| {
+                let dir_size = summary.dirs.get(&dir).map_or(0, |d| d.size);
+                let pct = if dir_size == 0 {
+                    0.0
+                } else {
+                    stat.size as f64 / dir_size as f64 * 100.0
+                };
+                vec![
+                    Cell::size(config, stat.size),
+                    format!("{:.1}%", pct).into(),
+                    format_num(stat.files).into(),
+                    display_relative_path(&dir, &config.root).into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            &format!("Cold directories (not read in {})", format_duration(window)),
+            &["Cold", "Of dir", "Files", "Path"],
+            &rows,
+        );
+    }
+
+    if config.suggest_ignore {
+        let suggestions = gitignore::suggestions(config, summary);
+        if !suggestions.is_empty() {
+            let rows: Vec<Vec<Cell>> = suggestions
+                .into_iter()
+                .map(|s| {
+                    vec![
+                        format_num(s.files).into(),
+                        Cell::size(config, s.size),
+                        s.line.into(),
+                    ]
+                })
+                .collect();
+            println!();
+            print_table(
+                &style,
+                if config.apply {
+                    "Added to .gitignore"
+                } else {
+                    "Suggested .gitignore lines (add them with --apply)"
+                },
+                &["Files", "Size", "Line"],
+                &rows,
+            );
+        }
+    }
+
+    if config.docs {
+        let rows: Vec<Vec<Cell>> = lists::docs(summary)
+            .into_iter()
+            .map(|(dir, stat)| {
+                vec![
+                    if stat.readmes > 0 { "yes" } else { "no" }
+                        .to_string()
+                        .into(),
+                    format_num(stat.doc_files).into(),
+                    Cell::size(config, stat.doc_bytes),
+                    Cell::size(config, stat.code_bytes),
+                    stat.ratio()
+                        .map_or("-".to_string(), |r| format!("{:.1}%", r))
+                        .into(),
+                    dir.into(),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            "Documentation by directory",
+            &[
+                "README",
+                "Docs",
+                "Doc size",
+                "Code size",
+                "Docs/code",
+                "Dir",
+            ],
+            &rows,
+        );
+    }
+
+    if config.licenses {
+        let rows: Vec<Vec<String>> = lists::licenses(config, summary)
+            .into_iter()
+            .map(|(license, paths)| vec![format_num(paths.len() as u64), license, paths.join(", ")])
+            .collect();
+        println!();
+        print_table(&style, "Licenses", &["Files", "License", "Paths"], &rows);
+    }
+
+    if config.case_collisions {
+        let rows: Vec<Vec<String>> = summary
+            .case_paths
+            .collisions()
+            .into_iter()
+            .map(|group| {
+                let paths: Vec<String> = group.iter().map(|p| p.display().to_string()).collect();
+                vec![format_num(paths.len() as u64), paths.join(", ")]
+            })
+            .collect();
+        if !rows.is_empty() {
+            println!();
+            print_table(&style, "Case collisions", &["Count", "Paths"], &rows);
+        }
+    }
+
+    if config.lint_names && summary.names.paths() > 0 {
+        let rows: Vec<Vec<String>> = Issue::ALL
+            .into_iter()
+            .flat_map(|issue| {
+                // The issue and its count head the first of its examples.
+                let mut head = Some([
+                    issue.label().to_string(),
+                    format_num(summary.names.count(issue)),
+                ]);
+                summary
+                    .names
+                    .examples(issue, names::EXAMPLES)
+                    .map(move |path| {
+                        let [label, count] = head.take().unwrap_or_default();
+                        vec![label, count, path.display().to_string()]
+                    })
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            "Unportable names",
+            &["Issue", "Paths", "Example"],
+            &rows,
+        );
+    }
+
+    let violations = budget::violations(config, summary);
+    if !violations.is_empty() {
+        let amount = |v: u64, unit: Unit| match unit {
+            Unit::Bytes => config.size(v),
+            Unit::Lines => format!("{} lines", format_num(v)),
+            Unit::Files => format!("{} files", format_num(v)),
+        };
+        let rows: Vec<Vec<String>> = violations
+            .iter()
+            .map(|v| {
+                vec![
+                    amount(v.value, v.budget.unit),
+                    amount(v.budget.limit, v.budget.unit),
+                    v.budget.glob.clone(),
+                    v.path
+                        .map(|p| display_relative_path(p, &config.root))
+                        .unwrap_or_else(|| "(total)".to_string()),
+                ]
+            })
+            .collect();
+        println!();
+        print_table(
+            &style,
+            "Budget violations",
+            &["Actual", "Limit", "Glob", "Path"],
+            &rows,
+        );
+    }
+
+    if let Some(profile) = &summary.profile {
+        print_profile(&style, profile);
+    }
+
+    // Last, where a total that doesn't match `df` sends people looking.
+    if !summary.skipped_mounts.is_empty() {
+        println!();
+    }
+    for (path, fstype) in &summary.skipped_mounts {
+        println!(
+            "{} {} ({}, a virtual filesystem: kernel state, not disk space; --virtual-fs walks it)",
+            style.label("skipped:"),
+            path.display(),
+            fstype
+        );
+    }
+}
+
+/// `--profile`: one row per walker thread and a total with each phase's
+/// share, which tells an I/O-bound scan (traversal, metadata) from a
+/// CPU-bound one (lines, checks).
+fn print_profile(style: &Style, profile: &Profile) {
+    let ms = |time| format!("{:.1} ms", profile::millis(time));
+    let row = |t: &Timings, name: String, shares: bool| {
+        let mut cells = vec![format_num(t.files)];
+        cells.extend(Phase::ALL.into_iter().map(|phase| {
+            let time = t.get(phase);
+            if shares {
+                let share = time.as_secs_f64() / t.total().as_secs_f64().max(1e-9) * 100.0;
+                format!("{} ({:.0}%)", ms(time), share)
+            } else {
+                ms(time)
+            }
+        }));
+        cells.push(name);
+        cells
+    };
+    let mut rows: Vec<Vec<String>> = profile
+        .threads
+        .iter()
+        .enumerate()
+        .map(|(i, t)| row(t, format!("thread {}", i + 1), false))
+        .collect();
+    rows.push(row(
+        &Timings::sum(&profile.threads),
+        "all threads".to_string(),
+        true,
+    ));
+    let mut headers = vec!["Files"];
+    headers.extend(Phase::ALL.map(Phase::label));
+    headers.push("Thread");
+    let title = format!(
+        "Profile ({} wall clock, {} aggregation)",
+        ms(profile.wall),
+        ms(profile.aggregation)
+    );
+    println!();
+    print_table(style, &title, &headers, &rows);
+}
+
+/// `--list` rows aligned together; each chunk is written as soon as it's
+/// formatted, so a pager has something to show right away.
+const LIST_CHUNK: usize = 1000;
+
+/// `--list`: one aligned line per file. Paths are never shortened so the
+/// output stays usable in pipes. A reader that goes away early (`head`, a
+/// pager quit) just ends the listing.
+pub fn print_file_list(config: &Config, summary: &Summary) -> io::Result<()> {
+    let style = Style::new(config);
+    let (entries, next) = lists::file_page(config, summary);
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for chunk in entries.chunks(LIST_CHUNK) {
+        let rows: Vec<[String; 3]> = chunk
+            .iter()
+            .map(|e| [config.size(e.size), format_num(e.lines), e.name.clone()])
+            .collect();
+        let size_width = rows.iter().map(|r| r[0].len()).max().unwrap_or(0);
+        let lines_width = rows.iter().map(|r| r[1].len()).max().unwrap_or(0);
+        for ([size, lines, path], e) in rows.into_iter().zip(chunk) {
+            let written = writeln!(
+                out,
+                "{}  {}  {}",
+                style.sized(&format!("{:>w$}", size, w = size_width), Some(e.size)),
+                style.value(&format!("{:>w$}", lines, w = lines_width)),
+                path
+            );
+            if !until_closed(written)? {
+                return Ok(());
+            }
+        }
+        if !until_closed(out.flush())? {
+            return Ok(());
+        }
+    }
+    note_next_page(next);
+    Ok(())
+}
+
+/// `--folded`: `root;src;main.rs 1234` per file, in path order, as
+/// Brendan Gregg's `stackcollapse` scripts write stacks, so `flamegraph.pl`
+/// or `inferno-flamegraph` draw disk usage with each frame a directory.
+pub fn print_folded(config: &Config, summary: &Summary) -> io::Result<()> {
+    let root_name = config.root.file_name().map_or_else(
+        || config.root.display().to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    );
+    let mut files: Vec<_> = summary.files.iter().collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for file in files {
+        let mut stack = folded_frame(&root_name);
+        for part in file.rel(&config.root).components() {
+            stack.push(';');
+            stack.push_str(&folded_frame(&part.as_os_str().to_string_lossy()));
+        }
+        if !until_closed(writeln!(out, "{} {}", stack, file.size))? {
+            return Ok(());
+        }
+    }
+    until_closed(out.flush()).map(|_| ())
+}
+
+/// A name as one frame: `;` separates frames and a newline ends the stack.
+fn folded_frame(name: &str) -> String {
+    name.replace([';', '\n', '\r'], "_")
+}
+
+/// Whether to keep writing: false once the reader has gone away.
+pub fn until_closed(written: io::Result<()>) -> io::Result<bool> {
+    match written {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// On stderr, so the page itself stays clean for whatever reads it.
+pub fn note_next_page(next: Option<usize>) {
+    if let Some(offset) = next {
+        eprintln!("More files follow; continue with --offset {}", offset);
+    }
+}
+
+/// `--oneline`: `1,234 files · 56.7 MB · 234,567 lines · largest: src/ (30 MB)`,
+/// short enough for a shell prompt or a tmux status bar. Never colored, as
+/// escape codes would throw off a prompt's width.
+pub fn print_oneline(config: &Config, summary: &Summary) {
+    println!("{}", oneline(config, summary));
+}
+
+fn oneline(config: &Config, summary: &Summary) -> String {
+    let t = |msg| config.lang.get(msg);
+    let mut parts = vec![
+        format!("{} {}", format_num(summary.total_files), t(Msg::Files)),
+        config.size(summary.total_size),
+    ];
+    if !config.skip_lines {
+        parts.push(format!(
+            "{} {}",
+            format_num(summary.total_lines),
+            t(Msg::Lines)
+        ));
+    }
+    if let Some((path, size)) = &summary.largest_dir {
+        parts.push(format!(
+            "{}: {}/ ({})",
+            t(Msg::Largest),
+            display_relative_path(path, &config.root).trim_end_matches('/'),
+            config.size(*size)
+        ));
+    }
+    parts.join(" · ")
+}
+
+const MIN_NAME_WIDTH: usize = 16;
+
+/// Print an aligned table. Every column is right-aligned except the last,
+/// which holds a name/path and is ellipsized to fit the terminal.
+pub fn print_table<C: TableCell>(style: &Style, title: &str, headers: &[&str], rows: &[Vec<C>]) {
+    println!("{}", style.label(title));
+    if rows.is_empty() {
+        println!("  {}", style.value("-"));
+        return;
+    }
+
+    let last = headers.len() - 1;
+    let mut widths: Vec<usize> = headers.iter().map(|h| width(h)).collect();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(width(cell.text()));
+        }
+    }
+
+    let fixed: usize = widths[..last].iter().map(|w| w + 2).sum::<usize>() + 2;
+    let max_name = terminal_width()
+        .map(|cols| cols.saturating_sub(fixed).max(MIN_NAME_WIDTH))
+        .unwrap_or(usize::MAX);
+
+    let render = |cells: Vec<(&str, Option<u64>)>, header: bool| {
+        let mut line = String::from("  ");
+        for (i, (cell, bytes)) in cells.into_iter().enumerate() {
+            let text = if i == last {
+                ellipsize_middle(cell, max_name)
+            } else {
+                let pad = widths[i].saturating_sub(width(cell));
+                format!("{}{}  ", " ".repeat(pad), cell)
+            };
+            if header {
+                line.push_str(&style.label(&text));
+            } else {
+                line.push_str(&style.sized(&text, bytes));
+            }
+        }
+        println!("{}", line.trim_end());
+    };
+
+    render(headers.iter().map(|h| (*h, None)).collect(), true);
+    for row in rows {
+        render(row.iter().map(|c| (c.text(), c.bytes())).collect(), false);
+    }
+}
@@ -0,0 +1,187 @@
+//! `--reuse-unchanged`: a coarse incremental scan. Every such scan stores
+//! the tree it saw in the root's store: each directory's modification time
+//! and entry count, and the records of the files directly in it. The next
+//! one still lists every directory, but where a directory's time and entry
+//! count are what they were, the stored records of its files stand in for
+//! stat'ing and reading them. Adding, removing or renaming a file changes
+//! both; editing one in place changes neither, so there's no per-file
+//! checking to pay for, and no per-file staleness caught either.
+//!
+//! The file is `MAGIC` and the options the records depend on (see
+//! `checkpoint::header`), then `RECORD` entries as in the checkpoint and
+//! `DIR` entries: a path, its modification time and its entry count.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::{Mutex, RwLock},
+    time::SystemTime,
+};
+
+use tracing::debug;
+
+use crate::checkpoint::{
+    header, read_path, read_record, read_time, read_u8, write_path, write_record, write_time,
+};
+use crate::config::Config;
+use crate::scan::FileRecord;
+use crate::spill::read_u64;
+use crate::store::RootStore;
+
+const FILE_NAME: &str = "tree";
+const MAGIC: &[u8] = b"tengok tree 1\n";
+
+const RECORD: u8 = 1;
+const DIR: u8 = 2;
+
+/// What tells a directory apart from how it was: its modification time and
+/// how many entries it holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirState {
+    mtime: Option<SystemTime>,
+    entries: u64,
+}
+
+/// The stored tree, as the walk's threads consult it, and the directories
+/// they've seen for the next one.
+#[derive(Default)]
+pub struct Reuse {
+    stored: HashMap<PathBuf, DirState>,
+    /// Taken out as their directories are reused.
+    records: Mutex<HashMap<PathBuf, Vec<FileRecord>>>,
+    reused: RwLock<HashSet<PathBuf>>,
+    seen: Mutex<Vec<(PathBuf, DirState)>>,
+}
+
+impl Reuse {
+    /// The tree the last `--reuse-unchanged` scan of the root stored with the
+    /// same options; empty when there's none.
+    pub fn load(store: &RootStore, config: &Config) -> io::Result<Self> {
+        let file = match File::open(store.path(FILE_NAME)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Reuse::default()),
+            Err(err) => return Err(err),
+        };
+        let mut input = BufReader::new(file);
+        let header = header(MAGIC, config);
+        let mut start = vec![0; header.len()];
+        if input.read_exact(&mut start).is_err() || start != header {
+            debug!("stored tree is for other options");
+            return Ok(Reuse::default());
+        }
+        let mut stored = HashMap::new();
+        let mut records: HashMap<PathBuf, Vec<FileRecord>> = HashMap::new();
+        // The tree is only replaced whole, so it ends at a tag.
+        while let Ok(tag) = read_u8(&mut input) {
+            match tag {
+                RECORD => {
+                    let record = read_record(&mut input)?;
+                    records
+                        .entry(record.parent.clone())
+                        .or_default()
+                        .push(record);
+                }
+                DIR => {
+                    let dir = read_path(&mut input)?;
+                    let mtime = read_time(&mut input)?;
+                    let entries = read_u64(&mut input)?;
+                    stored.insert(dir, DirState { mtime, entries });
+                }
+                _ => return Err(io::ErrorKind::InvalidData.into()),
+            }
+        }
+        debug!(dirs = stored.len(), "stored tree loaded");
+        Ok(Reuse {
+            stored,
+            records: Mutex::new(records),
+            ..Reuse::default()
+        })
+    }
+
+    /// Notes the state of `dir`, which the walk is entering, and returns the
+    /// stored records of the files in it when it's unchanged.
+    pub fn enter(&self, dir: &Path, mtime: Option<SystemTime>) -> Option<Vec<FileRecord>> {
+        let entries = fs::read_dir(dir).ok()?.count() as u64;
+        let state = DirState { mtime, entries };
+        lock(&self.seen).push((dir.to_path_buf(), state));
+        if mtime.is_none() || self.stored.get(dir) != Some(&state) {
+            return None;
+        }
+        self.reused
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(dir.to_path_buf());
+        Some(lock(&self.records).remove(dir).unwrap_or_default())
+    }
+
+    /// Whether the files in `dir` came from the stored tree.
+    pub fn is_reused(&self, dir: &Path) -> bool {
+        self.reused
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(dir)
+    }
+
+    pub fn reused_dirs(&self) -> usize {
+        self.reused.read().map_or(0, |reused| reused.len())
+    }
+}
+
+/// The tree being stored for the next scan, staged next to the stored one
+/// until it's complete.
+pub struct Writer {
+    staged: PathBuf,
+    path: PathBuf,
+    out: BufWriter<File>,
+}
+
+impl Writer {
+    pub fn create(store: &RootStore, config: &Config) -> io::Result<Self> {
+        let staged = store.path(&format!("{}.{}.tmp", FILE_NAME, process::id()));
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        // The paths of a whole tree are nobody else's business.
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut out = BufWriter::new(options.open(&staged)?);
+        out.write_all(&header(MAGIC, config))?;
+        Ok(Writer {
+            staged,
+            path: store.path(FILE_NAME),
+            out,
+        })
+    }
+
+    pub fn push(&mut self, record: &FileRecord) -> io::Result<()> {
+        self.out.write_all(&[RECORD])?;
+        write_record(&mut self.out, record)
+    }
+
+    /// Adds the directories the walk saw, and replaces the stored tree.
+    pub fn finish(mut self, reuse: Reuse) -> io::Result<()> {
+        let seen = reuse.seen.into_inner().unwrap_or_else(|e| e.into_inner());
+        for (dir, state) in seen {
+            self.out.write_all(&[DIR])?;
+            write_path(&mut self.out, &dir)?;
+            write_time(&mut self.out, state.mtime)?;
+            self.out.write_all(&state.entries.to_le_bytes())?;
+        }
+        self.out.flush()?;
+        self.out.get_ref().sync_all()?;
+        fs::rename(&self.staged, &self.path)
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        // Gone already once `finish` renamed it.
+        let _ = fs::remove_file(&self.staged);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,2753 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crossbeam_channel::{Sender, bounded};
+use ignore::{
+    Match, WalkBuilder, WalkState,
+    gitignore::{Gitignore, GitignoreBuilder},
+};
+use regex::bytes::Regex;
+use tracing::{debug, info, warn};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::budget::BudgetTotal;
+use crate::checkpoint::Checkpoint;
+use crate::comments::{self, CommentStat};
+use crate::config::{Config, FollowLinks, LineBackend, LineRules};
+use crate::docs::{self, DocStat};
+use crate::expr::MetricTotal;
+use crate::git::GitDir;
+use crate::gitignore;
+use crate::linguist::{Classifier, Kind};
+use crate::mime::ExecKind;
+use crate::names::{CaseIndex, NameLint};
+use crate::profile::{Phase, Profile, ThreadTimer, Timings};
+use crate::progress::Progress;
+use crate::reuse::{Reuse, Writer};
+use crate::sketch::Sketch;
+use crate::spill::{self, Spill};
+use crate::store::RootStore;
+use crate::submodules::{self, Submodules};
+use crate::throttle::Throttled;
+use crate::vfs::{FileSystem, Metadata, OsFs, ReadSeek};
+use crate::{
+    dupes, history, junk, lang, license, lists, mime, minified, mounts, pruned, secrets, vfs,
+};
+
+const BINARY_EXTS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "bmp", "webp", "ico", "svg", "tif", "tiff", "pdf", "zip", "gz",
+    "bz2", "xz", "7z", "tar", "rar", "mp4", "mov", "avi", "mkv", "mp3", "wav", "flac", "ogg",
+    "ttf", "otf", "woff", "woff2", "exe", "dll", "so", "dylib", "class", "jar", "bin",
+];
+
+#[cfg(feature = "compress")]
+const COMPRESS_SAMPLE_BYTES: u64 = 1024 * 1024;
+
+/// Bytes per read when counting lines; `--sequential` reads in bigger
+/// chunks, since every round trip to a network share costs latency.
+const READ_BUFFER: usize = 64 * 1024;
+const SEQUENTIAL_READ_BUFFER: usize = 1024 * 1024;
+
+/// Bucket used for files without an extension in the per-extension breakdown.
+pub const NO_EXT: &str = "(none)";
+
+/// Two-part extensions that name a different kind of file than their last
+/// part does: an archive rather than a compressed stream, type declarations
+/// rather than TypeScript, a minified asset, a source map.
+const COMPOUND_EXTS: &[&str] = &[
+    "tar.gz", "tar.bz2", "tar.xz", "tar.zst", "tar.lz4", "d.ts", "d.mts", "d.cts", "min.js",
+    "min.mjs", "min.css", "js.map", "css.map",
+];
+
+/// Entries that couldn't be read (stat, read_dir or line-count failures) in
+/// any scan of this process. They are logged; unstatable entries are left
+/// out of the totals, files whose lines couldn't be counted stay in with 0
+/// lines.
+static ERRORS: AtomicU64 = AtomicU64::new(0);
+
+/// How many entries couldn't be read so far; the CLI exits with status 2
+/// when there were any.
+pub fn error_count() -> u64 {
+    ERRORS.load(Ordering::Relaxed)
+}
+
+fn scan_error() {
+    ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Errors a network filesystem can return once and not again: I/O errors,
+/// timeouts, interrupted or would-block calls, and on Windows a dropped or
+/// busy network connection.
+pub fn is_transient(err: &io::Error) -> bool {
+    #[cfg(unix)]
+    const RAW: &[i32] = &[5]; // EIO
+    #[cfg(windows)]
+    const RAW: &[i32] = &[59, 64, 121]; // ERROR_UNEXP_NET_ERR, ERROR_NETNAME_DELETED, ERROR_SEM_TIMEOUT
+    #[cfg(not(any(unix, windows)))]
+    const RAW: &[i32] = &[];
+    matches!(
+        err.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock
+    ) || err.raw_os_error().is_some_and(|code| RAW.contains(&code))
+}
+
+/// `op`, tried again up to `--retries` times while it fails transiently,
+/// sleeping 10ms, 20ms, 40ms, ... (at most a second) in between.
+fn retrying<T>(config: &Config, mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let mut delay = Duration::from_millis(10);
+    let mut tries = 0;
+    loop {
+        match op() {
+            Err(err) if tries < config.retries && is_transient(&err) => {
+                debug!("retrying in {:?}: {}", delay, err);
+                thread::sleep(delay);
+                delay = (delay * 2).min(Duration::from_secs(1));
+                tries += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileStat {
+    pub path: PathBuf,
+    pub size: u64,
+    pub lines: u64,
+    pub mtime: Option<SystemTime>,
+}
+
+/// Files with an executable bit (Unix), by what their first bytes say.
+#[derive(Debug, Clone, Default)]
+pub struct Executables {
+    pub scripts: GroupStat,
+    pub binaries: GroupStat,
+    pub other: GroupStat,
+}
+
+impl Executables {
+    fn slot(&mut self, kind: ExecKind) -> &mut GroupStat {
+        match kind {
+            ExecKind::Script => &mut self.scripts,
+            ExecKind::Binary => &mut self.binaries,
+            ExecKind::Other => &mut self.other,
+        }
+    }
+
+    pub fn files(&self) -> u64 {
+        self.scripts.files + self.binaries.files + self.other.files
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Decompressed {
+    pub files: u64,
+    pub size: u64,
+    pub bytes: u64,
+}
+
+/// Symlinks the walker passed over without following; only counted with
+/// `--verbose`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Symlinks {
+    /// Pointing inside the scanned root.
+    pub internal_files: u64,
+    pub internal_dirs: u64,
+    /// Pointing outside it.
+    pub external_files: u64,
+    pub external_dirs: u64,
+    /// Dangling, or part of a loop.
+    pub broken: u64,
+    /// How many of the above are NTFS junctions (Windows only).
+    pub junctions: u64,
+}
+
+impl Symlinks {
+    pub fn total(&self) -> u64 {
+        self.internal_files
+            + self.internal_dirs
+            + self.external_files
+            + self.external_dirs
+            + self.broken
+    }
+
+    /// Classify the link at `link`; `root` must be canonical.
+    fn add(&mut self, link: &Path, root: &Path) {
+        #[cfg(windows)]
+        if crate::vfs::is_junction(link) {
+            self.junctions += 1;
+        }
+        let Some((target, meta)) = std::fs::canonicalize(link)
+            .ok()
+            .and_then(|target| std::fs::metadata(&target).ok().map(|m| (target, m)))
+        else {
+            self.broken += 1;
+            return;
+        };
+        let slot = match (target.starts_with(root), meta.is_dir()) {
+            (true, false) => &mut self.internal_files,
+            (true, true) => &mut self.internal_dirs,
+            (false, false) => &mut self.external_files,
+            (false, true) => &mut self.external_dirs,
+        };
+        *slot += 1;
+    }
+}
+
+/// Aggregated totals for a group of files (a directory, an extension, ...).
+#[derive(Debug, Clone, Default)]
+pub struct GroupStat {
+    pub files: u64,
+    pub size: u64,
+    pub lines: u64,
+    pub newest: Option<SystemTime>,
+    /// Member with the most lines (first one wins ties).
+    pub max_lines: Option<FileStat>,
+}
+
+impl GroupStat {
+    pub fn add(&mut self, record: &FileRecord) {
+        self.files += 1;
+        self.size += record.size;
+        self.lines += record.lines;
+        self.newest = self.newest.max(record.mtime);
+        if record.lines > self.max_lines.as_ref().map_or(0, |f| f.lines) {
+            self.max_lines = Some(record.clone().into());
+        }
+    }
+
+    // `newest` is left alone: it only feeds mtime sorting and recomputing it
+    // would need every member file. A removed `max_lines` is cleared and
+    // left for the caller to recompute, like `Summary::max_lines_file`.
+    pub fn remove(&mut self, record: &FileRecord) {
+        self.files -= 1;
+        self.size -= record.size;
+        self.lines -= record.lines;
+        if self
+            .max_lines
+            .as_ref()
+            .is_some_and(|f| f.path == record.path)
+        {
+            self.max_lines = None;
+        }
+    }
+}
+
+/// Sum of one plugin's metric, and how many files had a non-zero value.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PluginTotal {
+    pub value: u64,
+    pub files: u64,
+}
+
+/// Per-file distribution figures derived from `Summary::files`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub mean_size: f64,
+    pub median_size: f64,
+    pub mean_lines: f64,
+    pub median_lines: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub total_files: u64,
+    pub total_size: u64,
+    pub total_lines: u64,
+    pub total_compressed: u64,
+    /// `--count-pattern` matches, and how many files had at least one.
+    pub total_matches: u64,
+    pub matching_files: u64,
+    /// Per `--plugin`, in config order.
+    pub plugins: Vec<PluginTotal>,
+    /// Per `--metric`, in config order.
+    pub metrics: Vec<MetricTotal>,
+    /// Per budget, in config order.
+    pub budgets: Vec<BudgetTotal>,
+    pub max_lines_file: Option<FileStat>,
+    /// Most bytes; the first one seen wins a tie.
+    pub largest_file: Option<FileStat>,
+    /// Most recently modified file.
+    pub newest_file: Option<FileStat>,
+    pub largest_dir: Option<(PathBuf, u64)>,   // (path, size)
+    pub busiest_dir: Option<(PathBuf, u64)>,   // (path, files)
+    pub deepest_dir: Option<(PathBuf, usize)>, // (path, depth below the root)
+    /// The directory with the most direct entries, files or not.
+    pub widest_dir: Option<(PathBuf, u64)>,
+    /// Directories with more than `--max-entries` direct entries, most
+    /// first.
+    pub crowded_dirs: Vec<(PathBuf, u64)>,
+    pub dirs: HashMap<PathBuf, GroupStat>,
+    /// Files and bytes directly in each directory, per `--largest-dir-for`
+    /// extension.
+    pub ext_dirs: HashMap<String, HashMap<PathBuf, GroupStat>>,
+    pub exts: HashMap<String, GroupStat>,
+    pub langs: HashMap<&'static str, GroupStat>,
+    /// Lines-per-file distributions for the median columns of the
+    /// `--by-ext` and `--by-lang` tables.
+    pub ext_lines: HashMap<String, Sketch>,
+    pub lang_lines: HashMap<&'static str, Sketch>,
+    pub executables: Executables,
+    /// Extensionless scripts per shebang interpreter, with `--scripts`.
+    pub interpreters: HashMap<String, GroupStat>,
+    /// Only filled with `--comments`.
+    pub comments: CommentStat,
+    pub lang_comments: HashMap<&'static str, CommentStat>,
+    pub mimes: HashMap<&'static str, GroupStat>,
+    /// Files and bytes per owning user id; only filled with `--by-owner`.
+    pub owners: HashMap<u32, GroupStat>,
+    /// Files and bytes per device (Unix), and where the devices are
+    /// mounted; only filled with `--by-mount`.
+    pub devices: HashMap<u64, GroupStat>,
+    pub mount_points: Vec<(u64, PathBuf, String)>,
+    /// Files and bytes per asset class; only filled with `--by-class`.
+    pub classes: HashMap<String, GroupStat>,
+    /// Files and bytes per `--group-by` group.
+    pub groups: HashMap<String, GroupStat>,
+    /// Test files per (code) language; only filled with `--tests`.
+    pub lang_tests: HashMap<&'static str, GroupStat>,
+    /// Indexed by directory depth below the root (0 = files in the root);
+    /// only filled with `--by-depth`.
+    pub depths: Vec<GroupStat>,
+    /// Only filled with `--linguist`.
+    pub generated: GroupStat,
+    pub vendored: GroupStat,
+    /// Files below a `--deps` directory; the rest are source.
+    pub deps: GroupStat,
+    /// Minified and bundled assets.
+    pub minified: GroupStat,
+    /// Cloud placeholders, counted by size without reading them.
+    pub placeholders: GroupStat,
+    /// Files whose lines the size limit or binary extensions left out.
+    pub uncounted: GroupStat,
+    /// Files whose absolute path reaches Windows' `MAX_PATH`.
+    pub long_paths: u64,
+    /// Only filled with `--lint-names`.
+    pub names: NameLint,
+    /// Only filled with `--case-collisions`.
+    pub case_paths: CaseIndex,
+    /// Paths per file name; only filled with `--dup-names`.
+    pub basenames: HashMap<String, BTreeSet<PathBuf>>,
+    /// The root's submodules; only loaded with `--submodules exclude` or
+    /// `separate`.
+    pub submodules: Submodules,
+    /// The root's `.git`; only filled with `--include-git`.
+    pub git: GitDir,
+    /// OS junk files by kind; only filled with `--junk`.
+    pub junk: HashMap<&'static str, GroupStat>,
+    /// Files and bytes below each outermost hidden entry, relative to the
+    /// root; only filled with `--dotfiles`.
+    pub dotfiles: HashMap<PathBuf, GroupStat>,
+    /// Files and bytes below each outermost build-output or cache directory;
+    /// only filled with `--no-ignore` in a git repository.
+    pub untracked: HashMap<PathBuf, GroupStat>,
+    /// License files per license; only filled with `--licenses`.
+    pub licenses: HashMap<String, BTreeSet<PathBuf>>,
+    /// Docs and code per top-level directory, relative to the root; only
+    /// filled with `--docs`.
+    pub docs: HashMap<PathBuf, DocStat>,
+    /// Files under the `--tiny` threshold.
+    pub tiny: GroupStat,
+    /// Files read through a decompressor (`--decompress`): their count,
+    /// bytes on disk, and bytes decompressed.
+    pub decompressed: Decompressed,
+    /// Size and line-count distributions; only fed with `--verbose`.
+    pub size_sketch: Sketch,
+    pub lines_sketch: Sketch,
+    /// Filled by the parallel walker with `--verbose`.
+    pub symlinks: Symlinks,
+    pub files: Vec<FileRecord>,
+    /// Files with the same contents; only filled with `--dupes`.
+    pub duplicates: Vec<dupes::Group>,
+    /// What each ignore rule kept out; only filled with `--profile-ignores`.
+    pub ignore_rules: Vec<pruned::RuleStat>,
+    /// Virtual filesystems mounted below the root that the walk skipped,
+    /// with their types; empty with `--virtual-fs`.
+    pub skipped_mounts: Vec<(PathBuf, String)>,
+    /// The layers behind an overlayfs root; only filled with
+    /// `--overlay-layers`.
+    pub overlay_layers: Vec<mounts::Layer>,
+    /// When the walk started and the summary was finished.
+    pub started_at: Option<SystemTime>,
+    pub finished_at: Option<SystemTime>,
+    /// Time per phase; only filled with `--profile`.
+    pub profile: Option<Profile>,
+}
+
+impl Summary {
+    pub fn add(&mut self, record: &FileRecord, config: &Config) {
+        if config.submodules != submodules::Mode::Include {
+            self.submodules.add(record);
+            if self.skips(record, config) {
+                return;
+            }
+        }
+        self.total_files += 1;
+        self.total_size += record.size;
+        self.total_lines += record.lines;
+        self.total_compressed += record.compressed;
+        self.total_matches += record.matches;
+        self.matching_files += u64::from(record.matches > 0);
+        if self.plugins.len() < record.metrics.len() {
+            self.plugins
+                .resize(record.metrics.len(), PluginTotal::default());
+        }
+        for (total, &value) in self.plugins.iter_mut().zip(&record.metrics) {
+            total.value += value;
+            total.files += u64::from(value > 0);
+        }
+
+        if self.metrics.len() < config.metrics.len() {
+            self.metrics
+                .resize(config.metrics.len(), MetricTotal::default());
+        }
+        for (metric, total) in config.metrics.iter().zip(&mut self.metrics) {
+            if let Some(value) = metric.value(record, &config.root) {
+                total.add(value);
+            }
+        }
+        if self.budgets.len() < config.budgets.len() {
+            self.budgets
+                .resize(config.budgets.len(), BudgetTotal::default());
+        }
+        for (budget, total) in config.budgets.iter().zip(&mut self.budgets) {
+            total.add(budget, record, &config.root);
+        }
+
+        if record.mtime.is_some() && record.mtime > self.newest_file.as_ref().and_then(|f| f.mtime)
+        {
+            self.newest_file = Some(FileStat {
+                path: record.path.clone(),
+                size: record.size,
+                lines: record.lines,
+                mtime: record.mtime,
+            });
+        }
+
+        if self
+            .largest_file
+            .as_ref()
+            .is_none_or(|f| record.size > f.size)
+        {
+            self.largest_file = Some(FileStat {
+                path: record.path.clone(),
+                size: record.size,
+                lines: record.lines,
+                mtime: record.mtime,
+            });
+        }
+
+        let current_max = self.max_lines_file.as_ref().map(|f| f.lines).unwrap_or(0);
+
+        if record.lines > current_max {
+            self.max_lines_file = Some(FileStat {
+                path: record.path.clone(),
+                size: record.size,
+                lines: record.lines,
+                mtime: record.mtime,
+            });
+        }
+
+        if config.by_ext {
+            let key = extension_key(&record.path);
+            self.ext_lines
+                .entry(key.clone())
+                .or_default()
+                .add(record.lines);
+            self.exts.entry(key).or_default().add(record);
+        }
+
+        if let Some(lang) = record.lang {
+            if config.tests && docs::is_code(lang) && config.is_test(record.rel(&config.root)) {
+                self.lang_tests.entry(lang).or_default().add(record);
+            }
+            self.langs.entry(lang).or_default().add(record);
+            self.lang_lines.entry(lang).or_default().add(record.lines);
+            if let Some(comments) = record.comments {
+                self.comments.add(comments, record.lines);
+                self.lang_comments
+                    .entry(lang)
+                    .or_default()
+                    .add(comments, record.lines);
+            }
+        }
+
+        if config.by_depth {
+            let depth = depth_below(&record.parent, &config.root);
+            if self.depths.len() <= depth {
+                self.depths.resize(depth + 1, GroupStat::default());
+            }
+            self.depths[depth].add(record);
+        }
+
+        if config.tiny.is_some_and(|limit| record.size < limit) {
+            self.tiny.add(record);
+        }
+
+        if let Some(uid) = record.owner.filter(|_| config.by_owner) {
+            self.owners.entry(uid).or_default().add(record);
+        }
+        if let Some((dev, _)) = record.file_id.filter(|_| config.by_mount) {
+            self.devices.entry(dev).or_default().add(record);
+        }
+
+        if let Some(classes) = &config.classes {
+            let class = classes.classify(record.rel(&config.root));
+            match self.classes.get_mut(class) {
+                Some(stat) => stat.add(record),
+                None => {
+                    let mut stat = GroupStat::default();
+                    stat.add(record);
+                    self.classes.insert(class.to_string(), stat);
+                }
+            }
+        }
+
+        if let Some(groups) = &config.groups {
+            let group = groups.group(record.rel(&config.root));
+            self.groups.entry(group).or_default().add(record);
+        }
+
+        if let Some(bytes) = record.decompressed {
+            self.decompressed.files += 1;
+            self.decompressed.size += record.size;
+            self.decompressed.bytes += bytes;
+        }
+
+        if config.verbose {
+            self.size_sketch.add(record.size);
+            self.lines_sketch.add(record.lines);
+        }
+
+        match record.kind {
+            Some(Kind::Generated) => self.generated.add(record),
+            Some(Kind::Vendored) => self.vendored.add(record),
+            None => {}
+        }
+        if in_dep_dir(&record.path, config) {
+            self.deps.add(record);
+        }
+        if record.minified {
+            self.minified.add(record);
+        }
+        if record.placeholder {
+            self.placeholders.add(record);
+        }
+        if record.uncounted {
+            self.uncounted.add(record);
+        }
+        self.long_paths += u64::from(vfs::is_long_path(&record.path));
+        if config.lint_names {
+            self.names.add(
+                record
+                    .path
+                    .strip_prefix(&config.root)
+                    .unwrap_or(&record.path),
+            );
+        }
+        if config.case_collisions {
+            self.case_paths.add(
+                record
+                    .path
+                    .strip_prefix(&config.root)
+                    .unwrap_or(&record.path),
+            );
+        }
+        if let Some(name) = dup_name(&record.path, config) {
+            self.basenames
+                .entry(name)
+                .or_default()
+                .insert(record.path.clone());
+        }
+        if let Some(entry) = dotfile(&record.path, config) {
+            self.dotfiles.entry(entry).or_default().add(record);
+        }
+        if let Some(dir) = gitignore::candidate(&record.path, config) {
+            self.untracked.entry(dir).or_default().add(record);
+        }
+        if let Some(kind) = junk_kind(&record.path, config) {
+            self.junk.entry(kind).or_default().add(record);
+        }
+        if config.include_git {
+            self.git.add(record, &config.root);
+        }
+        if config.docs
+            && let Some((top, rel)) = docs_dir(&record.path, config)
+        {
+            self.docs.entry(top).or_default().add(rel, record.size);
+        }
+        if let Some(license) = &record.license {
+            self.licenses
+                .entry(license.clone())
+                .or_default()
+                .insert(record.path.clone());
+        }
+
+        if let Some(category) = record.category {
+            self.mimes.entry(category).or_default().add(record);
+        }
+        if let Some(kind) = record.executable {
+            self.executables.slot(kind).add(record);
+        }
+        if let Some(interpreter) = &record.interpreter {
+            self.interpreters
+                .entry(interpreter.clone())
+                .or_default()
+                .add(record);
+        }
+
+        self.dirs
+            .entry(record.parent.clone())
+            .or_default()
+            .add(record);
+        if !config.largest_dir_for.is_empty() {
+            let key = extension_key(&record.path);
+            if config.largest_dir_for.contains(&key) {
+                self.ext_dirs
+                    .entry(key)
+                    .or_default()
+                    .entry(record.parent.clone())
+                    .or_default()
+                    .add(record);
+            }
+        }
+    }
+
+    /// Add a record and, when lists need it, keep its per-file stats.
+    fn collect(&mut self, record: FileRecord, config: &Config) {
+        self.add(&record, config);
+        if config.keep_files() && !self.skips(&record, config) {
+            self.files.push(record);
+        }
+    }
+
+    /// Undo a previous `add`. If the record held the max-lines or newest-file
+    /// slot, the slot is cleared and the caller has to pick a new one.
+    pub fn remove(&mut self, record: &FileRecord, config: &Config) {
+        if config.submodules != submodules::Mode::Include {
+            self.submodules.remove(record);
+            if self.skips(record, config) {
+                return;
+            }
+        }
+        self.total_files -= 1;
+        self.total_size -= record.size;
+        self.total_lines -= record.lines;
+        self.total_compressed -= record.compressed;
+        self.total_matches -= record.matches;
+        self.matching_files -= u64::from(record.matches > 0);
+        for (total, &value) in self.plugins.iter_mut().zip(&record.metrics) {
+            total.value -= value;
+            total.files -= u64::from(value > 0);
+        }
+        for (metric, total) in config.metrics.iter().zip(&mut self.metrics) {
+            if let Some(value) = metric.value(record, &config.root) {
+                total.remove(value);
+            }
+        }
+        for (budget, total) in config.budgets.iter().zip(&mut self.budgets) {
+            total.remove(budget, record, &config.root);
+        }
+
+        if self
+            .max_lines_file
+            .as_ref()
+            .is_some_and(|f| f.path == record.path)
+        {
+            self.max_lines_file = None;
+        }
+        if self
+            .largest_file
+            .as_ref()
+            .is_some_and(|f| f.path == record.path)
+        {
+            self.largest_file = None;
+        }
+        if self
+            .newest_file
+            .as_ref()
+            .is_some_and(|f| f.path == record.path)
+        {
+            self.newest_file = None;
+        }
+
+        if config.by_ext {
+            let key = extension_key(&record.path);
+            if let Some(stat) = self.exts.get_mut(&key) {
+                stat.remove(record);
+                if stat.files == 0 {
+                    self.exts.remove(&key);
+                }
+            }
+            if let Some(sketch) = self.ext_lines.get_mut(&key) {
+                sketch.remove(record.lines);
+            }
+        }
+
+        if config.tiny.is_some_and(|limit| record.size < limit) {
+            self.tiny.remove(record);
+        }
+
+        if let Some(uid) = record.owner.filter(|_| config.by_owner)
+            && let Some(stat) = self.owners.get_mut(&uid)
+        {
+            stat.remove(record);
+            if stat.files == 0 {
+                self.owners.remove(&uid);
+            }
+        }
+        if let Some((dev, _)) = record.file_id.filter(|_| config.by_mount)
+            && let Some(stat) = self.devices.get_mut(&dev)
+        {
+            stat.remove(record);
+            if stat.files == 0 {
+                self.devices.remove(&dev);
+            }
+        }
+
+        if let Some(classes) = &config.classes {
+            let class = classes.classify(record.rel(&config.root));
+            if let Some(stat) = self.classes.get_mut(class) {
+                stat.remove(record);
+                if stat.files == 0 {
+                    self.classes.remove(class);
+                }
+            }
+        }
+
+        if let Some(groups) = &config.groups {
+            let group = groups.group(record.rel(&config.root));
+            if let Some(stat) = self.groups.get_mut(&group) {
+                stat.remove(record);
+                if stat.files == 0 {
+                    self.groups.remove(&group);
+                }
+            }
+        }
+
+        if let Some(bytes) = record.decompressed {
+            self.decompressed.files -= 1;
+            self.decompressed.size -= record.size;
+            self.decompressed.bytes -= bytes;
+        }
+
+        if config.verbose {
+            self.size_sketch.remove(record.size);
+            self.lines_sketch.remove(record.lines);
+        }
+
+        match record.kind {
+            Some(Kind::Generated) => self.generated.remove(record),
+            Some(Kind::Vendored) => self.vendored.remove(record),
+            None => {}
+        }
+        if in_dep_dir(&record.path, config) {
+            self.deps.remove(record);
+        }
+        if record.minified {
+            self.minified.remove(record);
+        }
+        if record.placeholder {
+            self.placeholders.remove(record);
+        }
+        if record.uncounted {
+            self.uncounted.remove(record);
+        }
+        self.long_paths -= u64::from(vfs::is_long_path(&record.path));
+        if config.lint_names {
+            self.names.remove(
+                record
+                    .path
+                    .strip_prefix(&config.root)
+                    .unwrap_or(&record.path),
+            );
+        }
+        if config.case_collisions {
+            self.case_paths.remove(
+                record
+                    .path
+                    .strip_prefix(&config.root)
+                    .unwrap_or(&record.path),
+            );
+        }
+        if let Some(name) = dup_name(&record.path, config)
+            && let Some(paths) = self.basenames.get_mut(&name)
+        {
+            paths.remove(&record.path);
+            if paths.is_empty() {
+                self.basenames.remove(&name);
+            }
+        }
+        if let Some(entry) = dotfile(&record.path, config)
+            && let Some(stat) = self.dotfiles.get_mut(&entry)
+        {
+            stat.remove(record);
+            if stat.files == 0 {
+                self.dotfiles.remove(&entry);
+            }
+        }
+        if let Some(dir) = gitignore::candidate(&record.path, config)
+            && let Some(stat) = self.untracked.get_mut(&dir)
+        {
+            stat.remove(record);
+            if stat.files == 0 {
+                self.untracked.remove(&dir);
+            }
+        }
+        if config.include_git {
+            self.git.remove(record, &config.root);
+        }
+        if let Some(kind) = junk_kind(&record.path, config)
+            && let Some(stat) = self.junk.get_mut(kind)
+        {
+            stat.remove(record);
+            if stat.files == 0 {
+                self.junk.remove(kind);
+            }
+        }
+        if config.docs
+            && let Some((top, rel)) = docs_dir(&record.path, config)
+            && let Some(stat) = self.docs.get_mut(&top)
+        {
+            stat.remove(rel, record.size);
+            if stat.files == 0 {
+                self.docs.remove(&top);
+            }
+        }
+        if let Some(license) = &record.license
+            && let Some(paths) = self.licenses.get_mut(license)
+        {
+            paths.remove(&record.path);
+            if paths.is_empty() {
+                self.licenses.remove(license);
+            }
+        }
+
+        if let Some(lang) = record.lang
+            && config.tests
+            && let Some(stat) = self.lang_tests.get_mut(lang)
+            && config.is_test(record.rel(&config.root))
+        {
+            stat.remove(record);
+            if stat.files == 0 {
+                self.lang_tests.remove(lang);
+            }
+        }
+        if let Some(lang) = record.lang
+            && let Some(stat) = self.langs.get_mut(lang)
+        {
+            stat.remove(record);
+            if stat.files == 0 {
+                self.langs.remove(lang);
+            }
+            if let Some(sketch) = self.lang_lines.get_mut(lang) {
+                sketch.remove(record.lines);
+            }
+            if let Some(comments) = record.comments {
+                self.comments.remove(comments, record.lines);
+                if let Some(stat) = self.lang_comments.get_mut(lang) {
+                    stat.remove(comments, record.lines);
+                    if stat.lines == 0 {
+                        self.lang_comments.remove(lang);
+                    }
+                }
+            }
+        }
+
+        if config.by_depth
+            && let Some(stat) = self
+                .depths
+                .get_mut(depth_below(&record.parent, &config.root))
+        {
+            stat.remove(record);
+            while self.depths.last().is_some_and(|d| d.files == 0) {
+                self.depths.pop();
+            }
+        }
+
+        if let Some(category) = record.category
+            && let Some(stat) = self.mimes.get_mut(category)
+        {
+            stat.remove(record);
+            if stat.files == 0 {
+                self.mimes.remove(category);
+            }
+        }
+        if let Some(kind) = record.executable {
+            self.executables.slot(kind).remove(record);
+        }
+        if let Some(interpreter) = &record.interpreter
+            && let Some(stat) = self.interpreters.get_mut(interpreter)
+        {
+            stat.remove(record);
+            if stat.files == 0 {
+                self.interpreters.remove(interpreter);
+            }
+        }
+
+        if let Some(stat) = self.dirs.get_mut(&record.parent) {
+            stat.remove(record);
+            if stat.files == 0 {
+                self.dirs.remove(&record.parent);
+            }
+        }
+        if !config.largest_dir_for.is_empty() {
+            let key = extension_key(&record.path);
+            if let Some(dirs) = self.ext_dirs.get_mut(&key) {
+                if let Some(stat) = dirs.get_mut(&record.parent) {
+                    stat.remove(record);
+                    if stat.files == 0 {
+                        dirs.remove(&record.parent);
+                    }
+                }
+                if dirs.is_empty() {
+                    self.ext_dirs.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Mean bytes per file; `None` without files.
+    pub fn average_size(&self) -> Option<f64> {
+        (self.total_files > 0).then(|| self.total_size as f64 / self.total_files as f64)
+    }
+
+    /// Mean lines per file; `None` without files.
+    pub fn average_lines(&self) -> Option<f64> {
+        (self.total_files > 0).then(|| self.total_lines as f64 / self.total_files as f64)
+    }
+
+    /// Mean and median over the kept files; `None` for an empty tree.
+    pub fn stats(&self) -> Option<Stats> {
+        if self.files.is_empty() {
+            return None;
+        }
+        let n = self.files.len() as f64;
+        let mut sizes: Vec<u64> = self.files.iter().map(|f| f.size).collect();
+        let mut lines: Vec<u64> = self.files.iter().map(|f| f.lines).collect();
+        Some(Stats {
+            mean_size: self.total_size as f64 / n,
+            median_size: median(&mut sizes),
+            mean_lines: self.total_lines as f64 / n,
+            median_lines: median(&mut lines),
+        })
+    }
+
+    /// `--dup-names`: how many file names occur more than once, and how
+    /// many files carry them.
+    pub fn duplicate_names(&self) -> (u64, u64) {
+        self.basenames
+            .values()
+            .filter(|paths| paths.len() > 1)
+            .fold((0, 0), |(names, files), paths| {
+                (names + 1, files + paths.len() as u64)
+            })
+    }
+
+    /// Whether `record` sits in a submodule left out by `--submodules
+    /// exclude`. It's still counted toward that submodule.
+    fn skips(&self, record: &FileRecord, config: &Config) -> bool {
+        config.submodules == submodules::Mode::Exclude && self.submodules.of(&record.path).is_some()
+    }
+
+    /// Everything outside the `--deps` directories.
+    pub fn source(&self) -> GroupStat {
+        GroupStat {
+            files: self.total_files - self.deps.files,
+            size: self.total_size - self.deps.size,
+            lines: self.total_lines - self.deps.lines,
+            ..GroupStat::default()
+        }
+    }
+
+    /// `widest_dir` and `crowded_dirs`, from what the walk listed.
+    fn count_entries(&mut self, entries: EntryCounts, config: &Config) {
+        self.widest_dir = entries
+            .iter()
+            .max_by(|(a_dir, a), (b_dir, b)| a.cmp(b).then_with(|| b_dir.cmp(a_dir)))
+            .map(|(dir, n)| (dir.clone(), *n));
+        if config.max_entries > 0 {
+            self.crowded_dirs = entries
+                .into_iter()
+                .filter(|(_, n)| *n > config.max_entries)
+                .collect();
+            self.crowded_dirs
+                .sort_by(|(a_dir, a), (b_dir, b)| b.cmp(a).then_with(|| a_dir.cmp(b_dir)));
+        }
+    }
+
+    /// Derive the fields that depend on every record having been added.
+    pub fn finish(&mut self, root: &Path) {
+        self.largest_dir = self
+            .dirs
+            .iter()
+            .max_by(|(a_dir, a), (b_dir, b)| a.size.cmp(&b.size).then_with(|| b_dir.cmp(a_dir)))
+            .map(|(dir, stat)| (dir.clone(), stat.size));
+        self.busiest_dir = self
+            .dirs
+            .iter()
+            .max_by(|(a_dir, a), (b_dir, b)| a.files.cmp(&b.files).then_with(|| b_dir.cmp(a_dir)))
+            .map(|(dir, stat)| (dir.clone(), stat.files));
+        self.deepest_dir = self
+            .dirs
+            .keys()
+            .map(|dir| (dir, depth_below(dir, root)))
+            .max_by(|(a_dir, a), (b_dir, b)| a.cmp(b).then_with(|| b_dir.cmp(a_dir)))
+            .map(|(dir, depth)| (dir.clone(), depth));
+    }
+}
+
+/// Middle value, or the mean of the two middle values for an even count.
+fn median(values: &mut [u64]) -> f64 {
+    values.sort_unstable();
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] as f64 + values[mid] as f64) / 2.0
+    } else {
+        values[mid] as f64
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileRecord {
+    pub path: PathBuf,
+    pub parent: PathBuf,
+    pub size: u64,
+    pub lines: u64,
+    pub mtime: Option<SystemTime>,
+    /// Estimated zstd-compressed size; 0 unless `--compress-estimate`.
+    pub compressed: u64,
+    /// Detected language; only set with `--by-lang`.
+    pub lang: Option<&'static str>,
+    /// Sniffed content category; only set with `--mime`.
+    pub category: Option<&'static str>,
+    /// Generated/vendored tag; only set with `--linguist`.
+    pub kind: Option<Kind>,
+    /// `--count-pattern` matches; 0 when lines weren't counted.
+    pub matches: u64,
+    /// One value per `--plugin`, in config order.
+    pub metrics: Vec<u64>,
+    /// Minified or bundled asset; its lines are 0 unless `--include-minified`.
+    pub minified: bool,
+    /// Cloud placeholder whose contents were left unread.
+    pub placeholder: bool,
+    /// Lines left uncounted for `--max-line-bytes` or a binary extension.
+    pub uncounted: bool,
+    /// Comment lines with `--comments`; `None` when lines weren't counted
+    /// or the language has no known comment syntax.
+    pub comments: Option<u64>,
+    /// Shebang interpreter of an extensionless script; only set with
+    /// `--scripts`.
+    pub interpreter: Option<String>,
+    /// What a file with an executable bit is (Unix).
+    pub executable: Option<ExecKind>,
+    /// Why the file looks like a credential; only set with `--secrets`.
+    pub secret: Option<secrets::Reason>,
+    /// License of a LICENSE, COPYING or NOTICE file; only set with
+    /// `--licenses`.
+    pub license: Option<String>,
+    /// Device and inode (Unix), which hard links share.
+    pub file_id: Option<(u64, u64)>,
+    /// Owning user id (Unix).
+    pub owner: Option<u32>,
+    /// Last access, where the filesystem records it.
+    pub atime: Option<SystemTime>,
+    /// Bytes after decompression, for a file `--decompress` read through
+    /// its decompressor.
+    pub decompressed: Option<u64>,
+}
+
+impl FileRecord {
+    /// The path relative to `root`.
+    pub fn rel(&self, root: &Path) -> &Path {
+        self.path.strip_prefix(root).unwrap_or(&self.path)
+    }
+}
+
+impl From<FileRecord> for FileStat {
+    fn from(record: FileRecord) -> Self {
+        FileStat {
+            path: record.path,
+            size: record.size,
+            lines: record.lines,
+            mtime: record.mtime,
+        }
+    }
+}
+
+/// The walker every scan uses, so watch-mode rescans of a single directory
+/// apply exactly the same ignore rules as the initial scan.
+pub fn walk_builder(path: &Path, config: &Config) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(path);
+    builder
+        .hidden(!config.walks_hidden())
+        .git_ignore(!config.no_ignore)
+        .git_exclude(!config.no_ignore)
+        .git_global(!config.no_ignore)
+        .ignore(!config.no_ignore)
+        .parents(!config.no_ignore)
+        .follow_links(config.follow_links.is_some());
+    let virtual_mounts = if config.virtual_fs {
+        Vec::new()
+    } else {
+        mounts::virtual_below(path)
+    };
+    if !config.exclude_dirs.is_empty()
+        || config.only.is_some()
+        || config.walks_hidden()
+        || !virtual_mounts.is_empty()
+    {
+        // Pruned before descending, so nothing below is ever read.
+        let config = config.clone();
+        builder.filter_entry(move |dent| {
+            if virtual_mounts.iter().any(|(m, _)| m == dent.path()) {
+                debug!(path = %dent.path().display(), "skipped: virtual filesystem");
+                return false;
+            }
+            let keeps = config.keeps(dent.path(), dent.file_type().is_some_and(|t| t.is_dir()));
+            if !keeps {
+                debug!(path = %dent.path().display(), "skipped by filters");
+            }
+            keeps
+        });
+    }
+    builder
+}
+
+/// Stat (and, if configured, line-count) a regular file found by the walker.
+/// `classifier` is loaded once per root when `--linguist` is on. With
+/// `timings`, the time spent goes to the metadata, lines and checks phases.
+pub fn read_record(
+    fs: &dyn FileSystem,
+    path: PathBuf,
+    root: &Path,
+    config: &Config,
+    classifier: Option<&Classifier>,
+    line_buf: &mut Vec<u8>,
+    timings: Option<&mut Timings>,
+) -> Option<FileRecord> {
+    let timed = timings.is_some();
+    let tick = || timed.then(Instant::now);
+    let started = tick();
+    let meta = match retrying(config, || fs.metadata(&path)) {
+        Ok(meta) => meta,
+        Err(err) => {
+            warn!(path = %path.display(), "cannot stat: {}", err);
+            scan_error();
+            return None;
+        }
+    };
+    if config.only_mine.is_some() && meta.owner != config.only_mine {
+        debug!(path = %path.display(), "skipped: owned by another user");
+        return None;
+    }
+    // Reading a placeholder would download it, so only its metadata counts;
+    // `--metadata-only` wants nothing else of any file.
+    let unread = Unreadable(fs);
+    let fs: &dyn FileSystem = if meta.placeholder || config.metadata_only {
+        &unread
+    } else {
+        fs
+    };
+
+    let stat_done = tick();
+    let size = meta.len;
+    let kind = classifier.and_then(|c| c.classify(&path));
+    let excluded = config.exclude_generated && kind.is_some();
+    let mut decompressed = None;
+    let mut uncounted = false;
+    // A placeholder's contents can't be read, which is no error of the scan;
+    // nor is it left uncounted, which `--force-lines` couldn't change.
+    let readable = !excluded && !meta.placeholder;
+    let (lines, matches) = if readable && !config.skip_lines && is_decompressed(&path, config) {
+        retrying(config, || {
+            count_lines_decompressed(fs, &path, config, line_buf)
+        })
+        .map(|(lines, matches, bytes)| {
+            decompressed = Some(bytes);
+            (lines, matches)
+        })
+        .unwrap_or_else(|err| {
+            warn!(path = %path.display(), "cannot decompress: {}", err);
+            scan_error();
+            (0, 0)
+        })
+    } else if readable && should_count_lines(&path, size, config) {
+        retrying(config, || count_lines(fs, &path, config, line_buf)).unwrap_or_else(|err| {
+            warn!(path = %path.display(), "cannot count lines: {}", err);
+            scan_error();
+            (0, 0)
+        })
+    } else {
+        uncounted = readable && !config.skip_lines;
+        (0, 0)
+    };
+    let lines_done = tick();
+    let minified = minified::detect(fs, &path, size, lines);
+    let lines = if minified && !config.include_minified {
+        0
+    } else {
+        lines
+    };
+
+    let compressed = if config.compress_estimate {
+        estimate_compressed(fs, &path, size).unwrap_or(size)
+    } else {
+        0
+    };
+
+    let metrics = config
+        .plugins
+        .iter()
+        .map(|plugin| {
+            plugin.measure(fs, &path, line_buf).unwrap_or_else(|err| {
+                warn!(path = %path.display(), plugin = %plugin.name, "plugin failed: {}", err);
+                0
+            })
+        })
+        .collect();
+
+    let lang = (config.by_lang || config.comments || config.tests).then(|| lang::detect(fs, &path));
+    let comments = lang
+        .filter(|_| config.comments && lines > 0)
+        .and_then(|lang| comments::count(fs, &path, lang))
+        .and_then(Result::ok)
+        // --terminated-only can leave a final comment line uncounted.
+        .map(|n| n.min(lines));
+    let interpreter = config
+        .scripts
+        .then(|| lang::script_interpreter(fs, &path))
+        .flatten();
+    let executable = meta.executable.then(|| mime::exec_kind(fs, &path));
+    let secret = config
+        .secrets
+        .then(|| secrets::check(fs, &path, size))
+        .flatten();
+    let license = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .filter(|n| config.licenses && license::is_license_file(n))
+        .map(|_| license::identify(fs, &path));
+    let category = config
+        .mime
+        .then(|| mime::sniff(fs, &path).unwrap_or(mime::BINARY));
+
+    // Finder-style size; the line limit and heuristics above see the data alone.
+    let size = if config.xattrs && fs.is_native() {
+        size + vfs::xattr_bytes(&path)
+    } else {
+        size
+    };
+
+    let parent = path
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| root.to_path_buf());
+
+    if let (Some(t), Some(started), Some(stat_done), Some(lines_done)) =
+        (timings, started, stat_done, lines_done)
+    {
+        t.add(Phase::Metadata, stat_done - started);
+        t.add(Phase::Lines, lines_done - stat_done);
+        t.add(Phase::Checks, lines_done.elapsed());
+        t.files += 1;
+    }
+
+    Some(FileRecord {
+        path,
+        parent,
+        size,
+        lines,
+        mtime: meta.modified,
+        compressed,
+        lang,
+        category,
+        kind,
+        matches,
+        metrics,
+        minified,
+        placeholder: meta.placeholder,
+        uncounted,
+        comments,
+        interpreter,
+        executable,
+        secret,
+        license,
+        file_id: meta.file_id,
+        owner: meta.owner,
+        atime: meta.accessed,
+        decompressed,
+    })
+}
+
+/// Stands in for `FileSystem` where a file's contents must stay unread;
+/// every measurement that needs them then falls back to its default.
+struct Unreadable<'a>(&'a dyn FileSystem);
+
+impl FileSystem for Unreadable<'_> {
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        self.0.metadata(path)
+    }
+
+    fn open(&self, _: &Path) -> io::Result<Box<dyn ReadSeek + '_>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "contents are not available locally",
+        ))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        self.0.read_dir(path)
+    }
+}
+
+/// A `FileSystem` that already knows one path's metadata, so the serial
+/// walk, which stats every entry to decide where it goes, doesn't stat files
+/// a second time in `read_record`.
+struct Stated<'a> {
+    fs: &'a dyn FileSystem,
+    path: &'a Path,
+    meta: Metadata,
+}
+
+impl FileSystem for Stated<'_> {
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        if path == self.path {
+            Ok(self.meta)
+        } else {
+            self.fs.metadata(path)
+        }
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek + '_>> {
+        self.fs.open(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        self.fs.read_dir(path)
+    }
+
+    fn is_native(&self) -> bool {
+        self.fs.is_native()
+    }
+}
+
+/// Scan `config.root` on the host filesystem: in parallel with the `ignore`
+/// walker, or serially through `scan_fs` on WASI, which has no threads, and
+/// with `--sequential`.
+pub fn scan_dir(config: &Arc<Config>) -> io::Result<Summary> {
+    scan_dir_with(config, |_| true)
+}
+
+/// `scan_dir` that also hands each record to `visit`, on the calling thread,
+/// in the order records arrive (before any `--deterministic` sorting).
+/// Returning `false` stops the walk; the summary then covers the files
+/// visited so far.
+pub fn scan_dir_with(
+    config: &Arc<Config>,
+    mut visit: impl FnMut(&FileRecord) -> bool,
+) -> io::Result<Summary> {
+    if !config.virtual_fs
+        && let Some(fstype) = mounts::virtual_fs(&config.root)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} is on a virtual filesystem ({}); pass --virtual-fs to scan it anyway",
+                config.root.display(),
+                fstype
+            ),
+        ));
+    }
+    if cfg!(target_os = "wasi") || config.sequential {
+        return scan_fs_with(&OsFs, config, visit);
+    }
+    let mut collector = Collector::new(config, &OsFs);
+    if config.dupes {
+        collector.hashes = Some(dupes::Pipeline::start(config));
+    }
+    let (symlinks, timings, entries) = if config.resume {
+        (
+            Symlinks::default(),
+            Vec::new(),
+            walk_resumable(config, &mut collector, &mut visit),
+        )
+    } else if config.reuse_unchanged {
+        (
+            Symlinks::default(),
+            Vec::new(),
+            walk_reusing(config, &mut collector, &mut visit),
+        )
+    } else {
+        let walked = walk_into(config, &config.root, None, None, &mut collector, &mut visit);
+        (walked.symlinks, walked.timings, walked.entries)
+    };
+    let hashes = collector.hashes.take();
+    let mut summary = collector.finish();
+    summary.symlinks = symlinks;
+    summary.count_entries(entries, config);
+    if let Some(profile) = &mut summary.profile {
+        profile.threads = timings;
+    }
+    if config.dupes {
+        summary.duplicates = dupes::find(&OsFs, config, &summary, hashes);
+    }
+    if config.profile_ignores {
+        summary.ignore_rules = pruned::profile(&OsFs, config);
+    }
+    if !config.virtual_fs {
+        summary.skipped_mounts = mounts::virtual_below(&config.root);
+    }
+    if config.by_mount {
+        summary.mount_points = mounts::devices(&config.root);
+    }
+    if config.overlay_layers {
+        summary.overlay_layers = overlay_layers(config);
+    }
+    Ok(summary)
+}
+
+/// What one run of the parallel walker saw besides the records; see
+/// `walk_records`.
+#[derive(Default)]
+struct Walked {
+    symlinks: Symlinks,
+    timings: Vec<Timings>,
+    entries: EntryCounts,
+    /// `visit` stopped the walk.
+    stopped: bool,
+}
+
+/// Walks `start` (to `max_depth` levels below it) on walker threads while
+/// the calling thread hands each record to `visit` and then `collector`.
+fn walk_into(
+    config: &Arc<Config>,
+    start: &Path,
+    max_depth: Option<usize>,
+    reuse: Option<&Reuse>,
+    collector: &mut Collector,
+    visit: &mut impl FnMut(&FileRecord) -> bool,
+) -> Walked {
+    // Walker threads wait for a slow aggregator (or terminal) rather than
+    // queueing records without bound.
+    let (tx, rx) = bounded::<FileRecord>(config.channel_capacity);
+    thread::scope(|scope| {
+        let walker = scope.spawn(|| walk_records_from(config, start, max_depth, reuse, tx));
+        let mut stopped = false;
+        for record in rx {
+            if !visit(&record) {
+                stopped = true;
+                break;
+            }
+            collector.push(record);
+        }
+        let (symlinks, timings, entries) = walker.join().unwrap_or_default();
+        Walked {
+            symlinks,
+            timings,
+            entries,
+            stopped,
+        }
+    })
+}
+
+/// How deep below the root `--resume` splits the walk: each directory this
+/// deep is walked, and checkpointed, on its own, after one walk down to
+/// here for the files above them.
+const RESUME_DEPTH: usize = 2;
+
+/// The `--resume` walk: the parts an earlier run checkpointed are read back,
+/// and the rest walked one after another, each checkpointed once it's done.
+/// Without a checkpoint to write, it's an ordinary walk. Returns the
+/// entry counts of every part.
+fn walk_resumable(
+    config: &Arc<Config>,
+    collector: &mut Collector,
+    visit: &mut impl FnMut(&FileRecord) -> bool,
+) -> EntryCounts {
+    let opened = Checkpoint::open(config, &mut |record| collector.push(record));
+    let (mut checkpoint, mut entries) = match opened {
+        Ok(opened) => opened,
+        Err(err) => {
+            warn!(
+                "Unable to open the checkpoint, scanning without one: {}",
+                err
+            );
+            return walk_into(config, &config.root, None, None, collector, visit).entries;
+        }
+    };
+    if checkpoint.parts_done() > 0 {
+        info!(
+            parts = checkpoint.parts_done(),
+            "resuming from the checkpoint"
+        );
+    }
+
+    let root = &config.root;
+    let mut parts = vec![(root.clone(), Some(RESUME_DEPTH))];
+    let mut listing = walk_builder(root, config);
+    listing
+        .max_depth(Some(RESUME_DEPTH))
+        .sort_by_file_path(Path::cmp);
+    for dent in listing.build().flatten() {
+        if dent.depth() == RESUME_DEPTH && dent.file_type().is_some_and(|t| t.is_dir()) {
+            parts.push((dent.into_path(), None));
+        }
+    }
+
+    let mut failed = false;
+    for (start, max_depth) in parts {
+        let rel = start.strip_prefix(root).unwrap_or(&start).to_path_buf();
+        if checkpoint.is_done(&rel) {
+            continue;
+        }
+        let mut visit = |record: &FileRecord| {
+            if !failed && let Err(err) = checkpoint.push(record) {
+                warn!("Unable to write the checkpoint: {}", err);
+                failed = true;
+            }
+            visit(record)
+        };
+        let walked = walk_into(config, &start, max_depth, None, collector, &mut visit);
+        if walked.stopped {
+            return entries;
+        }
+        if !failed && let Err(err) = checkpoint.complete(&rel, &walked.entries) {
+            warn!("Unable to write the checkpoint: {}", err);
+            failed = true;
+        }
+        for (dir, n) in walked.entries {
+            *entries.entry(dir).or_default() += n;
+        }
+    }
+    if let Err(err) = checkpoint.remove() {
+        warn!("Unable to remove the checkpoint: {}", err);
+    }
+    entries
+}
+
+/// The `--reuse-unchanged` walk: files in directories that look as they did
+/// in the stored tree come from it, and the tree seen is stored for the
+/// next scan. Returns the entry counts.
+fn walk_reusing(
+    config: &Arc<Config>,
+    collector: &mut Collector,
+    visit: &mut impl FnMut(&FileRecord) -> bool,
+) -> EntryCounts {
+    let opened = history::data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))
+        .and_then(|dir| RootStore::open(&dir, &history::root_id(&config.root)))
+        .and_then(|store| {
+            let lock = store.lock()?;
+            let reuse = Reuse::load(&store, config)?;
+            Ok((store, lock, reuse))
+        });
+    let (store, _lock, reuse) = match opened {
+        Ok(opened) => opened,
+        Err(err) => {
+            warn!(
+                "Unable to read the stored tree, scanning everything: {}",
+                err
+            );
+            return walk_into(config, &config.root, None, None, collector, visit).entries;
+        }
+    };
+    let mut writer = Writer::create(&store, config)
+        .map_err(|err| warn!("Unable to store the tree: {}", err))
+        .ok();
+    let mut failed = false;
+    let mut visit = |record: &FileRecord| {
+        if !failed
+            && let Some(writer) = &mut writer
+            && let Err(err) = writer.push(record)
+        {
+            warn!("Unable to store the tree: {}", err);
+            failed = true;
+        }
+        visit(record)
+    };
+    let walked = walk_into(
+        config,
+        &config.root,
+        None,
+        Some(&reuse),
+        collector,
+        &mut visit,
+    );
+    debug!(dirs = reuse.reused_dirs(), "directories reused");
+    if let Some(writer) = writer.filter(|_| !failed && !walked.stopped)
+        && let Err(err) = writer.finish(reuse)
+    {
+        warn!("Unable to store the tree: {}", err);
+    }
+    walked.entries
+}
+
+/// Run the parallel walker over `config.root`, sending every file's record
+/// to `tx` in whatever order the walker threads produce them. Returns when
+/// the walk is done, or early once the receiving side is dropped, with the
+/// symlinks seen on the way (with `--verbose`), each thread's timings
+/// (with `--profile`) and how many entries each directory held.
+pub fn walk_records(
+    config: &Arc<Config>,
+    tx: Sender<FileRecord>,
+) -> (Symlinks, Vec<Timings>, EntryCounts) {
+    walk_records_from(config, &config.root, None, None, tx)
+}
+
+/// `walk_records` over `start`, a directory in the root, down to
+/// `max_depth` levels below it. Entries are counted in the directories
+/// below `start`, not in `start` itself. With `reuse`, the files of
+/// unchanged directories are sent from the stored tree instead.
+fn walk_records_from(
+    config: &Arc<Config>,
+    start: &Path,
+    max_depth: Option<usize>,
+    reuse: Option<&Reuse>,
+    tx: Sender<FileRecord>,
+) -> (Symlinks, Vec<Timings>, EntryCounts) {
+    let root = config.root.clone();
+    let canonical_root = std::fs::canonicalize(&root).unwrap_or_else(|_| root.clone());
+    let symlinks = Mutex::new(Symlinks::default());
+    let timings = Mutex::new(Vec::new());
+    let entries = Mutex::new(EntryCounts::new());
+    // Targets outside the root already counted, for `--follow-links target`.
+    let outside = Mutex::new(HashSet::new());
+    let walker = walk_builder(start, config)
+        .max_depth(max_depth)
+        .threads(config.threads)
+        .build_parallel();
+
+    let classifier = config
+        .linguist
+        .then(|| Arc::new(Classifier::load(&OsFs, &root)));
+    let config_for_threads = Arc::clone(config);
+    let root_for_threads = root.clone();
+
+    walker.run(|| {
+        let tx = tx.clone();
+        let config = Arc::clone(&config_for_threads);
+        let root = root_for_threads.clone();
+        let classifier = classifier.clone();
+        let (symlinks, canonical_root, outside) = (&symlinks, &canonical_root, &outside);
+        let mut line_buf = Vec::with_capacity(64 * 1024);
+        let mut timer = config.profile.then(|| ThreadTimer::new(&timings));
+        let mut counter = EntryCounter {
+            counts: EntryCounts::new(),
+            out: &entries,
+        };
+        Box::new(move |result| {
+            if let Some(timer) = &mut timer {
+                timer.lap(Phase::Traversal);
+            }
+            let dent = match result {
+                Ok(d) => d,
+                // A link back up the tree is skipped, not a failure.
+                Err(err) if is_loop(&err) => {
+                    debug!("{}", err);
+                    return WalkState::Continue;
+                }
+                Err(err) => {
+                    warn!("{}", err);
+                    scan_error();
+                    return WalkState::Continue;
+                }
+            };
+
+            if dent.depth() > 0
+                && let Some(dir) = dent.path().parent()
+            {
+                counter.add(dir);
+            }
+            if let Some(reuse) = reuse
+                && dent.file_type().is_some_and(|t| t.is_dir())
+            {
+                let mtime = dent.metadata().ok().and_then(|m| m.modified().ok());
+                for record in reuse.enter(dent.path(), mtime).into_iter().flatten() {
+                    if tx.send(record).is_err() {
+                        return WalkState::Quit;
+                    }
+                }
+                return WalkState::Continue;
+            }
+            if config.verbose && dent.path_is_symlink() && dent.depth() > 0 {
+                if let Ok(mut symlinks) = symlinks.lock() {
+                    symlinks.add(dent.path(), canonical_root);
+                }
+                if config.follow_links.is_none() {
+                    return WalkState::Continue;
+                }
+            }
+            if !dent.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
+                return WalkState::Continue;
+            }
+            if let Some(reuse) = reuse
+                && dent.path().parent().is_some_and(|dir| reuse.is_reused(dir))
+            {
+                return WalkState::Continue;
+            }
+
+            let path = match config.follow_links {
+                Some(FollowLinks::Target) => {
+                    match real_location(dent.into_path(), &root, canonical_root, outside) {
+                        Some(path) => path,
+                        None => return WalkState::Continue,
+                    }
+                }
+                _ => dent.into_path(),
+            };
+            let record = read_record(
+                &OsFs,
+                path,
+                &root,
+                &config,
+                classifier.as_deref(),
+                &mut line_buf,
+                timer.as_mut().map(|t| &mut t.timings),
+            );
+            if let Some(timer) = &mut timer {
+                timer.reset();
+            }
+            let Some(record) = record else {
+                return WalkState::Continue;
+            };
+
+            if tx.send(record).is_err() {
+                return WalkState::Quit;
+            }
+
+            WalkState::Continue
+        })
+    });
+    (
+        symlinks.into_inner().unwrap_or_default(),
+        timings.into_inner().unwrap_or_default(),
+        entries.into_inner().unwrap_or_default(),
+    )
+}
+
+/// Direct entries (files, directories and anything else the walk visits)
+/// per directory.
+pub type EntryCounts = HashMap<PathBuf, u64>;
+
+/// One walker thread's `EntryCounts`, merged into `out` when the thread's
+/// visitor is dropped at the end of the walk.
+struct EntryCounter<'a> {
+    counts: EntryCounts,
+    out: &'a Mutex<EntryCounts>,
+}
+
+impl EntryCounter<'_> {
+    fn add(&mut self, dir: &Path) {
+        match self.counts.get_mut(dir) {
+            Some(n) => *n += 1,
+            None => {
+                self.counts.insert(dir.to_path_buf(), 1);
+            }
+        }
+    }
+}
+
+impl Drop for EntryCounter<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut out) = self.out.lock() {
+            for (dir, n) in self.counts.drain() {
+                *out.entry(dir).or_default() += n;
+            }
+        }
+    }
+}
+
+/// Where `--follow-links target` counts the file the walk found at `path`:
+/// there, unless a link led to it. A target inside the root is counted
+/// when the walk reaches it directly, so it's skipped here; one outside is
+/// counted once, under its canonical path.
+fn real_location(
+    path: PathBuf,
+    root: &Path,
+    canonical_root: &Path,
+    outside: &Mutex<HashSet<PathBuf>>,
+) -> Option<PathBuf> {
+    let real = std::fs::canonicalize(&path).ok()?;
+    match real.strip_prefix(canonical_root) {
+        Ok(rel) => (root.join(rel) == path).then_some(path),
+        Err(_) => {
+            let mut outside = outside.lock().unwrap_or_else(|e| e.into_inner());
+            outside.insert(real.clone()).then_some(real)
+        }
+    }
+}
+
+fn is_loop(err: &ignore::Error) -> bool {
+    match err {
+        ignore::Error::Loop { .. } => true,
+        ignore::Error::WithPath { err, .. }
+        | ignore::Error::WithDepth { err, .. }
+        | ignore::Error::WithLineNumber { err, .. } => is_loop(err),
+        _ => false,
+    }
+}
+
+/// Serial scan over any `FileSystem`, for WASI and for trees that don't live
+/// on the host, such as a snapshot held by an embedding repo viewer. Applies
+/// the walker's default filters: hidden entries are skipped unless
+/// `--hidden`, and unless `--no-ignore` each
+/// directory's `.gitignore` and `.ignore` apply below it (whether or not the
+/// tree is a git checkout). Entries are visited in path order.
+pub fn scan_fs(fs: &dyn FileSystem, config: &Config) -> io::Result<Summary> {
+    scan_fs_with(fs, config, |_| true)
+}
+
+fn scan_fs_with(
+    fs: &dyn FileSystem,
+    config: &Config,
+    visit: impl FnMut(&FileRecord) -> bool,
+) -> io::Result<Summary> {
+    let mut summary = walk_fs(fs, config, visit)?;
+    if config.dupes {
+        summary.duplicates = dupes::find(fs, config, &summary, None);
+    }
+    if config.profile_ignores {
+        summary.ignore_rules = pruned::profile(fs, config);
+    }
+    if fs.is_native() && !config.virtual_fs {
+        summary.skipped_mounts = mounts::virtual_below(&config.root);
+    }
+    if config.by_mount && fs.is_native() {
+        summary.mount_points = mounts::devices(&config.root);
+    }
+    if config.overlay_layers && fs.is_native() {
+        summary.overlay_layers = overlay_layers(config);
+    }
+    Ok(summary)
+}
+
+fn overlay_layers(config: &Config) -> Vec<mounts::Layer> {
+    mounts::overlay_layers(&config.root).unwrap_or_else(|| {
+        warn!(
+            "--overlay-layers: {} isn't on an overlay filesystem",
+            config.root.display()
+        );
+        Vec::new()
+    })
+}
+
+fn walk_fs(
+    fs: &dyn FileSystem,
+    config: &Config,
+    mut visit: impl FnMut(&FileRecord) -> bool,
+) -> io::Result<Summary> {
+    let root = &config.root;
+    let classifier = config.linguist.then(|| Classifier::load(fs, root));
+    let mut line_buf = Vec::with_capacity(64 * 1024);
+    let mut collector = Collector::new(config, fs);
+    let walk_start = Instant::now();
+    let mut timings = Timings::default();
+    // False once `visit` asks to stop.
+    let mut read = |fs: &dyn FileSystem, path: PathBuf, collector: &mut Collector| match read_record(
+        fs,
+        path,
+        root,
+        config,
+        classifier.as_ref(),
+        &mut line_buf,
+        config.profile.then_some(&mut timings),
+    ) {
+        Some(record) if !visit(&record) => false,
+        Some(record) => {
+            collector.push(record);
+            true
+        }
+        None => true,
+    };
+
+    if fs.metadata(root)?.is_file {
+        read(fs, root.clone(), &mut collector);
+        return Ok(finish_serial(collector, timings, walk_start));
+    }
+    let virtual_mounts = if fs.is_native() && !config.virtual_fs {
+        mounts::virtual_below(root)
+    } else {
+        Vec::new()
+    };
+    let mut entry_counts = EntryCounts::new();
+    let mut pending = vec![(root.clone(), Vec::new())];
+    'walk: while let Some((dir, mut ignores)) = pending.pop() {
+        ignores.extend(load_ignores(fs, &dir));
+        let mut entries = match retrying(config, || fs.read_dir(&dir)) {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!(path = %dir.display(), "cannot read directory: {}", err);
+                scan_error();
+                continue;
+            }
+        };
+        entries.sort_unstable();
+        let mut subdirs = Vec::new();
+        for path in entries {
+            let hidden = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with('.'));
+            let meta = match retrying(config, || fs.metadata(&path)) {
+                Ok(meta) => meta,
+                Err(err) => {
+                    warn!(path = %path.display(), "cannot stat: {}", err);
+                    scan_error();
+                    continue;
+                }
+            };
+            if hidden && !config.walks_hidden() {
+                debug!(path = %path.display(), "skipped hidden entry");
+                continue;
+            }
+            if !config.keeps(&path, meta.is_dir) {
+                debug!(path = %path.display(), "skipped by filters");
+                continue;
+            }
+            if !config.no_ignore && is_ignored(&ignores, &path, meta.is_dir) {
+                debug!(path = %path.display(), "skipped by ignore rules");
+                continue;
+            }
+            if meta.is_dir && virtual_mounts.iter().any(|(m, _)| *m == path) {
+                debug!(path = %path.display(), "skipped: virtual filesystem");
+                continue;
+            }
+            *entry_counts.entry(dir.clone()).or_default() += 1;
+            if meta.is_dir {
+                subdirs.push(path);
+            } else if meta.is_file {
+                let stated = Stated {
+                    fs,
+                    path: &path,
+                    meta,
+                };
+                if !read(&stated, path.clone(), &mut collector) {
+                    break 'walk;
+                }
+            }
+        }
+        // Reversed so popping the stack visits subdirectories in order.
+        pending.extend(subdirs.into_iter().rev().map(|d| (d, ignores.clone())));
+    }
+    let mut summary = finish_serial(collector, timings, walk_start);
+    summary.count_entries(entry_counts, config);
+    Ok(summary)
+}
+
+/// With `--profile`, the serial walk is the one thread; its traversal is
+/// whatever the walk spent outside reading files and aggregating.
+fn finish_serial(collector: Collector, mut timings: Timings, walk_start: Instant) -> Summary {
+    let walk = walk_start.elapsed();
+    let mut summary = collector.finish();
+    if let Some(profile) = &mut summary.profile {
+        let outside = walk.saturating_sub(timings.total() + profile.aggregation);
+        timings.add(Phase::Traversal, outside);
+        profile.threads = vec![timings];
+    }
+    summary
+}
+
+/// `dir`'s `.gitignore` and `.ignore`, each rule remembering its file.
+pub(crate) fn load_ignores(fs: &dyn FileSystem, dir: &Path) -> Vec<Arc<Gitignore>> {
+    [".gitignore", ".ignore"]
+        .into_iter()
+        .filter_map(|name| {
+            let file = dir.join(name);
+            let contents = fs.read_to_string(&file).ok()?;
+            let mut builder = GitignoreBuilder::new(dir);
+            for line in contents.lines() {
+                if let Err(err) = builder.add_line(Some(file.clone()), line) {
+                    warn!(path = %file.display(), "bad ignore rule: {}", err);
+                }
+            }
+            builder.build().ok().map(Arc::new)
+        })
+        .collect()
+}
+
+/// The deepest matching rule wins, like in git.
+fn is_ignored(ignores: &[Arc<Gitignore>], path: &Path, is_dir: bool) -> bool {
+    ignores
+        .iter()
+        .rev()
+        .find_map(|ignore| match ignore.matched(path, is_dir) {
+            Match::Ignore(_) => Some(true),
+            Match::Whitelist(_) => Some(false),
+            Match::None => None,
+        })
+        .unwrap_or(false)
+}
+
+/// Records read back from a spill between prunings to the listed ones.
+const SPILL_BATCH: usize = 1 << 16;
+
+/// Folds records into a `Summary` as a walker produces them, drawing the
+/// progress line on the way.
+struct Collector<'a> {
+    config: &'a Config,
+    summary: Summary,
+    buffered: Vec<FileRecord>,
+    /// With `--memory-limit`: the RAM the kept records take, and where they
+    /// go once that's over half the limit. `spill` stays `None` after it
+    /// couldn't be written, and the records stay in memory.
+    held: u64,
+    spill: Option<Spill>,
+    spill_failed: bool,
+    /// Time spent in `Summary::collect` and `finish`, with `--profile`.
+    aggregation: Duration,
+    progress: Option<Progress>,
+    /// With `--dupes` on the parallel walker: the hashing pool records are
+    /// fed to.
+    hashes: Option<dupes::Pipeline>,
+}
+
+impl<'a> Collector<'a> {
+    fn new(config: &'a Config, fs: &dyn FileSystem) -> Self {
+        let mut summary = Summary {
+            started_at: Some(SystemTime::now()),
+            ..Summary::default()
+        };
+        if config.submodules != submodules::Mode::Include {
+            summary.submodules = Submodules::load(fs, &config.root);
+        }
+        Self {
+            config,
+            summary,
+            buffered: Vec::new(),
+            held: 0,
+            spill: None,
+            spill_failed: false,
+            aggregation: Duration::ZERO,
+            progress: Progress::new(config),
+            hashes: None,
+        }
+    }
+
+    fn push(&mut self, record: FileRecord) {
+        let config = self.config;
+        if let Some(progress) = &mut self.progress {
+            progress.update(&record, &config.root);
+        }
+        if let Some(hashes) = &mut self.hashes {
+            hashes.feed(&record);
+        }
+
+        let kept = self.summary.files.len();
+        if config.deterministic {
+            self.buffered.push(record);
+        } else if config.profile {
+            let start = Instant::now();
+            self.summary.collect(record, config);
+            self.aggregation += start.elapsed();
+        } else {
+            self.summary.collect(record, config);
+        }
+        if config.memory_limit.is_some()
+            && let Some(record) = self.summary.files.get(kept)
+        {
+            self.held += spill::footprint(record);
+            self.hold_within_limit();
+        }
+    }
+
+    /// Spills the kept records once they take over half of `--memory-limit`.
+    fn hold_within_limit(&mut self) {
+        let Some(limit) = self.config.memory_limit else {
+            return;
+        };
+        if self.held <= limit / 2 || self.spill_failed {
+            return;
+        }
+        let spilled = match self.spill.take() {
+            Some(spill) => Ok(spill),
+            None => Spill::create(),
+        }
+        .and_then(|mut spill| {
+            for record in &self.summary.files {
+                spill.push(record)?;
+            }
+            Ok(spill)
+        });
+        match spilled {
+            Ok(spill) => {
+                debug!(records = spill.records, path = %spill.path().display(), "records spilled");
+                self.spill = Some(spill);
+                self.summary.files.clear();
+                self.held = 0;
+            }
+            Err(err) => {
+                warn!(
+                    "Unable to spill file records, keeping them in memory: {}",
+                    err
+                );
+                self.spill_failed = true;
+            }
+        }
+    }
+
+    /// Reads the spilled records back, keeping only those the lists show.
+    fn unspill(&mut self) {
+        let Some(spill) = self.spill.take() else {
+            return;
+        };
+        let config = self.config;
+        let mut files = std::mem::take(&mut self.summary.files);
+        lists::retain_listed(config, &mut files);
+        let mut pruned_at = files.len();
+        let read = spill.drain(&mut |record| {
+            files.push(record);
+            if files.len() >= pruned_at + SPILL_BATCH {
+                lists::retain_listed(config, &mut files);
+                pruned_at = files.len();
+            }
+        });
+        if let Err(err) = read {
+            warn!("Unable to read spilled file records back: {}", err);
+        }
+        lists::retain_listed(config, &mut files);
+        self.summary.files = files;
+    }
+
+    fn finish(mut self) -> Summary {
+        let config = self.config;
+        if let Some(progress) = self.progress.take() {
+            progress.finish();
+        }
+
+        let start = Instant::now();
+        // Walker threads deliver records in scheduling order; sorting by path
+        // makes every first-wins tie (max-lines and largest file, list order)
+        // reproducible.
+        if config.deterministic {
+            self.buffered.sort_by(|a, b| a.path.cmp(&b.path));
+            for record in std::mem::take(&mut self.buffered) {
+                self.summary.collect(record, config);
+            }
+        }
+
+        self.unspill();
+        self.summary.finish(&config.root);
+        self.summary.finished_at = Some(SystemTime::now());
+        let elapsed = self
+            .summary
+            .started_at
+            .and_then(|start| start.elapsed().ok())
+            .unwrap_or_default();
+        if config.profile {
+            self.summary.profile = Some(Profile {
+                threads: Vec::new(),
+                aggregation: self.aggregation + start.elapsed(),
+                wall: elapsed,
+            });
+        }
+        info!(
+            files = self.summary.total_files,
+            bytes = self.summary.total_size,
+            "scan finished in {:?}",
+            elapsed
+        );
+        self.summary
+    }
+}
+
+/// Extension a file is grouped and filtered by (`--by-ext`,
+/// `--dup-names-ext`, `ext` in `--metric`): the `COMPOUND_EXTS` entry its
+/// name ends in, else its last extension, both normalized.
+pub fn extension_key(path: &Path) -> String {
+    let Some(last) = last_extension(path) else {
+        return NO_EXT.to_string();
+    };
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(normalize_ext)
+        .unwrap_or_default();
+    COMPOUND_EXTS
+        .iter()
+        .find(|ext| {
+            // `.d.ts` alone is a hidden `.d` file with a `ts` extension.
+            name.len() > ext.len() + 1
+                && name.ends_with(*ext)
+                && name[..name.len() - ext.len()].ends_with('.')
+        })
+        .map_or(last, |ext| ext.to_string())
+}
+
+/// The part after the final dot, normalized; `None` for names without one
+/// (and dotfiles) or that aren't UTF-8.
+pub fn last_extension(path: &Path) -> Option<String> {
+    path.extension().and_then(|s| s.to_str()).map(normalize_ext)
+}
+
+/// An extension as files are matched against it: without a leading dot,
+/// lowercased with Unicode rules and in NFC, so `.JPG`, `.Ärger` typed on
+/// macOS (NFD) and `.ärger` are one extension.
+pub fn normalize_ext(ext: &str) -> String {
+    ext.trim_start_matches('.').to_lowercase().nfc().collect()
+}
+
+/// Count lines (and `--count-pattern` matches) with the configured backend.
+/// The memchr backend only handles the default rules without a pattern and
+/// otherwise falls back to bufread; mmap handles everything, but only on a
+/// native filesystem.
+fn count_lines(
+    fs: &dyn FileSystem,
+    path: &Path,
+    config: &Config,
+    buf: &mut Vec<u8>,
+) -> io::Result<(u64, u64)> {
+    let pattern = config.count_pattern.as_ref();
+    let simple = config.lines == LineRules::default() && pattern.is_none();
+    match config.line_backend {
+        LineBackend::Memchr if simple => Ok((count_lines_memchr(fs, path, config, buf)?, 0)),
+        // Page faults can't be paced, so --throttle reads instead.
+        LineBackend::Mmap if fs.is_native() && config.throttle.is_none() => {
+            count_lines_mmap(path, config.lines, pattern, simple)
+        }
+        _ => count_lines_fast(fs, path, config, pattern, buf),
+    }
+}
+
+fn read_buffer(config: &Config) -> usize {
+    if config.sequential {
+        SEQUENTIAL_READ_BUFFER
+    } else {
+        READ_BUFFER
+    }
+}
+
+/// Newline count over fixed-size reads, plus one for an unterminated tail.
+fn count_lines_memchr(
+    fs: &dyn FileSystem,
+    path: &Path,
+    config: &Config,
+    buf: &mut Vec<u8>,
+) -> io::Result<u64> {
+    let mut file = fs.open(path)?;
+    buf.resize(read_buffer(config), 0);
+    let mut lines: u64 = 0;
+    let mut last = b'\n';
+    loop {
+        let n = file.read(buf)?;
+        if n == 0 {
+            break;
+        }
+        if let Some(throttle) = &config.throttle {
+            throttle.take(n);
+        }
+        lines += memchr::memchr_iter(b'\n', &buf[..n]).count() as u64;
+        last = buf[n - 1];
+    }
+    Ok(lines + u64::from(last != b'\n'))
+}
+
+fn count_lines_mmap(
+    path: &Path,
+    rules: LineRules,
+    pattern: Option<&Regex>,
+    simple: bool,
+) -> io::Result<(u64, u64)> {
+    let file = vfs::open_file(path)?;
+    // Mapping an empty file fails on some platforms.
+    if file.metadata()?.len() == 0 {
+        return Ok((0, 0));
+    }
+    // SAFETY: the map is read-only and dropped before returning. A file
+    // truncated while mapped can fault, which the other backends avoid;
+    // that is the documented trade-off of choosing mmap.
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    if simple {
+        let lines = memchr::memchr_iter(b'\n', &map).count() as u64;
+        return Ok((lines + u64::from(map.last() != Some(&b'\n')), 0));
+    }
+    let mut lines: u64 = 0;
+    let mut matches: u64 = 0;
+    for chunk in map.split_inclusive(|&b| b == b'\n') {
+        lines += line_count(chunk, rules);
+        if let Some(re) = pattern {
+            matches += re.find_iter(chunk).count() as u64;
+        }
+    }
+    Ok((lines, matches))
+}
+
+/// Count lines and, with a pattern, its matches in the same pass. Matches
+/// never span lines.
+fn count_lines_fast(
+    fs: &dyn FileSystem,
+    path: &Path,
+    config: &Config,
+    pattern: Option<&Regex>,
+    buf: &mut Vec<u8>,
+) -> io::Result<(u64, u64)> {
+    let rules = config.lines;
+    let file = fs.open(path)?;
+    let file = Throttled {
+        inner: file,
+        throttle: config.throttle.as_deref(),
+    };
+    let reader = BufReader::with_capacity(read_buffer(config), file);
+    count_lines_in(reader, rules, pattern, buf)
+}
+
+fn count_lines_in(
+    mut reader: impl BufRead,
+    rules: LineRules,
+    pattern: Option<&Regex>,
+    buf: &mut Vec<u8>,
+) -> io::Result<(u64, u64)> {
+    let mut lines: u64 = 0;
+    let mut matches: u64 = 0;
+
+    loop {
+        buf.clear();
+        let bytes = reader.read_until(b'\n', buf)?;
+        if bytes == 0 {
+            break;
+        }
+        lines += line_count(buf, rules);
+        if let Some(re) = pattern {
+            matches += re.find_iter(buf).count() as u64;
+        }
+    }
+
+    Ok((lines, matches))
+}
+
+/// Extensions `--decompress` reads through a decompressor.
+const DECOMPRESSED_EXTS: &[&str] = &["zst", "gz", "bz2"];
+
+fn is_decompressed(path: &Path, config: &Config) -> bool {
+    config.decompress
+        && last_extension(path).is_some_and(|ext| DECOMPRESSED_EXTS.contains(&ext.as_str()))
+}
+
+/// Lines and matches in the decompressed contents, and how many bytes
+/// those came to. `--max-line-bytes` doesn't apply: reading compressed logs
+/// is what the flag asks for.
+#[cfg(feature = "compress")]
+fn count_lines_decompressed(
+    fs: &dyn FileSystem,
+    path: &Path,
+    config: &Config,
+    buf: &mut Vec<u8>,
+) -> io::Result<(u64, u64, u64)> {
+    let file = Throttled {
+        inner: fs.open(path)?,
+        throttle: config.throttle.as_deref(),
+    };
+    // Rotated logs are often several streams end to end, hence the Multi
+    // decoders.
+    let inner: Box<dyn Read + '_> = match last_extension(path).as_deref() {
+        Some("gz") => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        Some("bz2") => Box::new(bzip2::read::MultiBzDecoder::new(file)),
+        _ => Box::new(zstd::stream::read::Decoder::new(file)?),
+    };
+    let mut decoder = Counted { inner, bytes: 0 };
+    let reader = BufReader::with_capacity(read_buffer(config), &mut decoder);
+    let (lines, matches) =
+        count_lines_in(reader, config.lines, config.count_pattern.as_ref(), buf)?;
+    Ok((lines, matches, decoder.bytes))
+}
+
+// `Config::from_args` rejects --decompress in builds without the decoders.
+#[cfg(not(feature = "compress"))]
+fn count_lines_decompressed(
+    _: &dyn FileSystem,
+    _: &Path,
+    _: &Config,
+    _: &mut Vec<u8>,
+) -> io::Result<(u64, u64, u64)> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// `inner`, tallying the bytes read from it.
+#[cfg(feature = "compress")]
+struct Counted<R> {
+    inner: R,
+    bytes: u64,
+}
+
+#[cfg(feature = "compress")]
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes += n as u64;
+        Ok(n)
+    }
+}
+
+/// Compress up to `COMPRESS_SAMPLE_BYTES` from the start of the file with
+/// zstd level 1 and scale the ratio to the full size. Sampling keeps the
+/// estimate cheap on huge files at the cost of missing redundancy further in.
+#[cfg(feature = "compress")]
+fn estimate_compressed(fs: &dyn FileSystem, path: &Path, size: u64) -> io::Result<u64> {
+    if size == 0 {
+        return Ok(0);
+    }
+    let mut sample = Vec::with_capacity(size.min(COMPRESS_SAMPLE_BYTES) as usize);
+    fs.open(path)?
+        .take(COMPRESS_SAMPLE_BYTES)
+        .read_to_end(&mut sample)?;
+    if sample.is_empty() {
+        return Ok(0);
+    }
+    let compressed = zstd::bulk::compress(&sample, 1)?.len() as u64;
+    let sampled = sample.len() as u64;
+    Ok((compressed as u128 * size as u128 / sampled as u128) as u64)
+}
+
+// `Config::from_args` rejects --compress-estimate in builds without zstd.
+#[cfg(not(feature = "compress"))]
+fn estimate_compressed(_: &dyn FileSystem, _: &Path, size: u64) -> io::Result<u64> {
+    Ok(size)
+}
+
+/// Lines in one `read_until(b'\n')` chunk under `rules`: 1 for the default
+/// rules, more when `\r` splits it, 0 for a skipped blank or unterminated
+/// line.
+pub fn line_count(chunk: &[u8], rules: LineRules) -> u64 {
+    let (body, terminated) = match chunk.strip_suffix(b"\n") {
+        Some(body) => (body, true),
+        None => (chunk, false),
+    };
+    if !rules.cr && !rules.nonblank {
+        return u64::from(terminated || rules.unterminated);
+    }
+
+    let counts = |segment: &[u8], ends: bool| {
+        (ends || rules.unterminated)
+            && !(rules.nonblank && segment.iter().all(u8::is_ascii_whitespace))
+    };
+    if !rules.cr {
+        return u64::from(counts(body, terminated));
+    }
+
+    // `\r\n` is one break; only lone `\r`s split the chunk further.
+    let body = if terminated {
+        body.strip_suffix(b"\r").unwrap_or(body)
+    } else {
+        body
+    };
+    let mut segments = body.split(|&b| b == b'\r').peekable();
+    let mut lines = 0;
+    while let Some(segment) = segments.next() {
+        let last = segments.peek().is_none();
+        // A chunk ending in a lone `\r` leaves an empty tail that is not a line.
+        if last && !terminated && segment.is_empty() {
+            break;
+        }
+        lines += u64::from(counts(segment, !last || terminated));
+    }
+    lines
+}
+
+/// The kind of OS junk `path` is with `--junk`, if any.
+fn junk_kind(path: &Path, config: &Config) -> Option<&'static str> {
+    if !config.junk {
+        return None;
+    }
+    junk::kind(path.file_name()?.to_str()?)
+}
+
+/// Whether `path` sits below a `--deps` directory, relative to the root.
+fn in_dep_dir(path: &Path, config: &Config) -> bool {
+    let Some(dirs) = &config.dep_dirs else {
+        return false;
+    };
+    path.strip_prefix(&config.root)
+        .ok()
+        .and_then(Path::parent)
+        .is_some_and(|rel| {
+            rel.iter()
+                .any(|name| dirs.iter().any(|d| name == d.as_str()))
+        })
+}
+
+/// The outermost hidden file or directory `path` is, or sits in, with
+/// `--dotfiles`.
+fn dotfile(path: &Path, config: &Config) -> Option<PathBuf> {
+    if !config.dotfiles {
+        return None;
+    }
+    let rel = path.strip_prefix(&config.root).ok()?;
+    let mut entry = config.root.clone();
+    for name in rel.iter() {
+        entry.push(name);
+        if name.to_str().is_some_and(|n| n.starts_with('.')) {
+            return Some(entry);
+        }
+    }
+    None
+}
+
+fn docs_dir<'a>(path: &'a Path, config: &Config) -> Option<(PathBuf, &'a Path)> {
+    docs::split_top(path.strip_prefix(&config.root).ok()?)
+}
+
+/// The file name `--dup-names` groups `path` under, if it tracks it.
+fn dup_name(path: &Path, config: &Config) -> Option<String> {
+    let exts = config.dup_names.as_ref()?;
+    if !exts.is_empty() && !exts.contains(&extension_key(path)) {
+        return None;
+    }
+    Some(path.file_name()?.to_string_lossy().into_owned())
+}
+
+/// How many directories `dir` is below `root`.
+fn depth_below(dir: &Path, root: &Path) -> usize {
+    dir.strip_prefix(root)
+        .map_or(0, |rel| rel.components().count())
+}
+
+fn should_count_lines(path: &Path, size: u64, config: &Config) -> bool {
+    if config.skip_lines {
+        return false;
+    }
+    if config.force_lines {
+        return true;
+    }
+    let ext = last_extension(path);
+    // An explicit per-extension limit is a request to count that type; a
+    // compound extension's limit wins over its last part's.
+    if let Some(&limit) = config
+        .ext_max_line_bytes
+        .get(&extension_key(path))
+        .or_else(|| ext.as_ref().and_then(|e| config.ext_max_line_bytes.get(e)))
+    {
+        return limit == 0 || size <= limit;
+    }
+    if config.max_line_bytes > 0 && size > config.max_line_bytes {
+        return false;
+    }
+    !ext.is_some_and(|e| BINARY_EXTS.contains(&e.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(text: &str, rules: LineRules) -> u64 {
+        text.split_inclusive('\n')
+            .map(|chunk| line_count(chunk.as_bytes(), rules))
+            .sum()
+    }
+
+    #[test]
+    fn line_rules_change_what_counts() {
+        let text = "a\n\n  \nb\r\nc\rd\re";
+        assert_eq!(count(text, LineRules::default()), 5);
+
+        let nonblank = LineRules {
+            nonblank: true,
+            ..LineRules::default()
+        };
+        assert_eq!(count(text, nonblank), 3);
+
+        let cr = LineRules {
+            cr: true,
+            ..LineRules::default()
+        };
+        assert_eq!(count(text, cr), 7);
+
+        let strict = LineRules {
+            unterminated: false,
+            cr: true,
+            ..LineRules::default()
+        };
+        assert_eq!(count(text, strict), 6);
+        assert_eq!(count("x\r", strict), 1);
+    }
+
+    #[test]
+    fn extensions_are_normalized_and_compound() {
+        let key = |name: &str| extension_key(Path::new(name));
+        assert_eq!(key("a/Backup.TAR.GZ"), "tar.gz");
+        assert_eq!(key("dump.gz"), "gz");
+        assert_eq!(key("types/index.d.ts"), "d.ts");
+        assert_eq!(key("app.min.js"), "min.js");
+        assert_eq!(key("admin.js"), "js");
+        assert_eq!(key(".d.ts"), "ts");
+        assert_eq!(key("Makefile"), NO_EXT);
+        // NFD "Ä" (A + combining diaeresis), uppercase.
+        assert_eq!(key("notes.A\u{308}RGER"), "\u{e4}rger");
+        assert_eq!(last_extension(Path::new("x.tar.gz")).as_deref(), Some("gz"));
+    }
+
+    #[test]
+    fn scan_fs_applies_ignore_files() {
+        let mut fs = crate::vfs::MemFs::default();
+        fs.insert("r/.gitignore", "*.log\n");
+        fs.insert("r/main.rs", "fn main() {}\n");
+        fs.insert("r/debug.log", "x\n");
+        fs.insert("r/.hidden/x.rs", "x\n");
+        fs.insert("r/src/.ignore", "gen/\n!keep.log\n");
+        fs.insert("r/src/lib.rs", "a\nb\n");
+        fs.insert("r/src/keep.log", "c\n");
+        fs.insert("r/src/gen/out.rs", "d\n");
+        fs.insert("r/src/node_modules/dep.js", "e\n");
+
+        let args = ["r", "--by-ext", "--exclude-dir", "node_modules"];
+        let config = Config::from_args(args.map(String::from)).unwrap();
+        let summary = scan_fs(&fs, &config).unwrap();
+        assert_eq!(summary.total_files, 3);
+        assert_eq!(summary.total_lines, 4);
+        assert_eq!(summary.exts["log"].files, 1);
+        // Only what the filters keep is an entry.
+        assert_eq!(summary.widest_dir, Some((PathBuf::from("r"), 2)));
+    }
+
+    #[test]
+    fn memory_limit_spills_without_changing_the_lists() {
+        let mut fs = crate::vfs::MemFs::default();
+        for i in 0..200 {
+            fs.insert(
+                format!("r/d{}/f{}.txt", i % 7, i),
+                "x\n".repeat((i * 37) % 101),
+            );
+        }
+        let args = ["r", "--top", "5", "--top-lines", "3", "--tiny"];
+        let names = |config: &Config, summary: &Summary| {
+            [
+                lists::top_files(config, summary),
+                lists::top_lines(config, summary),
+                lists::smallest_files(config, summary),
+            ]
+            .map(|list| list.into_iter().map(|e| e.name).collect::<Vec<_>>())
+        };
+        let config = Config::from_args(args.map(String::from)).unwrap();
+        let summary = scan_fs(&fs, &config).unwrap();
+
+        let limited = args.iter().chain(&["--memory-limit", "4K"]);
+        let limited = Config::from_args(limited.map(|a| a.to_string())).unwrap();
+        let spilled = scan_fs(&fs, &limited).unwrap();
+        assert!(spilled.files.len() <= 13, "{}", spilled.files.len());
+        assert_eq!(spilled.total_files, 200);
+        assert_eq!(names(&limited, &spilled), names(&config, &summary));
+
+        let err = Config::from_args(["--memory-limit", "1G", "--dupes"].map(String::from));
+        assert!(err.unwrap_err().contains("--dupes"));
+    }
+
+    #[test]
+    fn retries_only_transient_errors() {
+        let config = Config::from_args(["--retries", "2"].map(String::from)).unwrap();
+        let mut calls = 0;
+        let result: io::Result<()> = retrying(&config, || {
+            calls += 1;
+            Err(io::ErrorKind::TimedOut.into())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+
+        calls = 0;
+        let _ = retrying(&config, || -> io::Result<()> {
+            calls += 1;
+            Err(io::ErrorKind::NotFound.into())
+        });
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn metadata_only_opens_nothing() {
+        let mut fs = crate::vfs::MemFs::default();
+        fs.insert("r/app.min.js", format!("{}\n", "x".repeat(5000)));
+        fs.insert("r/notes.txt", "a\nb\n");
+        let config = Config::from_args(["r", "--metadata-only"].map(String::from)).unwrap();
+        let summary = scan_fs(&fs, &config).unwrap();
+        assert_eq!((summary.total_files, summary.total_lines), (2, 0));
+        assert!(summary.files.iter().all(|f| !f.minified));
+
+        let err = Config::from_args(["--metadata-only", "--top-lines", "3"].map(String::from));
+        assert!(err.unwrap_err().contains("--top-lines"));
+    }
+
+    #[cfg(feature = "compress")]
+    #[test]
+    fn decompress_reads_zstd_gzip_and_bzip2() {
+        use std::io::Write;
+
+        let log = "a\nb\nc\n".repeat(100);
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        gz.write_all(log.as_bytes()).unwrap();
+        let mut bz = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::fast());
+        bz.write_all(log.as_bytes()).unwrap();
+        let mut fs = crate::vfs::MemFs::default();
+        fs.insert(
+            "r/app.log.zst",
+            zstd::encode_all(log.as_bytes(), 1).unwrap(),
+        );
+        fs.insert("r/app.log.1.gz", gz.finish().unwrap());
+        fs.insert("r/app.log.2.bz2", bz.finish().unwrap());
+        let config = Config::from_args(["r", "--decompress"].map(String::from)).unwrap();
+        let summary = scan_fs(&fs, &config).unwrap();
+        assert_eq!(summary.total_lines, 900);
+        assert_eq!(summary.decompressed.files, 3);
+        assert_eq!(summary.decompressed.bytes, 3 * log.len() as u64);
+    }
+
+    #[test]
+    fn placeholders_are_not_scan_errors() {
+        // A MemFs whose files are all cloud placeholders.
+        struct Cloud(crate::vfs::MemFs);
+        impl FileSystem for Cloud {
+            fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+                let meta = self.0.metadata(path)?;
+                Ok(Metadata {
+                    placeholder: meta.is_file,
+                    ..meta
+                })
+            }
+            fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek + '_>> {
+                self.0.open(path)
+            }
+            fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+                self.0.read_dir(path)
+            }
+        }
+
+        let mut fs = crate::vfs::MemFs::default();
+        fs.insert("r/notes.txt", "a\nb\n");
+        fs.insert("r/app.log.gz", "x");
+        let args = ["r"]
+            .into_iter()
+            .chain(cfg!(feature = "compress").then_some("--decompress"));
+        let config = Config::from_args(args.map(String::from)).unwrap();
+        let errors = error_count();
+        let summary = scan_fs(&Cloud(fs), &config).unwrap();
+        assert_eq!(error_count(), errors);
+        assert_eq!(
+            (summary.placeholders.files, summary.uncounted.files),
+            (2, 0)
+        );
+        assert_eq!(summary.total_lines, 0);
+    }
+
+    #[test]
+    fn files_left_uncounted_are_tallied() {
+        let mut fs = crate::vfs::MemFs::default();
+        fs.insert("r/logo.png", "a\nb\n");
+        fs.insert("r/big.log", "x\n".repeat(600));
+        fs.insert("r/notes.txt", "a\nb\n");
+        let scan = |args: &[&str]| {
+            let args = ["r"].iter().chain(args).map(|a| a.to_string());
+            scan_fs(&fs, &Config::from_args(args).unwrap()).unwrap()
+        };
+        let summary = scan(&["--max-line-bytes", "1K"]);
+        assert_eq!((summary.uncounted.files, summary.uncounted.size), (2, 1204));
+        assert_eq!(summary.total_lines, 2);
+        assert_eq!(scan(&["--force-lines"]).uncounted.files, 0);
+        assert_eq!(scan(&["--no-lines"]).uncounted.files, 0);
+        assert_eq!(scan(&["--metadata-only"]).uncounted.files, 0);
+    }
+}
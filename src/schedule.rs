@@ -0,0 +1,609 @@
+//! `tengok schedule`: register a recurring `tengok history` of a root with
+//! the platform's scheduler, so monitoring a tree is one command. Linux
+//! gets a systemd user timer when systemd runs (cron otherwise), macOS a
+//! launchd agent, Windows a Task Scheduler task and other Unixes a crontab
+//! line. Every job is named after the root's store key, so installing again
+//! replaces it and `schedule remove` finds it.
+//!
+//! The job runs from the directory `schedule install` ran in, so relative
+//! paths among the flags mean what they did, with `TENGOK_HOME` pinned to
+//! the data directory in use. Its output replaces `schedule.log` in the
+//! root's store on every run.
+
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use crate::config::{Config, parse_duration};
+use crate::history;
+use crate::store::{self, RootStore};
+
+const LOG_FILE: &str = "schedule.log";
+const SCRIPT_FILE: &str = "schedule.cmd";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Install,
+    Remove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Via {
+    Cron,
+    Systemd,
+    Launchd,
+    Schtasks,
+}
+
+impl Via {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "cron" => Ok(Via::Cron),
+            "systemd" => Ok(Via::Systemd),
+            "launchd" => Ok(Via::Launchd),
+            "schtasks" => Ok(Via::Schtasks),
+            _ => Err(format!(
+                "Unknown --via {:?}: use cron, systemd, launchd or schtasks",
+                name
+            )),
+        }
+    }
+
+    /// The scheduler this platform is expected to have.
+    fn detect() -> Self {
+        if cfg!(target_os = "macos") {
+            Via::Launchd
+        } else if cfg!(windows) {
+            Via::Schtasks
+        } else if cfg!(target_os = "linux") && Path::new("/run/systemd/system").is_dir() {
+            Via::Systemd
+        } else {
+            Via::Cron
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Via::Cron => "cron",
+            Via::Systemd => "systemd",
+            Via::Launchd => "launchd",
+            Via::Schtasks => "schtasks",
+        }
+    }
+}
+
+/// Options that only make sense for `tengok schedule`.
+pub struct ScheduleArgs {
+    pub action: Action,
+    /// Minutes between runs.
+    pub every: Option<u64>,
+    pub via: Option<Via>,
+    /// Print the files and commands instead of installing anything.
+    pub dry_run: bool,
+    /// The arguments the job passes to `tengok history`.
+    pub job: Vec<String>,
+}
+
+/// Splits `schedule install|remove` options from the job's, and returns the
+/// job's scan flags for validating them.
+pub fn split_args(
+    args: impl IntoIterator<Item = String>,
+) -> Result<(ScheduleArgs, Vec<String>), String> {
+    let mut args = args.into_iter();
+    let action = match args.next().as_deref() {
+        Some("install") => Action::Install,
+        Some("remove") => Action::Remove,
+        _ => return Err("schedule needs install or remove".to_string()),
+    };
+    let mut opts = ScheduleArgs {
+        action,
+        every: None,
+        via: None,
+        dry_run: false,
+        job: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        let flag = arg.split_once('=').map_or(arg.as_str(), |(flag, _)| flag);
+        let mut value = || match arg.split_once('=') {
+            Some((_, v)) => Ok(v.to_string()),
+            None => args
+                .next()
+                .ok_or_else(|| format!("{} requires a value", flag)),
+        };
+        match flag {
+            "--every" => {
+                let every = value()?;
+                opts.every = Some(parse_duration(&every).and_then(minutes).ok_or_else(|| {
+                    format!(
+                        "Unable to parse --every {:?}: use whole minutes or more, e.g. 30m, 6h, 1d",
+                        every
+                    )
+                })?);
+            }
+            "--via" => opts.via = Some(Via::parse(&value()?)?),
+            "--dry-run" => opts.dry_run = true,
+            _ => opts.job.push(arg),
+        }
+    }
+    match opts.action {
+        Action::Install if opts.every.is_none() => {
+            return Err("schedule install needs --every, e.g. --every 1d".to_string());
+        }
+        Action::Remove if opts.every.is_some() => {
+            return Err("--every is for schedule install".to_string());
+        }
+        _ => {}
+    }
+    let (history, scan) = history::split_args(opts.job.clone())?;
+    if !history.record {
+        return Err("A scheduled scan always records; leave out --no-record".to_string());
+    }
+    Ok((opts, scan))
+}
+
+fn minutes(every: Duration) -> Option<u64> {
+    let secs = every.as_secs();
+    (secs >= 60 && secs.is_multiple_of(60)).then_some(secs / 60)
+}
+
+/// `minutes` in the largest unit that divides it, as `--every` takes it.
+fn span(minutes: u64) -> String {
+    [(7 * 24 * 60, "w"), (24 * 60, "d"), (60, "h"), (1, "m")]
+        .iter()
+        .find(|(unit, _)| minutes.is_multiple_of(*unit))
+        .map(|(unit, suffix)| format!("{}{}", minutes / unit, suffix))
+        .unwrap_or_default()
+}
+
+/// What installing or removing a job takes.
+enum Step {
+    Write(PathBuf, String),
+    Remove(PathBuf),
+    /// A command, and whether it may fail (unloading what isn't loaded).
+    Run(Vec<String>, bool),
+    /// Replace the root's crontab line with this one, or drop it.
+    Crontab(Option<String>),
+}
+
+/// Where a job lives and what it runs.
+struct Job {
+    root: String,
+    /// `tengok-<store key>`.
+    name: String,
+    dir: PathBuf,
+    store: RootStore,
+    data_dir: PathBuf,
+    /// The program and its arguments.
+    command: Vec<String>,
+}
+
+pub fn run(config: &Config, opts: &ScheduleArgs) -> Result<(), String> {
+    let via = opts.via.unwrap_or_else(Via::detect);
+    let data_dir = history::data_dir().ok_or("no history directory: set TENGOK_HOME or HOME")?;
+    let root = history::root_id(&config.root);
+    let key = store::key(&root);
+    let store = RootStore::open(&data_dir, &root).map_err(|e| e.to_string())?;
+    let exe = env::current_exe()
+        .and_then(fs::canonicalize)
+        .map_err(|err| format!("Can't tell where tengok is installed: {}", err))?;
+    let mut command = vec![
+        exe.display().to_string(),
+        "history".to_string(),
+        "--plain".to_string(),
+    ];
+    command.extend(opts.job.iter().cloned());
+    let job = Job {
+        root,
+        name: format!("tengok-{}", key),
+        dir: env::current_dir().map_err(|e| e.to_string())?,
+        store,
+        data_dir,
+        command,
+    };
+    let steps = match (via, opts.every) {
+        (Via::Cron, Some(every)) => cron_install(&job, every)?,
+        (Via::Systemd, Some(every)) => systemd_install(&job, every)?,
+        (Via::Launchd, Some(every)) => launchd_install(&job, every)?,
+        (Via::Schtasks, Some(every)) => schtasks_install(&job, every)?,
+        (Via::Cron, None) => vec![Step::Crontab(None)],
+        (Via::Systemd, None) => systemd_remove(&job)?,
+        (Via::Launchd, None) => launchd_remove(&job)?,
+        (Via::Schtasks, None) => schtasks_remove(&job)?,
+    };
+    let marker = format!("# {}", job.name);
+    if opts.dry_run {
+        for step in &steps {
+            print_step(step, &marker);
+        }
+        return Ok(());
+    }
+    for step in &steps {
+        apply(step, &marker, &job.root)?;
+    }
+    match opts.every {
+        Some(every) => println!(
+            "Scheduled a scan of {} every {} via {} ({}); output goes to {}",
+            job.root,
+            span(every),
+            via.name(),
+            job.name,
+            job.store.path(LOG_FILE).display()
+        ),
+        None => println!(
+            "Removed the scheduled scan of {} ({})",
+            job.root,
+            via.name()
+        ),
+    }
+    Ok(())
+}
+
+/// The job as one `sh` command line: cron runs it as is, systemd and
+/// launchd through `/bin/sh -c`.
+fn shell_command(job: &Job) -> String {
+    format!(
+        "cd {} && TENGOK_HOME={} exec {} > {} 2>&1",
+        sh_quote(&job.dir.display().to_string()),
+        sh_quote(&job.data_dir.display().to_string()),
+        job.command
+            .iter()
+            .map(|arg| sh_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" "),
+        sh_quote(&job.store.path(LOG_FILE).display().to_string())
+    )
+}
+
+fn sh_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/._-=:,+@".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// A cron schedule for `every` minutes, which has to divide an hour or a
+/// day evenly, or be a day or a week.
+fn cron_spec(every: u64) -> Result<String, String> {
+    let hours = every / 60;
+    match every {
+        1 => Ok("* * * * *".to_string()),
+        _ if every < 60 && 60 % every == 0 => Ok(format!("*/{} * * * *", every)),
+        60 => Ok("0 * * * *".to_string()),
+        _ if every.is_multiple_of(60) && hours < 24 && 24 % hours == 0 => {
+            Ok(format!("0 */{} * * *", hours))
+        }
+        1440 => Ok("0 0 * * *".to_string()),
+        10080 => Ok("0 0 * * 1".to_string()),
+        _ => Err(format!(
+            "cron can't run every {}: use minutes or hours that divide an hour or a day, 1d or 1w",
+            span(every)
+        )),
+    }
+}
+
+fn cron_install(job: &Job, every: u64) -> Result<Vec<Step>, String> {
+    // `%` ends a cron command unless escaped.
+    let line = format!(
+        "{} {} # {}",
+        cron_spec(every)?,
+        shell_command(job).replace('%', "\\%"),
+        job.name
+    );
+    Ok(vec![Step::Crontab(Some(line))])
+}
+
+fn systemd_dir() -> Result<PathBuf, String> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("systemd/user"))
+        .ok_or_else(|| "no systemd user directory: set HOME".to_string())
+}
+
+/// An `ExecStart=` argument: systemd unquotes C-style and expands `%` and
+/// `$` on its own.
+fn systemd_quote(arg: &str) -> String {
+    format!(
+        "\"{}\"",
+        arg.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('%', "%%")
+            .replace('$', "$$")
+    )
+}
+
+fn systemctl(args: &[&str], may_fail: bool) -> Step {
+    let mut command = vec!["systemctl".to_string(), "--user".to_string()];
+    command.extend(args.iter().map(|arg| arg.to_string()));
+    Step::Run(command, may_fail)
+}
+
+fn systemd_install(job: &Job, every: u64) -> Result<Vec<Step>, String> {
+    let dir = systemd_dir()?;
+    let service = format!(
+        "[Unit]\nDescription=tengok scan of {}\n\n[Service]\nType=oneshot\nExecStart=/bin/sh -c {}\n",
+        job.root,
+        systemd_quote(&shell_command(job))
+    );
+    let timer = format!(
+        "[Unit]\nDescription=Scan {} every {}\n\n[Timer]\nOnBootSec=5min\nOnUnitActiveSec={}min\n\n[Install]\nWantedBy=timers.target\n",
+        job.root,
+        span(every),
+        every
+    );
+    let timer_unit = format!("{}.timer", job.name);
+    Ok(vec![
+        Step::Write(dir.join(format!("{}.service", job.name)), service),
+        Step::Write(dir.join(&timer_unit), timer),
+        systemctl(&["daemon-reload"], false),
+        systemctl(&["enable", "--now", &timer_unit], false),
+        // A timer already running keeps its old interval until restarted.
+        systemctl(&["restart", &timer_unit], false),
+    ])
+}
+
+fn systemd_remove(job: &Job) -> Result<Vec<Step>, String> {
+    let dir = systemd_dir()?;
+    let timer_unit = format!("{}.timer", job.name);
+    if !dir.join(&timer_unit).exists() {
+        return Err(not_scheduled(job, Via::Systemd));
+    }
+    Ok(vec![
+        systemctl(&["disable", "--now", &timer_unit], true),
+        Step::Remove(dir.join(&timer_unit)),
+        Step::Remove(dir.join(format!("{}.service", job.name))),
+        systemctl(&["daemon-reload"], false),
+    ])
+}
+
+fn launchd_plist(job: &Job) -> Result<PathBuf, String> {
+    env::var_os("HOME")
+        .map(|home| {
+            PathBuf::from(home)
+                .join("Library/LaunchAgents")
+                .join(format!("{}.plist", job.name))
+        })
+        .ok_or_else(|| "no LaunchAgents directory: set HOME".to_string())
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn launchd_install(job: &Job, every: u64) -> Result<Vec<Step>, String> {
+    let plist = launchd_plist(job)?;
+    let contents = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>Label</key>
+  <string>{}</string>
+  <key>ProgramArguments</key>
+  <array>
+    <string>/bin/sh</string>
+    <string>-c</string>
+    <string>{}</string>
+  </array>
+  <key>StartInterval</key>
+  <integer>{}</integer>
+  <key>RunAtLoad</key>
+  <true/>
+</dict>
+</plist>
+"#,
+        job.name,
+        xml_escape(&shell_command(job)),
+        every * 60
+    );
+    let path = plist.display().to_string();
+    Ok(vec![
+        Step::Run(
+            vec!["launchctl".into(), "unload".into(), path.clone()],
+            true,
+        ),
+        Step::Write(plist, contents),
+        Step::Run(
+            vec!["launchctl".into(), "load".into(), "-w".into(), path],
+            false,
+        ),
+    ])
+}
+
+fn launchd_remove(job: &Job) -> Result<Vec<Step>, String> {
+    let plist = launchd_plist(job)?;
+    if !plist.exists() {
+        return Err(not_scheduled(job, Via::Launchd));
+    }
+    let path = plist.display().to_string();
+    Ok(vec![
+        Step::Run(vec!["launchctl".into(), "unload".into(), path], true),
+        Step::Remove(plist),
+    ])
+}
+
+/// A `.cmd` argument; `%` would expand a variable.
+fn cmd_quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('%', "%%"))
+}
+
+/// `schtasks /SC` and `/MO` for `every` minutes.
+fn schtasks_spec(every: u64) -> Result<(&'static str, u64), String> {
+    let spec = [
+        ("WEEKLY", 7 * 24 * 60, 52),
+        ("DAILY", 24 * 60, 365),
+        ("HOURLY", 60, 23),
+        ("MINUTE", 1, 1439),
+    ]
+    .into_iter()
+    .find(|(_, unit, _)| every.is_multiple_of(*unit))
+    .filter(|(_, unit, max)| every / unit <= *max);
+    spec.map(|(schedule, unit, _)| (schedule, every / unit))
+        .ok_or_else(|| format!("Task Scheduler can't run every {}", span(every)))
+}
+
+fn schtasks_install(job: &Job, every: u64) -> Result<Vec<Step>, String> {
+    let (schedule, modifier) = schtasks_spec(every)?;
+    // A script sidesteps /TR's length limit and its quoting.
+    let script = job.store.path(SCRIPT_FILE);
+    let contents = format!(
+        "@echo off\r\ncd /d {}\r\nset \"TENGOK_HOME={}\"\r\n{} > {} 2>&1\r\n",
+        cmd_quote(&job.dir.display().to_string()),
+        job.data_dir.display().to_string().replace('%', "%%"),
+        job.command
+            .iter()
+            .map(|arg| cmd_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" "),
+        cmd_quote(&job.store.path(LOG_FILE).display().to_string())
+    );
+    let command = [
+        "schtasks",
+        "/Create",
+        "/F",
+        "/TN",
+        &job.name,
+        "/SC",
+        schedule,
+        "/MO",
+        &modifier.to_string(),
+        "/TR",
+        &format!("\"{}\"", script.display()),
+    ];
+    Ok(vec![
+        Step::Write(script, contents),
+        Step::Run(command.iter().map(|arg| arg.to_string()).collect(), false),
+    ])
+}
+
+fn schtasks_remove(job: &Job) -> Result<Vec<Step>, String> {
+    let script = job.store.path(SCRIPT_FILE);
+    if !script.exists() {
+        return Err(not_scheduled(job, Via::Schtasks));
+    }
+    let command = ["schtasks", "/Delete", "/F", "/TN", &job.name];
+    Ok(vec![
+        Step::Run(command.iter().map(|arg| arg.to_string()).collect(), false),
+        Step::Remove(script),
+    ])
+}
+
+fn not_scheduled(job: &Job, via: Via) -> String {
+    format!("No scheduled scan of {} via {}", job.root, via.name())
+}
+
+fn print_step(step: &Step, marker: &str) {
+    match step {
+        Step::Write(path, contents) => {
+            println!("Would write {}:\n{}", path.display(), contents.trim_end());
+        }
+        Step::Remove(path) => println!("Would remove {}", path.display()),
+        Step::Run(command, _) => println!("Would run: {}", command.join(" ")),
+        Step::Crontab(Some(line)) => println!("Would set in the crontab:\n{}", line),
+        Step::Crontab(None) => {
+            println!("Would remove the crontab line ending in {:?}", marker)
+        }
+    }
+}
+
+fn apply(step: &Step, marker: &str, root: &str) -> Result<(), String> {
+    match step {
+        Step::Write(path, contents) => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+            }
+            fs::write(path, contents).map_err(|e| format!("{}: {}", path.display(), e))
+        }
+        Step::Remove(path) => match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                Err(format!("{}: {}", path.display(), err))
+            }
+            _ => Ok(()),
+        },
+        Step::Run(command, may_fail) => {
+            let output = Command::new(&command[0])
+                .args(&command[1..])
+                .stdin(Stdio::null())
+                .output()
+                .map_err(|err| format!("Can't run {}: {}", command[0], err))?;
+            if !output.status.success() && !may_fail {
+                return Err(format!(
+                    "{} failed: {}",
+                    command.join(" "),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+            Ok(())
+        }
+        Step::Crontab(line) => {
+            // `crontab -l` fails when there's no crontab yet.
+            let current = Command::new("crontab")
+                .arg("-l")
+                .output()
+                .map_err(|err| format!("Can't run crontab: {}", err))?;
+            let current = if current.status.success() {
+                String::from_utf8_lossy(&current.stdout).into_owned()
+            } else {
+                String::new()
+            };
+            let mut lines: Vec<&str> = current.lines().filter(|l| !l.ends_with(marker)).collect();
+            if line.is_none() && lines.len() == current.lines().count() {
+                return Err(format!("No scheduled scan of {} via cron", root));
+            }
+            lines.extend(line.as_deref());
+            let mut child = Command::new("crontab")
+                .arg("-")
+                .stdin(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(|err| format!("Can't run crontab: {}", err))?;
+            let mut text = lines.join("\n");
+            text.push('\n');
+            child
+                .stdin
+                .take()
+                .map(|mut stdin| stdin.write_all(text.as_bytes()))
+                .transpose()
+                .map_err(|e| e.to_string())?;
+            let output = child.wait_with_output().map_err(|e| e.to_string())?;
+            if !output.status.success() {
+                return Err(format!(
+                    "crontab failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intervals_map_to_each_scheduler() {
+        let every = |s: &str| parse_duration(s).and_then(minutes).unwrap();
+        assert_eq!(cron_spec(every("15m")).unwrap(), "*/15 * * * *");
+        assert_eq!(cron_spec(every("6h")).unwrap(), "0 */6 * * *");
+        assert_eq!(cron_spec(every("1d")).unwrap(), "0 0 * * *");
+        assert!(cron_spec(every("7m")).is_err());
+        assert!(cron_spec(every("2d")).is_err());
+        assert_eq!(schtasks_spec(every("2d")).unwrap(), ("DAILY", 2));
+        assert_eq!(schtasks_spec(every("90m")).unwrap(), ("MINUTE", 90));
+        assert_eq!(span(every("14d")), "2w");
+        assert!(parse_duration("90s").and_then(minutes).is_none());
+        assert_eq!(sh_quote("it's"), "'it'\\''s'");
+    }
+}
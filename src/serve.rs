@@ -0,0 +1,277 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::json;
+
+use crate::config::{Config, Output};
+use crate::json;
+use crate::metrics::{self, Sample};
+use crate::scan::{self, Summary};
+
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:9345";
+pub const DEFAULT_INTERVAL_SECS: u64 = 60;
+
+/// How long a client gets to send its whole request.
+const REQUEST_DEADLINE: Duration = Duration::from_secs(5);
+
+pub struct ServeArgs {
+    pub listen: String,
+    pub interval: Duration,
+}
+
+pub fn split_args(
+    args: impl IntoIterator<Item = String>,
+) -> Result<(ServeArgs, Vec<String>), String> {
+    let mut opts = ServeArgs {
+        listen: DEFAULT_LISTEN.to_string(),
+        interval: Duration::from_secs(DEFAULT_INTERVAL_SECS),
+    };
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if arg.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        if flag != "--listen" && flag != "--interval" {
+            rest.push(arg);
+            continue;
+        }
+        let value = inline
+            .or_else(|| args.next())
+            .ok_or_else(|| format!("{} requires a value", flag))?;
+        if flag == "--listen" {
+            opts.listen = value;
+        } else {
+            let secs: u64 = value
+                .parse()
+                .map_err(|_| "Unable to parse --interval".to_string())?;
+            opts.interval = Duration::from_secs(secs.max(1));
+        }
+    }
+    Ok((opts, rest))
+}
+
+struct RootState {
+    config: Config,
+    result: Option<(Summary, f64, u64)>, // (summary, duration secs, finished at)
+}
+
+type Shared = Arc<Mutex<Vec<RootState>>>;
+
+pub fn run(config: &Config, opts: &ServeArgs) -> io::Result<()> {
+    let listener = TcpListener::bind(&opts.listen)?;
+    eprintln!(
+        "tengok serving {} root(s) on http://{} (rescan every {}s)",
+        config.roots.len(),
+        listener.local_addr()?,
+        opts.interval.as_secs()
+    );
+
+    let state: Shared = Arc::new(Mutex::new(
+        config
+            .roots
+            .iter()
+            .map(|root| {
+                let mut config = config.with_root(root.clone());
+                // Scans run in the background; never draw the spinner.
+                config.output = Output::Json;
+                RootState {
+                    config,
+                    result: None,
+                }
+            })
+            .collect(),
+    ));
+
+    let scanner_state = Arc::clone(&state);
+    let interval = opts.interval;
+    thread::spawn(move || scan_loop(scanner_state, interval));
+
+    accept(listener, state);
+    Ok(())
+}
+
+/// Answer each connection on a thread of its own, so a slow client never
+/// holds up the next dashboard poll.
+fn accept(listener: TcpListener, state: Shared) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let state = Arc::clone(&state);
+                thread::spawn(move || {
+                    if let Err(err) = handle(stream, &state) {
+                        eprintln!("tengok serve: {}", err);
+                    }
+                });
+            }
+            Err(err) => eprintln!("tengok serve: {}", err),
+        }
+    }
+}
+
+/// Reads from `stream` until `until`, however the bytes trickle in.
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    until: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self
+            .until
+            .checked_duration_since(Instant::now())
+            .filter(|left| !left.is_zero())
+            .ok_or(io::ErrorKind::TimedOut)?;
+        self.stream.set_read_timeout(Some(left))?;
+        let mut stream = self.stream;
+        stream.read(buf)
+    }
+}
+
+fn scan_loop(state: Shared, interval: Duration) {
+    let configs: Vec<Config> = state
+        .lock()
+        .map(|roots| roots.iter().map(|r| r.config.clone()).collect())
+        .unwrap_or_default();
+
+    loop {
+        for (idx, config) in configs.iter().enumerate() {
+            let started = Instant::now();
+            let config = Arc::new(config.clone());
+            match scan::scan_dir(&config) {
+                Ok(summary) => {
+                    let finished_at = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0);
+                    let duration = started.elapsed().as_secs_f64();
+                    if let Ok(mut roots) = state.lock() {
+                        roots[idx].result = Some((summary, duration, finished_at));
+                    }
+                }
+                Err(err) => eprintln!(
+                    "tengok serve: scan of {} failed: {}",
+                    config.root.display(),
+                    err
+                ),
+            }
+        }
+        thread::sleep(interval);
+    }
+}
+
+fn handle(stream: TcpStream, state: &Shared) -> io::Result<()> {
+    stream.set_write_timeout(Some(REQUEST_DEADLINE))?;
+    let mut reader = BufReader::new(Deadline {
+        stream: &stream,
+        until: Instant::now() + REQUEST_DEADLINE,
+    });
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain headers; nothing in them changes the response.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("/");
+    let path = path.split('?').next().unwrap_or(path);
+
+    let roots = state
+        .lock()
+        .map_err(|_| io::Error::other("scanner state poisoned"))?;
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/") | ("GET", "/json") => {
+            let docs: Vec<_> = roots
+                .iter()
+                .filter_map(|r| {
+                    r.result
+                        .as_ref()
+                        .map(|(s, _, _)| json::to_value(&r.config, s))
+                })
+                .collect();
+            let body = json!({
+                "schema_version": json::SCHEMA_VERSION,
+                "roots": docs,
+            });
+            ("200 OK", "application/json", format!("{}\n", body))
+        }
+        ("GET", "/metrics") => {
+            let samples: Vec<Sample> = roots
+                .iter()
+                .filter_map(|r| {
+                    r.result
+                        .as_ref()
+                        .map(|(summary, duration, finished)| Sample {
+                            config: &r.config,
+                            summary,
+                            duration_secs: *duration,
+                            finished_at: *finished,
+                        })
+                })
+                .collect();
+            (
+                "200 OK",
+                "text/plain; version=0.0.4",
+                metrics::render(&samples),
+            )
+        }
+        ("GET", _) => ("404 Not Found", "text/plain", "not found\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "method not allowed\n".to_string(),
+        ),
+    };
+    drop(roots);
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_args_extracts_listen_and_interval() {
+        let args = ["--listen=0.0.0.0:1", "a", "--interval", "5", "--plain", "b"].map(String::from);
+        let (opts, rest) = split_args(args).unwrap();
+        assert_eq!(opts.listen, "0.0.0.0:1");
+        assert_eq!(opts.interval, Duration::from_secs(5));
+        assert_eq!(rest, ["a", "--plain", "b"]);
+    }
+
+    #[test]
+    fn a_stalled_client_doesnt_hold_up_the_next() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || accept(listener, Arc::new(Mutex::new(Vec::new()))));
+
+        let _stalled = TcpStream::connect(addr).unwrap();
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.set_read_timeout(Some(REQUEST_DEADLINE / 2)).unwrap();
+        client.write_all(b"GET /json HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    }
+}
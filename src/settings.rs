@@ -0,0 +1,338 @@
+//! `tengok config [OPTIONS] [PATH]`: the settings a scan with those options
+//! would run with, and where each one came from: its default, a file tengok
+//! picks up by itself (`tengok-budgets.toml` in the root), an environment
+//! variable or a command-line flag. Handy when a flag "isn't taking
+//! effect" because something else set it first.
+//!
+//! `tengok config check [--budgets FILE] [PATH]` validates the files and
+//! variables a scan would read, reporting every problem with its line
+//! instead of stopping at the first one the scan would hit.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use humansize::{BINARY, DECIMAL, format_size};
+use tracing::level_filters::LevelFilter;
+use unicode_width::UnicodeWidthStr;
+
+use crate::budget;
+use crate::config::{Config, Output};
+use crate::history;
+use crate::i18n::LOCALE_VARS;
+use crate::lists;
+use crate::report::Style;
+use crate::theme::{self, Theme};
+
+enum Source {
+    Default,
+    File(&'static str),
+    Env(&'static str),
+    Flag(String),
+    Argument,
+}
+
+impl Source {
+    fn describe(&self) -> String {
+        match self {
+            Source::Default => "default".to_string(),
+            Source::File(name) => format!("file {}", name),
+            Source::Env(var) => format!("env {}", var),
+            Source::Flag(flag) => flag.clone(),
+            Source::Argument => "argument".to_string(),
+        }
+    }
+}
+
+pub fn run(config: &Config) {
+    let style = Style::new(config);
+    let flag = |flags: &[&str]| flag_source(config, flags);
+    let env_or_default = |vars: &[&'static str]| {
+        vars.iter()
+            .find(|var| env::var(var).is_ok_and(|v| !v.is_empty()))
+            .map_or(Source::Default, |var| Source::Env(var))
+    };
+    let on_off = |on: bool| if on { "on" } else { "off" }.to_string();
+
+    let paths = config
+        .roots
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let paths_source = if config.roots == [Path::new(".")] {
+        Source::Default
+    } else {
+        Source::Argument
+    };
+    let output = match config.output {
+        Output::Report => "report",
+        Output::Json => "json",
+        Output::List => "list",
+        Output::NdJson => "ndjson",
+        Output::JsonTree => "json-tree",
+        Output::Folded => "folded",
+        Output::Oneline => "oneline",
+        Output::OpenMetrics => "openmetrics",
+    };
+    let threads = match config.threads {
+        _ if config.sequential => "1 (sequential)".to_string(),
+        0 => format!(
+            "auto ({})",
+            thread::available_parallelism().map_or(1, |n| n.get())
+        ),
+        n => n.to_string(),
+    };
+    let line_counting = if config.skip_lines {
+        "off"
+    } else if config.force_lines {
+        "forced"
+    } else {
+        "on"
+    };
+    let budgets_source = flag(&["--budgets"]).unwrap_or(if config.budgets.is_empty() {
+        Source::Default
+    } else {
+        Source::File(budget::FILE_NAME)
+    });
+    let log_level = if config.log_level == LevelFilter::OFF {
+        "off".to_string()
+    } else {
+        config.log_level.to_string().to_lowercase()
+    };
+
+    let rows: Vec<(&str, String, Source)> = vec![
+        ("paths", paths, paths_source),
+        (
+            "output",
+            output.to_string(),
+            flag(&["--json", "--list", "--oneline"]).unwrap_or(Source::Default),
+        ),
+        (
+            "colors",
+            on_off(!config.plain),
+            flag(&["--plain", "--no-colors"]).unwrap_or(Source::Default),
+        ),
+        (
+            "theme",
+            config.theme.name().unwrap_or("custom").to_string(),
+            flag(&["--theme"]).unwrap_or_else(|| env_or_default(&[theme::ENV])),
+        ),
+        (
+            "lang",
+            config.lang.as_str().to_string(),
+            flag(&["--lang"]).unwrap_or_else(|| env_or_default(&LOCALE_VARS)),
+        ),
+        (
+            "times",
+            format!(
+                "{}, {}",
+                if config.utc { "UTC" } else { "local" },
+                config.time_format.as_str()
+            ),
+            flag(&["--utc", "--time-format"]).unwrap_or_else(|| env_or_default(&["TZ"])),
+        ),
+        (
+            "threads",
+            threads,
+            flag(&["--threads", "-j", "--sequential"]).unwrap_or(Source::Default),
+        ),
+        (
+            "follow links",
+            config
+                .follow_links
+                .map_or("off", |mode| mode.as_str())
+                .to_string(),
+            flag(&["--follow-links"]).unwrap_or(Source::Default),
+        ),
+        (
+            "throttle",
+            config.throttle.as_ref().map_or("off".to_string(), |t| {
+                format!("{}/s", format_size(t.bytes_per_sec(), DECIMAL))
+            }),
+            flag(&["--throttle"]).unwrap_or(Source::Default),
+        ),
+        (
+            "retries",
+            config.retries.to_string(),
+            flag(&["--retries"]).unwrap_or(Source::Default),
+        ),
+        (
+            "line backend",
+            config.line_backend.as_str().to_string(),
+            flag(&["--line-backend"]).unwrap_or(Source::Default),
+        ),
+        (
+            "line counting",
+            line_counting.to_string(),
+            flag(&["--no-lines", "--force-lines"]).unwrap_or(Source::Default),
+        ),
+        (
+            "max line bytes",
+            format_size(config.max_line_bytes, BINARY),
+            flag(&["--max-line-bytes"]).unwrap_or(Source::Default),
+        ),
+        (
+            "hidden files",
+            on_off(config.hidden),
+            flag(&["--hidden"]).unwrap_or(Source::Default),
+        ),
+        (
+            "ignore files",
+            on_off(!config.no_ignore),
+            flag(&["--no-ignore"]).unwrap_or(Source::Default),
+        ),
+        (
+            "sort",
+            format!(
+                "{}{}",
+                lists::sort_key(config).as_str(),
+                if config.reverse { ", reversed" } else { "" }
+            ),
+            flag(&["--sort", "--reverse"]).unwrap_or(Source::Default),
+        ),
+        (
+            "top",
+            config.top.map_or("-".to_string(), |n| n.to_string()),
+            flag(&["--top"]).unwrap_or(Source::Default),
+        ),
+        (
+            "fields",
+            config
+                .fields
+                .iter()
+                .map(|row| row.as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+            flag(&["--fields"]).unwrap_or(Source::Default),
+        ),
+        (
+            "budgets",
+            match config.budgets.len() {
+                0 => "none".to_string(),
+                n => format!("{} rules", n),
+            },
+            budgets_source,
+        ),
+        (
+            "baseline",
+            on_off(config.baseline.is_some()),
+            flag(&["--baseline"]).unwrap_or(Source::Default),
+        ),
+        (
+            "log level",
+            log_level,
+            flag(&["--log-level", "--log-file"]).unwrap_or(Source::Default),
+        ),
+        (
+            "log file",
+            config
+                .log_file
+                .as_ref()
+                .map_or("stderr".to_string(), |p| p.display().to_string()),
+            flag(&["--log-file"]).unwrap_or(Source::Default),
+        ),
+        (
+            "data directory",
+            history::data_dir().map_or("-".to_string(), |p| p.display().to_string()),
+            env_or_default(&["TENGOK_HOME", "XDG_DATA_HOME"]),
+        ),
+        (
+            "deterministic",
+            on_off(config.deterministic),
+            flag(&["--deterministic"]).unwrap_or(Source::Default),
+        ),
+    ];
+
+    let name_width = rows
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    let value_width = rows
+        .iter()
+        .map(|(_, value, _)| UnicodeWidthStr::width(value.as_str()))
+        .max()
+        .unwrap_or(0);
+    println!("{}", style.label("Effective configuration"));
+    for (name, value, source) in &rows {
+        let padding = value_width.saturating_sub(UnicodeWidthStr::width(value.as_str()));
+        println!(
+            "  {:<name_width$}  {}{}  {}",
+            name,
+            style.value(value),
+            " ".repeat(padding),
+            source.describe()
+        );
+    }
+}
+
+/// `tengok config check`: the budgets file (`--budgets`, else PATH's
+/// `tengok-budgets.toml`) and `TENGOK_THEME`. Whether everything was valid.
+pub fn check(args: impl IntoIterator<Item = String>) -> Result<bool, String> {
+    let mut budgets = None;
+    let mut root = PathBuf::from(".");
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.split_once('=') {
+            Some(("--budgets", file)) => budgets = Some(PathBuf::from(file)),
+            _ if arg == "--budgets" => {
+                budgets = Some(PathBuf::from(
+                    args.next().ok_or("--budgets requires a value")?,
+                ));
+            }
+            _ if arg.starts_with('-') => {
+                return Err(format!("Unknown config check option: {}", arg));
+            }
+            _ => root = PathBuf::from(arg),
+        }
+    }
+
+    let mut problems = Vec::new();
+    let path = budgets.unwrap_or_else(|| root.join(budget::FILE_NAME));
+    let explicit = path != root.join(budget::FILE_NAME);
+    match fs::read_to_string(&path) {
+        Ok(text) => {
+            for (line, problem) in budget::check(&text) {
+                problems.push(format!("{}:{}: {}", path.display(), line, problem));
+            }
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound && !explicit => {}
+        Err(err) => problems.push(format!("{}: {}", path.display(), err)),
+    }
+    if let Ok(spec) = env::var(theme::ENV)
+        && let Err(err) = Theme::parse(&spec)
+    {
+        problems.push(format!("env {}: {}", theme::ENV, err));
+    }
+
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    if problems.is_empty() {
+        println!("Configuration OK");
+    }
+    Ok(problems.is_empty())
+}
+
+/// The last of `flags` given on the command line, as `--flag` (or
+/// `--flag=value` spelled that way).
+fn flag_source(config: &Config, flags: &[&str]) -> Option<Source> {
+    config
+        .args
+        .iter()
+        .rev()
+        .find(|arg| {
+            let name = arg.split_once('=').map_or(arg.as_str(), |(name, _)| name);
+            flags.contains(&name)
+        })
+        .map(|arg| {
+            Source::Flag(
+                arg.split_once('=')
+                    .map_or(arg.as_str(), |(n, _)| n)
+                    .to_string(),
+            )
+        })
+}
@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+
+/// Relative error bound of `Sketch::quantile`.
+const ALPHA: f64 = 0.01;
+
+/// Quantiles reported by `--verbose` and in JSON.
+pub const PERCENTILES: [(&str, f64); 3] = [("p50", 0.5), ("p90", 0.9), ("p99", 0.99)];
+
+/// Streaming quantile sketch over non-negative integers, in the style of
+/// DDSketch: values land in logarithmic buckets so any quantile is within
+/// `ALPHA` relative error, memory grows with the value range rather than
+/// the count, and — unlike most sketches — values can be removed again,
+/// which watch mode needs.
+#[derive(Debug, Clone, Default)]
+pub struct Sketch {
+    zeros: u64,
+    buckets: BTreeMap<i32, u64>,
+    count: u64,
+}
+
+fn gamma() -> f64 {
+    (1.0 + ALPHA) / (1.0 - ALPHA)
+}
+
+fn bucket(value: u64) -> i32 {
+    (value as f64).log(gamma()).ceil() as i32
+}
+
+impl Sketch {
+    pub fn add(&mut self, value: u64) {
+        self.count += 1;
+        if value == 0 {
+            self.zeros += 1;
+        } else {
+            *self.buckets.entry(bucket(value)).or_default() += 1;
+        }
+    }
+
+    pub fn remove(&mut self, value: u64) {
+        if value == 0 {
+            if self.zeros > 0 {
+                self.zeros -= 1;
+                self.count -= 1;
+            }
+            return;
+        }
+        let key = bucket(value);
+        if let Some(n) = self.buckets.get_mut(&key) {
+            *n -= 1;
+            self.count -= 1;
+            if *n == 0 {
+                self.buckets.remove(&key);
+            }
+        }
+    }
+
+    /// Approximate value at quantile `q` (0.0..=1.0); `None` when empty.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = (q.clamp(0.0, 1.0) * (self.count - 1) as f64).floor() as u64;
+        if rank < self.zeros {
+            return Some(0.0);
+        }
+        let mut seen = self.zeros;
+        for (&key, &n) in &self.buckets {
+            seen += n;
+            if seen > rank {
+                // Midpoint of (γ^(key-1), γ^key] in relative terms.
+                return Some(2.0 * gamma().powi(key) / (gamma() + 1.0));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantiles_stay_within_relative_error() {
+        let mut sketch = Sketch::default();
+        for v in 1..=10_000u64 {
+            sketch.add(v);
+        }
+        for (q, exact) in [(0.5, 5_000.0), (0.9, 9_000.0), (0.99, 9_900.0)] {
+            let got = sketch.quantile(q).unwrap();
+            assert!((got - exact).abs() / exact <= ALPHA * 1.01, "{q}: {got}");
+        }
+
+        for v in 5_001..=10_000u64 {
+            sketch.remove(v);
+        }
+        let p99 = sketch.quantile(0.99).unwrap();
+        assert!((p99 - 4_950.0).abs() / 4_950.0 <= ALPHA * 1.01);
+    }
+}
@@ -0,0 +1,217 @@
+//! Binary snapshots (`.tsnap`): the `--json` document in a compact form for
+//! snapshot and diff workflows on trees with millions of files, where the
+//! JSON's `--top` lists grow large. Written with `--snapshot FILE`, read
+//! anywhere a JSON snapshot is (`--baseline`, `tengok diff`), and turned
+//! back into JSON (or the other way) with `tengok snapshot convert`.
+//!
+//! The file is `MAGIC` followed by one zstd stream of records, each a
+//! top-level key of the document and its value as compact JSON, both
+//! length-prefixed (u32 and u64, little-endian). `summary` comes first, so
+//! reading the totals stops after the first record.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use serde_json::{Map, Value};
+
+pub const EXTENSION: &str = "tsnap";
+const MAGIC: &[u8; 8] = b"TENGOKS1";
+
+/// Whether `path` starts like a binary snapshot.
+pub fn is_binary(path: &Path) -> io::Result<bool> {
+    let mut head = [0; MAGIC.len()];
+    let mut file = File::open(path)?;
+    let mut read = 0;
+    while read < head.len() {
+        match file.read(&mut head[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read == head.len() && &head == MAGIC)
+}
+
+/// Writes `doc`, a `--json` document, as a binary snapshot.
+pub fn write(path: &Path, doc: &Value) -> io::Result<()> {
+    let Some(object) = doc.as_object() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "a snapshot is a JSON object",
+        ));
+    };
+    let mut keys: Vec<&String> = object.keys().collect();
+    keys.sort_by_key(|key| *key != "summary");
+    let records = keys
+        .into_iter()
+        .map(|key| Ok((key.as_str(), serde_json::to_vec(&object[key])?)))
+        .collect::<io::Result<Vec<_>>>()?;
+    imp::write(path, &records)
+}
+
+/// The whole document.
+pub fn read(path: &Path) -> io::Result<Value> {
+    let mut doc = Map::new();
+    imp::read(path, &mut |key, value| {
+        doc.insert(key, value);
+        true
+    })?;
+    Ok(Value::Object(doc))
+}
+
+/// Just the `summary` record, as `{"summary": …}`, which is all the
+/// baseline needs.
+pub fn read_summary(path: &Path) -> io::Result<Value> {
+    let mut doc = Map::new();
+    imp::read(path, &mut |key, value| {
+        let found = key == "summary";
+        if found {
+            doc.insert(key, value);
+        }
+        !found
+    })?;
+    Ok(Value::Object(doc))
+}
+
+/// `tengok snapshot convert IN OUT`: OUT is binary when it ends in
+/// `.tsnap`, pretty-printed JSON otherwise; IN can be either.
+pub fn run(args: impl IntoIterator<Item = String>) -> Result<(), String> {
+    let mut args = args.into_iter();
+    if args.next().as_deref() != Some("convert") {
+        return Err("snapshot needs a command: convert <IN> <OUT>".to_string());
+    }
+    let (Some(input), Some(output), None) = (args.next(), args.next(), args.next()) else {
+        return Err("snapshot convert needs an input and an output file".to_string());
+    };
+    let (input, output) = (Path::new(&input), Path::new(&output));
+    let doc = load(input).map_err(|e| format!("Unable to read {}: {}", input.display(), e))?;
+    let written = if output.extension().is_some_and(|ext| ext == EXTENSION) {
+        write(output, &doc)
+    } else {
+        File::create(output).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            serde_json::to_writer_pretty(&mut out, &doc)?;
+            writeln!(out)?;
+            out.flush()
+        })
+    };
+    written.map_err(|e| format!("Unable to write {}: {}", output.display(), e))
+}
+
+/// A snapshot in either format.
+fn load(path: &Path) -> io::Result<Value> {
+    if is_binary(path)? {
+        return read(path);
+    }
+    Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+}
+
+#[cfg(feature = "compress")]
+mod imp {
+    use std::{
+        fs::File,
+        io::{self, BufReader, BufWriter, Read, Write},
+        path::Path,
+    };
+
+    use serde_json::Value;
+
+    use super::MAGIC;
+
+    pub fn write(path: &Path, records: &[(&str, Vec<u8>)]) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        let mut encoder = zstd::stream::Encoder::new(file, 3)?;
+        for (key, value) in records {
+            encoder.write_all(&(key.len() as u32).to_le_bytes())?;
+            encoder.write_all(key.as_bytes())?;
+            encoder.write_all(&(value.len() as u64).to_le_bytes())?;
+            encoder.write_all(value)?;
+        }
+        encoder.finish()?.flush()
+    }
+
+    /// Feeds each record to `each` until it returns false or the stream
+    /// ends.
+    pub fn read(path: &Path, each: &mut dyn FnMut(String, Value) -> bool) -> io::Result<()> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut magic = [0; MAGIC.len()];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a tengok snapshot"));
+        }
+        let mut decoder = zstd::stream::Decoder::with_buffer(file)?;
+        loop {
+            let mut len = [0; 4];
+            match decoder.read_exact(&mut len) {
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                result => result?,
+            }
+            let mut key = vec![0; u32::from_le_bytes(len) as usize];
+            decoder.read_exact(&mut key)?;
+            let key = String::from_utf8(key).map_err(|_| invalid("record key isn't UTF-8"))?;
+            let mut len = [0; 8];
+            decoder.read_exact(&mut len)?;
+            let mut value = Vec::new();
+            (&mut decoder)
+                .take(u64::from_le_bytes(len))
+                .read_to_end(&mut value)?;
+            if !each(key, serde_json::from_slice(&value)?) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn invalid(msg: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+    }
+}
+
+#[cfg(not(feature = "compress"))]
+mod imp {
+    use std::{io, path::Path};
+
+    use serde_json::Value;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "binary snapshots need tengok built with the `compress` feature",
+        )
+    }
+
+    pub fn write(_: &Path, _: &[(&str, Vec<u8>)]) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn read(_: &Path, _: &mut dyn FnMut(String, Value) -> bool) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(all(test, feature = "compress"))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn round_trips_a_document() {
+        let path =
+            std::env::temp_dir().join(format!("tengok-{}.{}", std::process::id(), EXTENSION));
+        let doc = json!({
+            "root": "src",
+            "summary": {"files": 3, "size_bytes": 120, "lines": 9},
+            "top_files": [{"path": "a.rs", "size_bytes": 100}],
+        });
+        write(&path, &doc).unwrap();
+        assert!(is_binary(&path).unwrap());
+        assert_eq!(read(&path).unwrap(), doc);
+        assert_eq!(
+            read_summary(&path).unwrap(),
+            json!({"summary": doc["summary"]})
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
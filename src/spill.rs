@@ -0,0 +1,169 @@
+//! `--memory-limit`: file records spilled to a temporary file. On trees with
+//! tens of millions of files the records kept for `--top`, `--top-lines`
+//! and `--tiny` outgrow RAM long before the per-directory totals do, so
+//! once they take more than their share of the limit they are written out
+//! here, and read back at the end of the scan only to pick what the lists
+//! show. Only what those lists use is kept: path, size, lines and
+//! modification time.
+//!
+//! Each record is the size, lines, and modification time (seconds and
+//! nanoseconds since the epoch, `u64::MAX` for none) as little-endian
+//! integers, then the path's length (u32) and bytes.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Write},
+    mem,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU32, Ordering},
+    time::{Duration, SystemTime},
+};
+
+use crate::scan::FileRecord;
+
+/// The RAM a kept record takes, near enough to budget with.
+pub fn footprint(record: &FileRecord) -> u64 {
+    (mem::size_of::<FileRecord>()
+        + record.path.capacity()
+        + record.parent.capacity()
+        + record.metrics.capacity() * mem::size_of::<u64>()) as u64
+}
+
+/// A temporary file of records, removed when dropped.
+#[derive(Debug)]
+pub struct Spill {
+    path: PathBuf,
+    out: BufWriter<File>,
+    pub records: u64,
+}
+
+impl Spill {
+    pub fn create() -> io::Result<Self> {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "tengok-spill-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        // The paths of a whole tree are nobody else's business.
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let out = BufWriter::new(options.open(&path)?);
+        Ok(Spill {
+            path,
+            out,
+            records: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn push(&mut self, record: &FileRecord) -> io::Result<()> {
+        let (secs, nanos) = match record
+            .mtime
+            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        {
+            Some(since) => (since.as_secs(), since.subsec_nanos()),
+            None => (u64::MAX, 0),
+        };
+        let path = path_bytes(&record.path);
+        self.out.write_all(&record.size.to_le_bytes())?;
+        self.out.write_all(&record.lines.to_le_bytes())?;
+        self.out.write_all(&secs.to_le_bytes())?;
+        self.out.write_all(&nanos.to_le_bytes())?;
+        self.out.write_all(&(path.len() as u32).to_le_bytes())?;
+        self.out.write_all(&path)?;
+        self.records += 1;
+        Ok(())
+    }
+
+    /// Feeds every record back to `each`, in the order they were pushed.
+    pub fn drain(mut self, each: &mut dyn FnMut(FileRecord)) -> io::Result<()> {
+        self.out.flush()?;
+        let mut input = BufReader::new(File::open(&self.path)?);
+        for _ in 0..self.records {
+            let size = read_u64(&mut input)?;
+            let lines = read_u64(&mut input)?;
+            let secs = read_u64(&mut input)?;
+            let mut nanos = [0; 4];
+            input.read_exact(&mut nanos)?;
+            let mut len = [0; 4];
+            input.read_exact(&mut len)?;
+            let mut path = vec![0; u32::from_le_bytes(len) as usize];
+            input.read_exact(&mut path)?;
+            let mtime = (secs != u64::MAX)
+                .then(|| SystemTime::UNIX_EPOCH + Duration::new(secs, u32::from_le_bytes(nanos)));
+            each(record(path_from_bytes(path), size, lines, mtime));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// A record with only what the lists use.
+fn record(path: PathBuf, size: u64, lines: u64, mtime: Option<SystemTime>) -> FileRecord {
+    FileRecord {
+        parent: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        path,
+        size,
+        lines,
+        mtime,
+        compressed: 0,
+        lang: None,
+        category: None,
+        kind: None,
+        matches: 0,
+        metrics: Vec::new(),
+        minified: false,
+        placeholder: false,
+        uncounted: false,
+        comments: None,
+        interpreter: None,
+        executable: None,
+        secret: None,
+        license: None,
+        file_id: None,
+        owner: None,
+        atime: None,
+        decompressed: None,
+    }
+}
+
+pub(crate) fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(unix)]
+pub(crate) fn path_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(unix)]
+pub(crate) fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+// Lists show paths through `display()`, so a lossy round trip shows the
+// same thing.
+#[cfg(not(unix))]
+pub(crate) fn path_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(not(unix))]
+pub(crate) fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
@@ -0,0 +1,99 @@
+//! Per-root state under the data directory: `roots/<key>/` holds whatever
+//! tengok keeps between runs for one root (the snapshot history and the
+//! `--dupes` hashes). Runs that overlap, like a cron job and a manual
+//! `tengok history`, take the directory's lock before touching it, and
+//! whole-file writes go through a temporary file and a rename, so a reader
+//! never sees half of one.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+pub struct RootStore {
+    dir: PathBuf,
+}
+
+/// Held while a run reads or writes a store; released on drop.
+pub struct Lock {
+    _file: File,
+}
+
+impl RootStore {
+    /// The store for the root identified by `root_id` (see
+    /// `history::root_id`) below `data_dir`, created if needed.
+    pub fn open(data_dir: &Path, root_id: &str) -> io::Result<Self> {
+        let dir = data_dir.join("roots").join(key(root_id));
+        fs::create_dir_all(&dir)?;
+        Ok(RootStore { dir })
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Wait for every other run using this store to finish. Filesystems
+    /// without locks (and WASI) get no locking rather than an error.
+    pub fn lock(&self) -> io::Result<Lock> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.path("lock"))?;
+        match file.lock() {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::Unsupported => {}
+            Err(err) => return Err(err),
+        }
+        Ok(Lock { _file: file })
+    }
+
+    /// Replace `name` with `contents` in one step.
+    pub fn write_atomic(&self, name: &str, contents: &[u8]) -> io::Result<()> {
+        let staged = self.path(&format!("{}.{}.tmp", name, process::id()));
+        let result = File::create(&staged)
+            .and_then(|mut file| {
+                file.write_all(contents)?;
+                file.sync_all()
+            })
+            .and_then(|()| fs::rename(&staged, self.path(name)));
+        if result.is_err() {
+            let _ = fs::remove_file(&staged);
+        }
+        result
+    }
+}
+
+/// FNV-1a of the root id, which keeps directory names short and
+/// filesystem-safe.
+pub fn key(root_id: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in root_id.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_atomically_under_a_lock() {
+        let data = std::env::temp_dir().join(format!("tengok-store-{}", process::id()));
+        let store = RootStore::open(&data, "/some/root").unwrap();
+        let _lock = store.lock().unwrap();
+        store.write_atomic("state.json", b"{}").unwrap();
+        store.write_atomic("state.json", b"[1]").unwrap();
+        assert_eq!(fs::read(store.path("state.json")).unwrap(), b"[1]");
+        let leftovers = fs::read_dir(&store.dir)
+            .unwrap()
+            .filter(|e| e.as_ref().unwrap().path().extension() == Some("tmp".as_ref()))
+            .count();
+        assert_eq!(leftovers, 0);
+        fs::remove_dir_all(&data).unwrap();
+    }
+}
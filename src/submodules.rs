@@ -0,0 +1,132 @@
+//! `--submodules`: git submodules listed in the root's `.gitmodules`. They
+//! are counted like any directory by default (`include`); `exclude` leaves
+//! them out of every total and reports what was skipped, and `separate`
+//! counts them and adds a subtotal per submodule.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::scan::{FileRecord, GroupStat};
+use crate::vfs::FileSystem;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    #[default]
+    Include,
+    Exclude,
+    Separate,
+}
+
+impl Mode {
+    pub fn parse(s: &str) -> Option<Mode> {
+        match s {
+            "include" => Some(Mode::Include),
+            "exclude" => Some(Mode::Exclude),
+            "separate" => Some(Mode::Separate),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Mode::Include => "include",
+            Mode::Exclude => "exclude",
+            Mode::Separate => "separate",
+        }
+    }
+}
+
+/// The root's submodules and the files found in each.
+#[derive(Debug, Clone, Default)]
+pub struct Submodules {
+    stats: BTreeMap<PathBuf, GroupStat>,
+}
+
+impl Submodules {
+    /// Read the `path = ...` entries of `root/.gitmodules`; none when it's
+    /// missing.
+    pub fn load(fs: &dyn FileSystem, root: &Path) -> Self {
+        let contents = fs
+            .read_to_string(&root.join(".gitmodules"))
+            .unwrap_or_default();
+        let stats = contents
+            .lines()
+            .filter_map(|line| {
+                let (key, value) = line.split_once('=')?;
+                (key.trim() == "path").then(|| (root.join(value.trim()), GroupStat::default()))
+            })
+            .collect();
+        Submodules { stats }
+    }
+
+    /// The submodule `path` sits in, if any.
+    pub fn of(&self, path: &Path) -> Option<&Path> {
+        self.stats
+            .keys()
+            .find(|dir| path.starts_with(dir))
+            .map(PathBuf::as_path)
+    }
+
+    pub fn add(&mut self, record: &FileRecord) {
+        if let Some(stat) = self.stat_mut(&record.path) {
+            stat.add(record);
+        }
+    }
+
+    pub fn remove(&mut self, record: &FileRecord) {
+        if let Some(stat) = self.stat_mut(&record.path) {
+            stat.remove(record);
+        }
+    }
+
+    fn stat_mut(&mut self, path: &Path) -> Option<&mut GroupStat> {
+        self.stats
+            .iter_mut()
+            .find(|(dir, _)| path.starts_with(dir))
+            .map(|(_, stat)| stat)
+    }
+
+    /// Every submodule with its files, in path order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Path, &GroupStat)> {
+        self.stats.iter().map(|(dir, stat)| (dir.as_path(), stat))
+    }
+
+    pub fn len(&self) -> usize {
+        self.stats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stats.is_empty()
+    }
+
+    /// Files and bytes across all submodules.
+    pub fn total(&self) -> (u64, u64) {
+        self.stats
+            .values()
+            .fold((0, 0), |(files, size), s| (files + s.files, size + s.size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemFs;
+
+    #[test]
+    fn reads_gitmodules() {
+        let mut fs = MemFs::default();
+        fs.insert(
+            "r/.gitmodules",
+            "[submodule \"lib\"]\n\tpath = vendor/lib\n\turl = https://example.com/lib.git\n",
+        );
+        let subs = Submodules::load(&fs, Path::new("r"));
+        assert_eq!(subs.len(), 1);
+        assert_eq!(
+            subs.of(Path::new("r/vendor/lib/src/a.c")),
+            Some(Path::new("r/vendor/lib"))
+        );
+        assert_eq!(subs.of(Path::new("r/vendor/other.c")), None);
+    }
+}
@@ -0,0 +1,158 @@
+//! `--theme`: the report's colors. The default is magenta labels on green
+//! values and borders; the `deuteranopia` and `protanopia` palettes avoid
+//! the red/green pairs those readers can't tell apart. Any role can be
+//! recolored on top of a palette (`deuteranopia,border=white`), and
+//! `TENGOK_THEME` sets the theme for every run without the flag. `warm` and
+//! `hot` are the `--heat` colors of large and very large sizes.
+
+use owo_colors::AnsiColors;
+
+pub const ENV: &str = "TENGOK_THEME";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub border: AnsiColors,
+    pub label: AnsiColors,
+    pub value: AnsiColors,
+    pub warm: AnsiColors,
+    pub hot: AnsiColors,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            border: AnsiColors::BrightGreen,
+            label: AnsiColors::BrightMagenta,
+            value: AnsiColors::BrightGreen,
+            warm: AnsiColors::BrightYellow,
+            hot: AnsiColors::BrightRed,
+        }
+    }
+}
+
+const COLORS: [(&str, AnsiColors); 16] = [
+    ("black", AnsiColors::Black),
+    ("red", AnsiColors::Red),
+    ("green", AnsiColors::Green),
+    ("yellow", AnsiColors::Yellow),
+    ("blue", AnsiColors::Blue),
+    ("magenta", AnsiColors::Magenta),
+    ("cyan", AnsiColors::Cyan),
+    ("white", AnsiColors::White),
+    ("bright-black", AnsiColors::BrightBlack),
+    ("bright-red", AnsiColors::BrightRed),
+    ("bright-green", AnsiColors::BrightGreen),
+    ("bright-yellow", AnsiColors::BrightYellow),
+    ("bright-blue", AnsiColors::BrightBlue),
+    ("bright-magenta", AnsiColors::BrightMagenta),
+    ("bright-cyan", AnsiColors::BrightCyan),
+    ("bright-white", AnsiColors::BrightWhite),
+];
+
+impl Theme {
+    /// Blue and yellow, which stay distinct without green-sensitive cones.
+    pub fn deuteranopia() -> Self {
+        Theme {
+            border: AnsiColors::BrightBlue,
+            label: AnsiColors::BrightYellow,
+            value: AnsiColors::BrightCyan,
+            warm: AnsiColors::BrightWhite,
+            hot: AnsiColors::BrightMagenta,
+        }
+    }
+
+    /// Blue and yellow again, but no red tints, which read as dark to
+    /// protanopes.
+    pub fn protanopia() -> Self {
+        Theme {
+            border: AnsiColors::BrightYellow,
+            label: AnsiColors::BrightBlue,
+            value: AnsiColors::BrightYellow,
+            warm: AnsiColors::BrightCyan,
+            hot: AnsiColors::BrightWhite,
+        }
+    }
+
+    /// The palette this is, unless some role was recolored.
+    pub fn name(&self) -> Option<&'static str> {
+        [
+            ("default", Theme::default()),
+            ("deuteranopia", Theme::deuteranopia()),
+            ("protanopia", Theme::protanopia()),
+        ]
+        .into_iter()
+        .find(|(_, theme)| theme == self)
+        .map(|(name, _)| name)
+    }
+
+    /// `NAME`, `ROLE=COLOR,...`, or both: `protanopia,label=bright-white`.
+    pub fn parse(spec: &str) -> Result<Theme, String> {
+        let mut theme = Theme::default();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let Some((role, color)) = part.split_once('=') else {
+                theme = match part {
+                    "default" => Theme::default(),
+                    "deuteranopia" => Theme::deuteranopia(),
+                    "protanopia" => Theme::protanopia(),
+                    _ => {
+                        return Err(format!(
+                            "Unknown theme: {} (expected default, deuteranopia or protanopia)",
+                            part
+                        ));
+                    }
+                };
+                continue;
+            };
+            let color = parse_color(color.trim())?;
+            match role.trim() {
+                "border" => theme.border = color,
+                "label" => theme.label = color,
+                "value" => theme.value = color,
+                "warm" => theme.warm = color,
+                "hot" => theme.hot = color,
+                other => {
+                    return Err(format!(
+                        "Unknown theme role: {} (expected border, label, value, warm or hot)",
+                        other
+                    ));
+                }
+            }
+        }
+        Ok(theme)
+    }
+}
+
+fn parse_color(name: &str) -> Result<AnsiColors, String> {
+    let name = name.to_ascii_lowercase().replace(['_', ' '], "-");
+    COLORS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, color)| *color)
+        .ok_or_else(|| {
+            format!(
+                "Unknown color: {} (expected black, red, green, yellow, blue, magenta, cyan or white, optionally bright-)",
+                name
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_palettes_and_overrides() {
+        assert_eq!(Theme::parse("default").unwrap(), Theme::default());
+        let theme = Theme::parse("deuteranopia, border=White").unwrap();
+        assert_eq!(theme.border, AnsiColors::White);
+        assert_eq!(theme.label, Theme::deuteranopia().label);
+        assert_eq!(
+            Theme::parse("label=bright_cyan").unwrap().label,
+            AnsiColors::BrightCyan
+        );
+        assert_eq!(Theme::parse("hot=blue").unwrap().hot, AnsiColors::Blue);
+        assert!(Theme::parse("tritanopia").is_err());
+        assert!(Theme::parse("title=red").is_err());
+        assert!(Theme::parse("label=orange").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//! `--throttle`: a cap on how fast line counting reads, shared by every
+//! walker thread, so a scan in the background of a busy server leaves the
+//! disk to the actual workload. Reads are accounted after they happen and
+//! the reader sleeps off whatever went over the rate.
+
+use std::{
+    io::{self, Read},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug)]
+pub struct Throttle {
+    bytes_per_sec: f64,
+    /// When the bytes read so far will have been paid for.
+    paid_until: Mutex<Instant>,
+}
+
+impl Throttle {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Throttle {
+            bytes_per_sec: bytes_per_sec as f64,
+            paid_until: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// `--throttle`'s value: megabytes (10^6 bytes) per second, fractions
+    /// allowed.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mb = value
+            .trim()
+            .trim_end_matches("MB/s")
+            .trim_end_matches("MB")
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|mb| mb.is_finite() && *mb > 0.0)
+            .ok_or_else(|| {
+                format!(
+                    "Invalid --throttle rate: {} (expected MB/s, e.g. 50)",
+                    value
+                )
+            })?;
+        Ok(Throttle::new((mb * 1_000_000.0).max(1.0) as u64))
+    }
+
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec as u64
+    }
+
+    /// Account for `bytes` just read, sleeping until they fit the rate.
+    pub fn take(&self, bytes: usize) {
+        if bytes == 0 {
+            return;
+        }
+        let cost = Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec);
+        let now = Instant::now();
+        let until = {
+            let mut paid_until = self.paid_until.lock().unwrap_or_else(|e| e.into_inner());
+            *paid_until = (*paid_until).max(now) + cost;
+            *paid_until
+        };
+        let wait = until.saturating_duration_since(now);
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// `inner`, with every read counted against `throttle` when there is one.
+pub struct Throttled<'a, R> {
+    pub inner: R,
+    pub throttle: Option<&'a Throttle>,
+}
+
+impl<R: Read> Read for Throttled<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(throttle) = self.throttle {
+            throttle.take(n);
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holds_reads_to_the_rate() {
+        let throttle = Throttle::parse("0.1").unwrap();
+        assert_eq!(throttle.bytes_per_sec(), 100_000);
+        let start = Instant::now();
+        let mut reader = Throttled {
+            inner: &[0u8; 20_000][..],
+            throttle: Some(&throttle),
+        };
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert!(Throttle::parse("0").is_err());
+        assert!(Throttle::parse("fast").is_err());
+    }
+}
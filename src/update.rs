@@ -0,0 +1,184 @@
+//! `tengok self-update`: replace the running binary with the latest GitHub
+//! release, for installs made with `install.sh` or a manual download. The
+//! release and its assets are fetched with `curl`, as the installer does,
+//! and the download must match its line in the release's `SHA256SUMS`
+//! before anything is replaced.
+
+use std::{env, fs, io, path::Path, process::Command};
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+const LATEST_RELEASE: &str = "https://api.github.com/repos/skolhustick/tengok/releases/latest";
+const CHECKSUMS: &str = "SHA256SUMS";
+
+pub struct UpdateArgs {
+    /// Only say whether a newer release exists.
+    pub check: bool,
+    /// Reinstall even when the latest release is not newer.
+    pub force: bool,
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<UpdateArgs, String> {
+    let mut opts = UpdateArgs {
+        check: false,
+        force: false,
+    };
+    for arg in args {
+        match arg.as_str() {
+            "--check" => opts.check = true,
+            "--force" => opts.force = true,
+            _ => return Err(format!("Unknown self-update option: {}", arg)),
+        }
+    }
+    Ok(opts)
+}
+
+pub fn run(opts: &UpdateArgs) -> Result<(), String> {
+    let asset = asset_name()
+        .ok_or("No release binary for this platform; update with `cargo install tengok` instead")?;
+    let release: Value = serde_json::from_slice(&fetch(LATEST_RELEASE)?)
+        .map_err(|err| format!("Unexpected response from GitHub: {}", err))?;
+    let tag = release["tag_name"]
+        .as_str()
+        .ok_or("The latest release has no tag")?;
+    let current = env!("CARGO_PKG_VERSION");
+    let newer = is_newer(tag, current);
+
+    if opts.check {
+        if newer {
+            println!("tengok {} is available (installed: {})", tag, current);
+        } else {
+            println!("tengok {} is up to date", current);
+        }
+        return Ok(());
+    }
+    if !newer && !opts.force {
+        println!("tengok {} is up to date", current);
+        return Ok(());
+    }
+
+    let binary = fetch(asset_url(&release, asset)?)?;
+    let sums = fetch(asset_url(&release, CHECKSUMS)?)?;
+    let expected = checksum_for(&String::from_utf8_lossy(&sums), asset)
+        .ok_or_else(|| format!("{} of {} has no entry for {}", CHECKSUMS, tag, asset))?;
+    let actual = hex(&Sha256::digest(&binary));
+    if !actual.eq_ignore_ascii_case(&expected) {
+        return Err(format!(
+            "Checksum mismatch for {}: expected {}, got {}; nothing was replaced",
+            asset, expected, actual
+        ));
+    }
+
+    let exe = env::current_exe().map_err(|err| format!("Cannot locate tengok: {}", err))?;
+    // Through symlinks, so a linked install updates the real file.
+    let exe = fs::canonicalize(&exe).unwrap_or(exe);
+    replace(&exe, &binary).map_err(|err| {
+        let hint = if err.kind() == io::ErrorKind::PermissionDenied {
+            " (try again with sudo)"
+        } else {
+            ""
+        };
+        format!("Cannot replace {}: {}{}", exe.display(), err, hint)
+    })?;
+    println!("Updated tengok {} to {} ({})", current, tag, exe.display());
+    Ok(())
+}
+
+/// The release asset for this platform, named as `build.sh` names them.
+fn asset_name() -> Option<&'static str> {
+    match (env::consts::OS, env::consts::ARCH) {
+        ("linux", "x86_64") => Some("tengok-linux-x86_64"),
+        ("linux", "aarch64") => Some("tengok-linux-arm64"),
+        ("macos", "x86_64") => Some("tengok-macos-x86_64"),
+        ("macos", "aarch64") => Some("tengok-macos-arm64"),
+        _ => None,
+    }
+}
+
+fn asset_url<'a>(release: &'a Value, name: &str) -> Result<&'a str, String> {
+    release["assets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|asset| asset["name"] == name)
+        .and_then(|asset| asset["browser_download_url"].as_str())
+        .ok_or_else(|| format!("The latest release has no {}", name))
+}
+
+fn fetch(url: &str) -> Result<Vec<u8>, String> {
+    let output = Command::new("curl")
+        .args(["-fsSL", "--proto", "=https", "--tlsv1.2", url])
+        .output()
+        .map_err(|err| format!("self-update needs curl: {}", err))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Download of {} failed: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Write `binary` next to `exe` and rename it over, so a failed write never
+/// leaves a broken install behind.
+fn replace(exe: &Path, binary: &[u8]) -> io::Result<()> {
+    let staged = exe.with_extension("new");
+    fs::write(&staged, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+    fs::rename(&staged, exe).inspect_err(|_| {
+        let _ = fs::remove_file(&staged);
+    })
+}
+
+/// Whether release `tag` (`v1.2.3`) is newer than `current` (`1.2.0`);
+/// any tag that isn't a version counts as newer when it differs.
+fn is_newer(tag: &str, current: &str) -> bool {
+    let parse = |v: &str| -> Option<Vec<u64>> {
+        let v = v.trim_start_matches('v');
+        let core = v.split_once('-').map_or(v, |(core, _)| core);
+        core.split('.').map(|n| n.parse().ok()).collect()
+    };
+    match (parse(tag), parse(current)) {
+        (Some(tag), Some(current)) => tag > current,
+        _ => tag.trim_start_matches('v') != current,
+    }
+}
+
+/// `asset`'s hash in `sha256sum` output (`<hex>  <name>`, or `*<name>` in
+/// binary mode).
+fn checksum_for(sums: &str, asset: &str) -> Option<String> {
+    sums.lines().find_map(|line| {
+        let (hash, name) = line.split_once(char::is_whitespace)?;
+        (name.trim().trim_start_matches('*') == asset).then(|| hash.to_string())
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_versions_and_reads_checksums() {
+        assert!(is_newer("v0.2.0", "0.1.1"));
+        assert!(is_newer("v0.1.10", "0.1.9"));
+        assert!(!is_newer("v0.1.1", "0.1.1"));
+        assert!(!is_newer("v0.1.0", "0.1.1"));
+
+        let sums = "abc123  tengok-linux-x86_64\ndef456 *tengok-macos-arm64\n";
+        assert_eq!(
+            checksum_for(sums, "tengok-macos-arm64").as_deref(),
+            Some("def456")
+        );
+        assert_eq!(checksum_for(sums, "tengok-linux-arm64"), None);
+    }
+}
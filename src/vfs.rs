@@ -0,0 +1,591 @@
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, Cursor, Read, Seek},
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+    time::SystemTime,
+};
+
+/// Win32's `MAX_PATH`, in UTF-16 units including the terminating NUL.
+pub const MAX_PATH: usize = 260;
+
+/// What the scanner needs to know about a path.
+#[derive(Debug, Clone, Copy)]
+pub struct Metadata {
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+    pub is_dir: bool,
+    pub is_file: bool,
+    /// The contents live elsewhere, as with a OneDrive Files On-Demand
+    /// placeholder, and reading them would download the file (Windows).
+    pub placeholder: bool,
+    /// A regular file with an executable bit set (Unix).
+    pub executable: bool,
+    /// Device and inode (Unix); hard links to one file share them.
+    pub file_id: Option<(u64, u64)>,
+    /// Owning user id (Unix).
+    pub owner: Option<u32>,
+    /// Last access, where the platform and mount record it.
+    pub accessed: Option<SystemTime>,
+}
+
+pub trait ReadSeek: Read + Seek + Send {}
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// Every read the scanning core makes goes through this trait, so a scan
+/// can run over something other than the host filesystem: a WASI preopen, a
+/// tarball, or a repo snapshot an embedding web viewer fetched over HTTP.
+pub trait FileSystem: Send + Sync {
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+    fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek + '_>>;
+    /// Direct children of `path`, as full paths.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Whether paths are host paths that `std::fs`, mmap and the `ignore`
+    /// walker can use directly.
+    fn is_native(&self) -> bool {
+        false
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        let mut out = String::new();
+        self.open(path)?.read_to_string(&mut out)?;
+        Ok(out)
+    }
+}
+
+/// The host filesystem.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsFs;
+
+impl FileSystem for OsFs {
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        #[cfg(all(target_os = "linux", any(target_env = "gnu", target_env = "musl")))]
+        if let Some(meta) = statx::metadata(path) {
+            return meta;
+        }
+        let meta = fs::metadata(extended(path))?;
+        Ok(Metadata {
+            len: meta.len(),
+            modified: meta.modified().ok(),
+            is_dir: meta.is_dir(),
+            is_file: meta.is_file(),
+            placeholder: is_placeholder(&meta),
+            executable: is_executable(&meta),
+            file_id: file_id(&meta),
+            owner: owner(&meta),
+            accessed: meta.accessed().ok(),
+        })
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek + '_>> {
+        Ok(Box::new(open_file(path)?))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        // Joined onto `path` so children keep the caller's spelling rather
+        // than the `\\?\` form.
+        fs::read_dir(extended(path))?
+            .map(|entry| entry.map(|e| path.join(e.file_name())))
+            .collect()
+    }
+
+    // WASI hosts can't mmap and only run the serial walker.
+    fn is_native(&self) -> bool {
+        cfg!(not(target_os = "wasi"))
+    }
+}
+
+/// The Linux fast path for `OsFs::metadata`. On metadata-only scans of
+/// millions of small files, the stats are most of the work: `fs::metadata`
+/// asks `statx` for every field, birth time included, and has the kernel
+/// resolve the whole path each time. Here `statx` is asked only for what
+/// `Metadata` holds, relative to a handle on the file's directory that each
+/// thread keeps while it works through that directory, so a lookup is one
+/// name. (Directory listings already come in `getdents64` batches.)
+#[cfg(all(target_os = "linux", any(target_env = "gnu", target_env = "musl")))]
+mod statx {
+    use std::{
+        cell::RefCell,
+        ffi::CString,
+        io,
+        mem::MaybeUninit,
+        os::{
+            fd::{AsRawFd, FromRawFd, OwnedFd},
+            unix::ffi::OsStrExt,
+        },
+        path::{Path, PathBuf},
+        sync::atomic::{AtomicBool, Ordering},
+        time::{Duration, SystemTime},
+    };
+
+    use super::Metadata;
+
+    const MASK: libc::c_uint = libc::STATX_TYPE
+        | libc::STATX_MODE
+        | libc::STATX_SIZE
+        | libc::STATX_MTIME
+        | libc::STATX_ATIME
+        | libc::STATX_INO
+        | libc::STATX_UID;
+
+    /// Set once `statx` turns out to be missing (kernels before 4.11, some
+    /// container seccomp profiles).
+    static UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+    thread_local! {
+        /// The directory this thread last looked a file up in.
+        static DIR: RefCell<Option<(PathBuf, OwnedFd)>> = const { RefCell::new(None) };
+    }
+
+    /// `None` when `statx` can't be used, and `fs::metadata` should be.
+    pub fn metadata(path: &Path) -> Option<io::Result<Metadata>> {
+        if UNAVAILABLE.load(Ordering::Relaxed) {
+            return None;
+        }
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return whole(path);
+        };
+        if dir.as_os_str().is_empty() {
+            return whole(path);
+        }
+        let name = CString::new(name.as_bytes()).ok()?;
+        let found = DIR.with_borrow_mut(|cached| {
+            if cached.as_ref().is_none_or(|(open, _)| open != dir) {
+                *cached = open_dir(dir).map(|fd| (dir.to_path_buf(), fd));
+            }
+            cached.as_ref().map(|(_, fd)| stat(fd.as_raw_fd(), &name))
+        });
+        match found {
+            // The directory may have been replaced since it was opened; the
+            // whole path says for sure.
+            Some(Some(Err(err))) if err.kind() == io::ErrorKind::NotFound => {
+                DIR.with_borrow_mut(|cached| *cached = None);
+                whole(path)
+            }
+            Some(result) => result,
+            None => whole(path),
+        }
+    }
+
+    fn whole(path: &Path) -> Option<io::Result<Metadata>> {
+        let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        stat(libc::AT_FDCWD, &path)
+    }
+
+    fn open_dir(dir: &Path) -> Option<OwnedFd> {
+        let dir = CString::new(dir.as_os_str().as_bytes()).ok()?;
+        let flags = libc::O_PATH | libc::O_DIRECTORY | libc::O_CLOEXEC;
+        // SAFETY: `dir` is NUL-terminated, and a descriptor `open` returns is
+        // ours to close.
+        let fd = unsafe { libc::open(dir.as_ptr(), flags) };
+        (fd >= 0).then(|| unsafe { OwnedFd::from_raw_fd(fd) })
+    }
+
+    fn stat(dirfd: libc::c_int, name: &CString) -> Option<io::Result<Metadata>> {
+        let mut buf = MaybeUninit::<libc::statx>::uninit();
+        // SAFETY: `name` is NUL-terminated and `buf` is a `statx` the kernel
+        // fills in when it returns 0.
+        let ret = unsafe { libc::statx(dirfd, name.as_ptr(), 0, MASK, buf.as_mut_ptr()) };
+        if ret != 0 {
+            let err = io::Error::last_os_error();
+            return match err.raw_os_error() {
+                Some(libc::ENOSYS | libc::EPERM) => {
+                    UNAVAILABLE.store(true, Ordering::Relaxed);
+                    None
+                }
+                _ => Some(Err(err)),
+            };
+        }
+        // SAFETY: initialized by the successful call above.
+        let stx = unsafe { buf.assume_init() };
+        if stx.stx_mask & MASK != MASK {
+            // A filesystem that couldn't supply a field; std's fallbacks
+            // are what the rest of the scan expects then.
+            return None;
+        }
+        let kind = u32::from(stx.stx_mode) & libc::S_IFMT;
+        let is_file = kind == libc::S_IFREG;
+        Some(Ok(Metadata {
+            len: stx.stx_size,
+            modified: time(stx.stx_mtime),
+            is_dir: kind == libc::S_IFDIR,
+            is_file,
+            placeholder: false,
+            executable: is_file && stx.stx_mode & 0o111 != 0,
+            file_id: Some((
+                libc::makedev(stx.stx_dev_major, stx.stx_dev_minor),
+                stx.stx_ino,
+            )),
+            owner: Some(stx.stx_uid),
+            accessed: time(stx.stx_atime),
+        }))
+    }
+
+    fn time(ts: libc::statx_timestamp) -> Option<SystemTime> {
+        let nanos = Duration::from_nanos(u64::from(ts.tv_nsec));
+        if ts.tv_sec >= 0 {
+            SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(ts.tv_sec as u64) + nanos)
+        } else {
+            SystemTime::UNIX_EPOCH
+                .checked_sub(Duration::from_secs(ts.tv_sec.unsigned_abs()))?
+                .checked_add(nanos)
+        }
+    }
+}
+
+/// Whether `path`, made absolute, is at least `MAX_PATH` long, so the
+/// plain Win32 APIs can't open it.
+pub fn is_long_path(path: &Path) -> bool {
+    static CWD_LEN: OnceLock<usize> = OnceLock::new();
+    let units = |p: &Path| p.to_string_lossy().encode_utf16().count();
+    let mut len = units(path);
+    if path.is_relative() {
+        len += 1 + *CWD_LEN.get_or_init(|| std::env::current_dir().map_or(0, |d| units(&d)));
+    }
+    len >= MAX_PATH
+}
+
+/// Opens `path` for reading without updating its access time where the
+/// kernel allows that (Linux, for files we own or as root), so a scan
+/// doesn't make every file look recently used to `--cold`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn open_file(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    let path = extended(path);
+    match fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOATIME)
+        .open(&path)
+    {
+        Err(err) if err.kind() == io::ErrorKind::PermissionDenied => File::open(&path),
+        result => result,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn open_file(path: &Path) -> io::Result<File> {
+    File::open(extended(path))
+}
+
+/// `path` in the `\\?\` extended-length form when it is too long for the
+/// plain Win32 APIs, so deep trees are measured instead of skipped.
+#[cfg(windows)]
+pub fn extended(path: &Path) -> Cow<'_, Path> {
+    let raw = path.as_os_str().to_string_lossy();
+    if raw.starts_with(r"\\?\") || !is_long_path(path) {
+        return Cow::Borrowed(path);
+    }
+    // Verbatim paths skip normalization, so resolve `.`, `..` and `/` first.
+    let Ok(abs) = std::path::absolute(path) else {
+        return Cow::Borrowed(path);
+    };
+    let abs = abs.to_string_lossy().into_owned();
+    Cow::Owned(PathBuf::from(match abs.strip_prefix(r"\\") {
+        Some(unc) => format!(r"\\?\UNC\{}", unc),
+        None => format!(r"\\?\{}", abs),
+    }))
+}
+
+#[cfg(not(windows))]
+pub fn extended(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+/// Bytes held in extended attributes, including the resource fork
+/// (`com.apple.ResourceFork`), which Finder and Time Machine count as part
+/// of the file.
+#[cfg(target_os = "macos")]
+pub fn xattr_bytes(path: &Path) -> u64 {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt, ptr};
+
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return 0;
+    };
+    let opts = libc::XATTR_NOFOLLOW;
+    // SAFETY: `c_path` and each name are NUL-terminated, and `names` is as
+    // long as the length passed with it. Null buffers only query sizes.
+    unsafe {
+        let len = libc::listxattr(c_path.as_ptr(), ptr::null_mut(), 0, opts);
+        if len <= 0 {
+            return 0;
+        }
+        let mut names = vec![0u8; len as usize];
+        let len = libc::listxattr(
+            c_path.as_ptr(),
+            names.as_mut_ptr().cast(),
+            names.len(),
+            opts,
+        );
+        if len <= 0 {
+            return 0;
+        }
+        names[..len as usize]
+            .split(|&b| b == 0)
+            .filter_map(|name| CString::new(name).ok().filter(|n| !n.is_empty()))
+            .map(|name| {
+                let size =
+                    libc::getxattr(c_path.as_ptr(), name.as_ptr(), ptr::null_mut(), 0, 0, opts);
+                size.max(0) as u64
+            })
+            .sum()
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn xattr_bytes(_: &Path) -> u64 {
+    0
+}
+
+#[cfg(windows)]
+fn is_placeholder(meta: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_OFFLINE: u32 = 0x1000;
+    const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x4_0000;
+    const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x40_0000;
+    meta.file_attributes()
+        & (FILE_ATTRIBUTE_OFFLINE
+            | FILE_ATTRIBUTE_RECALL_ON_OPEN
+            | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS)
+        != 0
+}
+
+#[cfg(not(windows))]
+fn is_placeholder(_: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn is_executable(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    meta.is_file() && meta.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn file_id(meta: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+// Windows' file index needs an open handle, which a stat doesn't give.
+#[cfg(not(unix))]
+fn file_id(_: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(unix)]
+fn owner(meta: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(meta.uid())
+}
+
+#[cfg(not(unix))]
+fn owner(_: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// The login name for `uid`, from the password database (NSS, so LDAP
+/// users resolve too).
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> Option<String> {
+    use std::{ffi::CStr, mem::MaybeUninit, ptr};
+
+    let mut buf = vec![0u8; 1024];
+    loop {
+        let mut pwd = MaybeUninit::<libc::passwd>::uninit();
+        let mut found = ptr::null_mut();
+        // SAFETY: `pwd` and `buf` outlive the call and `buf.len()` is its
+        // length; on success `found` points at `pwd`, whose strings live in
+        // `buf`.
+        let err = unsafe {
+            libc::getpwuid_r(
+                uid,
+                pwd.as_mut_ptr(),
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+                &mut found,
+            )
+        };
+        if err == libc::ERANGE && buf.len() < 1 << 20 {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if err != 0 || found.is_null() {
+            return None;
+        }
+        // SAFETY: a non-null result means `pwd` was filled in.
+        let name = unsafe { CStr::from_ptr(pwd.assume_init().pw_name) };
+        return Some(name.to_string_lossy().into_owned());
+    }
+}
+
+#[cfg(not(unix))]
+pub fn user_name(_: u32) -> Option<String> {
+    None
+}
+
+/// The effective user id of this process (Unix).
+#[cfg(unix)]
+pub fn current_uid() -> Option<u32> {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    Some(unsafe { libc::geteuid() })
+}
+
+#[cfg(not(unix))]
+pub fn current_uid() -> Option<u32> {
+    None
+}
+
+/// Whether `path` is an NTFS junction (a mount-point reparse point), which
+/// std reports as just another directory symlink.
+#[cfg(windows)]
+pub fn is_junction(path: &Path) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Foundation::INVALID_HANDLE_VALUE;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_ATTRIBUTE_REPARSE_POINT, FindClose, FindFirstFileW, WIN32_FIND_DATAW,
+    };
+    const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xA000_0003;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    // SAFETY: `wide` is NUL-terminated and `data` is plain old data that
+    // FindFirstFileW fills in; the handle is closed right away.
+    unsafe {
+        let mut data: WIN32_FIND_DATAW = std::mem::zeroed();
+        let handle = FindFirstFileW(wide.as_ptr(), &mut data);
+        if handle == INVALID_HANDLE_VALUE {
+            return false;
+        }
+        FindClose(handle);
+        data.dwFileAttributes & FILE_ATTRIBUTE_REPARSE_POINT != 0
+            && data.dwReserved0 == IO_REPARSE_TAG_MOUNT_POINT
+    }
+}
+
+/// An in-memory tree of files; directories exist implicitly. Lets an
+/// embedder (or a test) scan a tree that was never written to disk.
+#[derive(Debug, Clone, Default)]
+pub struct MemFs {
+    files: BTreeMap<PathBuf, (Arc<[u8]>, Option<SystemTime>)>,
+}
+
+impl MemFs {
+    pub fn insert(&mut self, path: impl Into<PathBuf>, contents: impl Into<Vec<u8>>) {
+        let contents: Vec<u8> = contents.into();
+        self.files.insert(path.into(), (contents.into(), None));
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.files
+            .range(path.to_path_buf()..)
+            .next()
+            .is_some_and(|(p, _)| p != path && p.starts_with(path))
+    }
+}
+
+impl FileSystem for MemFs {
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        if let Some((contents, modified)) = self.files.get(path) {
+            return Ok(Metadata {
+                len: contents.len() as u64,
+                modified: *modified,
+                is_dir: false,
+                is_file: true,
+                placeholder: false,
+                executable: false,
+                file_id: None,
+                owner: None,
+                accessed: None,
+            });
+        }
+        if self.is_dir(path) {
+            return Ok(Metadata {
+                len: 0,
+                modified: None,
+                is_dir: true,
+                is_file: false,
+                placeholder: false,
+                executable: false,
+                file_id: None,
+                owner: None,
+                accessed: None,
+            });
+        }
+        Err(io::ErrorKind::NotFound.into())
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek + '_>> {
+        let (contents, _) = self.files.get(path).ok_or(io::ErrorKind::NotFound)?;
+        Ok(Box::new(Cursor::new(&contents[..])))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        if !self.is_dir(path) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        let mut children: Vec<PathBuf> = self
+            .files
+            .range(path.to_path_buf()..)
+            .map(|(p, _)| p)
+            .take_while(|p| p.starts_with(path))
+            .filter_map(|p| {
+                let rest = p.strip_prefix(path).ok()?;
+                rest.components().next().map(|c| path.join(c))
+            })
+            .collect();
+        children.dedup();
+        Ok(children)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mem_fs_lists_implicit_directories() {
+        let mut fs = MemFs::default();
+        fs.insert("r/a.txt", "a");
+        fs.insert("r/src/b.rs", "b");
+        fs.insert("r/src/c.rs", "c");
+        fs.insert("rx/d.txt", "d");
+
+        let root = Path::new("r");
+        assert!(fs.metadata(root).unwrap().is_dir);
+        assert_eq!(
+            fs.read_dir(root).unwrap(),
+            vec![root.join("a.txt"), root.join("src")]
+        );
+        assert_eq!(fs.metadata(&root.join("a.txt")).unwrap().len, 1);
+        assert_eq!(fs.read_to_string(&root.join("src/c.rs")).unwrap(), "c");
+        assert!(fs.metadata(Path::new("r/missing")).is_err());
+    }
+
+    #[cfg(all(target_os = "linux", any(target_env = "gnu", target_env = "musl")))]
+    #[test]
+    fn statx_agrees_with_std() {
+        use std::os::unix::fs::MetadataExt;
+        for path in ["src/vfs.rs", "src", "Cargo.toml"] {
+            let Some(Ok(fast)) = statx::metadata(Path::new(path)) else {
+                continue;
+            };
+            let std = fs::metadata(path).unwrap();
+            assert_eq!(fast.len, std.len(), "{}", path);
+            assert_eq!(fast.modified, std.modified().ok(), "{}", path);
+            assert_eq!((fast.is_dir, fast.is_file), (std.is_dir(), std.is_file()));
+            assert_eq!(fast.file_id, Some((std.dev(), std.ino())), "{}", path);
+            assert_eq!(fast.owner, Some(std.uid()));
+        }
+        assert!(matches!(
+            statx::metadata(Path::new("src/missing.rs")),
+            None | Some(Err(_))
+        ));
+    }
+}
@@ -0,0 +1,477 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, mpsc},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor::MoveTo,
+    execute,
+    terminal::{Clear, ClearType},
+};
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use crate::config::{Config, Output};
+use crate::expr::MetricTotal;
+use crate::format::{display_relative_path, format_duration, format_num};
+use crate::json;
+use crate::linguist::Classifier;
+use crate::report::{self, Style};
+use crate::scan::{self, FileRecord, Summary};
+use crate::vfs::OsFs;
+
+/// How long to keep collecting events after the first one before applying
+/// them, so a burst of writes (a build, a checkout) causes one redraw.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Live view of a tree: every tracked record grouped by its directory, plus
+/// a summary kept in sync through `Summary::add`/`remove` deltas.
+struct Tracker {
+    config: Arc<Config>,
+    summary: Summary,
+    /// Every directory the walker would visit, mapped to the files directly
+    /// inside it. Empty directories are kept so events in them are noticed.
+    dirs: BTreeMap<PathBuf, HashMap<PathBuf, FileRecord>>,
+    classifier: Option<Classifier>,
+    line_buf: Vec<u8>,
+    /// Byte changes to each directory's own files, oldest first, reaching
+    /// back `--growth-window`.
+    growth: VecDeque<(Instant, PathBuf, i64)>,
+}
+
+impl Tracker {
+    fn new(config: Arc<Config>, mut summary: Summary) -> Self {
+        let mut dirs: BTreeMap<PathBuf, HashMap<PathBuf, FileRecord>> = BTreeMap::new();
+        dirs.insert(config.root.clone(), HashMap::new());
+        for record in summary.files.drain(..) {
+            let mut ancestor = record.parent.parent();
+            while let Some(dir) = ancestor {
+                if !dir.starts_with(&config.root) || dirs.contains_key(dir) {
+                    break;
+                }
+                dirs.insert(dir.to_path_buf(), HashMap::new());
+                ancestor = dir.parent();
+            }
+            dirs.entry(record.parent.clone())
+                .or_default()
+                .insert(record.path.clone(), record);
+        }
+        Self {
+            classifier: config
+                .linguist
+                .then(|| Classifier::load(&OsFs, &config.root)),
+            config,
+            summary,
+            dirs,
+            line_buf: Vec::with_capacity(64 * 1024),
+            growth: VecDeque::new(),
+        }
+    }
+
+    fn grew(&mut self, dir: &Path, delta: i64) {
+        if delta != 0 {
+            self.growth
+                .push_back((Instant::now(), dir.to_path_buf(), delta));
+        }
+    }
+
+    /// The directory whose files gained the most bytes within
+    /// `--growth-window`, and how many; `None` when nothing grew.
+    fn fastest_growing(&mut self) -> Option<(PathBuf, u64)> {
+        if let Some(cutoff) = Instant::now().checked_sub(self.config.growth_window) {
+            while self.growth.front().is_some_and(|(at, _, _)| *at < cutoff) {
+                self.growth.pop_front();
+            }
+        }
+        let mut totals: HashMap<&Path, i64> = HashMap::new();
+        for (_, dir, delta) in &self.growth {
+            *totals.entry(dir).or_default() += delta;
+        }
+        totals
+            .into_iter()
+            .filter(|(_, delta)| *delta > 0)
+            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(dir, delta)| (dir.to_path_buf(), delta as u64))
+    }
+
+    /// Closest tracked directory above `path` (never `path` itself).
+    fn nearest_known(&self, path: &Path) -> Option<PathBuf> {
+        let mut current = path.parent();
+        while let Some(dir) = current {
+            if self.dirs.contains_key(dir) {
+                return Some(dir.to_path_buf());
+            }
+            if dir == self.config.root {
+                return None;
+            }
+            current = dir.parent();
+        }
+        None
+    }
+
+    /// Bring one directory's direct entries back in line with disk, using
+    /// the same walker (and thus ignore rules) as the initial scan.
+    fn resync(&mut self, dir: &Path) {
+        let mut seen_files = HashSet::new();
+        let mut seen_dirs = HashSet::new();
+        let walker = scan::walk_builder(dir, &self.config)
+            .max_depth(Some(1))
+            .build();
+        for dent in walker.flatten() {
+            if dent.depth() == 0 {
+                continue;
+            }
+            let Some(file_type) = dent.file_type() else {
+                continue;
+            };
+            let path = dent.into_path();
+            if file_type.is_dir() {
+                if !self.dirs.contains_key(&path) {
+                    self.add_tree(&path);
+                }
+                seen_dirs.insert(path);
+            } else if file_type.is_file() {
+                self.refresh_file(&path);
+                seen_files.insert(path);
+            }
+        }
+
+        let stale_files: Vec<PathBuf> = self
+            .dirs
+            .get(dir)
+            .map(|files| {
+                files
+                    .keys()
+                    .filter(|p| !seen_files.contains(*p))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        for path in stale_files {
+            self.remove_file(dir, &path);
+        }
+
+        let stale_dirs: Vec<PathBuf> = self
+            .dirs
+            .range(dir.to_path_buf()..)
+            .map(|(d, _)| d)
+            .take_while(|d| d.starts_with(dir))
+            .filter(|d| d.parent() == Some(dir) && !seen_dirs.contains(*d))
+            .cloned()
+            .collect();
+        for stale in stale_dirs {
+            self.remove_tree(&stale);
+        }
+    }
+
+    fn add_tree(&mut self, dir: &Path) {
+        for dent in scan::walk_builder(dir, &self.config).build().flatten() {
+            let Some(file_type) = dent.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                self.dirs.entry(dent.into_path()).or_default();
+            } else if file_type.is_file() {
+                self.refresh_file(dent.path());
+            }
+        }
+    }
+
+    fn refresh_file(&mut self, path: &Path) {
+        let Ok(meta) = fs::metadata(path) else {
+            return;
+        };
+        let parent = path.parent().unwrap_or(&self.config.root).to_path_buf();
+        let unchanged = self
+            .dirs
+            .get(&parent)
+            .and_then(|files| files.get(path))
+            .is_some_and(|r| r.size == meta.len() && r.mtime == meta.modified().ok());
+        if unchanged {
+            return;
+        }
+
+        let Some(record) = scan::read_record(
+            &OsFs,
+            path.to_path_buf(),
+            &self.config.root,
+            &self.config,
+            self.classifier.as_ref(),
+            &mut self.line_buf,
+            None,
+        ) else {
+            return;
+        };
+        let files = self.dirs.entry(parent.clone()).or_default();
+        let mut delta = record.size as i64;
+        if let Some(old) = files.insert(record.path.clone(), record.clone()) {
+            delta -= old.size as i64;
+            self.summary.remove(&old, &self.config);
+        }
+        self.summary.add(&record, &self.config);
+        self.grew(&parent, delta);
+    }
+
+    fn remove_file(&mut self, dir: &Path, path: &Path) {
+        if let Some(old) = self.dirs.get_mut(dir).and_then(|files| files.remove(path)) {
+            self.summary.remove(&old, &self.config);
+            self.grew(dir, -(old.size as i64));
+        }
+    }
+
+    fn remove_tree(&mut self, dir: &Path) {
+        let doomed: Vec<PathBuf> = self
+            .dirs
+            .range(dir.to_path_buf()..)
+            .map(|(d, _)| d)
+            .take_while(|d| d.starts_with(dir))
+            .cloned()
+            .collect();
+        for d in doomed {
+            if let Some(files) = self.dirs.remove(&d) {
+                for old in files.values() {
+                    self.summary.remove(old, &self.config);
+                }
+                self.grew(&d, -(files.values().map(|f| f.size as i64).sum::<i64>()));
+            }
+        }
+    }
+
+    /// Recompute what deltas can't maintain, then hand out the summary.
+    fn summary(&mut self) -> &Summary {
+        if self.summary.max_lines_file.is_none() {
+            self.summary.max_lines_file = self
+                .dirs
+                .values()
+                .flat_map(|files| files.values())
+                .filter(|r| r.lines > 0)
+                .max_by(|a, b| a.lines.cmp(&b.lines).then_with(|| b.path.cmp(&a.path)))
+                .cloned()
+                .map(Into::into);
+        }
+        if self.summary.largest_file.is_none() {
+            self.summary.largest_file = self
+                .dirs
+                .values()
+                .flat_map(|files| files.values())
+                .max_by(|a, b| a.size.cmp(&b.size).then_with(|| b.path.cmp(&a.path)))
+                .cloned()
+                .map(Into::into);
+        }
+        if self.summary.newest_file.is_none() {
+            self.summary.newest_file = self
+                .dirs
+                .values()
+                .flat_map(|files| files.values())
+                .filter(|r| r.mtime.is_some())
+                .max_by(|a, b| a.mtime.cmp(&b.mtime).then_with(|| b.path.cmp(&a.path)))
+                .cloned()
+                .map(Into::into);
+        }
+        if self.config.by_ext {
+            let stale: HashSet<String> = self
+                .summary
+                .exts
+                .iter()
+                .filter(|(_, stat)| stat.max_lines.is_none() && stat.lines > 0)
+                .map(|(ext, _)| ext.clone())
+                .collect();
+            if !stale.is_empty() {
+                for record in self.dirs.values().flat_map(|files| files.values()) {
+                    let key = scan::extension_key(&record.path);
+                    if !stale.contains(&key) {
+                        continue;
+                    }
+                    let Some(stat) = self.summary.exts.get_mut(&key) else {
+                        continue;
+                    };
+                    let best = stat.max_lines.as_ref();
+                    if best.is_none_or(|b| {
+                        record.lines > b.lines || (record.lines == b.lines && record.path < b.path)
+                    }) {
+                        stat.max_lines = Some(record.clone().into());
+                    }
+                }
+            }
+        }
+        for (metric, total) in self.config.metrics.iter().zip(&mut self.summary.metrics) {
+            if !total.is_stale(metric.agg) {
+                continue;
+            }
+            let mut fresh = MetricTotal::default();
+            for record in self.dirs.values().flat_map(|files| files.values()) {
+                if let Some(value) = metric.value(record, &self.config.root) {
+                    fresh.add(value);
+                }
+            }
+            *total = fresh;
+        }
+        if self.config.needs_files() {
+            self.summary.files = self
+                .dirs
+                .values()
+                .flat_map(|files| files.values())
+                .cloned()
+                .collect();
+        }
+        self.summary.finish(&self.config.root);
+        &self.summary
+    }
+}
+
+pub fn run(config: Arc<Config>) -> io::Result<()> {
+    let summary = scan::scan_dir(&config)?;
+    let mut tracker = Tracker::new(Arc::clone(&config), summary);
+    let abs_root = fs::canonicalize(&config.root)?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(io::Error::other)?;
+    watcher
+        .watch(&abs_root, RecursiveMode::Recursive)
+        .map_err(io::Error::other)?;
+
+    let mut events: u64 = 0;
+    render(&config, tracker.summary(), None, events)?;
+
+    while let Ok(first) = rx.recv() {
+        let mut batch = vec![first];
+        while let Ok(next) = rx.recv_timeout(DEBOUNCE) {
+            batch.push(next);
+        }
+
+        let mut dirty = HashSet::new();
+        for event in batch.into_iter().flatten() {
+            // Our own reads during a resync show up as access events; only
+            // changes to the tree matter.
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            events += 1;
+            for path in event.paths {
+                // notify reports absolute paths; records use the root as typed.
+                let Ok(rel) = path.strip_prefix(&abs_root) else {
+                    continue;
+                };
+                if let Some(dir) = tracker.nearest_known(&config.root.join(rel)) {
+                    dirty.insert(dir);
+                }
+            }
+        }
+        if dirty.is_empty() {
+            continue;
+        }
+
+        let mut dirty: Vec<PathBuf> = dirty.into_iter().collect();
+        dirty.sort();
+        for dir in dirty {
+            // An earlier resync may have dropped this directory's parent.
+            if tracker.dirs.contains_key(&dir) {
+                tracker.resync(&dir);
+            }
+        }
+        let growing = tracker.fastest_growing();
+        render(&config, tracker.summary(), growing, events)?;
+    }
+
+    Ok(())
+}
+
+fn render(
+    config: &Config,
+    summary: &Summary,
+    growing: Option<(PathBuf, u64)>,
+    events: u64,
+) -> io::Result<()> {
+    if config.output == Output::Json {
+        return json::print_json(config, summary);
+    }
+
+    execute!(io::stdout(), Clear(ClearType::All), MoveTo(0, 0))?;
+    if config.output == Output::List {
+        report::print_file_list(config, summary)?;
+    } else if config.output == Output::Oneline {
+        report::print_oneline(config, summary);
+    } else {
+        report::print_report(config, summary);
+        report::print_lists(config, summary);
+    }
+    let style = Style::new(config);
+    println!();
+    if let Some((dir, bytes)) = growing {
+        println!(
+            "{} {} (+{} in the last {})",
+            style.label("Fastest growing:"),
+            display_relative_path(&dir, &config.root),
+            config.size(bytes),
+            format_duration(config.growth_window)
+        );
+    }
+    println!(
+        "{}",
+        style.label(&format!(
+            "Watching for changes… {} events applied (Ctrl-C to quit)",
+            format_num(events)
+        ))
+    );
+    io::stdout().flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resync_applies_created_and_deleted_files() {
+        let root = std::env::temp_dir().join(format!("tengok-watch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/a.txt"), "1\n2\n").unwrap();
+
+        let args = ["--watch".to_string(), root.display().to_string()];
+        let config = Arc::new(Config::from_args(args).unwrap());
+        let summary = scan::scan_dir(&config).unwrap();
+        let mut tracker = Tracker::new(Arc::clone(&config), summary);
+
+        fs::write(root.join("sub/b.txt"), "1\n2\n3\n").unwrap();
+        fs::remove_file(root.join("sub/a.txt")).unwrap();
+        tracker.resync(&root.join("sub"));
+
+        let summary = tracker.summary();
+        assert_eq!(summary.total_files, 1);
+        assert_eq!(summary.total_lines, 3);
+        assert_eq!(
+            summary.max_lines_file.as_ref().map(|f| f.path.clone()),
+            Some(root.join("sub/b.txt"))
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn fastest_growing_sums_each_directorys_changes() {
+        let root = std::env::temp_dir().join(format!("tengok-growth-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("logs")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/a.rs"), "x".repeat(100)).unwrap();
+
+        let args = ["--watch".to_string(), root.display().to_string()];
+        let config = Arc::new(Config::from_args(args).unwrap());
+        let summary = scan::scan_dir(&config).unwrap();
+        let mut tracker = Tracker::new(Arc::clone(&config), summary);
+        assert_eq!(tracker.fastest_growing(), None);
+
+        fs::write(root.join("logs/app.log"), "x".repeat(300)).unwrap();
+        fs::write(root.join("src/a.rs"), "x".repeat(150)).unwrap();
+        tracker.resync(&root.join("logs"));
+        tracker.resync(&root.join("src"));
+        assert_eq!(tracker.fastest_growing(), Some((root.join("logs"), 300)));
+
+        fs::remove_file(root.join("logs/app.log")).unwrap();
+        tracker.resync(&root.join("logs"));
+        assert_eq!(tracker.fastest_growing(), Some((root.join("src"), 50)));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
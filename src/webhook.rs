@@ -0,0 +1,134 @@
+//! `--notify-webhook`: POST a JSON alert when a check fails, so a scheduled
+//! scan can post to a Slack or Teams channel. The payload's `text` is what
+//! those incoming webhooks display; `summary`, `budget_violations` and
+//! `growth` are the same objects as in `--json`, for receivers that want
+//! more. It's sent with `curl`, as `self-update` fetches releases.
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use humansize::{DECIMAL, format_size};
+use serde_json::{Value, json};
+
+use crate::baseline;
+use crate::budget::{self, Unit};
+use crate::config::Config;
+use crate::format::{display_relative_path, format_num};
+use crate::json;
+use crate::names;
+use crate::scan::Summary;
+
+/// Gives up on a receiver that hasn't answered in this many seconds.
+const TIMEOUT_SECS: &str = "30";
+
+/// The body posted for a failed check.
+pub fn payload(config: &Config, summary: &Summary) -> Value {
+    let doc = json::to_value(config, summary);
+    let mut alert = json!({
+        "text": text(config, summary),
+        "root": doc["root"],
+        "summary": doc["summary"],
+    });
+    for key in ["budget_violations", "growth"] {
+        if let Some(value) = doc.get(key) {
+            alert[key] = value.clone();
+        }
+    }
+    alert
+}
+
+/// Posts `payload` to `url`.
+pub fn notify(url: &str, config: &Config, summary: &Summary) -> Result<(), String> {
+    let body = payload(config, summary).to_string();
+    let mut child = Command::new("curl")
+        .args(["-fsS", "--proto", "=http,https", "--max-time", TIMEOUT_SECS])
+        .args(["-X", "POST", "-H", "Content-Type: application/json"])
+        .args(["--data-binary", "@-", url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("--notify-webhook needs curl: {}", err))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(body.as_bytes())
+            .map_err(|err| format!("Unable to send the webhook: {}", err))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|err| format!("Unable to send the webhook: {}", err))?;
+    if !output.status.success() {
+        // The URL is left out: Slack and Teams put the secret in it.
+        return Err(format!(
+            "Webhook POST failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// One line per failed check, under a headline naming the root.
+fn text(config: &Config, summary: &Summary) -> String {
+    let mut lines = vec![format!(
+        "tengok: checks failed for {}",
+        config.root.display()
+    )];
+    let amount = |v: u64, unit: Unit| match unit {
+        Unit::Bytes => format_size(v, DECIMAL),
+        Unit::Lines => format!("{} lines", format_num(v)),
+        Unit::Files => format!("{} files", format_num(v)),
+    };
+    for v in budget::violations(config, summary) {
+        lines.push(format!(
+            "• {}{}: {} over the {} limit",
+            v.budget.glob,
+            v.path.map_or(String::new(), |p| format!(
+                " ({})",
+                display_relative_path(p, &config.root)
+            )),
+            amount(v.value, v.budget.unit),
+            amount(v.budget.limit, v.budget.unit)
+        ));
+    }
+    if let Some(growth) = baseline::growth_check(config, summary).filter(|g| g.exceeded) {
+        let pct = |p: Option<f64>| p.map_or("new".to_string(), |p| format!("{:+.1}%", p));
+        lines.push(format!(
+            "• grew {} in size and {} in lines (limit {}%)",
+            pct(growth.size),
+            pct(growth.lines),
+            growth.max_percent
+        ));
+    }
+    if names::strict_failure(config, summary) {
+        lines.push("• unportable or colliding file names (--strict)".to_string());
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan;
+    use crate::vfs::MemFs;
+
+    #[test]
+    fn payload_lists_the_violations() {
+        let mut fs = MemFs::default();
+        fs.insert("r/assets/a.png", "0".repeat(300));
+        fs.insert("r/src/main.rs", "fn main() {}\n");
+        let mut config = Config::from_args(["r".to_string()]).unwrap();
+        config.budgets = budget::parse(r#""assets/**" = "<= 100 B""#).unwrap();
+        let summary = scan::scan_fs(&fs, &config).unwrap();
+        let alert = payload(&config, &summary);
+        let text = alert["text"].as_str().unwrap();
+        assert!(
+            text.contains("assets/**: 300 B over the 100 B limit"),
+            "{}",
+            text
+        );
+        assert_eq!(alert["budget_violations"][0]["value"], 300);
+        assert_eq!(alert["summary"]["files"], 2);
+    }
+}
@@ -0,0 +1,30 @@
+from os import PathLike
+from typing import Any, Iterator, Optional, Union
+
+__version__: str
+
+class Summary:
+    root: str
+    files: int
+    size_bytes: int
+    lines: int
+    def to_json(self) -> str: ...
+    def to_dict(self) -> dict[str, Any]: ...
+
+class FileRecord:
+    path: str
+    dir: str
+    ext: str
+    size_bytes: int
+    lines: int
+    mtime: Optional[float]
+    language: Optional[str]
+    content_type: Optional[str]
+    linguist: Optional[str]
+    matches: int
+
+class FileIter(Iterator[FileRecord]):
+    def __next__(self) -> FileRecord: ...
+
+def scan(path: Union[str, PathLike[str]] = ".", **options: Any) -> Summary: ...
+def files(path: Union[str, PathLike[str]] = ".", **options: Any) -> FileIter: ...